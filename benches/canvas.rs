@@ -0,0 +1,128 @@
+//! throughput of filling, drawing lines on, and copying 1080p canvases. run with
+//! `cargo bench --bench canvas`, and add `--features parallel` to see
+//! the banded versions running on all cores.
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_game_examples::{Canvas, Rgb, draw::Draw, world_screen::WorldScreen};
+
+const WIDTH: usize = 1920;
+const HEIGHT: usize = 1080;
+const COLOR: Rgb = Rgb { red: 120, green: 180, blue: 90 };
+
+fn fill(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fill");
+    for bpp in [3, 4].iter() {
+        let mut canvas = Canvas::new(WIDTH, HEIGHT, *bpp);
+        group.throughput(Throughput::Bytes((WIDTH * HEIGHT * bpp) as u64));
+        group.bench_with_input(BenchmarkId::new("fill", bpp), bpp, |b, _| {
+            b.iter(|| canvas.fill(black_box(COLOR)))
+        });
+        // what `fill` used to do: one pixel at a time, down each column
+        group.bench_with_input(BenchmarkId::new("set_pixel_column_major", bpp), bpp, |b, _| {
+            b.iter(|| {
+                for x in 0..WIDTH {
+                    for y in 0..HEIGHT {
+                        canvas.set_pixel(x, y, black_box(COLOR));
+                    }
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("bands", bpp), bpp, |b, _| {
+            b.iter(|| canvas.for_each_band(64, |band| band.fill(black_box(COLOR))))
+        });
+    }
+    group.finish();
+}
+
+fn spans(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spans");
+    let mut canvas = Canvas::new(WIDTH, HEIGHT, 4);
+    group.throughput(Throughput::Bytes((WIDTH * HEIGHT * 4) as u64));
+    group.bench_function("full_width", |b| {
+        b.iter(|| {
+            for y in 0..HEIGHT {
+                canvas.draw_horizontal_line(y, 0, WIDTH, black_box(COLOR));
+            }
+        })
+    });
+    // a screen full of 32 pixel wide tiles, like a map zoomed out
+    group.bench_function("32px", |b| {
+        b.iter(|| {
+            for y in 0..HEIGHT {
+                for x in (0..WIDTH).step_by(32) {
+                    canvas.draw_horizontal_line(y, x, x + 32, black_box(COLOR));
+                }
+            }
+        })
+    });
+    let (w, h) = (WIDTH as f32, HEIGHT as f32);
+    group.bench_function("convex_polygon", |b| {
+        b.iter(|| canvas.fill_convex_polygon_f32(black_box(&[(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)]), COLOR))
+    });
+    group.bench_function("triangles", |b| {
+        b.iter(|| {
+            canvas.fill_triangle_f32(black_box(&[(0.0, 0.0), (w, 0.0), (w, h)]), COLOR);
+            canvas.fill_triangle_f32(black_box(&[(0.0, 0.0), (w, h), (0.0, h)]), COLOR);
+        })
+    });
+    group.finish();
+}
+
+/// lines across the whole screen in each direction, straight onto the
+/// canvas and through a WorldScreen (which transforms and clips them)
+fn lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lines");
+    let (w, h) = (WIDTH as f32, HEIGHT as f32);
+    let directions = [
+        ("horizontal", (0.0, h / 2.0, w - 1.0, h / 2.0)),
+        ("vertical", (w / 2.0, 0.0, w / 2.0, h - 1.0)),
+        ("diagonal_down", (0.0, 0.0, w - 1.0, h - 1.0)),
+        ("diagonal_up", (0.0, h - 1.0, w - 1.0, 0.0)),
+        ("steep", (w / 2.0 - 100.0, 0.0, w / 2.0 + 100.0, h - 1.0)),
+        ("right_to_left", (w - 1.0, 100.0, 0.0, h - 100.0)),
+    ];
+    let mut canvas = Canvas::new(WIDTH, HEIGHT, 4);
+    for (name, (x1, y1, x2, y2)) in directions.iter() {
+        group.bench_function(BenchmarkId::new("canvas", name), |b| {
+            b.iter(|| canvas.draw_diagonal_line_f32(black_box(*x1), *y1, *x2, *y2, COLOR))
+        });
+    }
+    let mut screen = WorldScreen::default();
+    screen.canvas = Canvas::new(WIDTH, HEIGHT, 4);
+    screen.screen_width = WIDTH;
+    screen.screen_height = HEIGHT;
+    screen.scale_x = 1.0;
+    screen.scale_y = 1.0;
+    for (name, (x1, y1, x2, y2)) in directions.iter() {
+        group.bench_function(BenchmarkId::new("world_screen", name), |b| {
+            b.iter(|| screen.draw_diagonal_line_f32(black_box(*x1), *y1, *x2, *y2, COLOR))
+        });
+    }
+    // mostly off screen, so almost all of it is clipped away
+    group.bench_function("world_screen/clipped", |b| {
+        b.iter(|| screen.draw_diagonal_line_f32(black_box(-100_000.0), -50_000.0, w + 100_000.0, h + 40_000.0, COLOR))
+    });
+    group.bench_function("thick", |b| {
+        b.iter(|| canvas.draw_thick_line_f32(black_box(0.0), 0.0, w - 1.0, h - 1.0, 8.0, COLOR))
+    });
+    group.finish();
+}
+
+fn blit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("blit");
+    let src = Canvas::new_with_color(WIDTH, HEIGHT, COLOR, 4);
+    let mut dest = Canvas::new(WIDTH, HEIGHT, 4);
+    group.throughput(Throughput::Bytes((WIDTH * HEIGHT * 4) as u64));
+    group.bench_function("blit", |b| {
+        b.iter(|| dest.blit(black_box(&src), 0, 0))
+    });
+    group.bench_function("blend_opaque", |b| {
+        b.iter(|| dest.blend_from(black_box(&src), 1.0, None))
+    });
+    group.bench_function("blend_half", |b| {
+        b.iter(|| dest.blend_from(black_box(&src), 0.5, Some(Rgb::TRANSPARENT_KEY)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, fill, spans, lines, blit);
+criterion_main!(benches);
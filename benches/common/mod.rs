@@ -0,0 +1,32 @@
+//! the worlds that the `world` and `frames` benches share.
+use simple_game_examples::elevation::HeightMap;
+use simple_game_examples::entity::{Entity, EntityLayer, Sprite};
+use simple_game_examples::Rgb;
+
+/// rolling hills, so that many cells are hidden behind others
+pub fn rolling_hills(width: usize, height: usize) -> HeightMap {
+    let mut heights = HeightMap::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let hill = ((x as f32 * 0.3).sin() + (y as f32 * 0.2).cos() + 2.0) * 12.0;
+            heights.set(x, y, hill.round());
+        }
+    }
+    heights
+}
+
+/// 400 entities on a 20 x 20 grid 5 cells apart, a quarter of them blocks
+/// and the rest billboards
+pub fn entities() -> EntityLayer {
+    let mut entities = EntityLayer::new();
+    for i in 0..400 {
+        let (x, y) = ((i % 20 * 5) as f32, (i / 20 * 5) as f32);
+        let sprite = if i % 4 == 0 {
+            Sprite::Block { height: 30.0, color: Rgb::RED }
+        } else {
+            Sprite::Billboard { width: 16.0, height: 36.0, anchor: (8.0, 34.0), color: Rgb::GREEN }
+        };
+        entities.add(Entity::new("bench", (x, y), (1.0, 1.0), sprite));
+    }
+    entities
+}
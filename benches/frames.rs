@@ -0,0 +1,138 @@
+//! whole 1080p frames of the scenes from the iso examples, from
+//! clearing the canvas to the last line, with the camera looking at the
+//! middle of the map.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use simple_game_examples::draw::Draw;
+use simple_game_examples::tile_map::{Orientation, TileMap};
+use simple_game_examples::world_screen::{DetailLevel, WorldScreen};
+use simple_game_examples::{Canvas, Rgb};
+
+mod common;
+
+const WIDTH: usize = 1920;
+const HEIGHT: usize = 1080;
+const GRASS: Rgb = Rgb { red: 120, green: 180, blue: 90 };
+
+/// a screen at `zoom`, centered on the middle of `map`
+fn screen(map: &TileMap, zoom: f32) -> WorldScreen {
+    let mut screen = WorldScreen::default();
+    screen.canvas = Canvas::new(WIDTH, HEIGHT, 4);
+    screen.screen_width = WIDTH;
+    screen.screen_height = HEIGHT;
+    screen.scale_x = zoom;
+    screen.scale_y = zoom;
+    let (center_x, center_y) = map.projection().apply(map.width as f32 / 2.0, map.height as f32 / 2.0);
+    screen.pan_offset_x = center_x - WIDTH as f32 / 2.0 / zoom;
+    screen.pan_offset_y = center_y - HEIGHT as f32 / 2.0 / zoom;
+    screen
+}
+
+/// the grid lines of `iso_transform_stack`
+fn draw_grid(screen: &mut WorldScreen, map: &TileMap) {
+    screen.fill(Rgb::WHITE);
+    screen.push_transform(map.projection());
+    let (x_range, y_range) = screen.visible_cell_range(1.0, 1.0, map.map_size());
+    let (x_start, x_end) = (x_range.start as f32, x_range.end as f32);
+    let (y_start, y_end) = (y_range.start as f32, y_range.end as f32);
+    let step = match screen.detail_level() {
+        DetailLevel::Full => 1,
+        DetailLevel::Simplified => 10,
+        DetailLevel::Minimal => usize::MAX,
+    };
+    for y in y_range.start..=y_range.end {
+        if y % step == 0 || y == map.height {
+            screen.draw_horizontal_line_f32(y as f32, x_start, x_end, Rgb::BLUE);
+        }
+    }
+    for x in x_range.start..=x_range.end {
+        if x % step == 0 || x == map.width {
+            screen.draw_vertical_line_f32(x as f32, y_start, y_end, Rgb::BLUE);
+        }
+    }
+    screen.pop_transform();
+}
+
+fn cell_corners(x: usize, y: usize) -> [(f32, f32); 4] {
+    let (x, y) = (x as f32, y as f32);
+    [(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)]
+}
+
+/// filled tiles with a grid on top, like `ui_widgets` and `debug_overlay`
+fn draw_filled(screen: &mut WorldScreen, map: &TileMap) {
+    screen.fill(Rgb::WHITE);
+    screen.push_transform(map.projection());
+    let (x_range, y_range) = screen.visible_cell_range(1.0, 1.0, map.map_size());
+    for y in y_range.clone() {
+        for x in x_range.clone() {
+            screen.fill_convex_polygon_f32(&cell_corners(x, y), GRASS);
+        }
+    }
+    for y in y_range.start..=y_range.end {
+        let y = y as f32;
+        screen.draw_diagonal_line_f32(x_range.start as f32, y, x_range.end as f32, y, Rgb::BLACK);
+    }
+    for x in x_range.start..=x_range.end {
+        let x = x as f32;
+        screen.draw_diagonal_line_f32(x, y_range.start as f32, x, y_range.end as f32, Rgb::BLACK);
+    }
+    screen.pop_transform();
+}
+
+/// `textured_tiles`: every tile is two textured triangles
+fn draw_textured(screen: &mut WorldScreen, map: &TileMap, texture: &Canvas) {
+    screen.fill(Rgb::WHITE);
+    screen.push_transform(map.projection());
+    let (x_range, y_range) = screen.visible_cell_range(1.0, 1.0, map.map_size());
+    for y in y_range {
+        for x in x_range.clone() {
+            screen.draw_textured_quad(&cell_corners(x, y), texture);
+        }
+    }
+    screen.pop_transform();
+}
+
+fn frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frames");
+    group.sample_size(20);
+
+    let big = TileMap::new(Orientation::Isometric, 1000, 1000, 80, 40);
+    for (name, zoom) in [("grid", 1.0), ("grid_zoomed_out", 0.3), ("grid_whole_map", 0.05)].iter() {
+        let mut screen = screen(&big, *zoom);
+        group.bench_function(*name, |b| b.iter(|| draw_grid(black_box(&mut screen), &big)));
+    }
+
+    let map = TileMap::new(Orientation::Isometric, 100, 100, 64, 32);
+    let mut screen = screen(&map, 1.0);
+    group.bench_function("filled_tiles", |b| b.iter(|| draw_filled(black_box(&mut screen), &map)));
+
+    let mut texture = Canvas::new(32, 32, 3);
+    for y in 0..32 {
+        for x in 0..32 {
+            texture.set_pixel(x, y, if (x / 8 + y / 8) % 2 == 0 { GRASS } else { Rgb::WHITE });
+        }
+    }
+    group.bench_function("textured_tiles", |b| b.iter(|| draw_textured(black_box(&mut screen), &map, &texture)));
+
+    let heights = common::rolling_hills(map.width, map.height);
+    let projection = map.projection();
+    group.bench_function("elevation", |b| {
+        b.iter(|| {
+            screen.fill(Rgb::WHITE);
+            let cells = heights.visible_cells(&projection, &screen.visible_world_rect());
+            heights.draw(&projection, cells, black_box(&mut screen), |(x, y)| GRASS.shade(0.6 + heights.get(x, y) / 100.0));
+        })
+    });
+
+    let entities = common::entities();
+    group.bench_function("entities", |b| {
+        b.iter(|| {
+            draw_filled(&mut screen, &map);
+            let visible = screen.visible_world_rect();
+            entities.draw(&projection, Some(&visible), black_box(&mut screen));
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, frames);
+criterion_main!(benches);
@@ -0,0 +1,118 @@
+//! converting between world and screen coordinates, and finding what
+//! is under the mouse, the way the iso examples do every frame.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use simple_game_examples::tile_map::{Orientation, TileMap};
+use simple_game_examples::world_screen::WorldScreen;
+use simple_game_examples::Canvas;
+
+mod common;
+
+const WIDTH: usize = 1920;
+const HEIGHT: usize = 1080;
+const POINTS: usize = 10_000;
+
+/// panned, zoomed in and a bit rotated, so none of the math is skipped
+fn screen() -> WorldScreen {
+    let mut screen = WorldScreen::default();
+    screen.canvas = Canvas::new(WIDTH, HEIGHT, 4);
+    screen.screen_width = WIDTH;
+    screen.screen_height = HEIGHT;
+    screen.pan_offset_x = 300.0;
+    screen.pan_offset_y = 200.0;
+    screen.scale_x = 1.5;
+    screen.scale_y = 1.5;
+    screen.rotation = 0.3;
+    screen
+}
+
+/// points spread over the whole screen, in a fixed order
+fn screen_points() -> Vec<(f32, f32)> {
+    (0..POINTS)
+        .map(|i| ((i * 7919 % WIDTH) as f32 + 0.5, (i * 104_729 % HEIGHT) as f32 + 0.5))
+        .collect()
+}
+
+fn transforms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transforms");
+    group.throughput(Throughput::Elements(POINTS as u64));
+    let map = TileMap::new(Orientation::Isometric, 1000, 1000, 80, 40);
+    let mut screen = screen();
+    screen.push_transform(map.projection());
+    let points = screen_points();
+    let world: Vec<(f32, f32)> = points.iter().map(|(x, y)| screen.screen_to_world(*x, *y)).collect();
+
+    group.bench_function("screen_to_world", |b| {
+        b.iter(|| {
+            for (x, y) in points.iter() {
+                black_box(screen.screen_to_world(*x, *y));
+            }
+        })
+    });
+    group.bench_function("world_to_screen", |b| {
+        b.iter(|| {
+            for (x, y) in world.iter() {
+                black_box(screen.world_to_screen(*x, *y));
+            }
+        })
+    });
+    group.bench_function("world_to_screen_f32", |b| {
+        b.iter(|| {
+            for (x, y) in world.iter() {
+                black_box(screen.world_to_screen_f32(*x, *y));
+            }
+        })
+    });
+    // what callers should do with a batch: get the transform once
+    group.bench_function("full_transform_apply", |b| {
+        b.iter(|| {
+            let transform = screen.full_transform();
+            for (x, y) in world.iter() {
+                black_box(transform.apply(*x, *y));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn picking(c: &mut Criterion) {
+    let mut group = c.benchmark_group("picking");
+    group.throughput(Throughput::Elements(POINTS as u64));
+    let map = TileMap::new(Orientation::Isometric, 100, 100, 64, 32);
+    let projection = map.projection();
+    let screen = screen();
+    let world: Vec<(f32, f32)> = screen_points().iter().map(|(x, y)| screen.screen_to_world(*x, *y)).collect();
+
+    group.bench_function("iso_cell", |b| {
+        b.iter(|| {
+            for (x, y) in world.iter() {
+                black_box(map.cell_at(*x, *y));
+            }
+        })
+    });
+
+    let heights = common::rolling_hills(100, 100);
+    group.bench_function("iso_elevation", |b| {
+        b.iter(|| {
+            for (x, y) in world.iter() {
+                black_box(heights.pick(&projection, *x, *y));
+            }
+        })
+    });
+
+    let entities = common::entities();
+    // every pick sorts the entities under the point, so it is a lot
+    // slower than the others. fewer points keep the run time sane.
+    let few = &world[..POINTS / 100];
+    group.throughput(Throughput::Elements(few.len() as u64));
+    group.bench_function("iso_entities", |b| {
+        b.iter(|| {
+            for (x, y) in few.iter() {
+                black_box(entities.pick(&projection, *x, *y));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, transforms, picking);
+criterion_main!(benches);
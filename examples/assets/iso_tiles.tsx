@@ -0,0 +1,4 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="iso_tiles" tilewidth="64" tileheight="32" tilecount="8" columns="4">
+ <image source="iso_tiles.png" width="256" height="64"/>
+</tileset>
@@ -0,0 +1,215 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{TileMap, Orientation};
+use simple_game_examples::debug::DebugOverlay;
+
+const GRASS_COLOR: Rgb = Rgb { red: 120, green: 180, blue: 90 };
+
+/// everything the console commands can change
+#[derive(Default)]
+pub struct Game {
+    pub map: TileMap,
+    pub selected: Option<(usize, usize)>,
+    pub show_grid: bool,
+    canvas: WorldScreen,
+}
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub game: Game,
+    /// kept next to the game rather than in it, so the console can
+    /// borrow the game mutably while it runs a command
+    pub debug: DebugOverlay<Game>,
+}
+
+fn parse_cell(map: &TileMap, args: &[&str]) -> Result<(usize, usize), String> {
+    if args.len() != 2 {
+        return Err("expected a cell: x y".into());
+    }
+    let x: usize = args[0].parse().map_err(|_| format!("'{}' is not a number", args[0]))?;
+    let y: usize = args[1].parse().map_err(|_| format!("'{}' is not a number", args[1]))?;
+    if x >= map.width || y >= map.height {
+        return Err(format!("{}, {} is outside of the {}x{} map", x, y, map.width, map.height));
+    }
+    Ok((x, y))
+}
+
+fn register_commands(debug: &mut DebugOverlay<Game>) {
+    let console = &mut debug.console;
+    console.register("select", "x y, selects a cell", |game, args| {
+        let cell = parse_cell(&game.map, args)?;
+        game.selected = Some(cell);
+        Ok(format!("selected {:?}", cell))
+    });
+    console.register("goto", "x y, centers the view on a cell", |game, args| {
+        let (x, y) = parse_cell(&game.map, args)?;
+        let (world_x, world_y) = game.map.projection().apply(x as f32 + 0.5, y as f32 + 0.5);
+        let screen = &mut game.canvas;
+        screen.pan_offset_x = world_x - screen.screen_width as f32 / 2.0 / screen.scale_x;
+        screen.pan_offset_y = world_y - screen.screen_height as f32 / 2.0 / screen.scale_y;
+        Ok(format!("looking at {}, {}", x, y))
+    });
+    console.register("zoom", "z, sets the zoom (1 is no zoom)", |game, args| {
+        let zoom: f32 = args.first().and_then(|z| z.parse().ok()).ok_or("expected a number")?;
+        if zoom <= 0.0 {
+            return Err("the zoom has to be more than 0".into());
+        }
+        game.canvas.scale_x = zoom;
+        game.canvas.scale_y = zoom;
+        Ok(format!("zoom is {}", zoom))
+    });
+    console.register("grid", "on|off, shows or hides the grid", |game, args| {
+        game.show_grid = match args.first() {
+            Some(&"on") => true,
+            Some(&"off") => false,
+            _ => return Err("expected on or off".into()),
+        };
+        Ok(String::new())
+    });
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            if self.debug.handle_event(&ev, &mut self.game) {
+                continue;
+            }
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.game.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        self.debug.timer.tick();
+        let game = &mut self.game;
+        if self.mouse_is_dragging {
+            game.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        game.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        let (world_x, world_y) = game.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        let hovered = game.map.cell_at(world_x, world_y);
+        if self.mouse_was_clicked {
+            game.selected = hovered;
+        }
+
+        game.canvas.fill(Rgb::WHITE);
+        game.canvas.push_transform(game.map.projection());
+        let (x_range, y_range) = game.canvas.visible_cell_range(1.0, 1.0, game.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let color = if game.selected == Some((x, y)) { Rgb::RED } else { GRASS_COLOR };
+                let (fx, fy) = (x as f32, y as f32);
+                let corners = [(fx, fy), (fx + 1.0, fy), (fx + 1.0, fy + 1.0), (fx, fy + 1.0)];
+                game.canvas.fill_convex_polygon_f32(&corners, color);
+            }
+        }
+        if game.show_grid {
+            for y in y_range.start..=y_range.end {
+                let y = y as f32;
+                game.canvas.draw_diagonal_line_f32(x_range.start as f32, y, x_range.end as f32, y, Rgb::BLACK);
+            }
+            for x in x_range.start..=x_range.end {
+                let x = x as f32;
+                game.canvas.draw_diagonal_line_f32(x, y_range.start as f32, x, y_range.end as f32, Rgb::BLACK);
+            }
+        }
+        game.canvas.pop_transform();
+
+        // instead of printing these to stdout every frame
+        self.debug.watch_screen(&game.canvas, self.mouse_x, self.mouse_y);
+        self.debug.watch("hovered", format!("{:?}", hovered));
+        self.debug.watch("selected", format!("{:?}", game.selected));
+        let (width, height) = (game.canvas.screen_width, game.canvas.screen_height);
+        self.debug.draw(&mut game.canvas.canvas, width, height);
+
+        backend::TextureUpdate::UpdateWhole(game.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        let canvas = &mut self.game.canvas;
+        canvas.canvas = new_canvas;
+        canvas.screen_width = width;
+        canvas.screen_height = height;
+        canvas.scale_x = 1.0;
+        canvas.scale_y = 1.0;
+        canvas.scale_factor_up = 1.040;
+        canvas.scale_factor_down = 0.960;
+        let data = canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    let mut debug = DebugOverlay::new();
+    register_commands(&mut debug);
+    debug.console.print("type help for a list of commands");
+    println!("`: toggle the debug overlay and console, right click: select a cell, left drag: pan, scroll: zoom");
+    let my_loop = MyGameLoop {
+        game: Game {
+            map: TileMap::new(Orientation::Isometric, 40, 40, 48, 24),
+            show_grid: true,
+            ..Default::default()
+        },
+        debug,
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "debug_overlay".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
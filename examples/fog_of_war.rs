@@ -0,0 +1,217 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{Cell, Tile, TileLayer, TileMap, Orientation};
+use simple_game_examples::fov::{self, CellVisibility, FogOfWar};
+
+const FLOOR: u32 = 1;
+const WALL: u32 = 2;
+const FLOOR_COLOR: Rgb = Rgb { red: 200, green: 190, blue: 160 };
+const WALL_COLOR: Rgb = Rgb { red: 100, green: 90, blue: 80 };
+const VIEW_RADIUS: usize = 8;
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map: TileMap,
+    pub player: Cell,
+    pub fog: FogOfWar,
+    canvas: WorldScreen,
+}
+
+fn is_wall(map: &TileMap, cell: Cell) -> bool {
+    map.layers[0].get(cell.0, cell.1).map(|t| t.gid == WALL).unwrap_or(true)
+}
+
+/// explored cells are drawn darker than the ones that are visible right now
+fn darken(color: Rgb) -> Rgb {
+    Rgb { red: color.red / 3, green: color.green / 3, blue: color.blue / 3 }
+}
+
+impl MyGameLoop {
+    fn move_player(&mut self, dx: isize, dy: isize) {
+        let x = self.player.0 as isize + dx;
+        let y = self.player.1 as isize + dy;
+        if x < 0 || y < 0 {
+            return;
+        }
+        let next = (x as usize, y as usize);
+        if !is_wall(&self.map, next) {
+            self.player = next;
+            self.update_fog();
+        }
+    }
+
+    fn update_fog(&mut self) {
+        let map = &self.map;
+        self.fog.update(self.player, VIEW_RADIUS, |cell| is_wall(map, cell));
+    }
+
+    fn draw_cell(&mut self, x: f32, y: f32, color: Rgb) {
+        let corners = [(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)];
+        self.canvas.fill_convex_polygon_f32(&corners, color);
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { code, .. } => {
+                    match code {
+                        KeyCode::W | KeyCode::Up => self.move_player(0, -1),
+                        KeyCode::S | KeyCode::Down => self.move_player(0, 1),
+                        KeyCode::A | KeyCode::Left => self.move_player(-1, 0),
+                        KeyCode::D | KeyCode::Right => self.move_player(1, 0),
+                        KeyCode::R => {
+                            self.fog.reset();
+                            self.update_fog();
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.canvas.fill(Rgb::BLACK);
+
+        self.canvas.push_transform(self.map.projection());
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let color = if is_wall(&self.map, (x, y)) { WALL_COLOR } else { FLOOR_COLOR };
+                let color = match self.fog.get((x, y)) {
+                    CellVisibility::Explored => darken(color),
+                    _ => color,
+                };
+                self.draw_cell(x as f32, y as f32, color);
+            }
+        }
+        // the whole map is drawn, and then the fog covers
+        // the cells that were never seen
+        self.fog.draw((x_range, y_range), &mut self.canvas, Rgb::BLACK, None);
+
+        // show the line of sight from the player to the hovered cell
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        if let Some(target) = self.map.cell_at(world_x, world_y) {
+            let map = &self.map;
+            let can_see = fov::line_of_sight(self.player, target, |cell| is_wall(map, cell));
+            let color = if can_see { Rgb::GREEN } else { Rgb::RED };
+            let line = fov::sight_line(self.player, target);
+            for (x, y) in line.into_iter().skip(1) {
+                let (x, y) = (x as f32, y as f32);
+                let corners = [(x + 0.35, y + 0.35), (x + 0.65, y + 0.35), (x + 0.65, y + 0.65), (x + 0.35, y + 0.65)];
+                self.canvas.fill_convex_polygon_f32(&corners, color);
+            }
+        }
+
+        let (x, y) = (self.player.0 as f32, self.player.1 as f32);
+        let corners = [(x + 0.2, y + 0.2), (x + 0.8, y + 0.2), (x + 0.8, y + 0.8), (x + 0.2, y + 0.8)];
+        self.canvas.fill_convex_polygon_f32(&corners, Rgb::BLUE);
+        self.canvas.pop_transform();
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::BLACK;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+/// a grid of rooms connected by doors, with a few pillars
+fn build_map(width: usize, height: usize) -> TileMap {
+    let mut map = TileMap::new(Orientation::Orthogonal, width, height, 16, 16);
+    let mut layer = TileLayer::new("walls", width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            let room_wall = (x % 10 == 0 && y % 10 != 5) || (y % 10 == 0 && x % 10 != 5);
+            let pillar = x % 10 == 3 && y % 10 == 3;
+            let gid = if border || room_wall || pillar { WALL } else { FLOOR };
+            layer.set(x, y, Tile::new(gid));
+        }
+    }
+    map.layers.push(layer);
+    map
+}
+
+fn main() {
+    let (width, height) = (41, 31);
+    let mut my_loop = MyGameLoop {
+        map: build_map(width, height),
+        player: (5, 5),
+        fog: FogOfWar::new(width, height),
+        ..Default::default()
+    };
+    my_loop.update_fog();
+    println!("WASD/arrows: move, hover: line of sight, R: forget explored cells, left drag: pan, scroll: zoom");
+
+    let my_conf = BackendConf {
+        window_title: "fog_of_war".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,175 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, FbBackend};
+use backend::evdev::{self, InputDevices};
+use backend::fb::{Framebuffer, FbLayout};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{TileMap, Orientation};
+use std::fs::File;
+use std::path::Path;
+
+const GRASS_COLOR: Rgb = Rgb { red: 120, green: 180, blue: 90 };
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map: TileMap,
+    pub selected: Option<(usize, usize)>,
+    canvas: WorldScreen,
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.canvas.fill(Rgb::WHITE);
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        let hovered = self.map.cell_at(world_x, world_y);
+        self.canvas.push_transform(self.map.projection());
+        if self.mouse_was_clicked {
+            self.selected = hovered;
+        }
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let color = if self.selected == Some((x, y)) {
+                    Rgb::RED
+                } else if hovered == Some((x, y)) {
+                    GRASS_COLOR.shade(1.2)
+                } else {
+                    GRASS_COLOR
+                };
+                let (fx, fy) = (x as f32, y as f32);
+                let corners = [(fx, fy), (fx + 1.0, fy), (fx + 1.0, fy + 1.0), (fx, fy + 1.0)];
+                self.canvas.fill_convex_polygon_f32(&corners, color);
+            }
+        }
+        self.canvas.pop_transform();
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let canvas = &mut self.canvas;
+        canvas.canvas = Canvas::new(width, height, bpp);
+        canvas.screen_width = width;
+        canvas.screen_height = height;
+        canvas.scale_x = 1.0;
+        canvas.scale_y = 1.0;
+        canvas.scale_factor_up = 1.040;
+        canvas.scale_factor_down = 0.960;
+        canvas.fill(Rgb::WHITE);
+        canvas.access_data().to_vec()
+    }
+}
+
+/// runs the game on a file instead of a screen, with the mouse read from
+/// another file, and saves what ended up on the "screen" as a png
+fn run_fake(dir: &Path, conf: BackendConf, my_loop: MyGameLoop) -> std::io::Result<()> {
+    // a 16 bit framebuffer, so the frames have to be converted on the way
+    let layout = FbLayout::rgb565(320, 240);
+    let framebuffer = Framebuffer::open_file(dir.join("fb0"), layout)?;
+
+    // a mouse that moves onto a cell and right clicks it, as 3 packets
+    let mouse_path = dir.join("mouse");
+    let mut mouse = File::create(&mouse_path)?;
+    evdev::write_event(&mut mouse, evdev::EV_REL, evdev::REL_X, -30)?;
+    evdev::write_event(&mut mouse, evdev::EV_REL, evdev::REL_Y, -60)?;
+    evdev::write_event(&mut mouse, evdev::EV_SYN, evdev::SYN_REPORT, 0)?;
+    evdev::write_event(&mut mouse, evdev::EV_KEY, evdev::BTN_RIGHT, 1)?;
+    evdev::write_event(&mut mouse, evdev::EV_SYN, evdev::SYN_REPORT, 0)?;
+    evdev::write_event(&mut mouse, evdev::EV_KEY, evdev::BTN_RIGHT, 0)?;
+    evdev::write_event(&mut mouse, evdev::EV_SYN, evdev::SYN_REPORT, 0)?;
+    drop(mouse);
+
+    let input = InputDevices::open(&[&mouse_path]);
+    let mut backend = FbBackend::new(&conf, framebuffer, input, my_loop);
+    backend.run(Some(3));
+    println!("selected {:?}", backend.game_loop().selected);
+
+    let fb = &backend.framebuffer;
+    let image = image::RgbImage::from_fn(layout.width as u32, layout.height as u32, |x, y| {
+        let color = fb.get_pixel(x as usize, y as usize);
+        image::Rgb([color.red, color.green, color.blue])
+    });
+    let png_path = dir.join("fb0.png");
+    image.save(&png_path).expect("failed to save png");
+    println!("the fake framebuffer is at {}, and as a png at {}", dir.join("fb0").display(), png_path.display());
+    Ok(())
+}
+
+fn main() {
+    let my_loop = MyGameLoop {
+        map: TileMap::new(Orientation::Isometric, 10, 10, 48, 24),
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "framebuffer".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: true,
+        window_resizable: false,
+    };
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--fake") {
+        let dir = std::env::temp_dir();
+        run_fake(&dir, my_conf, my_loop).expect("failed to run on a fake framebuffer");
+        return;
+    }
+    println!("right click: select a cell, left drag: pan, scroll: zoom, ctrl+c: quit");
+    FbBackend::start(my_conf, my_loop);
+}
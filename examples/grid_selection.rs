@@ -0,0 +1,210 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode, KeyMods};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{TileMap, Orientation};
+use simple_game_examples::selection::{DragSelect, DragShape, Selection, SelectionMode};
+
+const GRID_COLOR: Rgb = Rgb { red: 180, green: 180, blue: 180 };
+const SELECTED_COLOR: Rgb = Rgb { red: 255, green: 230, blue: 150 };
+const ORANGE: Rgb = Rgb { red: 230, green: 120, blue: 0 };
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    /// mouse events don't say which modifier keys are held, so keep
+    /// track of them from the key events
+    pub modifiers: KeyMods,
+
+    pub map: TileMap,
+    pub selection: Selection,
+    /// the rectangle or lasso being dragged out, if any
+    pub drag: Option<DragSelect>,
+    canvas: WorldScreen,
+}
+
+impl MyGameLoop {
+    fn track_modifiers(&mut self, modifier: KeyMods, code: KeyCode, down: bool) {
+        self.modifiers = modifier;
+        match code {
+            KeyCode::LeftShift | KeyCode::RightShift => self.modifiers.shift = down,
+            KeyCode::LeftControl | KeyCode::RightControl => self.modifiers.ctrl = down,
+            KeyCode::LeftAlt | KeyCode::RightAlt => self.modifiers.alt = down,
+            _ => {}
+        }
+    }
+
+    fn finish_drag(&mut self) {
+        if let Some(drag) = self.drag.take() {
+            self.canvas.push_transform(self.map.projection());
+            let cells = drag.cells(&self.canvas, self.map.map_size());
+            self.canvas.pop_transform();
+            self.selection.apply(drag.mode, cells);
+            println!("{} cells selected", self.selection.len());
+        }
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => {
+                    if let Some(drag) = self.drag.as_mut() {
+                        drag.drag_to(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseDown { button, x, y } => {
+                    match button {
+                        backend::MouseButton::Left if self.drag.is_none() => {
+                            // alt drags a lasso instead of a rectangle
+                            let shape = if self.modifiers.alt { DragShape::Lasso } else { DragShape::Rectangle };
+                            let mode = SelectionMode::from_mods(&self.modifiers);
+                            self.drag = Some(DragSelect::new(shape, mode, x, y));
+                        }
+                        backend::MouseButton::Right | backend::MouseButton::Middle if !self.mouse_is_dragging => {
+                            self.mouse_is_dragging = true;
+                            self.canvas.reset_pan(x, y);
+                        }
+                        _ => {}
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        if let Some(drag) = self.drag.as_mut() {
+                            drag.drag_to(x, y);
+                        }
+                        self.finish_drag();
+                    } else {
+                        self.mouse_is_dragging = false;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { modifier, code, .. } => {
+                    self.track_modifiers(modifier, code, true);
+                    match code {
+                        KeyCode::Escape => self.selection.clear(),
+                        KeyCode::A if self.modifiers.ctrl => {
+                            let (width, height) = self.map.map_size();
+                            let all = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+                            self.selection.apply(SelectionMode::Replace, all);
+                        }
+                        KeyCode::O => {
+                            self.map.orientation = match self.map.orientation {
+                                Orientation::Isometric => Orientation::Orthogonal,
+                                Orientation::Orthogonal => Orientation::Isometric,
+                            };
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                Event::KeyUp { modifier, code } => {
+                    self.track_modifiers(modifier, code, false);
+                    continue;
+                }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.canvas.fill(Rgb::WHITE);
+
+        self.canvas.push_transform(self.map.projection());
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+        // the selection is drawn first, so the grid lines stay on top of it
+        self.selection.draw((x_range.clone(), y_range.clone()), &mut self.canvas, Some(SELECTED_COLOR), SELECTED_COLOR);
+        for y in y_range.start..=y_range.end {
+            let y = y as f32;
+            self.canvas.draw_diagonal_line_f32(x_range.start as f32, y, x_range.end as f32, y, GRID_COLOR);
+        }
+        for x in x_range.start..=x_range.end {
+            let x = x as f32;
+            self.canvas.draw_diagonal_line_f32(x, y_range.start as f32, x, y_range.end as f32, GRID_COLOR);
+        }
+        self.selection.draw((x_range.clone(), y_range.clone()), &mut self.canvas, None, ORANGE);
+
+        // preview which cells the drag would select, before letting go
+        if let Some(drag) = &self.drag {
+            let color = match drag.mode {
+                SelectionMode::Remove => Rgb::RED,
+                _ => Rgb::BLUE,
+            };
+            for (x, y) in drag.cells(&self.canvas, self.map.map_size()) {
+                let (x, y) = (x as f32, y as f32);
+                let corners = [(x + 0.4, y + 0.4), (x + 0.6, y + 0.4), (x + 0.6, y + 0.6), (x + 0.4, y + 0.6)];
+                self.canvas.fill_convex_polygon_f32(&corners, color);
+            }
+        }
+        self.canvas.pop_transform();
+
+        if let Some(drag) = &self.drag {
+            drag.draw(&mut self.canvas.canvas, Rgb::BLACK);
+        }
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    println!("left drag: select a rectangle, alt + left drag: lasso, click: select a cell");
+    println!("hold shift to add to the selection, ctrl to remove, both to toggle");
+    println!("ctrl+A: select all, escape: clear, O: switch between isometric and square, right drag: pan, scroll: zoom");
+    let my_loop = MyGameLoop {
+        map: TileMap::new(Orientation::Isometric, 30, 30, 48, 24),
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "grid_selection".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,182 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::hex::{Hex, HexLayout, HexOrientation, OffsetParity};
+
+const MAP_SIZE: (usize, usize) = (60, 40);
+const LIGHT_GRAY: Rgb = Rgb { red: 220, green: 220, blue: 220 };
+const LIGHT_BLUE: Rgb = Rgb { red: 170, green: 200, blue: 255 };
+const ORANGE: Rgb = Rgb { red: 255, green: 170, blue: 60 };
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub layout: HexLayout,
+    /// set with a right click. a line is drawn from here to the mouse.
+    pub anchor: Option<Hex>,
+    pub hovered: Option<Hex>,
+    canvas: WorldScreen,
+}
+
+impl MyGameLoop {
+    /// hexes are stored in offset coordinates, so the map is a rectangle
+    fn is_on_map(&self, hex: Hex) -> bool {
+        let (col, row) = self.layout.to_offset(hex);
+        col >= 0 && row >= 0 && (col as usize) < MAP_SIZE.0 && (row as usize) < MAP_SIZE.1
+    }
+
+    fn fill_on_map(&mut self, hex: Hex, color: Rgb) {
+        if self.is_on_map(hex) {
+            self.layout.fill_hex(hex, &mut self.canvas, color);
+        }
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { code: KeyCode::T, .. } => {
+                    self.layout.orientation = match self.layout.orientation {
+                        HexOrientation::Pointy => HexOrientation::Flat,
+                        HexOrientation::Flat => HexOrientation::Pointy,
+                    };
+                    continue;
+                }
+                Event::KeyDown { code: KeyCode::P, .. } => {
+                    self.layout.parity = match self.layout.parity {
+                        OffsetParity::Odd => OffsetParity::Even,
+                        OffsetParity::Even => OffsetParity::Odd,
+                    };
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.canvas.fill(Rgb::WHITE);
+
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        let hovered = Some(self.layout.world_to_hex(world_x, world_y)).filter(|h| self.is_on_map(*h));
+        if hovered != self.hovered {
+            if let (Some(anchor), Some(hex)) = (self.anchor, hovered) {
+                println!("{:?} -> {:?}: {} steps", anchor, hex, anchor.distance(hex));
+            }
+            self.hovered = hovered;
+        }
+        if self.mouse_was_clicked {
+            self.anchor = hovered;
+        }
+
+        if let Some(hex) = hovered {
+            for ring_hex in hex.ring(3) {
+                self.fill_on_map(ring_hex, LIGHT_GRAY);
+            }
+            for neighbor in hex.neighbors().iter() {
+                self.fill_on_map(*neighbor, LIGHT_BLUE);
+            }
+            if let Some(anchor) = self.anchor {
+                for line_hex in anchor.line_to(hex) {
+                    self.fill_on_map(line_hex, ORANGE);
+                }
+            }
+            self.fill_on_map(hex, Rgb::RED);
+        }
+        if let Some(anchor) = self.anchor {
+            self.fill_on_map(anchor, Rgb::GREEN);
+        }
+
+        // only draw the outlines of the hexes that are on screen
+        let (col_range, row_range) = self.layout.offset_range(&self.canvas.visible_world_rect(), MAP_SIZE);
+        for row in row_range {
+            for col in col_range.clone() {
+                let hex = self.layout.from_offset(col as i32, row as i32);
+                self.layout.draw_hex(hex, &mut self.canvas, Rgb::BLACK);
+            }
+        }
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    println!("left drag: pan, scroll: zoom, right click: set line start, T: pointy/flat, P: odd/even offset rows");
+    let my_loop = MyGameLoop {
+        layout: HexLayout::new(HexOrientation::Pointy, 20.0, (30.0, 30.0)),
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "hex_grid".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,203 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{Cell, TileMap, Orientation, PropertyValue};
+use simple_game_examples::elevation::HeightMap;
+
+const WATER_COLOR: Rgb = Rgb { red: 70, green: 120, blue: 200 };
+const SAND_COLOR: Rgb = Rgb { red: 220, green: 200, blue: 140 };
+const GRASS_COLOR: Rgb = Rgb { red: 110, green: 170, blue: 80 };
+const ROCK_COLOR: Rgb = Rgb { red: 150, green: 140, blue: 130 };
+const SNOW_COLOR: Rgb = Rgb { red: 240, green: 240, blue: 250 };
+const YELLOW: Rgb = Rgb { red: 255, green: 220, blue: 0 };
+/// world pixels per height level
+const STEP: f32 = 12.0;
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map: TileMap,
+    pub heights: HeightMap,
+    /// also outline the cell that picking without heights would choose
+    pub show_flat_pick: bool,
+    canvas: WorldScreen,
+}
+
+fn terrain_color(height: f32) -> Rgb {
+    match (height / STEP) as usize {
+        0 => WATER_COLOR,
+        1 => SAND_COLOR,
+        2..=4 => GRASS_COLOR,
+        5..=6 => ROCK_COLOR,
+        _ => SNOW_COLOR,
+    }
+}
+
+impl MyGameLoop {
+    fn hovered_cell(&self) -> Option<Cell> {
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        self.heights.pick(&self.map.projection(), world_x, world_y)
+    }
+
+    /// changes the height of the hovered cell by a number of levels. the
+    /// heights are kept as a cell property, so they are saved with the map.
+    fn raise(&mut self, levels: f32) {
+        if let Some((x, y)) = self.hovered_cell() {
+            let height = (self.heights.get(x, y) + levels * STEP).max(0.0);
+            self.heights.set(x, y, height);
+            self.map.set_cell_property(x, y, "height", PropertyValue::Float(height as f64));
+        }
+    }
+
+    fn outline(&mut self, points: &[(f32, f32)], color: Rgb) {
+        for i in 0..points.len() {
+            let (x1, y1) = points[i];
+            let (x2, y2) = points[(i + 1) % points.len()];
+            self.canvas.draw_thick_line_f32(x1, y1, x2, y2, 2.0, color);
+        }
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { code, .. } => {
+                    match code {
+                        KeyCode::U => self.raise(1.0),
+                        KeyCode::J => self.raise(-1.0),
+                        KeyCode::P => self.show_flat_pick = !self.show_flat_pick,
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.canvas.fill(Rgb::WHITE);
+
+        let projection = self.map.projection();
+        let cells = self.heights.visible_cells(&projection, &self.canvas.visible_world_rect());
+        let heights = &self.heights;
+        heights.draw(&projection, cells, &mut self.canvas, |(x, y)| terrain_color(heights.get(x, y)));
+
+        if self.show_flat_pick {
+            let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+            if let Some((x, y)) = self.map.cell_at(world_x, world_y) {
+                let (x, y) = (x as f32, y as f32);
+                let flat = [
+                    projection.apply(x, y),
+                    projection.apply(x + 1.0, y),
+                    projection.apply(x + 1.0, y + 1.0),
+                    projection.apply(x, y + 1.0),
+                ];
+                self.outline(&flat, Rgb::RED);
+            }
+        }
+        if let Some((x, y)) = self.hovered_cell() {
+            let top = self.heights.top_face(&projection, x, y);
+            self.outline(&top, YELLOW);
+        }
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    let (width, height) = (24, 24);
+    let mut map = TileMap::new(Orientation::Isometric, width, height, 48, 24);
+    // a couple of hills, stepped into levels, with water around them
+    let hills = [(8.0, 8.0, 7.0, 8.0), (16.0, 15.0, 6.0, 6.0)];
+    for y in 0..height {
+        for x in 0..width {
+            let level = hills.iter()
+                .map(|(hx, hy, radius, peak)| {
+                    let (dx, dy) = (x as f32 - hx, y as f32 - hy);
+                    let distance = (dx * dx + dy * dy).sqrt();
+                    (peak * (1.0 - distance / radius)).max(0.0)
+                })
+                .fold(0.0, f32::max)
+                .round();
+            map.set_cell_property(x, y, "height", PropertyValue::Float((level * STEP) as f64));
+        }
+    }
+    let heights = HeightMap::from_properties(&map, "height");
+
+    println!("U/J: raise/lower the hovered cell, P: also show what picking without heights would select, left drag: pan, scroll: zoom");
+    let my_loop = MyGameLoop {
+        map,
+        heights,
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "iso_elevation".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,245 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{Tile, TileLayer, TileMap, Orientation};
+use simple_game_examples::entity::{Entity, EntityId, EntityLayer, Sprite};
+
+const GRASS_COLOR: Rgb = Rgb { red: 120, green: 180, blue: 90 };
+const GRID_COLOR: Rgb = Rgb { red: 100, green: 160, blue: 75 };
+const TREE_COLOR: Rgb = Rgb { red: 40, green: 110, blue: 50 };
+const HOUSE_COLOR: Rgb = Rgb { red: 200, green: 120, blue: 90 };
+const WALL_COLOR: Rgb = Rgb { red: 170, green: 170, blue: 180 };
+const UNIT_COLOR: Rgb = Rgb { red: 60, green: 90, blue: 220 };
+const YELLOW: Rgb = Rgb { red: 255, green: 220, blue: 0 };
+/// cells per frame
+const UNIT_SPEED: f32 = 0.05;
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map: TileMap,
+    pub entities: EntityLayer,
+    pub selected: Option<EntityId>,
+    /// where each moving unit is walking to
+    targets: Vec<(EntityId, (f32, f32))>,
+    canvas: WorldScreen,
+}
+
+fn unit(name: &str, x: usize, y: usize) -> Entity {
+    let sprite = Sprite::Billboard { width: 16.0, height: 36.0, anchor: (8.0, 34.0), color: UNIT_COLOR };
+    Entity::new(name, (x as f32 + 0.25, y as f32 + 0.25), (0.5, 0.5), sprite)
+}
+
+fn tree(x: usize, y: usize) -> Entity {
+    let sprite = Sprite::Billboard { width: 28.0, height: 64.0, anchor: (14.0, 60.0), color: TREE_COLOR };
+    Entity::new("tree", (x as f32, y as f32), (1.0, 1.0), sprite)
+}
+
+impl MyGameLoop {
+    fn move_units(&mut self) {
+        let entities = &mut self.entities;
+        self.targets.retain(|(id, (target_x, target_y))| {
+            let entity = match entities.get_mut(*id) {
+                Some(e) => e,
+                None => return false,
+            };
+            let (x, y) = entity.position;
+            let (dx, dy) = (target_x - x, target_y - y);
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance <= UNIT_SPEED {
+                entity.position = (*target_x, *target_y);
+                return false;
+            }
+            entity.position = (x + dx / distance * UNIT_SPEED, y + dy / distance * UNIT_SPEED);
+            true
+        });
+    }
+
+    /// right click selects the entity under the mouse, or sends the
+    /// selected unit to the clicked cell
+    fn click(&mut self, world_x: f32, world_y: f32) {
+        let projection = self.map.projection();
+        if let Some(id) = self.entities.pick(&projection, world_x, world_y) {
+            let entity = self.entities.get(id).unwrap();
+            println!("selected {} at {:?}", entity.name, entity.position);
+            self.selected = Some(id);
+            return;
+        }
+        let selected = self.selected.and_then(|id| self.entities.get(id));
+        if let (Some(entity), Some((x, y))) = (selected, self.map.cell_at(world_x, world_y)) {
+            if let Sprite::Billboard { .. } = entity.sprite {
+                let target = (x as f32 + 0.25, y as f32 + 0.25);
+                let id = entity.id;
+                self.targets.retain(|(moving, _)| *moving != id);
+                self.targets.push((id, target));
+            }
+        }
+    }
+
+    fn draw_cell(&mut self, x: f32, y: f32, color: Rgb) {
+        let corners = [(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)];
+        self.canvas.fill_convex_polygon_f32(&corners, color);
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        if self.mouse_was_clicked {
+            self.click(world_x, world_y);
+        }
+        self.move_units();
+
+        self.canvas.fill(Rgb::WHITE);
+        let projection = self.map.projection();
+        self.canvas.push_transform(projection);
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                self.draw_cell(x as f32, y as f32, GRASS_COLOR);
+            }
+        }
+        for y in y_range.start..=y_range.end {
+            let y = y as f32;
+            self.canvas.draw_diagonal_line_f32(x_range.start as f32, y, x_range.end as f32, y, GRID_COLOR);
+        }
+        for x in x_range.start..=x_range.end {
+            let x = x as f32;
+            self.canvas.draw_diagonal_line_f32(x, y_range.start as f32, x, y_range.end as f32, GRID_COLOR);
+        }
+        // mark the ground under the selected entity
+        if let Some(entity) = self.selected.and_then(|id| self.entities.get(id)) {
+            let (min_x, min_y, max_x, max_y) = entity.bounds();
+            let corners = [(min_x, min_y), (max_x, min_y), (max_x, max_y), (min_x, max_y)];
+            self.canvas.fill_convex_polygon_f32(&corners, YELLOW);
+        }
+        self.canvas.pop_transform();
+
+        // entities are drawn in world coordinates, with the projection
+        // passed in, since their heights are in world pixels
+        let visible = self.canvas.visible_world_rect();
+        self.entities.draw(&projection, Some(&visible), &mut self.canvas);
+
+        // outline whatever is under the mouse
+        if let Some(entity) = self.entities.pick(&projection, world_x, world_y).and_then(|id| self.entities.get(id)) {
+            let outline = entity.outline(&projection);
+            for i in 0..outline.len() {
+                let (x1, y1) = outline[i];
+                let (x2, y2) = outline[(i + 1) % outline.len()];
+                self.canvas.draw_thick_line_f32(x1, y1, x2, y2, 2.0, YELLOW);
+            }
+        }
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    let (width, height) = (16, 16);
+    let mut map = TileMap::new(Orientation::Isometric, width, height, 64, 32);
+    let mut ground = TileLayer::new("ground", width, height);
+    for y in 0..height {
+        for x in 0..width {
+            ground.set(x, y, Tile::new(1));
+        }
+    }
+    map.layers.push(ground);
+
+    let mut entities = EntityLayer::new();
+    // a 3x2 house, and a long thin wall that units can walk on either side of
+    entities.add(Entity::new("house", (6.0, 4.0), (3.0, 2.0), Sprite::Block { height: 40.0, color: HOUSE_COLOR }));
+    entities.add(Entity::new("wall", (3.0, 9.0), (8.0, 1.0), Sprite::Block { height: 24.0, color: WALL_COLOR }));
+    for (x, y) in [(2, 2), (3, 5), (12, 3), (13, 11), (6, 13), (11, 7)].iter() {
+        entities.add(tree(*x, *y));
+    }
+    let first = entities.add(unit("knight", 5, 7));
+    entities.add(unit("archer", 9, 11));
+
+    println!("right click: select an entity, or send the selected unit to a cell. left drag: pan, scroll: zoom");
+    let my_loop = MyGameLoop {
+        map,
+        entities,
+        selected: Some(first),
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "iso_entities".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,167 @@
+use simple_game_examples::backend;
+use simple_game_examples::Rgb;
+use backend::{GameLoop, Backend, BackendConf, Event};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw, transform::Transform};
+use simple_game_examples::layer::{Layer, Layers, LayerSpace};
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map_size: (usize, usize),
+    pub iso_projection: Transform,
+    pub selected: Option<(usize, usize)>,
+
+    canvas: WorldScreen,
+    layers: Layers,
+}
+
+fn draw_cell_outline(screen: &mut WorldScreen, x: f32, y: f32, color: Rgb) {
+    screen.draw_horizontal_line_f32(y, x, x + 1.0, color);
+    screen.draw_horizontal_line_f32(y + 1.0, x, x + 1.0, color);
+    screen.draw_vertical_line_f32(x, y, y + 1.0, color);
+    screen.draw_vertical_line_f32(x + 1.0, y, y + 1.0, color);
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        // if the camera moved, the world space layers get invalidated:
+        self.layers.update_view(&self.canvas);
+
+        self.canvas.push_transform(self.iso_projection);
+
+        let (map_x, map_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        let (width, height) = self.map_size;
+        let selected = if map_x >= 0.0 && map_y >= 0.0 && map_x < width as f32 && map_y < height as f32 {
+            Some((map_x as usize, map_y as usize))
+        } else {
+            None
+        };
+        if selected != self.selected {
+            self.selected = selected;
+            self.layers.invalidate("selection");
+        }
+
+        // the map is only redrawn when the camera moves. moving
+        // the mouse around only redraws the selection layer.
+        let map_size = self.map_size;
+        self.layers.draw_world("map", &mut self.canvas, |screen| {
+            let (x_range, y_range) = screen.visible_cell_range(1.0, 1.0, map_size);
+            for y in y_range.start..=y_range.end {
+                screen.draw_horizontal_line_f32(y as f32, x_range.start as f32, x_range.end as f32, Rgb::BLUE);
+            }
+            for x in x_range.start..=x_range.end {
+                screen.draw_vertical_line_f32(x as f32, y_range.start as f32, y_range.end as f32, Rgb::BLUE);
+            }
+        });
+        self.layers.draw_world("selection", &mut self.canvas, |screen| {
+            if let Some((x, y)) = selected {
+                draw_cell_outline(screen, x as f32, y as f32, Rgb::RED);
+            }
+        });
+        self.canvas.pop_transform();
+
+        // the ui layer is in screen space, and is never invalidated
+        // so it is only ever drawn once:
+        self.layers.draw_screen("ui", |canvas| {
+            for y in 10..60 {
+                canvas.draw_horizontal_line(y, 10, 200, Rgb::BLACK);
+            }
+        });
+
+        if self.layers.composite() {
+            backend::TextureUpdate::UpdateWhole(self.layers.output().access_data())
+        } else {
+            // nothing changed, so the backend can keep showing the last frame
+            backend::TextureUpdate::None
+        }
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+
+        self.layers = Layers::new(width, height, bpp, Rgb::WHITE);
+        self.layers.add(Layer::new("map", LayerSpace::World, 0));
+        self.layers.add(Layer::new("selection", LayerSpace::World, 1));
+        let mut ui = Layer::new("ui", LayerSpace::Screen, 2);
+        ui.opacity = 0.5;
+        self.layers.add(ui);
+
+        self.layers.composite();
+        self.layers.output().access_data().to_vec()
+    }
+}
+
+fn main() {
+    let my_loop = MyGameLoop {
+        map_size: (20, 10),
+        iso_projection: Transform::isometric(80.0, 40.0, 440.0, 40.0),
+        ..Default::default()
+    };
+
+    let my_conf = BackendConf {
+        window_title: "iso_layers".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
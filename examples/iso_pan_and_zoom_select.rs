@@ -1,7 +1,8 @@
 use simple_game_examples::backend;
 use simple_game_examples::{Rgb, Canvas};
 use backend::{GameLoop, Backend, BackendConf, Event};
-use simple_game_examples::{world_screen::WorldScreen, draw::Draw, Point, LineSegment};
+use simple_game_examples::{world_screen::{WorldScreen, WorldRect}, draw::Draw, Point, LineSegment};
+use std::ops::Range;
 
 #[derive(Debug, Default)]
 pub struct Tile {
@@ -159,6 +160,31 @@ impl GameMap {
         Some((selected_cell_x, selected_cell_y))
     }
 
+    /// the inverse of transform_coordinate, but without snapping to a cell.
+    /// returns fractional map coordinates for any point in the world.
+    pub fn world_to_map_f32(&self, x: f32, y: f32) -> (f32, f32) {
+        let half_w = (self.tile.width / 2) as f32;
+        let half_h = (self.tile.height / 2) as f32;
+        // the top corner of cell (0, 0) is the middle of the top
+        // of the tiles bounding box
+        let px = (x - self.shift_x as f32 - self.tile.pt1.0 as f32) / half_w;
+        let py = (y - self.shift_y as f32 - self.tile.pt1.1 as f32) / half_h;
+        ((py + px) / 2.0, (py - px) / 2.0)
+    }
+
+    /// the range of cells that overlap the visible world rectangle.
+    /// the corners of the (axis aligned) world rectangle are diamond shaped
+    /// in map space, so we take the bounding box of all 4 corners.
+    pub fn visible_cells(&self, visible: &WorldRect) -> (Range<usize>, Range<usize>) {
+        let corners = [
+            self.world_to_map_f32(visible.min_x, visible.min_y),
+            self.world_to_map_f32(visible.max_x, visible.min_y),
+            self.world_to_map_f32(visible.max_x, visible.max_y),
+            self.world_to_map_f32(visible.min_x, visible.max_y),
+        ];
+        WorldRect::from_points(&corners).cell_range(1.0, 1.0, self.map_size)
+    }
+
     pub fn draw_tile<D: Draw>(&self, x: usize, y: usize, canvas: &mut D) {
         let (new_x, new_y) = self.transform_coordinate(x, y);
         // println!("Drawing {}, {} at {:?}", x, y, (new_x, new_y));
@@ -244,10 +270,11 @@ impl GameLoop for MyGameLoop {
             self.is_scrolling_up, self.is_scrolling_down
         );
 
-        // always re draw the tiles:
+        // always re draw the tiles, but only the ones that are on screen:
         self.canvas.fill(Rgb::WHITE);
-        for y in 0..self.map.map_size.1 {
-            for x in 0..self.map.map_size.0 {
+        let (x_range, y_range) = self.map.visible_cells(&self.canvas.visible_world_rect());
+        for y in y_range {
+            for x in x_range.clone() {
                 self.map.draw_tile(x, y, &mut self.canvas);
             }
         }
@@ -0,0 +1,190 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode};
+use simple_game_examples::{draw::Draw, transform::Transform};
+use simple_game_examples::world_screen::{WorldScreen, StrokeWidth, DetailLevel, LodThresholds};
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub rotating_left: bool,
+    pub rotating_right: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map_size: (usize, usize),
+    pub iso_projection: Transform,
+    pub spin: f32,
+
+    canvas: WorldScreen,
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::KeyDown { code, .. } => {
+                    match code {
+                        KeyCode::Q => self.rotating_left = true,
+                        KeyCode::E => self.rotating_right = true,
+                        _ => {}
+                    }
+                    continue;
+                }
+                Event::KeyUp { code, .. } => {
+                    match code {
+                        KeyCode::Q => self.rotating_left = false,
+                        KeyCode::E => self.rotating_right = false,
+                        _ => {}
+                    }
+                    continue;
+                }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.rotating_left {
+            self.canvas.rotation -= 0.02;
+        }
+        if self.rotating_right {
+            self.canvas.rotation += 0.02;
+        }
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.spin += 0.03;
+
+        self.canvas.fill(Rgb::WHITE);
+
+        // everything below is drawn in map coordinates, where each
+        // cell is a 1x1 square. The WorldScreen takes care of
+        // projecting that to the isometric diamond, then panning/zooming/rotating.
+        self.canvas.push_transform(self.iso_projection);
+        // only draw the part of the grid that is on screen.
+        // the visible range is in map coordinates because of the pushed projection.
+        let (width, height) = self.map_size;
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map_size);
+        let (x_start, x_end) = (x_range.start as f32, x_range.end as f32);
+        let (y_start, y_end) = (y_range.start as f32, y_range.end as f32);
+        // when zoomed far out, drawing every cell turns into noise, so
+        // we only draw every 10th line, or just the outline of the map.
+        let step = match self.canvas.detail_level() {
+            DetailLevel::Full => 1,
+            DetailLevel::Simplified => 10,
+            DetailLevel::Minimal => usize::MAX,
+        };
+        for y in y_range.start..=y_range.end {
+            if y % step == 0 || y == height {
+                self.canvas.draw_horizontal_line_f32(y as f32, x_start, x_end, Rgb::BLUE);
+            }
+        }
+        for x in x_range.start..=x_range.end {
+            if x % step == 0 || x == width {
+                self.canvas.draw_vertical_line_f32(x as f32, y_start, y_end, Rgb::BLUE);
+            }
+        }
+
+        // because the iso projection is on the stack, screen_to_world
+        // gives us map coordinates directly:
+        let (map_x, map_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        if map_x >= 0.0 && map_y >= 0.0 && map_x < width as f32 && map_y < height as f32 {
+            let (cell_x, cell_y) = (map_x.floor(), map_y.floor());
+            self.canvas.draw_horizontal_line_f32(cell_y, cell_x, cell_x + 1.0, Rgb::RED);
+            self.canvas.draw_horizontal_line_f32(cell_y + 1.0, cell_x, cell_x + 1.0, Rgb::RED);
+            self.canvas.draw_vertical_line_f32(cell_x, cell_y, cell_y + 1.0, Rgb::RED);
+            self.canvas.draw_vertical_line_f32(cell_x + 1.0, cell_y, cell_y + 1.0, Rgb::RED);
+
+            // a local object: a small square spinning in the middle of the
+            // selected cell. it only knows about its own local coordinates.
+            let local = Transform::rotate(self.spin)
+                .then(Transform::translate(cell_x + 0.5, cell_y + 0.5));
+            self.canvas.push_transform(local);
+            self.canvas.draw_horizontal_line_f32(-0.25, -0.25, 0.25, Rgb::GREEN);
+            self.canvas.draw_horizontal_line_f32(0.25, -0.25, 0.25, Rgb::GREEN);
+            self.canvas.draw_vertical_line_f32(-0.25, -0.25, 0.25, Rgb::GREEN);
+            self.canvas.draw_vertical_line_f32(0.25, -0.25, 0.25, Rgb::GREEN);
+            self.canvas.pop_transform();
+        }
+        self.canvas.pop_transform();
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        // lines are 2 world units wide, so they get thicker as we zoom in
+        self.canvas.stroke = StrokeWidth::World(2.0);
+        self.canvas.lod_thresholds = LodThresholds {
+            simplified_below: 0.3,
+            minimal_below: 0.03,
+        };
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    let my_loop = MyGameLoop {
+        map_size: (1000, 1000),
+        // same tile size and origin as the iso_pan_and_zoom_select example
+        iso_projection: Transform::isometric(80.0, 40.0, 440.0, 40.0),
+        ..Default::default()
+    };
+
+    let my_conf = BackendConf {
+        window_title: "iso_transform_stack".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
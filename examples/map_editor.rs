@@ -0,0 +1,330 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode, MouseButton};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::editor::{MapEditor, Tool};
+use simple_game_examples::tile_map::{Tile, TileLayer, TileMap, Orientation};
+use simple_game_examples::{tiled, map_format};
+use std::path::PathBuf;
+
+// there are no textures, so each tile id just gets a color:
+const TILE_COLORS: [Rgb; 6] = [
+    Rgb { red: 120, green: 180, blue: 90 },
+    Rgb { red: 90, green: 140, blue: 200 },
+    Rgb { red: 140, green: 140, blue: 140 },
+    Rgb { red: 200, green: 160, blue: 80 },
+    Rgb { red: 60, green: 110, blue: 50 },
+    Rgb { red: 170, green: 90, blue: 60 },
+];
+const PALETTE_SWATCH: f32 = 32.0;
+const PALETTE_MARGIN: f32 = 8.0;
+
+fn tile_color(tile: Tile) -> Rgb {
+    TILE_COLORS[(tile.gid as usize - 1) % TILE_COLORS.len()]
+}
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_panning: bool,
+    pub mouse_is_editing: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub editor: MapEditor,
+    pub save_path: PathBuf,
+    canvas: WorldScreen,
+}
+
+impl MyGameLoop {
+    fn cell_under_mouse(&self) -> Option<(usize, usize)> {
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        self.editor.map.cell_at(world_x, world_y)
+    }
+
+    /// the palette is a row of swatches along the bottom of the screen
+    fn palette_swatch_at(&self, x: f32, y: f32) -> Option<usize> {
+        let top = self.canvas.screen_height as f32 - PALETTE_SWATCH - PALETTE_MARGIN;
+        if y < top || y >= top + PALETTE_SWATCH || x < PALETTE_MARGIN {
+            return None;
+        }
+        let index = ((x - PALETTE_MARGIN) / (PALETTE_SWATCH + PALETTE_MARGIN)) as usize;
+        if index < TILE_COLORS.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    fn print_status(&self) {
+        let layer = self.editor.layer().map(|l| l.name.as_str()).unwrap_or("-");
+        println!(
+            "tool: {:?}, tile: {}, brush size: {}, layer: {} ({}/{}){}",
+            self.editor.tool, self.editor.brush_tile.gid, self.editor.brush_size,
+            layer, self.editor.current_layer + 1, self.editor.map.layers.len(),
+            if self.editor.is_modified() { " *" } else { "" },
+        );
+    }
+
+    fn handle_key(&mut self, code: KeyCode, ctrl: bool, shift: bool) {
+        match code {
+            KeyCode::Z if ctrl && shift => { self.editor.redo(); }
+            KeyCode::Z if ctrl => { self.editor.undo(); }
+            KeyCode::Y if ctrl => { self.editor.redo(); }
+            KeyCode::S if ctrl => {
+                match self.editor.save(&self.save_path) {
+                    Ok(()) => println!("saved {}", self.save_path.display()),
+                    Err(e) => eprintln!("failed to save {}: {}", self.save_path.display(), e),
+                }
+            }
+            KeyCode::O if ctrl => {
+                match self.editor.load(&self.save_path) {
+                    Ok(()) => println!("loaded {}", self.save_path.display()),
+                    Err(e) => eprintln!("failed to load {}: {}", self.save_path.display(), e),
+                }
+            }
+            KeyCode::B => self.editor.set_tool(Tool::Brush),
+            KeyCode::E => self.editor.set_tool(Tool::Eraser),
+            KeyCode::F => self.editor.set_tool(Tool::Fill),
+            KeyCode::R => self.editor.set_tool(Tool::Rectangle),
+            KeyCode::I => self.editor.set_tool(Tool::Eyedropper),
+            KeyCode::LeftBracket => self.editor.brush_size = self.editor.brush_size.saturating_sub(1).max(1),
+            KeyCode::RightBracket => self.editor.brush_size = (self.editor.brush_size + 1).min(9),
+            KeyCode::Tab if shift => self.editor.previous_layer(),
+            KeyCode::Tab => self.editor.next_layer(),
+            KeyCode::H => self.editor.toggle_layer_visibility(),
+            KeyCode::Key1 | KeyCode::Key2 | KeyCode::Key3
+            | KeyCode::Key4 | KeyCode::Key5 | KeyCode::Key6 => {
+                let index = code as u32 - KeyCode::Key1 as u32;
+                self.editor.brush_tile = Tile::new(index + 1);
+            }
+            _ => return,
+        }
+        self.print_status();
+    }
+
+    fn draw_cell(&mut self, x: f32, y: f32, color: Rgb) {
+        let corners = [(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)];
+        self.canvas.fill_convex_polygon_f32(&corners, color);
+    }
+
+    fn draw_rect_outline(&mut self, min: (usize, usize), max: (usize, usize), color: Rgb) {
+        let (x1, y1) = (min.0 as f32, min.1 as f32);
+        let (x2, y2) = (max.0 as f32 + 1.0, max.1 as f32 + 1.0);
+        self.canvas.draw_horizontal_line_f32(y1, x1, x2, color);
+        self.canvas.draw_horizontal_line_f32(y2, x1, x2, color);
+        self.canvas.draw_vertical_line_f32(x1, y1, y2, color);
+        self.canvas.draw_vertical_line_f32(x2, y1, y2, color);
+    }
+
+    fn draw_palette(&mut self) {
+        let top = self.canvas.screen_height as f32 - PALETTE_SWATCH - PALETTE_MARGIN;
+        for (i, color) in TILE_COLORS.iter().enumerate() {
+            let left = PALETTE_MARGIN + i as f32 * (PALETTE_SWATCH + PALETTE_MARGIN);
+            let (right, bottom) = (left + PALETTE_SWATCH, top + PALETTE_SWATCH);
+            let corners = [(left, top), (right, top), (right, bottom), (left, bottom)];
+            // the palette is drawn in screen space, directly on the canvas
+            let canvas = &mut self.canvas.canvas;
+            canvas.fill_convex_polygon_f32(&corners, *color);
+            if self.editor.brush_tile.gid as usize == i + 1 {
+                let outline = [(left, top), (right, top), (right, bottom), (left, bottom), (left, top)];
+                for pair in outline.windows(2) {
+                    let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+                    canvas.draw_thick_line_f32(x1, y1, x2, y2, 3.0, Rgb::BLACK);
+                }
+            }
+        }
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            match ev {
+                Event::MouseMove { x, y } => {
+                    self.mouse_x = x;
+                    self.mouse_y = y;
+                    if self.mouse_is_editing {
+                        if let Some((cell_x, cell_y)) = self.cell_under_mouse() {
+                            self.editor.drag(cell_x, cell_y);
+                        }
+                    }
+                }
+                Event::MouseDown { button, x, y } => {
+                    self.mouse_x = x;
+                    self.mouse_y = y;
+                    match button {
+                        MouseButton::Left => {
+                            if let Some(index) = self.palette_swatch_at(x, y) {
+                                self.editor.brush_tile = Tile::new(index as u32 + 1);
+                                self.print_status();
+                            } else if let Some((cell_x, cell_y)) = self.cell_under_mouse() {
+                                self.mouse_is_editing = true;
+                                let tool = self.editor.tool;
+                                self.editor.press(cell_x, cell_y);
+                                if tool != self.editor.tool {
+                                    self.print_status();
+                                }
+                            }
+                        }
+                        MouseButton::Right | MouseButton::Middle => {
+                            self.mouse_is_panning = true;
+                            self.canvas.reset_pan(x, y);
+                        }
+                        MouseButton::Unknown => {}
+                    }
+                }
+                Event::MouseUp { button, x, y } => {
+                    self.mouse_x = x;
+                    self.mouse_y = y;
+                    if let MouseButton::Left = button {
+                        if self.mouse_is_editing {
+                            self.mouse_is_editing = false;
+                            self.editor.release();
+                        }
+                    } else {
+                        self.mouse_is_panning = false;
+                    }
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                }
+                Event::KeyDown { modifier, code, .. } => {
+                    self.handle_key(code, modifier.ctrl, modifier.shift);
+                }
+                Event::KeyUp { .. } => {}
+            }
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_panning {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.canvas.fill(Rgb::WHITE);
+
+        self.canvas.push_transform(self.editor.map.projection());
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.editor.map.map_size());
+        for layer_index in 0..self.editor.map.layers.len() {
+            if !self.editor.map.layers[layer_index].visible {
+                continue;
+            }
+            for y in y_range.clone() {
+                for x in x_range.clone() {
+                    let tile = match self.editor.map.layers[layer_index].get(x, y) {
+                        Some(t) if !t.is_empty() => t,
+                        _ => continue,
+                    };
+                    self.draw_cell(x as f32, y as f32, tile_color(tile));
+                }
+            }
+        }
+        for y in y_range.clone() {
+            self.canvas.draw_horizontal_line_f32(y as f32, x_range.start as f32, x_range.end as f32, Rgb::BLACK);
+        }
+        for x in x_range.clone() {
+            self.canvas.draw_vertical_line_f32(x as f32, y_range.start as f32, y_range.end as f32, Rgb::BLACK);
+        }
+
+        // show what the current tool would change
+        if let Some((start, end)) = self.editor.rect_preview() {
+            let min = (start.0.min(end.0), start.1.min(end.1));
+            let max = (start.0.max(end.0), start.1.max(end.1));
+            self.draw_rect_outline(min, max, Rgb::BLUE);
+        } else if let Some((cell_x, cell_y)) = self.cell_under_mouse() {
+            let cells = match self.editor.tool {
+                Tool::Brush | Tool::Eraser => self.editor.brush_cells(cell_x, cell_y),
+                _ => vec![(cell_x, cell_y)],
+            };
+            if let (Some(first), Some(last)) = (cells.first().copied(), cells.last().copied()) {
+                self.draw_rect_outline(first, last, Rgb::RED);
+            }
+        }
+        self.canvas.pop_transform();
+
+        self.draw_palette();
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        if self.editor.map.orientation == Orientation::Isometric {
+            self.canvas.pan_offset_x = -50.0;
+            self.canvas.pan_offset_y = -50.0;
+        }
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn new_map() -> TileMap {
+    let mut map = TileMap::new(Orientation::Isometric, 24, 24, 64, 32);
+    map.layers.push(TileLayer::new("ground", 24, 24));
+    map.layers.push(TileLayer::new("decoration", 24, 24));
+    map
+}
+
+fn main() {
+    // pass a map to edit. Tiled maps (.tmx/.tmj) are saved next to
+    // the original as a .map file. without a path, a new map is created.
+    let path = std::env::args().nth(1).map(PathBuf::from);
+    let (map, save_path) = match path {
+        Some(path) => {
+            let is_tiled = matches!(path.extension().and_then(|e| e.to_str()), Some("tmx") | Some("tmj"));
+            let loaded = if is_tiled {
+                tiled::load(&path).map_err(|e| e.to_string())
+            } else {
+                map_format::load(&path).map_err(|e| e.to_string())
+            };
+            let map = match loaded {
+                Ok(m) => m,
+                Err(e) => {
+                    eprintln!("Failed to load {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            let save_path = if is_tiled { path.with_extension("map") } else { path };
+            (map, save_path)
+        }
+        None => (new_map(), PathBuf::from("new_map.map")),
+    };
+
+    println!("left click: use tool, right drag: pan, scroll: zoom");
+    println!("B: brush, E: eraser, F: fill, R: rectangle, I: eyedropper, 1-6: pick tile, [ ]: brush size");
+    println!("Tab/Shift+Tab: switch layer, H: hide layer, Ctrl+Z/Ctrl+Y: undo/redo, Ctrl+S: save, Ctrl+O: reload");
+    let my_loop = MyGameLoop {
+        editor: MapEditor::new(map),
+        save_path,
+        ..Default::default()
+    };
+    my_loop.print_status();
+    let my_conf = BackendConf {
+        window_title: "map_editor".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,35 @@
+use simple_game_examples::{tiled, map_format};
+use simple_game_examples::tile_map::PropertyValue;
+
+// no window in this one. it loads a map, saves it in both of our own
+// formats, loads them again, and shows that nothing was lost on the way.
+// (the tests in map_format.rs check this, and the versioning, in detail)
+fn main() {
+    let path = std::env::args().nth(1).unwrap_or_else(|| "examples/assets/iso_map.tmx".into());
+    let mut map = tiled::load(&path).expect("failed to load map");
+
+    // give a few cells some data that a game would care about
+    map.origin = (16.0, 32.0);
+    map.set_cell_property(0, 0, "spawn", PropertyValue::Bool(true));
+    map.set_cell_property(3, 2, "move_cost", PropertyValue::Int(3));
+    map.set_cell_property(3, 2, "height", PropertyValue::Float(1.5));
+    map.set_cell_property(5, 5, "trigger", PropertyValue::String("open_door".into()));
+
+    let dir = std::env::temp_dir();
+    let json_path = dir.join("simple_game_map.json");
+    let binary_path = dir.join("simple_game_map.map");
+    map_format::save(&map, &json_path).expect("failed to save json");
+    map_format::save(&map, &binary_path).expect("failed to save binary");
+
+    let from_json = map_format::load(&json_path).expect("failed to load json");
+    let from_binary = map_format::load(&binary_path).expect("failed to load binary");
+    assert_eq!(from_json, map);
+    assert_eq!(from_binary, map);
+    println!("round trip ok");
+    println!("  {}: {} bytes", json_path.display(), file_size(&json_path));
+    println!("  {}: {} bytes", binary_path.display(), file_size(&binary_path));
+}
+
+fn file_size(path: &std::path::Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
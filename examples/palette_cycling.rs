@@ -0,0 +1,306 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{TileMap, Orientation};
+use simple_game_examples::pixel_format::Indexed8;
+use simple_game_examples::palette::{Palette, ColorCycle, PaletteSwap};
+use std::time::Instant;
+
+// what each palette index is used for. sprites use 0 for transparent.
+const TRANSPARENT: u8 = 0;
+const BACKGROUND: u8 = 1;
+const GRASS: u8 = 2;
+const GRASS_DARK: u8 = 3;
+const HIGHLIGHT: u8 = 4;
+const SKIN: u8 = 5;
+const OUTLINE: u8 = 6;
+const WATER: u8 = 16;
+const LAVA: u8 = 24;
+/// how many entries the water and lava ramps have
+const RAMP: u8 = 8;
+/// main, dark and light color of each team
+const RED_TEAM: u8 = 40;
+const BLUE_TEAM: u8 = 44;
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    pub toggle_night: bool,
+    pub toggle_cycling: bool,
+
+    pub map: TileMap,
+    /// palette index for each cell
+    pub cells: Vec<u8>,
+    /// (x, y, is on the blue team)
+    pub units: Vec<(usize, usize, bool)>,
+    pub unit_sprite: Canvas<Indexed8>,
+    pub day: Palette,
+    pub night: Palette,
+    pub is_night: bool,
+    pub cycles: Vec<ColorCycle>,
+    pub paused_at: Option<f32>,
+    start: Option<Instant>,
+    /// the game draws palette indices onto this
+    screen: WorldScreen<Indexed8>,
+    /// and the palette turns them into colors here, in the backend's format
+    output: Canvas,
+}
+
+fn ramp(from: Rgb, to: Rgb, count: u8) -> Vec<Rgb> {
+    let mix = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    (0..count).map(|i| {
+        // up and back down again, so the cycle has no seam
+        let t = 1.0 - (i as f32 / count as f32 * 2.0 - 1.0).abs();
+        Rgb { red: mix(from.red, to.red, t), green: mix(from.green, to.green, t), blue: mix(from.blue, to.blue, t) }
+    }).collect()
+}
+
+fn day_palette() -> Palette {
+    let mut palette = Palette::new();
+    palette.set(BACKGROUND, Rgb { red: 20, green: 20, blue: 30 });
+    palette.set(GRASS, Rgb { red: 110, green: 170, blue: 80 });
+    palette.set(GRASS_DARK, Rgb { red: 90, green: 150, blue: 70 });
+    palette.set(HIGHLIGHT, Rgb { red: 255, green: 255, blue: 120 });
+    palette.set(SKIN, Rgb { red: 240, green: 200, blue: 160 });
+    palette.set(OUTLINE, Rgb { red: 30, green: 20, blue: 20 });
+    palette.set_range(WATER, &ramp(Rgb { red: 30, green: 80, blue: 170 }, Rgb { red: 120, green: 190, blue: 240 }, RAMP));
+    palette.set_range(LAVA, &ramp(Rgb { red: 150, green: 20, blue: 0 }, Rgb { red: 255, green: 220, blue: 60 }, RAMP));
+    palette.set_range(RED_TEAM, &[Rgb { red: 210, green: 50, blue: 40 }, Rgb { red: 130, green: 30, blue: 20 }, Rgb { red: 255, green: 130, blue: 110 }]);
+    palette.set_range(BLUE_TEAM, &[Rgb { red: 50, green: 90, blue: 220 }, Rgb { red: 30, green: 50, blue: 130 }, Rgb { red: 130, green: 170, blue: 255 }]);
+    palette
+}
+
+/// the whole day palette, darker and bluer, except for the lava which glows
+fn night_palette(day: &Palette) -> Palette {
+    let mut night = day.clone();
+    for (i, color) in night.colors.iter_mut().enumerate() {
+        if (LAVA as usize..(LAVA + RAMP) as usize).contains(&i) {
+            continue;
+        }
+        let dark = color.shade(0.4);
+        *color = Rgb { blue: dark.blue.saturating_add(30), ..dark };
+    }
+    night
+}
+
+/// a little person, drawn in the red team's colors
+fn unit_sprite() -> Canvas<Indexed8> {
+    let rows = [
+        "....oooo....",
+        "...osssso...",
+        "...osssso...",
+        "....oooo....",
+        "..oorrrroo..",
+        ".orrrllrrro.",
+        ".orrrllrrro.",
+        ".osrrrrrrso.",
+        ".osrrrrrrso.",
+        "..orrrrrro..",
+        "..oddddddo..",
+        "..odd..ddo..",
+        "..odd..ddo..",
+        "..ooo..ooo..",
+    ];
+    let mut sprite = Canvas::with_format(12, rows.len(), Indexed8);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let index = match c {
+                'o' => OUTLINE,
+                's' => SKIN,
+                'r' => RED_TEAM,
+                'd' => RED_TEAM + 1,
+                'l' => RED_TEAM + 2,
+                _ => TRANSPARENT,
+            };
+            sprite.set_index(x, y, index);
+        }
+    }
+    sprite
+}
+
+impl MyGameLoop {
+    fn time(&mut self) -> f32 {
+        self.start.get_or_insert_with(Instant::now).elapsed().as_secs_f32()
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.screen.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { code, repeated: false, .. } => {
+                    match code {
+                        KeyCode::N => self.toggle_night = true,
+                        KeyCode::C => self.toggle_cycling = true,
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.screen.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.screen.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        let now = self.time();
+        if self.toggle_night {
+            self.toggle_night = false;
+            self.is_night = !self.is_night;
+        }
+        if self.toggle_cycling {
+            self.toggle_cycling = false;
+            self.paused_at = match self.paused_at {
+                Some(_) => None,
+                None => Some(now),
+            };
+        }
+
+        // only indices are drawn. nothing below changes when the
+        // palette cycles or switches to night.
+        self.screen.fill(Indexed8::color(BACKGROUND));
+        let (world_x, world_y) = self.screen.screen_to_world(self.mouse_x, self.mouse_y);
+        let hovered = self.map.cell_at(world_x, world_y);
+        self.screen.push_transform(self.map.projection());
+        let (x_range, y_range) = self.screen.visible_cell_range(1.0, 1.0, self.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let (fx, fy) = (x as f32, y as f32);
+                let corners = [(fx, fy), (fx + 1.0, fy), (fx + 1.0, fy + 1.0), (fx, fy + 1.0)];
+                let index = self.cells[y * self.map.width + x];
+                self.screen.fill_convex_polygon_f32(&corners, Indexed8::color(index));
+                if hovered == Some((x, y)) {
+                    for i in 0..4 {
+                        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                        self.screen.draw_diagonal_line_f32(a.0, a.1, b.0, b.1, Indexed8::color(HIGHLIGHT));
+                    }
+                }
+            }
+        }
+        // the same sprite for both teams, the blue ones are drawn
+        // with their red entries swapped for blue ones
+        let blue = PaletteSwap::new().swap_range(RED_TEAM, BLUE_TEAM, 3);
+        let red = PaletteSwap::new();
+        for (x, y, is_blue) in self.units.iter() {
+            let (sx, sy) = self.screen.world_to_screen_f32(*x as f32 + 0.5, *y as f32 + 0.5);
+            let swap = if *is_blue { &blue } else { &red };
+            let (left, top) = (sx as i32 - self.unit_sprite.width as i32 / 2, sy as i32 - self.unit_sprite.height as i32 + 2);
+            self.screen.canvas.draw_sprite(&self.unit_sprite, left, top, swap, Some(TRANSPARENT));
+        }
+        self.screen.pop_transform();
+
+        // the palette is only applied here, when the frame is handed to the backend
+        let base = if self.is_night { &self.night } else { &self.day };
+        let palette = base.cycled(&self.cycles, self.paused_at.unwrap_or(now));
+        palette.present(&self.screen.canvas, &mut self.output);
+
+        backend::TextureUpdate::UpdateWhole(self.output.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        self.screen.canvas = Canvas::with_format(width, height, Indexed8);
+        self.screen.screen_width = width;
+        self.screen.screen_height = height;
+        self.screen.scale_x = 1.0;
+        self.screen.scale_y = 1.0;
+        self.screen.scale_factor_up = 1.040;
+        self.screen.scale_factor_down = 0.960;
+        self.output = Canvas::new(width, height, bpp);
+        self.day.present(&self.screen.canvas, &mut self.output);
+        self.output.access_data().to_vec()
+    }
+}
+
+fn main() {
+    let (width, height) = (20, 20);
+    let mut cells = vec![GRASS; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let cell = &mut cells[y * width + x];
+            if (x + y) % 3 == 0 {
+                *cell = GRASS_DARK;
+            }
+            // a river running down the map, with the ramp along it so the
+            // cycle looks like it flows
+            let river = (width as i32 / 2 + (y as i32 / 4) - 2 - x as i32).abs() <= 1;
+            if river {
+                *cell = WATER + ((x + RAMP as usize * 4 - y) % RAMP as usize) as u8;
+            }
+            // and a pool of lava, bubbling outwards from the middle
+            let (dx, dy) = (x as f32 - 4.5, y as f32 - 14.5);
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance < 3.0 {
+                *cell = LAVA + ((distance * 2.0) as u8 % RAMP);
+            }
+        }
+    }
+    let units = vec![(2, 2, false), (4, 3, false), (3, 5, false), (15, 12, true), (17, 14, true), (14, 16, true)];
+    let day = day_palette();
+    println!("N: switch between day and night palettes, C: pause the color cycling, left drag: pan, scroll: zoom");
+    let my_loop = MyGameLoop {
+        map: TileMap::new(Orientation::Isometric, width, height, 48, 24),
+        cells,
+        units,
+        unit_sprite: unit_sprite(),
+        night: night_palette(&day),
+        day,
+        cycles: vec![
+            ColorCycle::new(WATER, RAMP, 6.0),
+            // lava moves outwards, so it cycles the other way
+            ColorCycle::new(LAVA, RAMP, -4.0),
+        ],
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "palette_cycling".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
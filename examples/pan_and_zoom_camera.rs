@@ -0,0 +1,219 @@
+use std::time::Instant;
+
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::camera::{Camera, Easing, FollowTarget};
+
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub toggle_follow: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    pub last_frame: Instant,
+
+    // a point that moves around the grid in a circle.
+    // press F to have the camera follow it.
+    pub mover_angle: f32,
+
+    pub grid_start_x: usize,
+    pub grid_start_y: usize,
+    pub grid_box_size: usize,
+    pub grid_num_boxes: usize,
+    canvas: WorldScreen,
+    camera: Camera,
+}
+
+impl MyGameLoop {
+    pub fn mover_position(&self) -> (f32, f32) {
+        let grid_size = (self.grid_num_boxes * self.grid_box_size) as f32;
+        let center_x = self.grid_start_x as f32 + grid_size / 2.0;
+        let center_y = self.grid_start_y as f32 + grid_size / 2.0;
+        let radius = grid_size / 3.0;
+        (
+            center_x + radius * self.mover_angle.cos(),
+            center_y + radius * self.mover_angle.sin(),
+        )
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.camera.start_drag(&mut self.canvas, x, y);
+                    }
+
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                        self.camera.end_drag();
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { code: KeyCode::F, repeated: false, .. } => {
+                    self.toggle_follow = true;
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_frame).as_secs_f32();
+        self.last_frame = now;
+
+        self.canvas.fill(Rgb::WHITE);
+
+        self.mover_angle += dt * 0.5;
+        let (mover_x, mover_y) = self.mover_position();
+        if self.toggle_follow {
+            self.toggle_follow = false;
+            self.camera.follow = match self.camera.follow {
+                Some(_) => None,
+                None => Some(FollowTarget {
+                    world_x: mover_x,
+                    world_y: mover_y,
+                    deadzone_x: 60.0,
+                    deadzone_y: 40.0,
+                    lerp: 0.1,
+                }),
+            };
+        }
+        self.camera.set_follow_target(mover_x, mover_y);
+
+        if self.mouse_is_dragging {
+            self.camera.drag_to(&mut self.canvas, self.mouse_x, self.mouse_y, dt);
+        }
+
+        self.camera.handle_scroll(
+            &self.canvas,
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+
+        // right click flies the camera to the clicked point and zooms in
+        if self.mouse_was_clicked {
+            let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+            let target_scale = self.canvas.scale_x * 2.0;
+            self.camera.fly_to(&self.canvas, world_x, world_y, target_scale, 0.6, Easing::EaseInOutQuad);
+        }
+
+        self.camera.update(&mut self.canvas, dt);
+
+        let box_size = self.grid_box_size;
+        let grid_start_x = self.grid_start_x;
+        let grid_start_y = self.grid_start_y;
+        let num_boxes = self.grid_num_boxes;
+        let grid_stop_x = grid_start_x + (num_boxes * box_size);
+        let grid_stop_y = grid_start_y + (num_boxes * box_size);
+
+        let mut v = grid_start_y as f32;
+        for _ in 0..=num_boxes {
+            self.canvas.draw_horizontal_line_f32(v, grid_start_x as f32, grid_stop_x as f32, Rgb::BLACK);
+            v += box_size as f32;
+        }
+        let mut v = grid_start_x as f32;
+        for _ in 0..=num_boxes {
+            self.canvas.draw_vertical_line_f32(v, grid_start_y as f32, grid_stop_y as f32, Rgb::BLACK);
+            v += box_size as f32;
+        }
+
+        // draw a small x where the moving point is
+        self.canvas.draw_diagonal_line_f32(mover_x - 5.0, mover_y - 5.0, mover_x + 5.0, mover_y + 5.0, Rgb::RED);
+        self.canvas.draw_diagonal_line_f32(mover_x - 5.0, mover_y + 5.0, mover_x + 5.0, mover_y - 5.0, Rgb::RED);
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.grid_box_size = 20;
+        self.grid_start_x = 100;
+        self.grid_start_y = 100;
+        self.grid_num_boxes = 40;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.2;
+        self.canvas.scale_factor_down = 0.8;
+
+        // the camera is not allowed to leave the grid, and
+        // can zoom out until the whole grid is about the size of the screen
+        let grid_size = (self.grid_num_boxes * self.grid_box_size) as f32;
+        let min_x = self.grid_start_x as f32;
+        let min_y = self.grid_start_y as f32;
+        self.camera = Camera::new()
+            .with_bounds(min_x, min_y, min_x + grid_size, min_y + grid_size)
+            .with_zoom_limits(0.5, 8.0);
+        self.camera.clamp(&mut self.canvas);
+
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    let my_loop = MyGameLoop {
+        is_scrolling_up: false,
+        is_scrolling_down: false,
+        mouse_is_dragging: false,
+        mouse_was_clicked: false,
+        toggle_follow: false,
+        mouse_x: 0.0,
+        mouse_y: 0.0,
+        last_frame: Instant::now(),
+        mover_angle: 0.0,
+        grid_start_x: 0,
+        grid_start_y: 0,
+        grid_box_size: 0,
+        grid_num_boxes: 0,
+        canvas: WorldScreen::default(),
+        camera: Camera::default(),
+    };
+    let my_conf = BackendConf {
+        window_title: "pan_and_zoom_camera".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,250 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{Cell, Tile, TileLayer, TileMap, Orientation};
+use simple_game_examples::pathfinding::{self, FlowField, Neighbors, Pathfinder};
+
+const GRASS: u32 = 1;
+const WATER: u32 = 2;
+const MUD: u32 = 3;
+const GRASS_COLOR: Rgb = Rgb { red: 120, green: 180, blue: 90 };
+const WATER_COLOR: Rgb = Rgb { red: 90, green: 140, blue: 200 };
+const MUD_COLOR: Rgb = Rgb { red: 140, green: 110, blue: 70 };
+const ORANGE: Rgb = Rgb { red: 255, green: 170, blue: 60 };
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map: TileMap,
+    pub pathfinder: Pathfinder,
+    pub goal: Cell,
+    pub show_flow_field: bool,
+    /// the flow field only has to be rebuilt when the map or the goal changes
+    flow_field: Option<FlowField>,
+    agents: Vec<Cell>,
+    frame: usize,
+    canvas: WorldScreen,
+}
+
+/// water can't be crossed, and mud is slow
+fn movement_cost(map: &TileMap, _from: Cell, to: Cell) -> Option<f32> {
+    match map.layers[0].get(to.0, to.1)?.gid {
+        WATER => None,
+        MUD => Some(3.0),
+        _ => Some(1.0),
+    }
+}
+
+impl MyGameLoop {
+    fn hovered_cell(&self) -> Option<Cell> {
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        self.map.cell_at(world_x, world_y)
+    }
+
+    fn paint(&mut self, gid: u32) {
+        if let Some((x, y)) = self.hovered_cell() {
+            let current = self.map.layers[0].get(x, y).map(|t| t.gid);
+            let tile = if current == Some(gid) { GRASS } else { gid };
+            self.map.layers[0].set(x, y, Tile::new(tile));
+            self.flow_field = None;
+        }
+    }
+
+    fn draw_cell(&mut self, x: f32, y: f32, color: Rgb) {
+        let corners = [(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)];
+        self.canvas.fill_convex_polygon_f32(&corners, color);
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { code, .. } => {
+                    match code {
+                        KeyCode::W => self.paint(WATER),
+                        KeyCode::M => self.paint(MUD),
+                        KeyCode::F => self.show_flow_field = !self.show_flow_field,
+                        KeyCode::N => {
+                            let neighbors = match self.pathfinder.neighbors {
+                                Neighbors::Four => Neighbors::Eight,
+                                Neighbors::Eight => Neighbors::Four,
+                            };
+                            self.pathfinder = Pathfinder::new(self.map.width, self.map.height, neighbors);
+                            self.flow_field = None;
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        if self.mouse_was_clicked {
+            if let Some(cell) = self.hovered_cell() {
+                self.goal = cell;
+                self.flow_field = None;
+            }
+        }
+
+        let map = &self.map;
+        let cost = |from: Cell, to: Cell| movement_cost(map, from, to);
+        if self.flow_field.is_none() {
+            self.flow_field = Some(self.pathfinder.flow_field(&[self.goal], &cost));
+        }
+        let flow_field = self.flow_field.as_ref().unwrap();
+
+        // the agents all share the one flow field, and take a step every few frames
+        self.frame += 1;
+        if self.frame == 10 {
+            self.frame = 0;
+            for agent in self.agents.iter_mut() {
+                match flow_field.next_cell(*agent) {
+                    Some(next) => *agent = next,
+                    // made it to the goal, start over from a corner
+                    None if *agent == self.goal => *agent = (0, 0),
+                    None => {}
+                }
+            }
+        }
+        let path = self.hovered_cell().and_then(|start| self.pathfinder.find_path(start, self.goal, &cost));
+
+        self.canvas.fill(Rgb::WHITE);
+        self.canvas.push_transform(self.map.projection());
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let color = match self.map.layers[0].get(x, y).map(|t| t.gid) {
+                    Some(WATER) => WATER_COLOR,
+                    Some(MUD) => MUD_COLOR,
+                    _ => GRASS_COLOR,
+                };
+                self.draw_cell(x as f32, y as f32, color);
+            }
+        }
+        let flow_field = self.flow_field.as_ref().unwrap();
+        if self.show_flow_field {
+            let cells = (x_range.clone(), y_range.clone());
+            pathfinding::draw_distance_map(&flow_field.distances, cells.clone(), &mut self.canvas, Rgb::WHITE, Rgb::BLUE);
+            pathfinding::draw_flow_field(flow_field, cells, &mut self.canvas, Rgb::BLACK);
+        }
+        for agent in self.agents.clone() {
+            let (x, y) = (agent.0 as f32, agent.1 as f32);
+            let corners = [(x + 0.3, y + 0.3), (x + 0.7, y + 0.3), (x + 0.7, y + 0.7), (x + 0.3, y + 0.7)];
+            self.canvas.fill_convex_polygon_f32(&corners, ORANGE);
+        }
+        let (x, y) = (self.goal.0 as f32, self.goal.1 as f32);
+        let corners = [(x + 0.2, y + 0.2), (x + 0.8, y + 0.2), (x + 0.8, y + 0.8), (x + 0.2, y + 0.8)];
+        self.canvas.fill_convex_polygon_f32(&corners, Rgb::RED);
+        if let Some(path) = path {
+            pathfinding::draw_path(&path, &mut self.canvas, Rgb::RED);
+        }
+        self.canvas.pop_transform();
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    let (width, height) = (24, 24);
+    let mut map = TileMap::new(Orientation::Isometric, width, height, 48, 24);
+    let mut ground = TileLayer::new("ground", width, height);
+    for y in 0..height {
+        for x in 0..width {
+            // a river with a couple of crossings, and a muddy patch
+            let gid = if x == 10 && y != 4 && y != 18 {
+                WATER
+            } else if (14..19).contains(&x) && (8..14).contains(&y) {
+                MUD
+            } else {
+                GRASS
+            };
+            ground.set(x, y, Tile::new(gid));
+        }
+    }
+    map.layers.push(ground);
+
+    println!("hover: path to the goal, right click: move goal, W/M: toggle water/mud, N: 4/8 neighbours, F: show flow field");
+    let my_loop = MyGameLoop {
+        pathfinder: Pathfinder::new(width, height, Neighbors::Eight),
+        goal: (20, 12),
+        agents: vec![(0, 0), (0, 23), (3, 10), (6, 2), (2, 20)],
+        map,
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "pathfinding".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
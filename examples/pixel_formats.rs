@@ -0,0 +1,75 @@
+use simple_game_examples::{Canvas, Rgb};
+use simple_game_examples::draw::Draw;
+use simple_game_examples::font;
+use simple_game_examples::pixel_format::{Bgra32, Gray8, Indexed8, PixelFormat, Rgb24};
+use simple_game_examples::tile_map::{Orientation, TileMap};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 200;
+
+/// the "game code": it only knows that it is drawing onto something
+/// that implements Draw, not how the pixels are stored.
+fn draw_scene<D: Draw>(canvas: &mut D, label: &str) {
+    canvas.fill(Rgb { red: 30, green: 30, blue: 50 });
+    let map = TileMap::new(Orientation::Isometric, 6, 6, 48, 24);
+    let projection = map.projection();
+    for y in 0..6 {
+        for x in 0..6 {
+            let (fx, fy) = (x as f32, y as f32);
+            let corners = [(fx, fy), (fx + 1.0, fy), (fx + 1.0, fy + 1.0), (fx, fy + 1.0)]
+                .iter()
+                .map(|(cx, cy)| {
+                    let (sx, sy) = projection.apply(*cx, *cy);
+                    (sx + 16.0, sy + 40.0)
+                })
+                .collect::<Vec<_>>();
+            let colors = [Rgb::RED, Rgb::GREEN, Rgb::BLUE, Rgb::WHITE];
+            let shade = |i: usize| colors[(x + y + i) % 4];
+            canvas.fill_triangle_gradient(&[corners[0], corners[1], corners[2]], [shade(0), shade(1), shade(2)]);
+            canvas.fill_triangle_gradient(&[corners[0], corners[2], corners[3]], [shade(0), shade(2), shade(3)]);
+        }
+    }
+    font::draw_text(canvas, 8, 8, label, Rgb::WHITE, 2);
+}
+
+/// draws the scene in a format, and saves it as a png (which is Rgb24)
+fn render<P: PixelFormat>(format: P, name: &str) -> Canvas<P> {
+    let mut canvas = Canvas::with_format(WIDTH, HEIGHT, format);
+    draw_scene(&mut canvas, name);
+    let png = canvas.convert(Rgb24);
+    let path = std::env::temp_dir().join(format!("pixel_formats_{}.png", name));
+    let (width, height, data) = png.into_raw();
+    image::save_buffer(&path, &data, width as u32, height as u32, image::ColorType::Rgb8).expect("failed to save png");
+    println!("{:>8}: {} bytes ({} per pixel), saved to {}", name, canvas.access_data().len(), canvas.bpp(), path.display());
+    canvas
+}
+
+// no window in this one. the same scene is drawn into canvases with
+// different pixel formats, and each is converted to rgb to be saved.
+fn main() {
+    let rgb = render(Rgb24, "rgb");
+    let bgra = render(Bgra32, "bgra");
+    let gray = render(Gray8, "gray");
+    let indexed = render(Indexed8, "indexed");
+
+    // the formats store the same colors in different orders
+    let (rgb_pixel, bgra_pixel) = (&rgb.access_data()[..3], &bgra.access_data()[..4]);
+    println!("top left pixel: rgb {:?}, bgra {:?}", rgb_pixel, bgra_pixel);
+    assert_eq!(rgb.get_pixel(0, 0), bgra.get_pixel(0, 0));
+
+    // the palette has 3 bits of red and green and 2 of blue, so colors
+    // come out close, but not exact
+    let (x, y) = (WIDTH / 2, HEIGHT / 2);
+    println!("middle pixel: rgb {:?}, indexed {:?}, gray {:?}", rgb.get_pixel(x, y), indexed.get_pixel(x, y), gray.get_pixel(x, y));
+
+    // a grayscale canvas works as a mask: anything that is not black
+    let covered = gray.access_data().iter().filter(|luma| **luma > 40).count();
+    println!("the mask covers {}% of the screen", covered * 100 / (WIDTH * HEIGHT));
+
+    // canvases can only be blitted onto canvases of the same format, so
+    // `bgra.blit(&gray, 0, 0)` doesn't compile. it has to be converted first:
+    let mut composed = Canvas::with_format(WIDTH, HEIGHT, Bgra32);
+    composed.blit(&gray.convert(Bgra32), 0, 0);
+    composed.blend_from(&bgra, 0.5, None);
+    println!("blended bgra and gray: {:?}", composed.get_pixel(x, y));
+}
@@ -0,0 +1,138 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, MouseButton};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::viewport::{Viewport, Viewports, ScreenRect};
+
+const GRID_BOX_SIZE: usize = 20;
+const GRID_NUM_BOXES: usize = 50;
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub dragging: Option<usize>,
+    pub clicked_points: Vec<(f32, f32)>,
+
+    canvas: Canvas,
+    viewports: Viewports,
+}
+
+/// draws the world the same way for every viewport. each viewport
+/// only differs in its pan/zoom.
+fn draw_world(screen: &mut WorldScreen, clicked_points: &[(f32, f32)]) {
+    screen.fill(Rgb::WHITE);
+    let grid_stop = (GRID_NUM_BOXES * GRID_BOX_SIZE) as f32;
+    let (x_range, y_range) = screen.visible_cell_range(GRID_BOX_SIZE as f32, GRID_BOX_SIZE as f32, (GRID_NUM_BOXES, GRID_NUM_BOXES));
+    for y in y_range.start..=y_range.end {
+        let v = (y * GRID_BOX_SIZE) as f32;
+        screen.draw_horizontal_line_f32(v, 0.0, grid_stop, Rgb::BLACK);
+    }
+    for x in x_range.start..=x_range.end {
+        let v = (x * GRID_BOX_SIZE) as f32;
+        screen.draw_vertical_line_f32(v, 0.0, grid_stop, Rgb::BLACK);
+    }
+    for (x, y) in clicked_points {
+        screen.draw_diagonal_line_f32(x - 5.0, y - 5.0, x + 5.0, y + 5.0, Rgb::RED);
+        screen.draw_diagonal_line_f32(x - 5.0, y + 5.0, x + 5.0, y - 5.0, Rgb::RED);
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        for ev in events {
+            let routed = match self.viewports.route_event(ev) {
+                Some(r) => r,
+                None => continue,
+            };
+            let viewport = &mut self.viewports.viewports[routed.viewport];
+            match routed.event {
+                Event::MouseDown { button: MouseButton::Left, x, y } => {
+                    self.dragging = Some(routed.viewport);
+                    viewport.screen.reset_pan(x, y);
+                }
+                Event::MouseUp { button: MouseButton::Left, .. } => {
+                    self.dragging = None;
+                }
+                Event::MouseUp { button: MouseButton::Right, .. } => {
+                    // the routed event already knows where in the world we clicked,
+                    // no matter which viewport it happened in
+                    if let Some(world) = routed.world {
+                        self.clicked_points.push(world);
+                    }
+                }
+                Event::MouseMove { x, y } if self.dragging == Some(routed.viewport) => {
+                    viewport.screen.pan_to(x, y);
+                }
+                Event::MouseScroll { up } => {
+                    if let Some(world) = routed.world {
+                        let (x, y) = viewport.screen.world_to_screen_f32(world.0, world.1);
+                        viewport.screen.handle_scroll((x, y), up, !up);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        for viewport in self.viewports.viewports.iter_mut() {
+            draw_world(&mut viewport.screen, &self.clicked_points);
+        }
+
+        // show what the left and right views are looking at on the minimap:
+        let left = self.viewports.get("left").map(|v| v.screen.visible_world_rect());
+        let right = self.viewports.get("right").map(|v| v.screen.visible_world_rect());
+        if let Some(minimap) = self.viewports.get_mut("minimap") {
+            for (rect, color) in [(left, Rgb::BLUE), (right, Rgb::GREEN)].iter() {
+                if let Some(r) = rect {
+                    minimap.screen.draw_horizontal_line_f32(r.min_y, r.min_x, r.max_x, *color);
+                    minimap.screen.draw_horizontal_line_f32(r.max_y, r.min_x, r.max_x, *color);
+                    minimap.screen.draw_vertical_line_f32(r.min_x, r.min_y, r.max_y, *color);
+                    minimap.screen.draw_vertical_line_f32(r.max_x, r.min_y, r.max_y, *color);
+                }
+            }
+        }
+
+        self.viewports.composite(&mut self.canvas);
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        self.canvas = Canvas::new_with_color(width, height, Rgb::BLACK, bpp);
+
+        let half = width / 2;
+        let mut left = Viewport::new("left", ScreenRect::new(0, 0, half - 1, height), bpp);
+        left.screen.pan_offset_x = -20.0;
+        left.screen.pan_offset_y = -20.0;
+        let mut right = Viewport::new("right", ScreenRect::new(half + 1, 0, width - half - 1, height), bpp);
+        right.screen.scale_x = 2.0;
+        right.screen.scale_y = 2.0;
+        right.screen.pan_offset_x = 400.0;
+        right.screen.pan_offset_y = 400.0;
+
+        // the minimap is zoomed out enough to show the entire grid
+        let minimap_size = 150;
+        let mut minimap = Viewport::new("minimap", ScreenRect::new(width - minimap_size - 10, 10, minimap_size, minimap_size), bpp);
+        let scale = minimap_size as f32 / (GRID_NUM_BOXES * GRID_BOX_SIZE) as f32;
+        minimap.screen.scale_x = scale;
+        minimap.screen.scale_y = scale;
+        minimap.border = Some(Rgb::BLACK);
+
+        self.viewports.add(left);
+        self.viewports.add(right);
+        self.viewports.add(minimap);
+
+        self.canvas.access_data().to_vec()
+    }
+}
+
+fn main() {
+    let my_loop = MyGameLoop::default();
+    let my_conf = BackendConf {
+        window_title: "split_screen".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,168 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode, TerminalBackend};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{TileMap, Orientation};
+use simple_game_examples::pixel_format::Rgb24;
+
+const GRASS_COLOR: Rgb = Rgb { red: 120, green: 180, blue: 90 };
+const WATER_COLOR: Rgb = Rgb { red: 60, green: 110, blue: 200 };
+/// how far the arrow keys move the view, in pixels
+const PAN_STEP: f32 = 4.0;
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    /// moved by the arrow keys, since not every terminal sends the mouse
+    pub pan_x: f32,
+    pub pan_y: f32,
+
+    pub map: TileMap,
+    pub selected: Option<(usize, usize)>,
+    canvas: WorldScreen<Rgb24>,
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { code, .. } => {
+                    match code {
+                        KeyCode::Left => self.pan_x -= PAN_STEP,
+                        KeyCode::Right => self.pan_x += PAN_STEP,
+                        KeyCode::Up => self.pan_y -= PAN_STEP,
+                        KeyCode::Down => self.pan_y += PAN_STEP,
+                        KeyCode::Equal => is_scrolling_up = true,
+                        KeyCode::Minus => is_scrolling_down = true,
+                        KeyCode::Enter | KeyCode::Space => self.mouse_was_clicked = true,
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        // the pan offset is in world units, so a key moves the same
+        // number of pixels on screen at any zoom
+        self.canvas.pan_offset_x += self.pan_x / self.canvas.scale_x;
+        self.canvas.pan_offset_y += self.pan_y / self.canvas.scale_y;
+        self.pan_x = 0.0;
+        self.pan_y = 0.0;
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        let hovered = self.map.cell_at(world_x, world_y);
+        if self.mouse_was_clicked {
+            self.selected = hovered;
+        }
+
+        self.canvas.fill(Rgb::BLACK);
+        self.canvas.push_transform(self.map.projection());
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let base = if (x + y * 3) % 7 == 0 { WATER_COLOR } else { GRASS_COLOR };
+                let color = if self.selected == Some((x, y)) {
+                    Rgb::RED
+                } else if hovered == Some((x, y)) {
+                    base.shade(1.3)
+                } else if (x + y) % 2 == 0 {
+                    base
+                } else {
+                    // every other cell a little darker, since there are
+                    // too few pixels for grid lines
+                    base.shade(0.85)
+                };
+                let (fx, fy) = (x as f32, y as f32);
+                let corners = [(fx, fy), (fx + 1.0, fy), (fx + 1.0, fy + 1.0), (fx, fy + 1.0)];
+                self.canvas.fill_convex_polygon_f32(&corners, color);
+            }
+        }
+        self.canvas.pop_transform();
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, _bpp: usize) -> Vec<u8> {
+        let canvas = &mut self.canvas;
+        canvas.canvas = Canvas::with_format(width, height, Rgb24);
+        canvas.screen_width = width;
+        canvas.screen_height = height;
+        canvas.scale_x = 1.0;
+        canvas.scale_y = 1.0;
+        canvas.scale_factor_up = 1.100;
+        canvas.scale_factor_down = 0.900;
+        // start looking at the middle of the map
+        let (middle_x, middle_y) = (self.map.width as f32 / 2.0, self.map.height as f32 / 2.0);
+        let (world_x, world_y) = self.map.projection().apply(middle_x, middle_y);
+        canvas.pan_offset_x = world_x - width as f32 / 2.0;
+        canvas.pan_offset_y = world_y - height as f32 / 2.0;
+        canvas.fill(Rgb::BLACK);
+        canvas.access_data().to_vec()
+    }
+}
+
+fn main() {
+    // small tiles, a character is only 1 x 2 pixels
+    let map = TileMap::new(Orientation::Isometric, 24, 24, 12, 6);
+    let my_loop = MyGameLoop {
+        map,
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "terminal".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: true,
+        window_resizable: false,
+    };
+    TerminalBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,234 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event, KeyCode};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{TileMap, Orientation};
+
+const TEXTURE_SIZE: usize = 32;
+
+/// how each tile is drawn, switched with T
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+enum Mode {
+    Wireframe,
+    Gradient,
+    #[default]
+    Textured,
+}
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    pub toggle_mode: bool,
+
+    pub map: TileMap,
+    /// index into textures for each cell
+    pub cells: Vec<usize>,
+    pub textures: Vec<Canvas>,
+    mode: Mode,
+    canvas: WorldScreen,
+}
+
+/// a bit of noise so that the textures don't look flat
+fn hash(x: usize, y: usize, seed: usize) -> u8 {
+    let mut h = (x * 374_761_393 + y * 668_265_263 + seed * 2_147_483_647) as u32;
+    h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+    (h ^ (h >> 16)) as u8
+}
+
+fn make_texture(base: Rgb, seed: usize, pattern: fn(usize, usize) -> f32) -> Canvas {
+    let mut texture = Canvas::new(TEXTURE_SIZE, TEXTURE_SIZE, 3);
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            let noise = 0.85 + hash(x, y, seed) as f32 / 255.0 * 0.3;
+            texture.set_pixel(x, y, base.shade(noise * pattern(x, y)));
+        }
+    }
+    texture
+}
+
+fn make_textures() -> Vec<Canvas> {
+    vec![
+        make_texture(Rgb { red: 110, green: 170, blue: 80 }, 1, |_, _| 1.0),
+        // bricks, with darker mortar between them
+        make_texture(Rgb { red: 170, green: 90, blue: 70 }, 2, |x, y| {
+            let offset = if (y / 8) % 2 == 0 { 0 } else { 8 };
+            if y % 8 == 0 || (x + offset) % 16 == 0 { 0.6 } else { 1.0 }
+        }),
+        // water, with some stripes
+        make_texture(Rgb { red: 70, green: 120, blue: 200 }, 3, |x, y| {
+            if (x + 2 * y) % 11 < 2 { 1.2 } else { 1.0 }
+        }),
+        // a checkerboard, which shows the affine mapping best
+        make_texture(Rgb { red: 230, green: 230, blue: 230 }, 4, |x, y| {
+            if (x / 8 + y / 8) % 2 == 0 { 1.0 } else { 0.4 }
+        }),
+    ]
+}
+
+impl MyGameLoop {
+    fn hovered_cell(&self) -> Option<(usize, usize)> {
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        self.map.cell_at(world_x, world_y)
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                Event::KeyDown { code: KeyCode::T, repeated: false, .. } => {
+                    self.toggle_mode = true;
+                    continue;
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        if self.toggle_mode {
+            self.toggle_mode = false;
+            self.mode = match self.mode {
+                Mode::Wireframe => Mode::Gradient,
+                Mode::Gradient => Mode::Textured,
+                Mode::Textured => Mode::Wireframe,
+            };
+            println!("drawing tiles as: {:?}", self.mode);
+        }
+        let hovered = self.hovered_cell();
+        if self.mouse_was_clicked {
+            if let Some((x, y)) = hovered {
+                let cell = &mut self.cells[y * self.map.width + x];
+                *cell = (*cell + 1) % self.textures.len();
+            }
+        }
+
+        self.canvas.fill(Rgb::WHITE);
+        self.canvas.push_transform(self.map.projection());
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let (fx, fy) = (x as f32, y as f32);
+                // clockwise from the top left corner of the texture
+                let corners = [(fx, fy), (fx + 1.0, fy), (fx + 1.0, fy + 1.0), (fx, fy + 1.0)];
+                match self.mode {
+                    Mode::Wireframe => {
+                        for i in 0..4 {
+                            let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                            self.canvas.draw_diagonal_line_f32(a.0, a.1, b.0, b.1, Rgb::BLACK);
+                        }
+                    }
+                    Mode::Gradient => {
+                        // every corner gets a brightness, so neighbouring
+                        // tiles blend into each other
+                        let color = |(cx, cy): (f32, f32)| {
+                            let light = 0.5 + hash(cx as usize, cy as usize, 0) as f32 / 255.0 * 0.6;
+                            Rgb { red: 110, green: 170, blue: 80 }.shade(light)
+                        };
+                        let colors = [color(corners[0]), color(corners[1]), color(corners[2]), color(corners[3])];
+                        self.canvas.fill_triangle_gradient(&[corners[0], corners[1], corners[2]], [colors[0], colors[1], colors[2]]);
+                        self.canvas.fill_triangle_gradient(&[corners[0], corners[2], corners[3]], [colors[0], colors[2], colors[3]]);
+                    }
+                    Mode::Textured => {
+                        let texture = &self.textures[self.cells[y * self.map.width + x]];
+                        self.canvas.draw_textured_quad(&corners, texture);
+                    }
+                }
+                if hovered == Some((x, y)) {
+                    for i in 0..4 {
+                        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                        self.canvas.draw_diagonal_line_f32(a.0, a.1, b.0, b.1, Rgb::RED);
+                    }
+                }
+            }
+        }
+        self.canvas.pop_transform();
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    let (width, height) = (16, 16);
+    let textures = make_textures();
+    let cells = (0..width * height)
+        .map(|i| hash(i % width, i / width, 9) as usize % textures.len())
+        .collect();
+    println!("T: switch between wireframe, gradient and textured tiles, right click: change a tile's texture, left drag: pan, scroll: zoom");
+    let my_loop = MyGameLoop {
+        map: TileMap::new(Orientation::Isometric, width, height, 64, 32),
+        cells,
+        textures,
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "textured_tiles".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,198 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::{tiled, tile_map::{TileMap, Orientation}};
+
+// there are no textures, so each tile id just gets a color:
+const TILE_COLORS: [Rgb; 6] = [
+    Rgb { red: 120, green: 180, blue: 90 },
+    Rgb { red: 90, green: 140, blue: 200 },
+    Rgb { red: 140, green: 140, blue: 140 },
+    Rgb { red: 200, green: 160, blue: 80 },
+    Rgb { red: 60, green: 110, blue: 50 },
+    Rgb { red: 170, green: 90, blue: 60 },
+];
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map: TileMap,
+    canvas: WorldScreen,
+}
+
+impl MyGameLoop {
+    fn draw_cell(&mut self, x: f32, y: f32, color: Rgb) {
+        let corners = [(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)];
+        self.canvas.fill_convex_polygon_f32(&corners, color);
+    }
+
+    fn draw_cell_outline(&mut self, x: f32, y: f32, color: Rgb) {
+        self.canvas.draw_horizontal_line_f32(y, x, x + 1.0, color);
+        self.canvas.draw_horizontal_line_f32(y + 1.0, x, x + 1.0, color);
+        self.canvas.draw_vertical_line_f32(x, y, y + 1.0, color);
+        self.canvas.draw_vertical_line_f32(x + 1.0, y, y + 1.0, color);
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.canvas.fill(Rgb::WHITE);
+
+        self.canvas.push_transform(self.map.projection());
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+
+        // layers are drawn bottom to top
+        for layer_index in 0..self.map.layers.len() {
+            if !self.map.layers[layer_index].visible {
+                continue;
+            }
+            for y in y_range.clone() {
+                for x in x_range.clone() {
+                    let tile = match self.map.layers[layer_index].get(x, y) {
+                        Some(t) if !t.is_empty() => t,
+                        _ => continue,
+                    };
+                    let color = TILE_COLORS[(tile.gid as usize - 1) % TILE_COLORS.len()];
+                    self.draw_cell(x as f32, y as f32, color);
+                }
+            }
+        }
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                self.draw_cell_outline(x as f32, y as f32, Rgb::BLACK);
+            }
+        }
+
+        // objects are drawn as a small x
+        let mut object_positions = vec![];
+        for layer in self.map.object_layers.iter().filter(|l| l.visible) {
+            for object in layer.objects.iter().filter(|o| o.visible) {
+                object_positions.push(self.map.object_position(object));
+            }
+        }
+        for (x, y) in object_positions {
+            self.canvas.draw_diagonal_line_f32(x - 0.2, y - 0.2, x + 0.2, y + 0.2, Rgb::RED);
+            self.canvas.draw_diagonal_line_f32(x - 0.2, y + 0.2, x + 0.2, y - 0.2, Rgb::RED);
+        }
+        self.canvas.pop_transform();
+
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        if let Some((cell_x, cell_y)) = self.map.cell_at(world_x, world_y) {
+            self.canvas.push_transform(self.map.projection());
+            self.draw_cell_outline(cell_x as f32, cell_y as f32, Rgb::RED);
+            self.canvas.pop_transform();
+
+            if self.mouse_was_clicked {
+                for layer in self.map.layers.iter() {
+                    if let Some(tile) = layer.get(cell_x, cell_y) {
+                        let tileset = self.map.tileset_for(tile.gid).map(|(t, id)| (t.name.clone(), id));
+                        println!("({}, {}) {}: {:?} {:?}", cell_x, cell_y, layer.name, tile, tileset);
+                    }
+                }
+            }
+        }
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        if self.map.orientation == Orientation::Isometric {
+            self.canvas.pan_offset_x = -50.0;
+            self.canvas.pan_offset_y = -50.0;
+        }
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    // pass a path to any .tmx or .tmj map, or view the included example map
+    let path = std::env::args().nth(1)
+        .unwrap_or_else(|| "examples/assets/iso_map.tmx".into());
+    let map = match tiled::load(&path) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Failed to load {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    let my_loop = MyGameLoop {
+        map,
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "tiled_map_viewer".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
@@ -0,0 +1,213 @@
+use simple_game_examples::backend;
+use simple_game_examples::{Rgb, Canvas};
+use backend::{GameLoop, Backend, BackendConf, Event};
+use simple_game_examples::{world_screen::WorldScreen, draw::Draw};
+use simple_game_examples::tile_map::{TileMap, Orientation};
+use simple_game_examples::ui::Ui;
+
+const COLORS: [(&str, Rgb); 6] = [
+    ("grass", Rgb { red: 120, green: 180, blue: 90 }),
+    ("water", Rgb { red: 90, green: 140, blue: 200 }),
+    ("sand", Rgb { red: 220, green: 200, blue: 140 }),
+    ("rock", Rgb { red: 140, green: 130, blue: 120 }),
+    ("lava", Rgb { red: 220, green: 80, blue: 30 }),
+    ("snow", Rgb { red: 240, green: 240, blue: 250 }),
+];
+
+#[derive(Default)]
+pub struct MyGameLoop {
+    pub is_scrolling_up: bool,
+    pub is_scrolling_down: bool,
+    pub mouse_is_dragging: bool,
+    pub mouse_was_clicked: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+
+    pub map: TileMap,
+    /// index into COLORS for each cell
+    pub cells: Vec<usize>,
+    pub brush: usize,
+    pub show_grid: bool,
+    pub zoom: f32,
+    ui: Ui,
+    canvas: WorldScreen,
+}
+
+impl MyGameLoop {
+    fn hovered_cell(&self) -> Option<(usize, usize)> {
+        let (world_x, world_y) = self.canvas.screen_to_world(self.mouse_x, self.mouse_y);
+        self.map.cell_at(world_x, world_y)
+    }
+
+    /// the controls, declared again every frame
+    fn build_ui(&mut self) {
+        let hovered = self.hovered_cell();
+        let ui = &mut self.ui;
+        ui.begin(self.canvas.screen_width, self.canvas.screen_height);
+        ui.begin_panel("tools", 10.0, 10.0, 190.0);
+        match hovered {
+            Some((x, y)) => ui.label(&format!("cell: {}, {}", x, y)),
+            None => ui.label("cell: -"),
+        }
+        ui.checkbox("show grid", &mut self.show_grid);
+        if ui.slider("zoom", &mut self.zoom, 0.25, 4.0) {
+            self.canvas.scale_x = self.zoom;
+            self.canvas.scale_y = self.zoom;
+        }
+        ui.begin_row();
+        if ui.button("reset view") {
+            self.canvas.pan_offset_x = 0.0;
+            self.canvas.pan_offset_y = 0.0;
+            self.zoom = 1.0;
+            self.canvas.scale_x = 1.0;
+            self.canvas.scale_y = 1.0;
+        }
+        if ui.button("clear") {
+            for cell in self.cells.iter_mut() {
+                *cell = 0;
+            }
+        }
+        ui.end_row();
+        ui.separator();
+        ui.label(&format!("brush: {}", COLORS[self.brush].0));
+        ui.begin_scroll_area("brushes", 70.0);
+        for (i, (name, _)) in COLORS.iter().enumerate() {
+            if ui.button(name) {
+                self.brush = i;
+            }
+        }
+        ui.end_scroll_area();
+        ui.end_panel();
+        ui.end();
+    }
+}
+
+impl GameLoop for MyGameLoop {
+    fn update(&mut self, events: Vec<Event>) {
+        self.mouse_was_clicked = false;
+        let mut last_pos = None;
+        let mut is_scrolling_up = false;
+        let mut is_scrolling_down = false;
+        for ev in events {
+            // the ui sees every event first. it keeps the ones that are
+            // over its panels, so clicking a button doesn't also paint
+            // the cell under it.
+            let used_by_ui = self.ui.handle_event(&ev);
+            let (mx, my) = match ev {
+                Event::MouseMove { x, y } => (x, y),
+                _ if used_by_ui => { continue; }
+                Event::MouseDown { button, x, y } => {
+                    if self.mouse_is_dragging { continue; }
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = true;
+                        self.canvas.reset_pan(x, y);
+                    }
+                    (x, y)
+                }
+                Event::MouseScroll { up } => {
+                    if up {
+                        is_scrolling_up = true;
+                    } else {
+                        is_scrolling_down = true;
+                    }
+                    continue;
+                }
+                Event::MouseUp { button, x, y } => {
+                    if let backend::MouseButton::Left = button {
+                        self.mouse_is_dragging = false;
+                    } else {
+                        self.mouse_was_clicked = true;
+                    }
+                    (x, y)
+                }
+                _ => { continue; }
+            };
+            last_pos = Some((mx, my));
+        }
+        self.is_scrolling_down = is_scrolling_down;
+        self.is_scrolling_up = is_scrolling_up;
+        if let Some(new_pos) = last_pos {
+            self.mouse_x = new_pos.0;
+            self.mouse_y = new_pos.1;
+        }
+    }
+
+    fn draw(&mut self) -> backend::TextureUpdate {
+        if self.mouse_is_dragging {
+            self.canvas.pan_to(self.mouse_x, self.mouse_y);
+        }
+        self.canvas.handle_scroll(
+            (self.mouse_x, self.mouse_y),
+            self.is_scrolling_up, self.is_scrolling_down
+        );
+        self.zoom = self.canvas.scale_x;
+        if self.mouse_was_clicked {
+            if let Some((x, y)) = self.hovered_cell() {
+                self.cells[y * self.map.width + x] = self.brush;
+            }
+        }
+        self.build_ui();
+
+        self.canvas.fill(Rgb::WHITE);
+        self.canvas.push_transform(self.map.projection());
+        let (x_range, y_range) = self.canvas.visible_cell_range(1.0, 1.0, self.map.map_size());
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let color = COLORS[self.cells[y * self.map.width + x]].1;
+                let (fx, fy) = (x as f32, y as f32);
+                let corners = [(fx, fy), (fx + 1.0, fy), (fx + 1.0, fy + 1.0), (fx, fy + 1.0)];
+                self.canvas.fill_convex_polygon_f32(&corners, color);
+            }
+        }
+        if self.show_grid {
+            for y in y_range.start..=y_range.end {
+                let y = y as f32;
+                self.canvas.draw_diagonal_line_f32(x_range.start as f32, y, x_range.end as f32, y, Rgb::BLACK);
+            }
+            for x in x_range.start..=x_range.end {
+                let x = x as f32;
+                self.canvas.draw_diagonal_line_f32(x, y_range.start as f32, x, y_range.end as f32, Rgb::BLACK);
+            }
+        }
+        self.canvas.pop_transform();
+
+        // the ui is in screen coordinates, so it goes straight onto the canvas
+        self.ui.render(&mut self.canvas.canvas);
+
+        backend::TextureUpdate::UpdateWhole(self.canvas.access_data())
+    }
+
+    fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let color = Rgb::WHITE;
+        let new_canvas = Canvas::new_with_color(width, height, color, bpp);
+        self.canvas.canvas = new_canvas;
+        self.canvas.screen_width = width;
+        self.canvas.screen_height = height;
+        self.canvas.scale_x = 1.0;
+        self.canvas.scale_y = 1.0;
+        self.canvas.scale_factor_up = 1.040;
+        self.canvas.scale_factor_down = 0.960;
+        let data = self.canvas.access_data();
+        data.to_vec()
+    }
+}
+
+fn main() {
+    let (width, height) = (20, 20);
+    println!("right click: paint the cell with the brush, left drag: pan, scroll: zoom. everything else is in the tools panel");
+    let my_loop = MyGameLoop {
+        map: TileMap::new(Orientation::Isometric, width, height, 48, 24),
+        cells: vec![0; width * height],
+        show_grid: true,
+        zoom: 1.0,
+        ..Default::default()
+    };
+    let my_conf = BackendConf {
+        window_title: "ui_widgets".into(),
+        window_width: 800,
+        window_height: 600,
+        fullscreen: false,
+        window_resizable: true,
+    };
+    backend::MQBackend::start(my_conf, my_loop);
+}
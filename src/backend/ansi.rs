@@ -0,0 +1,358 @@
+//! Draws frames in a terminal with 24 bit color escape codes, and turns
+//! what the terminal sends back (keys, and xterm's SGR mouse reports) into
+//! our `Event`s. nothing in here touches the terminal itself, that is
+//! `TerminalBackend`'s job, so it works on plain bytes and strings.
+
+use std::fmt::Write;
+
+use super::{Event, KeyCode, KeyMods, MouseButton};
+use crate::Rgb;
+
+/// the upper half block. its foreground color is the top pixel of a
+/// cell, and the background color is the bottom one.
+pub const HALF_BLOCK: char = '▀';
+
+/// switches to the alternate screen, hides the cursor and turns on
+/// reporting of every mouse move and button in the SGR format
+pub const ENTER: &str = "\x1b[?1049h\x1b[?25l\x1b[?1003h\x1b[?1006h\x1b[2J";
+/// undoes `ENTER`
+pub const LEAVE: &str = "\x1b[?1006l\x1b[?1003l\x1b[0m\x1b[?25h\x1b[?1049l";
+
+/// Turns `Rgb24` frames into the text that draws them, two pixels to a
+/// character cell. it remembers what is on the terminal, and only
+/// redraws the cells that changed since the last frame, which matters
+/// over a slow connection.
+#[derive(Debug, Default)]
+pub struct HalfBlockRenderer {
+    /// the (top, bottom) colors of every cell on the terminal, empty
+    /// until the first frame
+    cells: Vec<(Rgb, Rgb)>,
+    columns: usize,
+}
+
+impl HalfBlockRenderer {
+    pub fn new() -> HalfBlockRenderer {
+        HalfBlockRenderer::default()
+    }
+
+    /// forgets what is on the terminal, so the next frame is drawn whole
+    pub fn invalidate(&mut self) {
+        self.cells.clear();
+    }
+
+    /// the text that draws a `width` x `height` frame of `Rgb24` pixels
+    /// at the top left of the terminal. an odd last row is drawn over
+    /// black.
+    pub fn render(&mut self, frame: &[u8], width: usize, height: usize) -> String {
+        let rows = height.div_ceil(2);
+        if self.columns != width || self.cells.len() != width * rows {
+            self.columns = width;
+            self.cells = vec![];
+        }
+        let pixel = |x: usize, y: usize| {
+            if y >= height {
+                return Rgb::BLACK;
+            }
+            let i = (y * width + x) * 3;
+            Rgb { red: frame[i], green: frame[i + 1], blue: frame[i + 2] }
+        };
+        let mut out = String::new();
+        let (mut foreground, mut background) = (None, None);
+        for row in 0..rows {
+            // where the terminal's cursor is, if it is on this row
+            let mut cursor = None;
+            for column in 0..width {
+                let cell = (pixel(column, row * 2), pixel(column, row * 2 + 1));
+                let i = row * width + column;
+                if self.cells.get(i) == Some(&cell) {
+                    continue;
+                }
+                if cursor != Some(column) {
+                    let _ = write!(out, "\x1b[{};{}H", row + 1, column + 1);
+                }
+                if foreground != Some(cell.0) {
+                    let _ = write!(out, "\x1b[38;2;{};{};{}m", cell.0.red, cell.0.green, cell.0.blue);
+                    foreground = Some(cell.0);
+                }
+                if background != Some(cell.1) {
+                    let _ = write!(out, "\x1b[48;2;{};{};{}m", cell.1.red, cell.1.green, cell.1.blue);
+                    background = Some(cell.1);
+                }
+                out.push(HALF_BLOCK);
+                cursor = Some(column + 1);
+            }
+        }
+        self.cells = (0..rows * width).map(|i| (pixel(i % width, i / width * 2), pixel(i % width, i / width * 2 + 1))).collect();
+        if !out.is_empty() {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+}
+
+/// Collects the bytes that a terminal in raw mode sends, and turns them
+/// into events. a terminal only says that a key was typed, so every key
+/// is a `KeyDown` followed straight away by a `KeyUp`. mouse positions
+/// are in pixels, ie: two per character row.
+#[derive(Debug, Default)]
+pub struct InputParser {
+    /// the start of an escape sequence that hasn't all arrived yet
+    pending: Vec<u8>,
+}
+
+enum Parsed {
+    /// this many bytes were used up
+    Used(usize),
+    /// the sequence isn't complete yet
+    Incomplete,
+}
+
+impl InputParser {
+    pub fn new() -> InputParser {
+        InputParser::default()
+    }
+
+    /// adds the bytes that were just read, and returns the events in them.
+    /// an escape on its own at the end is the escape key, since the rest
+    /// of a sequence arrives in the same read.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        let mut events = vec![];
+        self.pending.extend_from_slice(bytes);
+        let mut start = 0;
+        while start < self.pending.len() {
+            let rest = &self.pending[start..];
+            match parse(rest, &mut events) {
+                Parsed::Used(count) => start += count,
+                Parsed::Incomplete if rest == [0x1b] => {
+                    push_key(&mut events, KeyCode::Escape, KeyMods::default());
+                    start += 1;
+                }
+                Parsed::Incomplete => break,
+            }
+        }
+        self.pending.drain(..start);
+        events
+    }
+}
+
+fn push_key(events: &mut Vec<Event>, code: KeyCode, modifier: KeyMods) {
+    events.push(Event::KeyDown { modifier, code, repeated: false });
+    events.push(Event::KeyUp { modifier, code });
+}
+
+/// the key for a printable ascii character, and whether it needs shift
+fn char_key(c: u8) -> Option<(KeyCode, bool)> {
+    let key = match c {
+        b'a'..=b'z' => (KeyCode::from(KeyCode::A as u32 + (c - b'a') as u32), false),
+        b'A'..=b'Z' => (KeyCode::from(KeyCode::A as u32 + (c - b'A') as u32), true),
+        b'0'..=b'9' => (KeyCode::from(KeyCode::Key0 as u32 + (c - b'0') as u32), false),
+        b' ' => (KeyCode::Space, false),
+        b'\'' => (KeyCode::Apostrophe, false),
+        b',' => (KeyCode::Comma, false),
+        b'-' => (KeyCode::Minus, false),
+        b'.' => (KeyCode::Period, false),
+        b'/' => (KeyCode::Slash, false),
+        b';' => (KeyCode::Semicolon, false),
+        b'=' => (KeyCode::Equal, false),
+        b'[' => (KeyCode::LeftBracket, false),
+        b'\\' => (KeyCode::Backslash, false),
+        b']' => (KeyCode::RightBracket, false),
+        b'`' => (KeyCode::GraveAccent, false),
+        // the shifted symbols of a us keyboard
+        b'"' => (KeyCode::Apostrophe, true),
+        b'<' => (KeyCode::Comma, true),
+        b'_' => (KeyCode::Minus, true),
+        b'>' => (KeyCode::Period, true),
+        b'?' => (KeyCode::Slash, true),
+        b':' => (KeyCode::Semicolon, true),
+        b'+' => (KeyCode::Equal, true),
+        b'{' => (KeyCode::LeftBracket, true),
+        b'|' => (KeyCode::Backslash, true),
+        b'}' => (KeyCode::RightBracket, true),
+        b'~' => (KeyCode::GraveAccent, true),
+        b'!' => (KeyCode::Key1, true),
+        b'@' => (KeyCode::Key2, true),
+        b'#' => (KeyCode::Key3, true),
+        b'$' => (KeyCode::Key4, true),
+        b'%' => (KeyCode::Key5, true),
+        b'^' => (KeyCode::Key6, true),
+        b'&' => (KeyCode::Key7, true),
+        b'*' => (KeyCode::Key8, true),
+        b'(' => (KeyCode::Key9, true),
+        b')' => (KeyCode::Key0, true),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// a single byte that isn't part of an escape sequence
+fn byte_key(c: u8) -> Option<(KeyCode, KeyMods)> {
+    let mut modifier = KeyMods::default();
+    let code = match c {
+        b'\r' | b'\n' => KeyCode::Enter,
+        b'\t' => KeyCode::Tab,
+        0x7f | 0x08 => KeyCode::Backspace,
+        // ctrl and a letter
+        0x01..=0x1a => {
+            modifier.ctrl = true;
+            KeyCode::from(KeyCode::A as u32 + (c - 1) as u32)
+        }
+        _ => {
+            let (code, shift) = char_key(c)?;
+            modifier.shift = shift;
+            code
+        }
+    };
+    Some((code, modifier))
+}
+
+/// the modifiers of a `1;<n>` parameter, which is 1 + a bit for each
+fn modifier_param(n: u32) -> KeyMods {
+    let bits = n.saturating_sub(1);
+    KeyMods { shift: bits & 1 != 0, alt: bits & 2 != 0, ctrl: bits & 4 != 0, logo: bits & 8 != 0 }
+}
+
+/// `ESC [` followed by numbers and a final letter, eg: arrows, `~` keys
+/// and mouse reports
+fn parse_csi(bytes: &[u8], events: &mut Vec<Event>) -> Parsed {
+    // bytes[0..2] is "\x1b["
+    let end = match bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+        Some(i) => i + 2,
+        None => return Parsed::Incomplete,
+    };
+    let last = bytes[end];
+    let params = &bytes[2..end];
+    let used = Parsed::Used(end + 1);
+    if params.first() == Some(&b'<') {
+        parse_sgr_mouse(&params[1..], last, events);
+        return used;
+    }
+    let numbers: Vec<u32> = std::str::from_utf8(params).unwrap_or("")
+        .split(';')
+        .map(|n| n.parse().unwrap_or(0))
+        .collect();
+    let modifier = modifier_param(numbers.get(1).copied().unwrap_or(1));
+    let code = match last {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P' => KeyCode::F1,
+        b'Q' => KeyCode::F2,
+        b'R' => KeyCode::F3,
+        b'S' => KeyCode::F4,
+        b'Z' => {
+            push_key(events, KeyCode::Tab, KeyMods { shift: true, ..Default::default() });
+            return used;
+        }
+        b'~' => match numbers.first().copied().unwrap_or(0) {
+            1 | 7 => KeyCode::Home,
+            2 => KeyCode::Insert,
+            3 => KeyCode::Delete,
+            4 | 8 => KeyCode::End,
+            5 => KeyCode::PageUp,
+            6 => KeyCode::PageDown,
+            15 => KeyCode::F5,
+            17 => KeyCode::F6,
+            18 => KeyCode::F7,
+            19 => KeyCode::F8,
+            20 => KeyCode::F9,
+            21 => KeyCode::F10,
+            23 => KeyCode::F11,
+            24 => KeyCode::F12,
+            _ => KeyCode::Unknown,
+        },
+        _ => KeyCode::Unknown,
+    };
+    push_key(events, code, modifier);
+    used
+}
+
+/// `ESC [ < button ; column ; row` and `M` for a press or `m` for a release
+fn parse_sgr_mouse(params: &[u8], last: u8, events: &mut Vec<Event>) {
+    let numbers: Vec<u32> = std::str::from_utf8(params).unwrap_or("")
+        .split(';')
+        .filter_map(|n| n.parse().ok())
+        .collect();
+    let (button, column, row) = match numbers[..] {
+        [button, column, row] => (button, column, row),
+        _ => return,
+    };
+    // cells count from 1, and each is one pixel wide and two high
+    let (x, y) = (column.saturating_sub(1) as f32, row.saturating_sub(1) as f32 * 2.0);
+    if button & 64 != 0 {
+        // wheel buttons 4 and 5 only ever press
+        events.push(Event::MouseScroll { up: button & 1 == 0 });
+        return;
+    }
+    if button & 32 != 0 {
+        events.push(Event::MouseMove { x, y });
+        return;
+    }
+    let button = match button & 3 {
+        0 => MouseButton::Left,
+        1 => MouseButton::Middle,
+        2 => MouseButton::Right,
+        _ => MouseButton::Unknown,
+    };
+    events.push(if last == b'M' { Event::MouseDown { button, x, y } } else { Event::MouseUp { button, x, y } });
+}
+
+fn parse(bytes: &[u8], events: &mut Vec<Event>) -> Parsed {
+    if bytes[0] != 0x1b {
+        // a utf-8 character that isn't ascii has no key of its own
+        let length = match bytes[0] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        if bytes.len() < length {
+            return Parsed::Incomplete;
+        }
+        if let Some((code, modifier)) = byte_key(bytes[0]) {
+            push_key(events, code, modifier);
+        }
+        return Parsed::Used(length);
+    }
+    match bytes.get(1) {
+        None => Parsed::Incomplete,
+        Some(b'[') => parse_csi(bytes, events),
+        // F1 to F4 on most terminals
+        Some(b'O') => match bytes.get(2) {
+            None => Parsed::Incomplete,
+            Some(c) => {
+                let code = match c {
+                    b'P' => KeyCode::F1,
+                    b'Q' => KeyCode::F2,
+                    b'R' => KeyCode::F3,
+                    b'S' => KeyCode::F4,
+                    b'A' => KeyCode::Up,
+                    b'B' => KeyCode::Down,
+                    b'C' => KeyCode::Right,
+                    b'D' => KeyCode::Left,
+                    b'H' => KeyCode::Home,
+                    b'F' => KeyCode::End,
+                    _ => KeyCode::Unknown,
+                };
+                push_key(events, code, KeyMods::default());
+                Parsed::Used(3)
+            }
+        },
+        // escape twice is the key itself, and the second one starts over
+        Some(0x1b) => {
+            push_key(events, KeyCode::Escape, KeyMods::default());
+            Parsed::Used(1)
+        }
+        // alt and a key
+        Some(c) => {
+            if let Some((code, mut modifier)) = byte_key(*c) {
+                modifier.alt = true;
+                push_key(events, code, modifier);
+            }
+            Parsed::Used(2)
+        }
+    }
+}
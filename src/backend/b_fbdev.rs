@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant};
+
+use super::*;
+use super::evdev::{InputDevices, InputState};
+use super::fb::Framebuffer;
+use crate::pixel_format::{Bgra32, Rgba32};
+use crate::{Canvas, Rgb};
+
+/// Draws straight to a linux framebuffer (fbdev or a drm dumb buffer)
+/// and reads input from evdev, so a game can run on a console without
+/// X, wayland or a gpu, eg: on a kiosk. there is no window: with
+/// `fullscreen` the game gets the whole screen, otherwise a canvas of the
+/// window size in the middle of it. the game draws rgb like it would for
+/// any other backend, which is converted to the framebuffer's bgr.
+pub struct FbBackend<T: GameLoop> {
+    pub framebuffer: Framebuffer,
+    pub input: InputDevices,
+    pub state: InputState,
+    /// where the game's canvas is on the screen
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+    /// draws a mouse cursor once a mouse has moved, since there is
+    /// nothing else to draw one
+    pub show_cursor: bool,
+    game_loop: T,
+    /// the game's last frame
+    frame: Canvas<Rgba32>,
+    /// the last frame as the framebuffer wants it, kept so the cursor can
+    /// be drawn over it without reading back from the framebuffer (which
+    /// is slow)
+    screen: Canvas<Bgra32>,
+}
+
+impl<T: GameLoop> Backend<T> for FbBackend<T> {
+    type Format = Rgba32;
+
+    fn start(bconf: BackendConf, game_loop: T) where Self: Sized + 'static {
+        let framebuffer = Framebuffer::open_default()
+            .unwrap_or_else(|e| panic!("failed to open a framebuffer: {}", e));
+        let input = InputDevices::open_all();
+        let mut backend = FbBackend::new(&bconf, framebuffer, input, game_loop);
+        backend.run(None);
+    }
+}
+
+impl<T: GameLoop> FbBackend<T> {
+    /// clears the screen and gets the game's first frame
+    pub fn new(bconf: &BackendConf, framebuffer: Framebuffer, input: InputDevices, game_loop: T) -> FbBackend<T> {
+        let layout = framebuffer.layout;
+        let (width, height) = if bconf.fullscreen {
+            (layout.width, layout.height)
+        } else {
+            ((bconf.window_width.max(1) as usize).min(layout.width), (bconf.window_height.max(1) as usize).min(layout.height))
+        };
+        let mut game_loop = game_loop;
+        let bpp = <FbBackend<T> as Backend<T>>::bytes_per_pixel();
+        let mut frame = Canvas::with_format(width, height, Rgba32);
+        let pixels = game_loop.init_canvas(width, height, bpp);
+        let count = pixels.len().min(frame.data.len());
+        frame.data[..count].copy_from_slice(&pixels[..count]);
+        let mut screen = Canvas::with_format(width, height, Bgra32);
+        frame.convert_into(&mut screen);
+        let mut backend = FbBackend {
+            framebuffer,
+            input,
+            state: InputState::new(width, height),
+            left: (layout.width - width) / 2,
+            top: (layout.height - height) / 2,
+            width,
+            height,
+            show_cursor: true,
+            game_loop,
+            frame,
+            screen,
+        };
+        backend.framebuffer.fill(Rgb::BLACK);
+        backend.present();
+        backend
+    }
+
+    pub fn game_loop(&self) -> &T {
+        &self.game_loop
+    }
+
+    pub fn game_loop_mut(&mut self) -> &mut T {
+        &mut self.game_loop
+    }
+
+    /// reads the input, updates and draws the game, and shows the frame
+    /// if anything changed
+    pub fn step(&mut self) {
+        let cursor = (self.state.x, self.state.y);
+        let events = self.input.poll(&mut self.state);
+        self.game_loop.update(events);
+        let changed = match self.game_loop.draw() {
+            TextureUpdate::None => false,
+            TextureUpdate::UpdateWhole(pixels) => {
+                let count = pixels.len().min(self.frame.data.len());
+                self.frame.data[..count].copy_from_slice(&pixels[..count]);
+                true
+            }
+            TextureUpdate::UpdatePart(x, y, width, height, pixels) => {
+                let (x, y, width) = (x.max(0) as usize, y.max(0) as usize, width.max(0) as usize);
+                let columns = width.min(self.width.saturating_sub(x));
+                for row in 0..(height.max(0) as usize).min(self.height.saturating_sub(y)) {
+                    let src = &pixels[row * width * 4..(row * width + columns) * 4];
+                    let start = ((y + row) * self.width + x) * 4;
+                    self.frame.data[start..start + columns * 4].copy_from_slice(src);
+                }
+                true
+            }
+        };
+        if changed {
+            self.frame.convert_into(&mut self.screen);
+        }
+        if changed || cursor != (self.state.x, self.state.y) {
+            self.present();
+        }
+    }
+
+    /// runs `frames` frames, or forever. it waits for the vertical blank
+    /// between them, or if the framebuffer can't do that, for 1/60 of a
+    /// second.
+    pub fn run(&mut self, frames: Option<usize>) {
+        let frame_time = Duration::from_secs_f32(1.0 / 60.0);
+        let mut count = 0;
+        while frames.is_none_or(|frames| count < frames) {
+            let start = Instant::now();
+            self.step();
+            count += 1;
+            if !self.framebuffer.wait_for_vsync() {
+                if let Some(rest) = frame_time.checked_sub(start.elapsed()) {
+                    std::thread::sleep(rest);
+                }
+            }
+        }
+    }
+
+    fn present(&mut self) {
+        self.framebuffer.write_frame(&self.screen.data, self.width, self.height, self.left, self.top);
+        if self.show_cursor && self.state.has_mouse {
+            self.draw_cursor();
+        }
+    }
+
+    fn draw_cursor(&mut self) {
+        let (x, y) = (self.left + self.state.x as usize, self.top + self.state.y as usize);
+        for (row, line) in CURSOR.iter().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let color = match c {
+                    '#' => Rgb::BLACK,
+                    '.' => Rgb::WHITE,
+                    _ => continue,
+                };
+                self.framebuffer.set_pixel(x + column, y + row, color);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "fbdev"))]
+mod tests {
+    use super::*;
+    use crate::draw::Draw;
+    use crate::backend::fb::FbLayout;
+
+    /// draws a plain `Canvas`, like a game would for any other backend
+    struct Red(Canvas);
+
+    impl GameLoop for Red {
+        fn update(&mut self, _events: Vec<Event>) {}
+
+        fn draw(&mut self) -> TextureUpdate<'_> {
+            self.0.fill(Rgb::RED);
+            TextureUpdate::UpdateWhole(self.0.access_data())
+        }
+
+        fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+            self.0 = Canvas::new_with_color(width, height, Rgb::BLUE, bpp);
+            self.0.access_data().to_vec()
+        }
+    }
+
+    #[test]
+    fn rgb_frames_keep_their_colors() {
+        let path = std::env::temp_dir().join(format!("simple_game_fb_backend_{}", std::process::id()));
+        let framebuffer = Framebuffer::open_file(&path, FbLayout::xrgb8888(8, 6)).unwrap();
+        let bconf = BackendConf {
+            window_title: "test".into(),
+            window_width: 4,
+            window_height: 2,
+            fullscreen: false,
+            window_resizable: false,
+        };
+        let mut backend = FbBackend::new(&bconf, framebuffer, InputDevices::default(), Red(Canvas::new(0, 0, 4)));
+        assert_eq!((backend.left, backend.top), (2, 2));
+        assert_eq!(backend.framebuffer.get_pixel(2, 2), Rgb::BLUE);
+        assert_eq!(backend.framebuffer.get_pixel(1, 2), Rgb::BLACK);
+        backend.step();
+        assert_eq!(backend.framebuffer.get_pixel(2, 2), Rgb::RED);
+        assert_eq!(backend.framebuffer.get_pixel(5, 3), Rgb::RED);
+        assert_eq!(backend.framebuffer.get_pixel(6, 3), Rgb::BLACK);
+        std::fs::remove_file(path).unwrap();
+    }
+}
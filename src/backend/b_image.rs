@@ -1,9 +1,12 @@
 use image::{RgbImage};
 use super::{Backend, GameLoop, BackendConf};
+use crate::pixel_format::Rgb24;
 
 pub struct ImageBackend{}
 
 impl<T: GameLoop> Backend<T> for ImageBackend {
+    type Format = Rgb24;
+
     fn start(bconf: BackendConf, game_loop: T) where Self: Sized + 'static {
         let mut game_loop = game_loop;
         let bpp = <ImageBackend as Backend<T>>::bytes_per_pixel();
@@ -12,8 +15,4 @@ impl<T: GameLoop> Backend<T> for ImageBackend {
         let output_file_name = format!("{}.png", bconf.window_title);
         img_out.save(output_file_name).unwrap();
     }
-
-    fn bytes_per_pixel() -> usize {
-        3
-    }
 }
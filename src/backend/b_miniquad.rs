@@ -1,6 +1,7 @@
 use miniquad::{UserData, conf, EventHandler, Context, Pipeline, Bindings, Buffer, BufferType, Texture, Shader, BufferLayout, VertexAttribute, VertexFormat};
 
 use super::*;
+use crate::pixel_format::Rgba32;
 
 #[repr(C)]
 struct Vec2 {
@@ -154,6 +155,8 @@ impl<T: GameLoop> EventHandler for MQBackend<T> {
 }
 
 impl<T: GameLoop> Backend<T> for MQBackend<T> {
+    type Format = Rgba32;
+
     fn start(bconf: BackendConf, game_loop: T) where Self: Sized + 'static {
         let mut conf = conf::Conf::default();
         conf.fullscreen = bconf.fullscreen;
@@ -166,10 +169,6 @@ impl<T: GameLoop> Backend<T> for MQBackend<T> {
             UserData::owning(init_obj, ctx)
         });
     }
-
-    fn bytes_per_pixel() -> usize {
-        4
-    }
 }
 
 impl<T: GameLoop> MQBackend<T> {
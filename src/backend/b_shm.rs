@@ -0,0 +1,226 @@
+use std::env;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
+
+use super::*;
+use super::wayland::WaylandWindow;
+use super::x11::X11Window;
+use crate::pixel_format::{Bgra32, Rgba32};
+use crate::Canvas;
+
+/// whether there is something to read from `fd` within `timeout_ms` (-1
+/// waits for as long as it takes)
+pub(crate) fn readable(fd: RawFd, timeout_ms: i32) -> io::Result<bool> {
+    let mut poll = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    loop {
+        match unsafe { libc::poll(&mut poll, 1, timeout_ms) } {
+            -1 => {
+                let error = io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+            count => return Ok(count > 0),
+        }
+    }
+}
+
+/// A window whose frames are in memory shared with the window system, on
+/// whichever of wayland or X11 is running.
+pub enum ShmWindow {
+    Wayland(WaylandWindow),
+    X11(X11Window),
+}
+
+impl ShmWindow {
+    /// a wayland window if `WAYLAND_DISPLAY` is set, otherwise (or if
+    /// that fails and there is an X11 server too, eg: xwayland) an X11
+    /// window on `DISPLAY`
+    pub fn open(bconf: &BackendConf) -> io::Result<ShmWindow> {
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            match WaylandWindow::open_default(bconf) {
+                Ok(window) => return Ok(ShmWindow::Wayland(window)),
+                Err(e) if env::var_os("DISPLAY").is_none() => return Err(e),
+                Err(_) => {}
+            }
+        }
+        X11Window::open_default(bconf).map(ShmWindow::X11)
+    }
+
+    /// the size of the window when it was opened, which is what the
+    /// frames are
+    pub fn size(&self) -> (usize, usize) {
+        match self {
+            ShmWindow::Wayland(window) => (window.width, window.height),
+            ShmWindow::X11(window) => (window.width, window.height),
+        }
+    }
+
+    pub fn closed(&self) -> bool {
+        match self {
+            ShmWindow::Wayland(window) => window.closed,
+            ShmWindow::X11(window) => window.closed,
+        }
+    }
+
+    /// whether a frame can be shown now. a wayland compositor says when
+    /// it wants the next one, an X11 server takes them whenever.
+    pub fn wants_frame(&self) -> bool {
+        match self {
+            ShmWindow::Wayland(window) => window.wants_frame(),
+            ShmWindow::X11(_) => true,
+        }
+    }
+
+    /// whether the last frame has to be shown again, because part of the
+    /// window was uncovered. a wayland compositor keeps it.
+    pub fn needs_redraw(&self) -> bool {
+        match self {
+            ShmWindow::Wayland(_) => false,
+            ShmWindow::X11(window) => window.exposed,
+        }
+    }
+
+    /// waits up to `timeout_ms` for input (0 doesn't wait), and adds
+    /// whatever arrived to `events`
+    pub fn poll(&mut self, timeout_ms: i32, events: &mut Vec<Event>) -> io::Result<()> {
+        match self {
+            ShmWindow::Wayland(window) => window.poll(timeout_ms, events),
+            ShmWindow::X11(window) => window.poll(timeout_ms, events),
+        }
+    }
+
+    /// shows a `width` x `height` frame of `Bgra32` pixels
+    pub fn present(&mut self, frame: &[u8], width: usize, height: usize) -> io::Result<()> {
+        match self {
+            ShmWindow::Wayland(window) => window.present(frame),
+            ShmWindow::X11(window) => window.present(frame, width, height),
+        }
+    }
+}
+
+/// Shows the game in a window without a gpu: each frame is copied into
+/// shared memory that a wayland compositor (`wl_shm`) or an X11 server
+/// (MIT-SHM, or plain `PutImage` if the server is on another machine)
+/// reads it from, so it works on machines without gl drivers and in Xvfb.
+/// the canvas is the size the window was opened at, or the screen's if
+/// `fullscreen`, and is drawn in the top left corner of a bigger window.
+/// the game draws rgb like it would for any other backend, which is
+/// converted to the bgr that both window systems want.
+pub struct ShmBackend<T: GameLoop> {
+    pub window: ShmWindow,
+    pub width: usize,
+    pub height: usize,
+    pub fps: f32,
+    game_loop: T,
+    /// the game's last frame, so a partial update can be shown
+    frame: Canvas<Rgba32>,
+    /// the last frame as the window system wants it
+    screen: Canvas<Bgra32>,
+    /// whether the frame changed since it was last shown
+    changed: bool,
+    /// input that arrived while waiting for the next frame
+    events: Vec<Event>,
+}
+
+impl<T: GameLoop> Backend<T> for ShmBackend<T> {
+    type Format = Rgba32;
+
+    fn start(bconf: BackendConf, game_loop: T) where Self: Sized + 'static {
+        let window = ShmWindow::open(&bconf).unwrap_or_else(|e| panic!("failed to open a window: {}", e));
+        let mut backend = ShmBackend::new(&bconf, window, game_loop);
+        if let Err(e) = backend.run(None) {
+            panic!("shm backend failed: {}", e);
+        }
+    }
+}
+
+impl<T: GameLoop> ShmBackend<T> {
+    pub fn new(_bconf: &BackendConf, window: ShmWindow, game_loop: T) -> ShmBackend<T> {
+        let (width, height) = window.size();
+        let mut game_loop = game_loop;
+        let bpp = <ShmBackend<T> as Backend<T>>::bytes_per_pixel();
+        let mut frame = Canvas::with_format(width, height, Rgba32);
+        let pixels = game_loop.init_canvas(width, height, bpp);
+        let count = pixels.len().min(frame.data.len());
+        frame.data[..count].copy_from_slice(&pixels[..count]);
+        ShmBackend {
+            window,
+            width,
+            height,
+            fps: 60.0,
+            game_loop,
+            frame,
+            screen: Canvas::with_format(width, height, Bgra32),
+            changed: true,
+            events: vec![],
+        }
+    }
+
+    pub fn game_loop(&self) -> &T {
+        &self.game_loop
+    }
+
+    pub fn game_loop_mut(&mut self) -> &mut T {
+        &mut self.game_loop
+    }
+
+    /// reads the input, updates and draws the game, and shows the frame
+    /// if it changed and the window system wants one. false once the
+    /// window was closed.
+    pub fn step(&mut self) -> io::Result<bool> {
+        self.window.poll(0, &mut self.events)?;
+        if self.window.closed() {
+            return Ok(false);
+        }
+        self.game_loop.update(std::mem::take(&mut self.events));
+        match self.game_loop.draw() {
+            TextureUpdate::None => {}
+            TextureUpdate::UpdateWhole(pixels) => {
+                let count = pixels.len().min(self.frame.data.len());
+                self.frame.data[..count].copy_from_slice(&pixels[..count]);
+                self.changed = true;
+            }
+            TextureUpdate::UpdatePart(x, y, width, height, pixels) => {
+                let (x, y, width) = (x.max(0) as usize, y.max(0) as usize, width.max(0) as usize);
+                let columns = width.min(self.width.saturating_sub(x));
+                for row in 0..(height.max(0) as usize).min(self.height.saturating_sub(y)) {
+                    let src = &pixels[row * width * 4..(row * width + columns) * 4];
+                    let start = ((y + row) * self.width + x) * 4;
+                    self.frame.data[start..start + columns * 4].copy_from_slice(src);
+                }
+                self.changed = true;
+            }
+        }
+        if (self.changed || self.window.needs_redraw()) && self.window.wants_frame() {
+            if self.changed {
+                self.frame.convert_into(&mut self.screen);
+            }
+            self.window.present(&self.screen.data, self.width, self.height)?;
+            self.changed = false;
+        }
+        Ok(true)
+    }
+
+    /// runs `frames` frames, or until the window is closed. in between
+    /// them it waits for input, for up to 1/`fps` of a second.
+    pub fn run(&mut self, frames: Option<usize>) -> io::Result<()> {
+        let frame_time = Duration::from_secs_f32(1.0 / self.fps);
+        let mut count = 0;
+        while frames.is_none_or(|frames| count < frames) {
+            let start = Instant::now();
+            if !self.step()? {
+                return Ok(());
+            }
+            count += 1;
+            while let Some(rest) = frame_time.checked_sub(start.elapsed()) {
+                self.window.poll(rest.as_millis() as i32, &mut self.events)?;
+                if rest.as_millis() == 0 {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
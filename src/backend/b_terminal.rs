@@ -0,0 +1,189 @@
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use super::*;
+use super::ansi::{HalfBlockRenderer, InputParser, ENTER, LEAVE};
+use crate::pixel_format::Rgb24;
+
+/// The terminal that the program runs in, in raw mode: keys arrive as
+/// they are typed and aren't echoed, reads don't wait, and ctrl+c is a
+/// key rather than a signal. everything is put back on drop.
+pub struct Terminal {
+    saved: libc::termios,
+}
+
+impl Terminal {
+    pub fn enter() -> io::Result<Terminal> {
+        let fd = libc::STDIN_FILENO;
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = saved;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        // reads return whatever is there, even nothing
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let terminal = Terminal { saved };
+        terminal.write(ENTER)?;
+        Ok(terminal)
+    }
+
+    /// the size in characters, (columns, rows)
+    pub fn size(&self) -> io::Result<(usize, usize)> {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((size.ws_col as usize, size.ws_row as usize))
+    }
+
+    /// everything that was typed since the last read
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        let mut input = vec![];
+        let mut buffer = [0u8; 1024];
+        loop {
+            match io::stdin().lock().read(&mut buffer) {
+                Ok(0) => return Ok(input),
+                Ok(count) => input.extend_from_slice(&buffer[..count]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn write(&self, text: &str) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        out.write_all(text.as_bytes())?;
+        out.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.write(LEAVE);
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+        }
+    }
+}
+
+/// Runs a game in the terminal, eg: over ssh on a machine without a
+/// screen. each character is two pixels, drawn as a `▀` with 24 bit
+/// colors (so the terminal has to support those), and the mouse is read
+/// with xterm's SGR mouse reports. the canvas is as big as the terminal,
+/// or as the window size if that is smaller and not `fullscreen`.
+/// ctrl+c quits.
+pub struct TerminalBackend<T: GameLoop> {
+    pub terminal: Terminal,
+    pub width: usize,
+    pub height: usize,
+    /// frames per second, terminals are slow to draw so this is lower
+    /// than a window's
+    pub fps: f32,
+    renderer: HalfBlockRenderer,
+    parser: InputParser,
+    game_loop: T,
+    /// the game's last frame, so a partial update can be drawn
+    frame: Vec<u8>,
+}
+
+impl<T: GameLoop> Backend<T> for TerminalBackend<T> {
+    type Format = Rgb24;
+
+    fn start(bconf: BackendConf, game_loop: T) where Self: Sized + 'static {
+        let terminal = Terminal::enter().unwrap_or_else(|e| panic!("failed to set up the terminal: {}", e));
+        let mut backend = TerminalBackend::new(&bconf, terminal, game_loop);
+        if let Err(e) = backend.run() {
+            drop(backend);
+            panic!("terminal backend failed: {}", e);
+        }
+    }
+}
+
+impl<T: GameLoop> TerminalBackend<T> {
+    pub fn new(bconf: &BackendConf, terminal: Terminal, game_loop: T) -> TerminalBackend<T> {
+        // some terminals (eg: a pipe, or a pty nobody sized) say they are empty
+        let (columns, rows) = terminal.size().ok()
+            .filter(|(columns, rows)| *columns > 0 && *rows > 0)
+            .unwrap_or((80, 24));
+        let (mut width, mut height) = (columns, rows * 2);
+        if !bconf.fullscreen {
+            width = width.min(bconf.window_width.max(1) as usize);
+            height = height.min(bconf.window_height.max(1) as usize);
+        }
+        let mut game_loop = game_loop;
+        let bpp = <TerminalBackend<T> as Backend<T>>::bytes_per_pixel();
+        let mut renderer = HalfBlockRenderer::new();
+        let mut frame = game_loop.init_canvas(width, height, bpp);
+        frame.resize(width * height * bpp, 0);
+        let _ = terminal.write(&renderer.render(&frame, width, height));
+        TerminalBackend {
+            terminal,
+            width,
+            height,
+            fps: 30.0,
+            renderer,
+            parser: InputParser::new(),
+            game_loop,
+            frame,
+        }
+    }
+
+    pub fn game_loop(&self) -> &T {
+        &self.game_loop
+    }
+
+    /// reads the input, updates and draws the game, and draws whatever
+    /// changed. false once ctrl+c was pressed.
+    pub fn step(&mut self) -> io::Result<bool> {
+        let events = self.parser.feed(&self.terminal.read()?);
+        let quit = events.iter().any(|event| matches!(
+            event,
+            Event::KeyDown { code: KeyCode::C, modifier: KeyMods { ctrl: true, .. }, .. }
+        ));
+        if quit {
+            return Ok(false);
+        }
+        // events outside of a smaller canvas are still sent, like a
+        // window's are when the mouse is dragged out of it
+        self.game_loop.update(events);
+        match self.game_loop.draw() {
+            TextureUpdate::None => return Ok(true),
+            TextureUpdate::UpdateWhole(pixels) => {
+                let count = pixels.len().min(self.frame.len());
+                self.frame[..count].copy_from_slice(&pixels[..count]);
+            }
+            TextureUpdate::UpdatePart(x, y, width, height, pixels) => {
+                let (x, y, width) = (x.max(0) as usize, y.max(0) as usize, width.max(0) as usize);
+                let columns = width.min(self.width.saturating_sub(x));
+                for row in 0..(height.max(0) as usize).min(self.height.saturating_sub(y)) {
+                    let src = &pixels[row * width * 3..(row * width + columns) * 3];
+                    let start = ((y + row) * self.width + x) * 3;
+                    self.frame[start..start + columns * 3].copy_from_slice(src);
+                }
+            }
+        }
+        // only the cells that changed are sent
+        let text = self.renderer.render(&self.frame, self.width, self.height);
+        self.terminal.write(&text)?;
+        Ok(true)
+    }
+
+    /// runs until ctrl+c
+    pub fn run(&mut self) -> io::Result<()> {
+        let frame_time = Duration::from_secs_f32(1.0 / self.fps);
+        loop {
+            let start = Instant::now();
+            if !self.step()? {
+                return Ok(());
+            }
+            if let Some(rest) = frame_time.checked_sub(start.elapsed()) {
+                std::thread::sleep(rest);
+            }
+        }
+    }
+}
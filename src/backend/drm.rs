@@ -0,0 +1,331 @@
+//! Just enough of the kernel's mode setting (KMS) api to show a single
+//! dumb buffer, ie: plain memory that the cpu draws into, on a display.
+//! this is what `Framebuffer::open_drm` uses on machines that have a drm
+//! driver but no fbdev emulation.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem::size_of;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use super::fb::{ioc, ioctl, FbLayout, Mapping};
+
+const DRM_MODE_CONNECTED: u32 = 1;
+const DRM_MODE_TYPE_PREFERRED: u32 = 1 << 3;
+const DRM_VBLANK_RELATIVE: u32 = 1;
+
+const fn drm_iowr<T>(nr: u8) -> u32 {
+    ioc(3, b'd', nr, size_of::<T>())
+}
+
+/// `struct drm_mode_modeinfo`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct ModeInfo {
+    clock: u32,
+    hdisplay: u16,
+    hsync_start: u16,
+    hsync_end: u16,
+    htotal: u16,
+    hskew: u16,
+    vdisplay: u16,
+    vsync_start: u16,
+    vsync_end: u16,
+    vtotal: u16,
+    vscan: u16,
+    vrefresh: u32,
+    flags: u32,
+    kind: u32,
+    name: [u8; 32],
+}
+
+/// `struct drm_mode_card_res`
+#[repr(C)]
+#[derive(Default)]
+struct CardResources {
+    fb_id_ptr: u64,
+    crtc_id_ptr: u64,
+    connector_id_ptr: u64,
+    encoder_id_ptr: u64,
+    count_fbs: u32,
+    count_crtcs: u32,
+    count_connectors: u32,
+    count_encoders: u32,
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+}
+
+/// `struct drm_mode_get_connector`
+#[repr(C)]
+#[derive(Default)]
+struct GetConnector {
+    encoders_ptr: u64,
+    modes_ptr: u64,
+    props_ptr: u64,
+    prop_values_ptr: u64,
+    count_modes: u32,
+    count_props: u32,
+    count_encoders: u32,
+    encoder_id: u32,
+    connector_id: u32,
+    connector_type: u32,
+    connector_type_id: u32,
+    connection: u32,
+    mm_width: u32,
+    mm_height: u32,
+    subpixel: u32,
+    pad: u32,
+}
+
+/// `struct drm_mode_get_encoder`
+#[repr(C)]
+#[derive(Default)]
+struct GetEncoder {
+    encoder_id: u32,
+    encoder_type: u32,
+    crtc_id: u32,
+    possible_crtcs: u32,
+    possible_clones: u32,
+}
+
+/// `struct drm_mode_crtc`
+#[repr(C)]
+#[derive(Default)]
+struct Crtc {
+    set_connectors_ptr: u64,
+    count_connectors: u32,
+    crtc_id: u32,
+    fb_id: u32,
+    x: u32,
+    y: u32,
+    gamma_size: u32,
+    mode_valid: u32,
+    mode: ModeInfo,
+}
+
+/// `struct drm_mode_create_dumb`
+#[repr(C)]
+#[derive(Default)]
+struct CreateDumb {
+    height: u32,
+    width: u32,
+    bpp: u32,
+    flags: u32,
+    handle: u32,
+    pitch: u32,
+    size: u64,
+}
+
+/// `struct drm_mode_map_dumb`
+#[repr(C)]
+#[derive(Default)]
+struct MapDumb {
+    handle: u32,
+    pad: u32,
+    offset: u64,
+}
+
+/// `struct drm_mode_destroy_dumb`
+#[repr(C)]
+#[derive(Default)]
+struct DestroyDumb {
+    handle: u32,
+}
+
+/// `struct drm_mode_fb_cmd`
+#[repr(C)]
+#[derive(Default)]
+struct FbCmd {
+    fb_id: u32,
+    width: u32,
+    height: u32,
+    pitch: u32,
+    bpp: u32,
+    depth: u32,
+    handle: u32,
+}
+
+/// `union drm_wait_vblank`, which is a request going in and a reply
+/// (with the time of the vblank in the last two fields) coming out
+#[repr(C)]
+#[derive(Default)]
+struct WaitVblank {
+    kind: u32,
+    sequence: u32,
+    signal_or_seconds: libc::c_ulong,
+    microseconds: libc::c_long,
+}
+
+const GETRESOURCES: u32 = drm_iowr::<CardResources>(0xA0);
+const GETCRTC: u32 = drm_iowr::<Crtc>(0xA1);
+const SETCRTC: u32 = drm_iowr::<Crtc>(0xA2);
+const GETENCODER: u32 = drm_iowr::<GetEncoder>(0xA6);
+const GETCONNECTOR: u32 = drm_iowr::<GetConnector>(0xA7);
+const ADDFB: u32 = drm_iowr::<FbCmd>(0xAE);
+const RMFB: u32 = drm_iowr::<u32>(0xAF);
+const CREATE_DUMB: u32 = drm_iowr::<CreateDumb>(0xB2);
+const MAP_DUMB: u32 = drm_iowr::<MapDumb>(0xB3);
+const DESTROY_DUMB: u32 = drm_iowr::<DestroyDumb>(0xB4);
+const WAIT_VBLANK: u32 = drm_iowr::<WaitVblank>(0x3A);
+
+fn not_found(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, msg)
+}
+
+/// A 32 bit xrgb buffer in video memory, shown on one display for as long
+/// as it lives. when it is dropped the display is set back to what it was
+/// showing before (usually the console).
+pub struct DumbBuffer {
+    pub layout: FbLayout,
+    file: File,
+    handle: u32,
+    fb_id: u32,
+    size: u64,
+    connector_id: u32,
+    /// what the crtc showed before, to put back on drop
+    saved_crtc: Crtc,
+}
+
+impl DumbBuffer {
+    /// finds the first connected display of the card at `path` and shows
+    /// a new buffer on it at the display's preferred mode
+    pub fn open(path: &Path) -> io::Result<DumbBuffer> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let fd = file.as_raw_fd();
+
+        // asked twice: once for the counts, and then for the ids
+        let mut resources = CardResources::default();
+        ioctl(fd, GETRESOURCES, &mut resources)?;
+        let mut crtcs = vec![0u32; resources.count_crtcs as usize];
+        let mut connectors = vec![0u32; resources.count_connectors as usize];
+        resources = CardResources {
+            crtc_id_ptr: crtcs.as_mut_ptr() as u64,
+            connector_id_ptr: connectors.as_mut_ptr() as u64,
+            count_crtcs: crtcs.len() as u32,
+            count_connectors: connectors.len() as u32,
+            ..Default::default()
+        };
+        ioctl(fd, GETRESOURCES, &mut resources)?;
+        crtcs.truncate(resources.count_crtcs as usize);
+        connectors.truncate(resources.count_connectors as usize);
+
+        let (connector, mode) = connectors.iter()
+            .filter_map(|id| connected_mode(&file, *id).transpose())
+            .next()
+            .ok_or_else(|| not_found("no connected display"))??;
+        let crtc_id = crtc_for(&file, &connector, &crtcs)?;
+
+        let mut saved_crtc = Crtc { crtc_id, ..Default::default() };
+        ioctl(fd, GETCRTC, &mut saved_crtc)?;
+
+        let (width, height) = (mode.hdisplay as u32, mode.vdisplay as u32);
+        let mut create = CreateDumb { width, height, bpp: 32, ..Default::default() };
+        ioctl(fd, CREATE_DUMB, &mut create)?;
+        let mut buffer = DumbBuffer {
+            layout: FbLayout { stride: create.pitch as usize, ..FbLayout::xrgb8888(width as usize, height as usize) },
+            file,
+            handle: create.handle,
+            fb_id: 0,
+            size: create.size,
+            connector_id: connector.connector_id,
+            saved_crtc,
+        };
+        // from here on, drop cleans up whatever was made
+        let mut fb = FbCmd { width, height, pitch: create.pitch, bpp: 32, depth: 24, handle: create.handle, ..Default::default() };
+        ioctl(fd, ADDFB, &mut fb)?;
+        buffer.fb_id = fb.fb_id;
+
+        let mut connector_id = buffer.connector_id;
+        let mut set = Crtc {
+            set_connectors_ptr: &mut connector_id as *mut u32 as u64,
+            count_connectors: 1,
+            crtc_id,
+            fb_id: fb.fb_id,
+            mode_valid: 1,
+            mode,
+            ..Default::default()
+        };
+        ioctl(fd, SETCRTC, &mut set)?;
+        Ok(buffer)
+    }
+
+    pub(crate) fn map(&self) -> io::Result<Mapping> {
+        let mut map = MapDumb { handle: self.handle, ..Default::default() };
+        ioctl(self.file.as_raw_fd(), MAP_DUMB, &mut map)?;
+        Mapping::new(self.file.as_raw_fd(), self.size as usize, map.offset)
+    }
+
+    /// waits for the next vertical blank of the first crtc
+    pub fn wait_for_vblank(&self) -> bool {
+        let mut wait = WaitVblank { kind: DRM_VBLANK_RELATIVE, sequence: 1, ..Default::default() };
+        ioctl(self.file.as_raw_fd(), WAIT_VBLANK, &mut wait).is_ok()
+    }
+}
+
+impl Drop for DumbBuffer {
+    fn drop(&mut self) {
+        let fd = self.file.as_raw_fd();
+        let saved = &self.saved_crtc;
+        if saved.fb_id != 0 {
+            let mut connector_id = self.connector_id;
+            let mut restore = Crtc {
+                set_connectors_ptr: &mut connector_id as *mut u32 as u64,
+                count_connectors: 1,
+                crtc_id: saved.crtc_id,
+                fb_id: saved.fb_id,
+                x: saved.x,
+                y: saved.y,
+                mode_valid: saved.mode_valid,
+                mode: saved.mode,
+                ..Default::default()
+            };
+            let _ = ioctl(fd, SETCRTC, &mut restore);
+        }
+        if self.fb_id != 0 {
+            let _ = ioctl(fd, RMFB, &mut self.fb_id);
+        }
+        let _ = ioctl(fd, DESTROY_DUMB, &mut DestroyDumb { handle: self.handle });
+    }
+}
+
+/// the connector and its preferred mode (or its first one), if there is
+/// a display connected to it
+fn connected_mode(file: &File, connector_id: u32) -> io::Result<Option<(GetConnector, ModeInfo)>> {
+    let fd = file.as_raw_fd();
+    let mut connector = GetConnector { connector_id, ..Default::default() };
+    ioctl(fd, GETCONNECTOR, &mut connector)?;
+    if connector.connection != DRM_MODE_CONNECTED || connector.count_modes == 0 {
+        return Ok(None);
+    }
+    let mut modes = vec![ModeInfo::default(); connector.count_modes as usize];
+    connector = GetConnector {
+        connector_id,
+        modes_ptr: modes.as_mut_ptr() as u64,
+        count_modes: modes.len() as u32,
+        ..Default::default()
+    };
+    ioctl(fd, GETCONNECTOR, &mut connector)?;
+    // the count can go up between the two calls, but only `modes.len()` were written
+    modes.truncate(connector.count_modes as usize);
+    let mode = modes.iter().find(|mode| mode.kind & DRM_MODE_TYPE_PREFERRED != 0).or_else(|| modes.first());
+    Ok(mode.map(|mode| (connector, *mode)))
+}
+
+/// the crtc that the connector's encoder is using, or the first one it could use
+fn crtc_for(file: &File, connector: &GetConnector, crtcs: &[u32]) -> io::Result<u32> {
+    if connector.encoder_id == 0 {
+        return crtcs.first().copied().ok_or_else(|| not_found("no crtc"));
+    }
+    let mut encoder = GetEncoder { encoder_id: connector.encoder_id, ..Default::default() };
+    ioctl(file.as_raw_fd(), GETENCODER, &mut encoder)?;
+    if encoder.crtc_id != 0 {
+        return Ok(encoder.crtc_id);
+    }
+    crtcs.iter().enumerate()
+        .find(|(i, _)| encoder.possible_crtcs & (1 << i) != 0)
+        .map(|(_, id)| *id)
+        .ok_or_else(|| not_found("no crtc for the display"))
+}
@@ -0,0 +1,358 @@
+//! Reads keyboards, mice and touchscreens straight from their evdev
+//! devices (`/dev/input/event*`), for backends that have no window system
+//! to get events from.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem::size_of;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use super::fb::{ioc, ioctl};
+pub use super::keycodes::{key_code, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT};
+use super::{Event, KeyCode, KeyMods, MouseButton};
+
+pub const EV_SYN: u16 = 0;
+pub const EV_KEY: u16 = 1;
+pub const EV_REL: u16 = 2;
+pub const EV_ABS: u16 = 3;
+pub const SYN_REPORT: u16 = 0;
+pub const SYN_DROPPED: u16 = 3;
+pub const REL_X: u16 = 0;
+pub const REL_Y: u16 = 1;
+pub const REL_WHEEL: u16 = 8;
+pub const ABS_X: u16 = 0;
+pub const ABS_Y: u16 = 1;
+pub const BTN_TOUCH: u16 = 0x14a;
+
+/// `struct input_absinfo`
+#[repr(C)]
+#[derive(Default)]
+struct AbsInfo {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
+/// writes one event the way the kernel would, eg: to make a file that a
+/// fake `InputDevice` reads from
+pub fn write_event<W: Write>(out: &mut W, kind: u16, code: u16, value: i32) -> io::Result<()> {
+    let event = libc::input_event {
+        time: libc::timeval { tv_sec: 0, tv_usec: 0 },
+        type_: kind,
+        code,
+        value,
+    };
+    let bytes = unsafe { std::slice::from_raw_parts(&event as *const _ as *const u8, size_of::<libc::input_event>()) };
+    out.write_all(bytes)
+}
+
+fn mouse_button(code: u16) -> Option<MouseButton> {
+    match code {
+        BTN_LEFT | BTN_TOUCH => Some(MouseButton::Left),
+        BTN_RIGHT => Some(MouseButton::Right),
+        BTN_MIDDLE => Some(MouseButton::Middle),
+        // the other buttons of mice, which are all below the joystick ones
+        0x113..=0x117 => Some(MouseButton::Unknown),
+        _ => None,
+    }
+}
+
+/// Turns evdev events into our `Event`s. the kernel sends them in packets
+/// that end with a `SYN_REPORT`, eg: the x and y of a mouse move and a
+/// button press, so nothing comes out until the end of a packet, and then
+/// the move comes first so buttons are pressed where the mouse ended up.
+#[derive(Debug, Default)]
+pub struct InputState {
+    /// where the mouse is, kept inside of the screen
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub modifiers: KeyMods,
+    /// whether a mouse (rather than only a touchscreen) has moved the
+    /// pointer, ie: whether the backend should draw a cursor for it
+    pub has_mouse: bool,
+    moved: bool,
+    scroll: i32,
+    buttons: Vec<(MouseButton, bool)>,
+    keys: Vec<Event>,
+    /// events were lost, so the rest of the packet is thrown away
+    dropping: bool,
+}
+
+impl InputState {
+    /// the pointer starts in the middle of a `width` x `height` screen
+    pub fn new(width: usize, height: usize) -> InputState {
+        InputState {
+            x: width as f32 / 2.0,
+            y: height as f32 / 2.0,
+            width: width as f32,
+            height: height as f32,
+            ..Default::default()
+        }
+    }
+
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = (x.clamp(0.0, (self.width - 1.0).max(0.0)), y.clamp(0.0, (self.height - 1.0).max(0.0)));
+        if (x, y) != (self.x, self.y) {
+            self.x = x;
+            self.y = y;
+            self.moved = true;
+        }
+    }
+
+    fn set_modifier(&mut self, code: KeyCode, down: bool) {
+        match code {
+            KeyCode::LeftShift | KeyCode::RightShift => self.modifiers.shift = down,
+            KeyCode::LeftControl | KeyCode::RightControl => self.modifiers.ctrl = down,
+            KeyCode::LeftAlt | KeyCode::RightAlt => self.modifiers.alt = down,
+            KeyCode::LeftSuper | KeyCode::RightSuper => self.modifiers.logo = down,
+            _ => {}
+        }
+    }
+
+    /// handles one evdev event, and adds to `events` at the end of a
+    /// packet. `range` is the minimum and maximum of an `EV_ABS` axis,
+    /// which is scaled to the screen (without one the values are pixels).
+    pub fn handle(&mut self, kind: u16, code: u16, value: i32, range: Option<(i32, i32)>, events: &mut Vec<Event>) {
+        if self.dropping {
+            if (kind, code) == (EV_SYN, SYN_REPORT) {
+                self.dropping = false;
+            }
+            return;
+        }
+        match (kind, code) {
+            (EV_SYN, SYN_REPORT) => self.flush(events),
+            (EV_SYN, SYN_DROPPED) => {
+                self.moved = false;
+                self.scroll = 0;
+                self.buttons.clear();
+                self.keys.clear();
+                self.dropping = true;
+            }
+            (EV_REL, REL_X) => {
+                self.has_mouse = true;
+                self.move_to(self.x + value as f32, self.y);
+            }
+            (EV_REL, REL_Y) => {
+                self.has_mouse = true;
+                self.move_to(self.x, self.y + value as f32);
+            }
+            (EV_REL, REL_WHEEL) => self.scroll += value,
+            (EV_ABS, ABS_X) | (EV_ABS, ABS_Y) => {
+                let (size, position) = if code == ABS_X { (self.width, self.x) } else { (self.height, self.y) };
+                let position = match range {
+                    Some((min, max)) if max > min => (value - min) as f32 / (max - min) as f32 * size,
+                    Some(_) => position,
+                    None => value as f32,
+                };
+                if code == ABS_X {
+                    self.move_to(position, self.y);
+                } else {
+                    self.move_to(self.x, position);
+                }
+            }
+            (EV_KEY, code) => {
+                if let Some(button) = mouse_button(code) {
+                    // a touchscreen also sends BTN_TOUCH with its tools, ignore repeats
+                    if value != 2 {
+                        self.buttons.push((button, value == 1));
+                    }
+                    return;
+                }
+                let code = key_code(code);
+                self.set_modifier(code, value != 0);
+                let modifier = self.modifiers;
+                self.keys.push(match value {
+                    0 => Event::KeyUp { modifier, code },
+                    _ => Event::KeyDown { modifier, code, repeated: value == 2 },
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn flush(&mut self, events: &mut Vec<Event>) {
+        let (x, y) = (self.x, self.y);
+        if self.moved {
+            events.push(Event::MouseMove { x, y });
+            self.moved = false;
+        }
+        for (button, down) in self.buttons.drain(..) {
+            events.push(if down { Event::MouseDown { button, x, y } } else { Event::MouseUp { button, x, y } });
+        }
+        if self.scroll != 0 {
+            events.push(Event::MouseScroll { up: self.scroll > 0 });
+            self.scroll = 0;
+        }
+        events.append(&mut self.keys);
+    }
+}
+
+/// One evdev device, read without blocking. it can also be any other file
+/// of events (see `write_event`), which is read to the end and then has
+/// nothing more to say.
+pub struct InputDevice {
+    pub path: PathBuf,
+    file: File,
+    /// the range of ABS_X and ABS_Y, if the device has them
+    abs_range: [Option<(i32, i32)>; 2],
+    /// the start of an event that was only partly read
+    partial: Vec<u8>,
+}
+
+impl InputDevice {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<InputDevice> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(&path)?;
+        let mut abs_range = [None; 2];
+        for (axis, range) in abs_range.iter_mut().enumerate() {
+            // EVIOCGABS, which fails for devices without the axis and for files
+            let mut info = AbsInfo::default();
+            let request = ioc(2, b'E', 0x40 + axis as u8, size_of::<AbsInfo>());
+            if ioctl(file.as_raw_fd(), request, &mut info).is_ok() {
+                *range = Some((info.minimum, info.maximum));
+            }
+        }
+        Ok(InputDevice { path, file, abs_range, partial: vec![] })
+    }
+
+    /// reads every event that is waiting into `state`
+    pub fn read(&mut self, state: &mut InputState, events: &mut Vec<Event>) -> io::Result<()> {
+        let event_size = size_of::<libc::input_event>();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let count = match self.file.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(count) => count,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.partial.extend_from_slice(&buffer[..count]);
+            let whole = self.partial.len() / event_size * event_size;
+            for bytes in self.partial[..whole].chunks_exact(event_size) {
+                let event: libc::input_event = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const _) };
+                let range = match (event.type_, event.code) {
+                    (EV_ABS, ABS_X) => self.abs_range[0],
+                    (EV_ABS, ABS_Y) => self.abs_range[1],
+                    _ => None,
+                };
+                state.handle(event.type_, event.code, event.value, range, events);
+            }
+            self.partial.drain(..whole);
+        }
+    }
+}
+
+/// Every input device that could be opened.
+#[derive(Default)]
+pub struct InputDevices {
+    pub devices: Vec<InputDevice>,
+}
+
+impl InputDevices {
+    /// every `/dev/input/event*` that we are allowed to read. on most
+    /// systems that means being root or in the `input` group.
+    pub fn open_all() -> InputDevices {
+        let mut paths: Vec<PathBuf> = fs::read_dir("/dev/input")
+            .map(|dir| dir.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect())
+            .unwrap_or_default();
+        paths.retain(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("event")));
+        paths.sort();
+        InputDevices::open(&paths)
+    }
+
+    /// the devices at `paths`, skipping the ones that can't be opened
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> InputDevices {
+        let devices = paths.iter().filter_map(|path| InputDevice::open(path).ok()).collect();
+        InputDevices { devices }
+    }
+
+    /// the events from every device since the last poll. a device that
+    /// fails (eg: it was unplugged) is dropped.
+    pub fn poll(&mut self, state: &mut InputState) -> Vec<Event> {
+        let mut events = vec![];
+        self.devices.retain_mut(|device| device.read(state, &mut events).is_ok());
+        events
+    }
+}
+
+#[cfg(all(test, feature = "fbdev"))]
+mod tests {
+    use super::*;
+
+    const KEY_A: u16 = 30;
+    const KEY_LEFTSHIFT: u16 = 42;
+
+    /// the events that `packets` (written to a file the way the kernel
+    /// would) come out as, on a 100x100 screen
+    fn events_of(name: &str, packets: &[&[(u16, u16, i32)]]) -> Vec<Event> {
+        let path = std::env::temp_dir().join(format!("simple_game_evdev_{}_{}", std::process::id(), name));
+        let mut file = File::create(&path).unwrap();
+        for packet in packets {
+            for (kind, code, value) in packet.iter() {
+                write_event(&mut file, *kind, *code, *value).unwrap();
+            }
+        }
+        drop(file);
+        let mut devices = InputDevices::open(&[&path]);
+        assert_eq!(devices.devices.len(), 1);
+        let mut state = InputState::new(100, 100);
+        let events = devices.poll(&mut state);
+        // a file has nothing more to say once it was read
+        assert!(devices.poll(&mut state).is_empty());
+        fs::remove_file(path).unwrap();
+        events
+    }
+
+    #[test]
+    fn mouse_moves_before_its_buttons() {
+        let events = events_of("mouse", &[
+            &[(EV_KEY, BTN_RIGHT, 1), (EV_REL, REL_X, 10), (EV_REL, REL_Y, 5), (EV_SYN, SYN_REPORT, 0)],
+            &[(EV_KEY, BTN_RIGHT, 0), (EV_REL, REL_WHEEL, -1), (EV_SYN, SYN_REPORT, 0)],
+            // kept on the screen
+            &[(EV_REL, REL_X, -1000), (EV_SYN, SYN_REPORT, 0)],
+        ]);
+        assert_eq!(events, vec![
+            Event::MouseMove { x: 60.0, y: 55.0 },
+            Event::MouseDown { button: MouseButton::Right, x: 60.0, y: 55.0 },
+            Event::MouseUp { button: MouseButton::Right, x: 60.0, y: 55.0 },
+            Event::MouseScroll { up: false },
+            Event::MouseMove { x: 0.0, y: 55.0 },
+        ]);
+    }
+
+    #[test]
+    fn keys_carry_the_modifiers() {
+        let events = events_of("keys", &[
+            &[(EV_KEY, KEY_LEFTSHIFT, 1), (EV_KEY, KEY_A, 1), (EV_SYN, SYN_REPORT, 0)],
+            &[(EV_KEY, KEY_A, 2), (EV_SYN, SYN_REPORT, 0)],
+            &[(EV_KEY, KEY_LEFTSHIFT, 0), (EV_KEY, KEY_A, 0), (EV_SYN, SYN_REPORT, 0)],
+        ]);
+        let shift = KeyMods { shift: true, ..Default::default() };
+        assert_eq!(events, vec![
+            Event::KeyDown { modifier: shift, code: KeyCode::LeftShift, repeated: false },
+            Event::KeyDown { modifier: shift, code: KeyCode::A, repeated: false },
+            Event::KeyDown { modifier: shift, code: KeyCode::A, repeated: true },
+            Event::KeyUp { modifier: KeyMods::default(), code: KeyCode::LeftShift },
+            Event::KeyUp { modifier: KeyMods::default(), code: KeyCode::A },
+        ]);
+    }
+
+    #[test]
+    fn nothing_comes_out_of_a_dropped_packet() {
+        let events = events_of("dropped", &[
+            &[(EV_REL, REL_X, 5), (EV_SYN, SYN_DROPPED, 0), (EV_KEY, BTN_LEFT, 1), (EV_SYN, SYN_REPORT, 0)],
+            &[(EV_KEY, BTN_LEFT, 0), (EV_SYN, SYN_REPORT, 0)],
+            // not a whole packet yet
+            &[(EV_REL, REL_Y, 5)],
+        ]);
+        assert_eq!(events, vec![Event::MouseUp { button: MouseButton::Left, x: 55.0, y: 50.0 }]);
+    }
+}
@@ -0,0 +1,472 @@
+//! Linux framebuffers that frames can be written straight into: the fbdev
+//! device (`/dev/fb0`), a KMS dumb buffer (see `drm`), or a plain file
+//! that pretends to be one, for testing without a screen.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+
+use super::drm::DumbBuffer;
+use crate::Rgb;
+
+const FBIOGET_VSCREENINFO: u32 = 0x4600;
+const FBIOGET_FSCREENINFO: u32 = 0x4602;
+const FB_VISUAL_TRUECOLOR: u32 = 2;
+
+/// the number of an ioctl request, like the `_IOC` macro of the kernel
+/// headers. `dir` is 1 for write, 2 for read and 3 for both.
+pub(crate) const fn ioc(dir: u32, kind: u8, nr: u8, size: usize) -> u32 {
+    (dir << 30) | ((size as u32) << 16) | ((kind as u32) << 8) | nr as u32
+}
+
+pub(crate) fn ioctl<T>(fd: RawFd, request: u32, arg: &mut T) -> io::Result<()> {
+    // retried when a signal interrupts it, which the drm ones often are
+    loop {
+        let result = unsafe { libc::ioctl(fd, request as _, arg as *mut T) };
+        if result != -1 {
+            return Ok(());
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::other(msg)
+}
+
+#[repr(C)]
+#[derive(Default, Copy, Clone)]
+struct FbBitfield {
+    offset: u32,
+    length: u32,
+    msb_right: u32,
+}
+
+/// `struct fb_var_screeninfo`
+#[repr(C)]
+#[derive(Default)]
+struct FbVarScreenInfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    // pixclock, margins, sync lengths, sync, vmode and rotate
+    timings: [u32; 10],
+    colorspace: u32,
+    reserved: [u32; 4],
+}
+
+/// `struct fb_fix_screeninfo`
+#[repr(C)]
+#[derive(Default)]
+struct FbFixScreenInfo {
+    id: [u8; 16],
+    smem_start: libc::c_ulong,
+    smem_len: u32,
+    kind: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: libc::c_ulong,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+/// where a color channel is in a pixel, counted in bits from the lowest one
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Channel {
+    pub offset: u32,
+    pub length: u32,
+}
+
+impl Channel {
+    fn pack(&self, value: u8) -> u32 {
+        let value = value as u32;
+        let value = if self.length >= 8 { value << (self.length - 8) } else { value >> (8 - self.length) };
+        value << self.offset
+    }
+
+    fn unpack(&self, pixel: u32) -> u8 {
+        if self.length == 0 {
+            return 0;
+        }
+        let mask = ((1u64 << self.length) - 1) as u32;
+        let value = (pixel >> self.offset) & mask;
+        if self.length >= 8 { (value >> (self.length - 8)) as u8 } else { (value * 255 / mask) as u8 }
+    }
+}
+
+/// The size of a framebuffer and how its pixels are laid out: each pixel
+/// is a little endian number `bits_per_pixel` long, with the channels at
+/// their offsets in it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FbLayout {
+    pub width: usize,
+    pub height: usize,
+    /// bytes from the start of one row to the next, which can be more
+    /// than `width` pixels
+    pub stride: usize,
+    pub bits_per_pixel: u32,
+    pub red: Channel,
+    pub green: Channel,
+    pub blue: Channel,
+}
+
+impl FbLayout {
+    /// 32 bits with blue, green and red in the low bytes, so the same bytes
+    /// as `Bgra32`. what almost every framebuffer and dumb buffer uses.
+    pub fn xrgb8888(width: usize, height: usize) -> FbLayout {
+        FbLayout {
+            width,
+            height,
+            stride: width * 4,
+            bits_per_pixel: 32,
+            red: Channel { offset: 16, length: 8 },
+            green: Channel { offset: 8, length: 8 },
+            blue: Channel { offset: 0, length: 8 },
+        }
+    }
+
+    /// 16 bits: 5 of red, 6 of green and 5 of blue, eg: small spi lcds
+    pub fn rgb565(width: usize, height: usize) -> FbLayout {
+        FbLayout {
+            width,
+            height,
+            stride: width * 2,
+            bits_per_pixel: 16,
+            red: Channel { offset: 11, length: 5 },
+            green: Channel { offset: 5, length: 6 },
+            blue: Channel { offset: 0, length: 5 },
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        (self.bits_per_pixel as usize).div_ceil(8)
+    }
+
+    /// how many bytes the whole framebuffer takes
+    pub fn size(&self) -> usize {
+        self.stride * self.height
+    }
+
+    fn is_xrgb8888(&self) -> bool {
+        let xrgb = FbLayout::xrgb8888(self.width, self.height);
+        (self.bits_per_pixel, self.red, self.green, self.blue) == (xrgb.bits_per_pixel, xrgb.red, xrgb.green, xrgb.blue)
+    }
+
+    /// `color` as a pixel of this layout, to be written in the lowest
+    /// `bytes_per_pixel` bytes (little endian)
+    pub fn pack(&self, color: Rgb) -> u32 {
+        self.red.pack(color.red) | self.green.pack(color.green) | self.blue.pack(color.blue)
+    }
+
+    /// the color of a pixel, `pixel` is `bytes_per_pixel` long
+    pub fn unpack(&self, pixel: &[u8]) -> Rgb {
+        let mut value = 0;
+        for (i, byte) in pixel.iter().enumerate().take(4) {
+            value |= (*byte as u32) << (i * 8);
+        }
+        Rgb { red: self.red.unpack(value), green: self.green.unpack(value), blue: self.blue.unpack(value) }
+    }
+}
+
+/// memory shared with a device (or a file) by `mmap`, unmapped on drop
+pub(crate) struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    pub(crate) fn new(fd: RawFd, len: usize, offset: u64) -> io::Result<Mapping> {
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, fd, offset as libc::off_t)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping { ptr: ptr as *mut u8, len })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+enum Device {
+    Fbdev(File),
+    Drm(DumbBuffer),
+    /// the mapping keeps the file's memory after it is closed
+    File,
+}
+
+/// A screen's worth of memory that is shown as it is written to. frames
+/// are written in the `Bgra32` format, and turned into the framebuffer's
+/// own layout on the way.
+pub struct Framebuffer {
+    pub layout: FbLayout,
+    /// the byte of the memory that is the top left pixel on screen
+    origin: usize,
+    // dropped before the device, which may need the memory unmapped first
+    memory: Mapping,
+    device: Device,
+}
+
+impl Framebuffer {
+    /// the framebuffer named by the `FRAMEBUFFER` environment variable
+    /// (the same one other fbdev programs read), or else `/dev/fb0`, or
+    /// else the first drm card.
+    pub fn open_default() -> io::Result<Framebuffer> {
+        if let Some(path) = std::env::var_os("FRAMEBUFFER") {
+            return Framebuffer::open(path);
+        }
+        Framebuffer::open_fbdev("/dev/fb0").or_else(|fb_error| {
+            Framebuffer::open_drm("/dev/dri/card0")
+                .map_err(|drm_error| invalid(format!("/dev/fb0: {}, /dev/dri/card0: {}", fb_error, drm_error)))
+        })
+    }
+
+    /// a drm card if the path is in `/dev/dri`, otherwise an fbdev device
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Framebuffer> {
+        if path.as_ref().starts_with("/dev/dri") {
+            Framebuffer::open_drm(path)
+        } else {
+            Framebuffer::open_fbdev(path)
+        }
+    }
+
+    /// an fbdev device, eg: `/dev/fb0`, at whatever mode it is already in.
+    /// only true color modes are supported, not ones with a color map.
+    pub fn open_fbdev<P: AsRef<Path>>(path: P) -> io::Result<Framebuffer> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut var = FbVarScreenInfo::default();
+        let mut fix = FbFixScreenInfo::default();
+        ioctl(file.as_raw_fd(), FBIOGET_VSCREENINFO, &mut var)?;
+        ioctl(file.as_raw_fd(), FBIOGET_FSCREENINFO, &mut fix)?;
+        if fix.visual != FB_VISUAL_TRUECOLOR {
+            return Err(invalid(format!("framebuffer visual {} is not true color", fix.visual)));
+        }
+        if var.bits_per_pixel == 0 || var.bits_per_pixel > 32 {
+            return Err(invalid(format!("unsupported framebuffer depth of {} bits", var.bits_per_pixel)));
+        }
+        let channel = |field: FbBitfield| Channel { offset: field.offset, length: field.length };
+        let layout = FbLayout {
+            width: var.xres as usize,
+            height: var.yres as usize,
+            stride: fix.line_length as usize,
+            bits_per_pixel: var.bits_per_pixel,
+            red: channel(var.red),
+            green: channel(var.green),
+            blue: channel(var.blue),
+        };
+        // the visible part of a larger virtual screen
+        let origin = var.yoffset as usize * layout.stride + var.xoffset as usize * layout.bytes_per_pixel();
+        let len = (fix.smem_len as usize).max(origin + layout.size());
+        let memory = Mapping::new(file.as_raw_fd(), len, 0)?;
+        Ok(Framebuffer { layout, origin, memory, device: Device::Fbdev(file) })
+    }
+
+    /// a dumb buffer shown on the first connected display of a drm card,
+    /// eg: `/dev/dri/card0`, at the display's preferred mode. the display
+    /// goes back to what it showed before when this is dropped.
+    pub fn open_drm<P: AsRef<Path>>(path: P) -> io::Result<Framebuffer> {
+        let buffer = DumbBuffer::open(path.as_ref())?;
+        let layout = buffer.layout;
+        let memory = buffer.map()?;
+        Ok(Framebuffer { layout, origin: 0, memory, device: Device::Drm(buffer) })
+    }
+
+    /// a plain file that stands in for a framebuffer with `layout`. it is
+    /// created (or grown) to the size of the framebuffer, and everything
+    /// written to the framebuffer ends up in it.
+    pub fn open_file<P: AsRef<Path>>(path: P, layout: FbLayout) -> io::Result<Framebuffer> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        if (file.metadata()?.len() as usize) < layout.size() {
+            file.set_len(layout.size() as u64)?;
+        }
+        let memory = Mapping::new(file.as_raw_fd(), layout.size(), 0)?;
+        Ok(Framebuffer { layout, origin: 0, memory, device: Device::File })
+    }
+
+    /// the visible framebuffer memory, `layout.stride` bytes per row
+    pub fn data(&self) -> &[u8] {
+        &self.memory.as_slice()[self.origin..self.origin + self.layout.size()]
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        let (origin, size) = (self.origin, self.layout.size());
+        &mut self.memory.as_mut_slice()[origin..origin + size]
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Rgb {
+        let bpp = self.layout.bytes_per_pixel();
+        let start = y * self.layout.stride + x * bpp;
+        self.layout.unpack(&self.data()[start..start + bpp])
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb) {
+        if x >= self.layout.width || y >= self.layout.height {
+            return;
+        }
+        let bpp = self.layout.bytes_per_pixel();
+        let value = self.layout.pack(color).to_le_bytes();
+        let start = y * self.layout.stride + x * bpp;
+        self.data_mut()[start..start + bpp].copy_from_slice(&value[..bpp]);
+    }
+
+    pub fn fill(&mut self, color: Rgb) {
+        let bpp = self.layout.bytes_per_pixel();
+        let value = self.layout.pack(color).to_le_bytes();
+        let (width, stride) = (self.layout.width, self.layout.stride);
+        for row in self.data_mut().chunks_exact_mut(stride) {
+            for pixel in row[..width * bpp].chunks_exact_mut(bpp) {
+                pixel.copy_from_slice(&value[..bpp]);
+            }
+        }
+    }
+
+    /// writes a `width` x `height` frame of `Bgra32` pixels with its top
+    /// left corner at (left, top). anything off the screen is clipped.
+    pub fn write_frame(&mut self, frame: &[u8], width: usize, height: usize, left: usize, top: usize) {
+        let layout = self.layout;
+        let columns = width.min(layout.width.saturating_sub(left));
+        let rows = height.min(layout.height.saturating_sub(top));
+        if columns == 0 || rows == 0 {
+            return;
+        }
+        let bpp = layout.bytes_per_pixel();
+        let data = self.data_mut();
+        for y in 0..rows {
+            let src = &frame[y * width * 4..(y * width + columns) * 4];
+            let start = (top + y) * layout.stride + left * bpp;
+            let dest = &mut data[start..start + columns * bpp];
+            if layout.is_xrgb8888() {
+                dest.copy_from_slice(src);
+                continue;
+            }
+            for (pixel, color) in dest.chunks_exact_mut(bpp).zip(src.chunks_exact(4)) {
+                let value = layout.pack(Rgb { red: color[2], green: color[1], blue: color[0] }).to_le_bytes();
+                pixel.copy_from_slice(&value[..bpp]);
+            }
+        }
+    }
+
+    /// blocks until the next vertical blank, so a frame written right
+    /// after it doesn't tear. false if the device can't tell (or it is a
+    /// file), in which case it returns straight away.
+    pub fn wait_for_vsync(&self) -> bool {
+        match &self.device {
+            Device::Fbdev(file) => {
+                // FBIO_WAITFORVSYNC, for the first crtc
+                let mut crtc: u32 = 0;
+                ioctl(file.as_raw_fd(), ioc(1, b'F', 0x20, 4), &mut crtc).is_ok()
+            }
+            Device::Drm(buffer) => buffer.wait_for_vblank(),
+            Device::File => false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "fbdev"))]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("simple_game_fb_{}_{}", std::process::id(), name))
+    }
+
+    /// a 2x2 `Bgra32` frame: red, green / blue, grass
+    fn frame() -> Vec<u8> {
+        vec![0, 0, 255, 255, 0, 255, 0, 255, 255, 0, 0, 255, 90, 180, 120, 255]
+    }
+
+    #[test]
+    fn xrgb8888_is_copied() {
+        let path = temp_path("xrgb8888");
+        let mut fb = Framebuffer::open_file(&path, FbLayout::xrgb8888(4, 3)).unwrap();
+        fb.fill(Rgb::BLACK);
+        fb.write_frame(&frame(), 2, 2, 1, 1);
+        let stride = fb.layout.stride;
+        assert_eq!(&fb.data()[stride + 4..stride + 12], &frame()[..8]);
+        assert_eq!(&fb.data()[2 * stride + 4..2 * stride + 12], &frame()[8..]);
+        assert_eq!(fb.get_pixel(1, 1), Rgb::RED);
+        assert_eq!(fb.get_pixel(2, 2), Rgb { red: 120, green: 180, blue: 90 });
+        assert_eq!(fb.get_pixel(0, 1), Rgb::BLACK);
+        assert_eq!(fb.get_pixel(3, 1), Rgb::BLACK);
+        drop(fb);
+        // and it really is in the file
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[stride + 4..stride + 8], &[0, 0, 255, 255]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rgb565_is_converted() {
+        let path = temp_path("rgb565");
+        let mut fb = Framebuffer::open_file(&path, FbLayout::rgb565(4, 3)).unwrap();
+        fb.fill(Rgb::BLACK);
+        fb.write_frame(&frame(), 2, 2, 0, 0);
+        let stride = fb.layout.stride;
+        let pixel = |x: usize, y: usize| u16::from_le_bytes([fb.data()[y * stride + x * 2], fb.data()[y * stride + x * 2 + 1]]);
+        assert_eq!(pixel(0, 0), 0xf800);
+        assert_eq!(pixel(1, 0), 0x07e0);
+        assert_eq!(pixel(0, 1), 0x001f);
+        assert_eq!(pixel(1, 1), (120 >> 3) << 11 | (180 >> 2) << 5 | 90 >> 3);
+        assert_eq!(pixel(2, 0), 0);
+        assert_eq!(fb.get_pixel(0, 0), Rgb::RED);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn frames_are_clipped() {
+        let path = temp_path("clipped");
+        let mut fb = Framebuffer::open_file(&path, FbLayout::xrgb8888(4, 4)).unwrap();
+        fb.fill(Rgb::BLACK);
+        let big = vec![255; 100 * 3 * 4];
+        // entirely to the right of or below the screen
+        fb.write_frame(&big, 100, 3, 100, 3);
+        fb.write_frame(&big, 1, 1, 100, 3);
+        fb.write_frame(&big, 1, 1, 0, 4);
+        assert!(fb.data().iter().all(|byte| *byte == 0));
+        // hanging off the bottom right corner
+        fb.write_frame(&big, 100, 3, 3, 2);
+        assert_eq!(fb.get_pixel(3, 2), Rgb::WHITE);
+        assert_eq!(fb.get_pixel(3, 3), Rgb::WHITE);
+        assert_eq!(fb.get_pixel(2, 3), Rgb::BLACK);
+        std::fs::remove_file(path).unwrap();
+    }
+}
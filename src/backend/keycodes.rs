@@ -0,0 +1,123 @@
+//! The codes that linux gives keys and mouse buttons (see
+//! `input-event-codes.h`), which evdev devices and wayland compositors
+//! both send.
+
+use super::KeyCode;
+
+pub const BTN_LEFT: u16 = 0x110;
+pub const BTN_RIGHT: u16 = 0x111;
+pub const BTN_MIDDLE: u16 = 0x112;
+
+/// the linux key code of a key, see `input-event-codes.h`
+pub fn key_code(code: u16) -> KeyCode {
+    match code {
+        1 => KeyCode::Escape,
+        2 => KeyCode::Key1,
+        3 => KeyCode::Key2,
+        4 => KeyCode::Key3,
+        5 => KeyCode::Key4,
+        6 => KeyCode::Key5,
+        7 => KeyCode::Key6,
+        8 => KeyCode::Key7,
+        9 => KeyCode::Key8,
+        10 => KeyCode::Key9,
+        11 => KeyCode::Key0,
+        12 => KeyCode::Minus,
+        13 => KeyCode::Equal,
+        14 => KeyCode::Backspace,
+        15 => KeyCode::Tab,
+        16 => KeyCode::Q,
+        17 => KeyCode::W,
+        18 => KeyCode::E,
+        19 => KeyCode::R,
+        20 => KeyCode::T,
+        21 => KeyCode::Y,
+        22 => KeyCode::U,
+        23 => KeyCode::I,
+        24 => KeyCode::O,
+        25 => KeyCode::P,
+        26 => KeyCode::LeftBracket,
+        27 => KeyCode::RightBracket,
+        28 => KeyCode::Enter,
+        29 => KeyCode::LeftControl,
+        30 => KeyCode::A,
+        31 => KeyCode::S,
+        32 => KeyCode::D,
+        33 => KeyCode::F,
+        34 => KeyCode::G,
+        35 => KeyCode::H,
+        36 => KeyCode::J,
+        37 => KeyCode::K,
+        38 => KeyCode::L,
+        39 => KeyCode::Semicolon,
+        40 => KeyCode::Apostrophe,
+        41 => KeyCode::GraveAccent,
+        42 => KeyCode::LeftShift,
+        43 => KeyCode::Backslash,
+        44 => KeyCode::Z,
+        45 => KeyCode::X,
+        46 => KeyCode::C,
+        47 => KeyCode::V,
+        48 => KeyCode::B,
+        49 => KeyCode::N,
+        50 => KeyCode::M,
+        51 => KeyCode::Comma,
+        52 => KeyCode::Period,
+        53 => KeyCode::Slash,
+        54 => KeyCode::RightShift,
+        55 => KeyCode::KpMultiply,
+        56 => KeyCode::LeftAlt,
+        57 => KeyCode::Space,
+        58 => KeyCode::CapsLock,
+        59 => KeyCode::F1,
+        60 => KeyCode::F2,
+        61 => KeyCode::F3,
+        62 => KeyCode::F4,
+        63 => KeyCode::F5,
+        64 => KeyCode::F6,
+        65 => KeyCode::F7,
+        66 => KeyCode::F8,
+        67 => KeyCode::F9,
+        68 => KeyCode::F10,
+        69 => KeyCode::NumLock,
+        70 => KeyCode::ScrollLock,
+        71 => KeyCode::Kp7,
+        72 => KeyCode::Kp8,
+        73 => KeyCode::Kp9,
+        74 => KeyCode::KpSubtract,
+        75 => KeyCode::Kp4,
+        76 => KeyCode::Kp5,
+        77 => KeyCode::Kp6,
+        78 => KeyCode::KpAdd,
+        79 => KeyCode::Kp1,
+        80 => KeyCode::Kp2,
+        81 => KeyCode::Kp3,
+        82 => KeyCode::Kp0,
+        83 => KeyCode::KpDecimal,
+        86 => KeyCode::World2,
+        87 => KeyCode::F11,
+        88 => KeyCode::F12,
+        96 => KeyCode::KpEnter,
+        97 => KeyCode::RightControl,
+        98 => KeyCode::KpDivide,
+        99 => KeyCode::PrintScreen,
+        100 => KeyCode::RightAlt,
+        102 => KeyCode::Home,
+        103 => KeyCode::Up,
+        104 => KeyCode::PageUp,
+        105 => KeyCode::Left,
+        106 => KeyCode::Right,
+        107 => KeyCode::End,
+        108 => KeyCode::Down,
+        109 => KeyCode::PageDown,
+        110 => KeyCode::Insert,
+        111 => KeyCode::Delete,
+        117 => KeyCode::KpEqual,
+        119 => KeyCode::Pause,
+        125 => KeyCode::LeftSuper,
+        126 => KeyCode::RightSuper,
+        127 => KeyCode::Menu,
+        183..=194 => KeyCode::from(KeyCode::F13 as u32 + (code - 183) as u32),
+        _ => KeyCode::Unknown,
+    }
+}
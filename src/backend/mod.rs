@@ -4,6 +4,54 @@ pub use b_miniquad::*;
 pub mod b_image;
 pub use b_image::*;
 
+pub mod ansi;
+#[cfg(all(unix, feature = "terminal"))]
+pub mod b_terminal;
+#[cfg(all(unix, feature = "terminal"))]
+pub use b_terminal::*;
+
+#[cfg(all(target_os = "linux", feature = "fbdev"))]
+pub mod b_fbdev;
+#[cfg(all(target_os = "linux", feature = "fbdev"))]
+pub use b_fbdev::*;
+#[cfg(all(target_os = "linux", feature = "fbdev"))]
+pub mod drm;
+#[cfg(all(target_os = "linux", feature = "fbdev"))]
+pub mod evdev;
+#[cfg(all(target_os = "linux", feature = "fbdev"))]
+pub mod fb;
+#[cfg(all(target_os = "linux", any(feature = "fbdev", feature = "shm")))]
+pub mod keycodes;
+
+#[cfg(all(target_os = "linux", feature = "shm"))]
+pub mod b_shm;
+#[cfg(all(target_os = "linux", feature = "shm"))]
+pub use b_shm::*;
+#[cfg(all(target_os = "linux", feature = "shm"))]
+pub mod wayland;
+#[cfg(all(target_os = "linux", feature = "shm"))]
+pub mod x11;
+
+use crate::pixel_format::PixelFormat;
+
+/// an arrow, `#` is the outline and `.` is the inside. for backends that
+/// have to draw the mouse cursor themselves.
+#[cfg(all(target_os = "linux", any(feature = "fbdev", feature = "shm")))]
+pub(crate) const CURSOR: [&str; 12] = [
+    "#",
+    "##",
+    "#.#",
+    "#..#",
+    "#...#",
+    "#....#",
+    "#.....#",
+    "#......#",
+    "#....###",
+    "#.#..#",
+    "##.#..#",
+    "#  ###",
+];
+
 /// copied from miniquad Conf and modified to remove
 /// parts that arent relevant to us.
 #[derive(Debug, Clone)]
@@ -308,7 +356,7 @@ impl From<u32> for KeyCode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Event {
     MouseDown { button: MouseButton, x: f32, y: f32 },
     MouseUp { button: MouseButton, x: f32, y: f32 },
@@ -325,8 +373,15 @@ pub trait GameLoop {
 }
 
 pub trait Backend<T: GameLoop> {
+    /// how the backend reads the pixels the game loop gives it. a game can
+    /// draw onto a `Canvas<Format>` directly, or convert to it.
+    type Format: PixelFormat;
+
     fn start(_conf: BackendConf, _game_loop: T) where Self: Sized + 'static {}
-    fn bytes_per_pixel() -> usize;
+
+    fn bytes_per_pixel() -> usize {
+        Self::Format::default().bytes_per_pixel()
+    }
 }
 
 pub enum TextureUpdate<'a> {
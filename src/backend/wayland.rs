@@ -0,0 +1,743 @@
+//! Just enough of the wayland protocol, spoken straight over the
+//! compositor's socket, to show a window (an `xdg_toplevel`) whose frames
+//! are in shared memory (`wl_shm`) and to read the seat's pointer and
+//! keyboard. no libwayland is needed, so neither is a gl driver. see
+//! <https://wayland.freedesktop.org/docs/html/ch04.html> for the wire
+//! format, and `wayland.xml` and `xdg-shell.xml` for the messages.
+
+use std::collections::VecDeque;
+use std::env;
+use std::io::{self, Write};
+use std::mem::size_of;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::keycodes::{key_code, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT};
+use super::b_shm::readable;
+use super::{BackendConf, Event, KeyCode, KeyMods, MouseButton, CURSOR};
+
+const DISPLAY: u32 = 1;
+
+// requests, by interface
+const DISPLAY_SYNC: u16 = 0;
+const DISPLAY_GET_REGISTRY: u16 = 1;
+const REGISTRY_BIND: u16 = 0;
+const COMPOSITOR_CREATE_SURFACE: u16 = 0;
+const SHM_CREATE_POOL: u16 = 0;
+const SHM_POOL_CREATE_BUFFER: u16 = 0;
+const SURFACE_ATTACH: u16 = 1;
+const SURFACE_DAMAGE: u16 = 2;
+const SURFACE_FRAME: u16 = 3;
+const SURFACE_COMMIT: u16 = 6;
+const WM_BASE_GET_XDG_SURFACE: u16 = 2;
+const WM_BASE_PONG: u16 = 3;
+const XDG_SURFACE_GET_TOPLEVEL: u16 = 1;
+const XDG_SURFACE_ACK_CONFIGURE: u16 = 4;
+const TOPLEVEL_SET_TITLE: u16 = 2;
+const TOPLEVEL_SET_MAX_SIZE: u16 = 7;
+const TOPLEVEL_SET_MIN_SIZE: u16 = 8;
+const TOPLEVEL_SET_FULLSCREEN: u16 = 11;
+const SEAT_GET_POINTER: u16 = 0;
+const SEAT_GET_KEYBOARD: u16 = 1;
+const POINTER_SET_CURSOR: u16 = 0;
+
+const FORMAT_ARGB8888: u32 = 0;
+const FORMAT_XRGB8888: u32 = 1;
+const SEAT_POINTER: u32 = 1;
+const SEAT_KEYBOARD: u32 = 2;
+
+fn pad(length: usize) -> usize {
+    (4 - length % 4) % 4
+}
+
+/// A request being written, in the machine's byte order like the protocol
+/// wants. the size is filled in at the end.
+struct Message(Vec<u8>);
+
+impl Message {
+    fn new(object: u32, opcode: u16) -> Message {
+        let mut message = Message(Vec::with_capacity(32));
+        message.0.extend_from_slice(&object.to_ne_bytes());
+        message.0.extend_from_slice(&(opcode as u32).to_ne_bytes());
+        message
+    }
+
+    fn u32(mut self, value: u32) -> Message {
+        self.0.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    fn i32(self, value: i32) -> Message {
+        self.u32(value as u32)
+    }
+
+    fn string(mut self, value: &str) -> Message {
+        let length = value.len() + 1;
+        self = self.u32(length as u32);
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.resize(self.0.len() + 1 + pad(length), 0);
+        self
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let header = ((self.0.len() as u32) << 16) | u32::from_ne_bytes([self.0[4], self.0[5], self.0[6], self.0[7]]);
+        self.0[4..8].copy_from_slice(&header.to_ne_bytes());
+        self.0
+    }
+}
+
+/// The arguments of an event, read in order.
+struct Args<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Args<'a> {
+    fn u32(&mut self) -> u32 {
+        let bytes = self.data.get(self.at..self.at + 4).unwrap_or(&[0; 4]);
+        self.at += 4;
+        u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn i32(&mut self) -> i32 {
+        self.u32() as i32
+    }
+
+    /// a 24.8 fixed point number
+    fn fixed(&mut self) -> f32 {
+        self.i32() as f32 / 256.0
+    }
+
+    fn array(&mut self) -> &'a [u8] {
+        let length = self.u32() as usize;
+        let bytes = self.data.get(self.at..self.at + length).unwrap_or(&[]);
+        self.at += length + pad(length);
+        bytes
+    }
+
+    /// without the nul at the end
+    fn string(&mut self) -> String {
+        let bytes = self.array();
+        String::from_utf8_lossy(bytes.strip_suffix(&[0]).unwrap_or(bytes)).into_owned()
+    }
+}
+
+/// sends `bytes` along with a file descriptor for the compositor to use
+fn send_with_fd(socket: &UnixStream, bytes: &[u8], fd: RawFd) -> io::Result<()> {
+    let mut iov = libc::iovec { iov_base: bytes.as_ptr() as *mut libc::c_void, iov_len: bytes.len() };
+    let space = unsafe { libc::CMSG_SPACE(size_of::<RawFd>() as u32) } as usize;
+    // u64s, so the control message is aligned
+    let mut control = vec![0u64; space.div_ceil(8)];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = space as _;
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<RawFd>() as u32) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+    }
+    match unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) } {
+        -1 => Err(io::Error::last_os_error()),
+        sent if sent as usize != bytes.len() => Err(io::Error::new(io::ErrorKind::WriteZero, "short send to the compositor")),
+        _ => Ok(()),
+    }
+}
+
+/// Memory that the compositor maps too: a file (from `memfd_create`)
+/// mapped into ours.
+struct SharedMemory {
+    fd: RawFd,
+    memory: *mut u8,
+    size: usize,
+}
+
+impl SharedMemory {
+    fn new(size: usize) -> io::Result<SharedMemory> {
+        let name = b"simple-game-examples\0";
+        let fd = unsafe { libc::memfd_create(name.as_ptr() as *const libc::c_char, libc::MFD_CLOEXEC) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { libc::ftruncate(fd, size as libc::off_t) } != 0 {
+            let error = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(error);
+        }
+        let memory = unsafe {
+            libc::mmap(std::ptr::null_mut(), size, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, fd, 0)
+        };
+        if memory == libc::MAP_FAILED {
+            let error = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(error);
+        }
+        Ok(SharedMemory { fd, memory: memory as *mut u8, size })
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.memory, self.size) }
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.memory as *mut libc::c_void, self.size);
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// A `wl_buffer` in the pool, and whether the compositor is still using it.
+struct Buffer {
+    id: u32,
+    offset: usize,
+    busy: bool,
+}
+
+/// A window on a wayland compositor, with xrgb pixels, ie: `Bgra32` with
+/// the alpha ignored. frames are double buffered: one is drawn into while
+/// the compositor shows the other.
+pub struct WaylandWindow {
+    /// the size of the frames
+    pub width: usize,
+    pub height: usize,
+    /// the compositor asked for the window to be closed
+    pub closed: bool,
+    socket: UnixStream,
+    next_id: u32,
+    /// what was read but not handled yet, and the file descriptors that came with it
+    input: Vec<u8>,
+    fds: VecDeque<RawFd>,
+    registry: u32,
+    compositor: u32,
+    shm: u32,
+    wm_base: u32,
+    seat: u32,
+    surface: u32,
+    xdg_surface: u32,
+    toplevel: u32,
+    pointer: u32,
+    keyboard: u32,
+    cursor_surface: u32,
+    /// the last `wl_display.sync` that is waited on, 0 once it is done
+    sync: u32,
+    /// the frame callback of the last frame, 0 once the compositor wants another
+    frame_callback: u32,
+    configured: bool,
+    /// the size the compositor suggested, 0 means we choose
+    suggested: (i32, i32),
+    memory: Option<SharedMemory>,
+    buffers: Vec<Buffer>,
+    pointer_position: (f32, f32),
+    modifiers: KeyMods,
+    /// the key being held, which repeats (on the client side in wayland),
+    /// and when it repeats next
+    held: Option<(KeyCode, Instant)>,
+    /// key repeats per second, and the milliseconds before they start
+    repeat_rate: i32,
+    repeat_delay: i32,
+    events: Vec<Event>,
+}
+
+impl WaylandWindow {
+    /// connects to the compositor in `WAYLAND_DISPLAY` and opens a window
+    pub fn open_default(bconf: &BackendConf) -> io::Result<WaylandWindow> {
+        let display = env::var_os("WAYLAND_DISPLAY").unwrap_or_else(|| "wayland-0".into());
+        let mut path = PathBuf::from(&display);
+        if path.is_relative() {
+            let runtime = env::var_os("XDG_RUNTIME_DIR")
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR isn't set"))?;
+            path = PathBuf::from(runtime).join(display);
+        }
+        WaylandWindow::open(UnixStream::connect(path)?, bconf)
+    }
+
+    /// opens a window with the title in `bconf`, of its size or (if
+    /// `fullscreen`) of the size the compositor chooses, on the compositor
+    /// at the other end of `socket`
+    pub fn open(socket: UnixStream, bconf: &BackendConf) -> io::Result<WaylandWindow> {
+        let mut wayland = WaylandWindow {
+            width: 0,
+            height: 0,
+            closed: false,
+            socket,
+            next_id: DISPLAY,
+            input: vec![],
+            fds: VecDeque::new(),
+            registry: 0,
+            compositor: 0,
+            shm: 0,
+            wm_base: 0,
+            seat: 0,
+            surface: 0,
+            xdg_surface: 0,
+            toplevel: 0,
+            pointer: 0,
+            keyboard: 0,
+            cursor_surface: 0,
+            sync: 0,
+            frame_callback: 0,
+            configured: false,
+            suggested: (0, 0),
+            memory: None,
+            buffers: vec![],
+            pointer_position: (0.0, 0.0),
+            modifiers: KeyMods::default(),
+            held: None,
+            repeat_rate: 25,
+            repeat_delay: 600,
+            events: vec![],
+        };
+        wayland.registry = wayland.new_id();
+        wayland.send(Message::new(DISPLAY, DISPLAY_GET_REGISTRY).u32(wayland.registry))?;
+        // the globals are bound as they are announced
+        wayland.roundtrip()?;
+        let missing = [(wayland.compositor, "wl_compositor"), (wayland.shm, "wl_shm"), (wayland.wm_base, "xdg_wm_base")];
+        if let Some((_, name)) = missing.iter().find(|(id, _)| *id == 0) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("the compositor has no {}", name)));
+        }
+
+        wayland.surface = wayland.new_id();
+        wayland.send(Message::new(wayland.compositor, COMPOSITOR_CREATE_SURFACE).u32(wayland.surface))?;
+        wayland.xdg_surface = wayland.new_id();
+        wayland.send(Message::new(wayland.wm_base, WM_BASE_GET_XDG_SURFACE).u32(wayland.xdg_surface).u32(wayland.surface))?;
+        wayland.toplevel = wayland.new_id();
+        wayland.send(Message::new(wayland.xdg_surface, XDG_SURFACE_GET_TOPLEVEL).u32(wayland.toplevel))?;
+        wayland.send(Message::new(wayland.toplevel, TOPLEVEL_SET_TITLE).string(&bconf.window_title))?;
+        let (width, height) = (bconf.window_width.max(1), bconf.window_height.max(1));
+        if bconf.fullscreen {
+            // on whichever output the compositor likes
+            wayland.send(Message::new(wayland.toplevel, TOPLEVEL_SET_FULLSCREEN).u32(0))?;
+        } else if !bconf.window_resizable {
+            wayland.send(Message::new(wayland.toplevel, TOPLEVEL_SET_MIN_SIZE).i32(width).i32(height))?;
+            wayland.send(Message::new(wayland.toplevel, TOPLEVEL_SET_MAX_SIZE).i32(width).i32(height))?;
+        }
+        // committing without a buffer asks for the first configure, which
+        // has to be acked before a buffer can be attached
+        wayland.send(Message::new(wayland.surface, SURFACE_COMMIT))?;
+        while !wayland.configured {
+            wayland.roundtrip()?;
+        }
+
+        let (width, height) = match wayland.suggested {
+            (w, h) if bconf.fullscreen && w > 0 && h > 0 => (w, h),
+            _ => (width, height),
+        };
+        wayland.width = width as usize;
+        wayland.height = height as usize;
+        wayland.create_buffers()?;
+        Ok(wayland)
+    }
+
+    fn new_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        self.socket.write_all(&message.finish())
+    }
+
+    /// waits until the compositor has handled every request, and sent
+    /// everything it had to say about them
+    fn roundtrip(&mut self) -> io::Result<()> {
+        self.sync = self.new_id();
+        self.send(Message::new(DISPLAY, DISPLAY_SYNC).u32(self.sync))?;
+        while self.sync != 0 {
+            self.receive()?;
+            self.dispatch()?;
+        }
+        Ok(())
+    }
+
+    /// makes a pool with two frames and the cursor, and buffers for them
+    fn create_buffers(&mut self) -> io::Result<()> {
+        let frame_size = self.width * self.height * 4;
+        let cursor_width = CURSOR.iter().map(|line| line.len()).max().unwrap_or(0);
+        let cursor_size = cursor_width * CURSOR.len() * 4;
+        let mut memory = SharedMemory::new(frame_size * 2 + cursor_size)?;
+        let cursor = &mut memory.bytes_mut()[frame_size * 2..];
+        for (row, line) in CURSOR.iter().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let argb: u32 = match c {
+                    '#' => 0xff000000,
+                    '.' => 0xffffffff,
+                    _ => continue,
+                };
+                let at = (row * cursor_width + column) * 4;
+                cursor[at..at + 4].copy_from_slice(&argb.to_le_bytes());
+            }
+        }
+
+        let pool = self.new_id();
+        let message = Message::new(self.shm, SHM_CREATE_POOL).u32(pool).i32(memory.size as i32).finish();
+        // the fd goes along with the message, in between its two arguments
+        send_with_fd(&self.socket, &message, memory.fd)?;
+        for offset in [0, frame_size] {
+            let id = self.new_id();
+            self.send(Message::new(pool, SHM_POOL_CREATE_BUFFER)
+                .u32(id).i32(offset as i32)
+                .i32(self.width as i32).i32(self.height as i32).i32(self.width as i32 * 4)
+                .u32(FORMAT_XRGB8888))?;
+            self.buffers.push(Buffer { id, offset, busy: false });
+        }
+
+        let cursor_buffer = self.new_id();
+        self.send(Message::new(pool, SHM_POOL_CREATE_BUFFER)
+            .u32(cursor_buffer).i32((frame_size * 2) as i32)
+            .i32(cursor_width as i32).i32(CURSOR.len() as i32).i32(cursor_width as i32 * 4)
+            .u32(FORMAT_ARGB8888))?;
+        self.cursor_surface = self.new_id();
+        self.send(Message::new(self.compositor, COMPOSITOR_CREATE_SURFACE).u32(self.cursor_surface))?;
+        self.send(Message::new(self.cursor_surface, SURFACE_ATTACH).u32(cursor_buffer).i32(0).i32(0))?;
+        self.send(Message::new(self.cursor_surface, SURFACE_DAMAGE).i32(0).i32(0).i32(cursor_width as i32).i32(CURSOR.len() as i32))?;
+        self.send(Message::new(self.cursor_surface, SURFACE_COMMIT))?;
+        self.memory = Some(memory);
+        Ok(())
+    }
+
+    /// reads whatever the compositor sent, waiting for something if
+    /// nothing has arrived yet
+    fn receive(&mut self) -> io::Result<()> {
+        let mut buffer = [0u8; 4096];
+        let mut iov = libc::iovec { iov_base: buffer.as_mut_ptr() as *mut libc::c_void, iov_len: buffer.len() };
+        // the most fds that libwayland sends with one message
+        let space = unsafe { libc::CMSG_SPACE((size_of::<RawFd>() * 28) as u32) } as usize;
+        let mut control = vec![0u64; space.div_ceil(8)];
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = space as _;
+        let count = loop {
+            match unsafe { libc::recvmsg(self.socket.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) } {
+                -1 => {
+                    let error = io::Error::last_os_error();
+                    if error.kind() != io::ErrorKind::Interrupted {
+                        return Err(error);
+                    }
+                }
+                0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the compositor closed the connection")),
+                count => break count as usize,
+            }
+        };
+        self.input.extend_from_slice(&buffer[..count]);
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                    let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                    let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize) / size_of::<RawFd>();
+                    for i in 0..count {
+                        self.fds.push_back(std::ptr::read_unaligned(data.add(i)));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+        Ok(())
+    }
+
+    /// handles every whole event that was received
+    fn dispatch(&mut self) -> io::Result<()> {
+        let mut at = 0;
+        while self.input.len() >= at + 8 {
+            let header = u32::from_ne_bytes([self.input[at + 4], self.input[at + 5], self.input[at + 6], self.input[at + 7]]);
+            let size = (header >> 16) as usize;
+            if size < 8 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "bad message from the compositor"));
+            }
+            if self.input.len() < at + size {
+                break;
+            }
+            let object = u32::from_ne_bytes([self.input[at], self.input[at + 1], self.input[at + 2], self.input[at + 3]]);
+            let data = self.input[at + 8..at + size].to_vec();
+            at += size;
+            self.handle(object, (header & 0xffff) as u16, Args { data: &data, at: 0 })?;
+        }
+        self.input.drain(..at);
+        Ok(())
+    }
+
+    fn handle(&mut self, object: u32, opcode: u16, mut args: Args) -> io::Result<()> {
+        match (object, opcode) {
+            (DISPLAY, 0) => {
+                let (object, code, message) = (args.u32(), args.u32(), args.string());
+                return Err(io::Error::other(format!("wayland error {} on object {}: {}", code, object, message)));
+            }
+            (object, 0) if object == self.registry => {
+                let (name, interface, version) = (args.u32(), args.string(), args.u32());
+                let bind = match interface.as_str() {
+                    "wl_compositor" if self.compositor == 0 => 1,
+                    "wl_shm" if self.shm == 0 => 1,
+                    "xdg_wm_base" if self.wm_base == 0 => 1,
+                    // version 4 has the keyboard's repeat info
+                    "wl_seat" if self.seat == 0 => version.min(4),
+                    _ => return Ok(()),
+                };
+                let id = self.new_id();
+                self.send(Message::new(self.registry, REGISTRY_BIND).u32(name).string(&interface).u32(bind).u32(id))?;
+                match interface.as_str() {
+                    "wl_compositor" => self.compositor = id,
+                    "wl_shm" => self.shm = id,
+                    "xdg_wm_base" => self.wm_base = id,
+                    _ => self.seat = id,
+                }
+            }
+            // wl_callback.done
+            (object, 0) if object == self.sync => self.sync = 0,
+            (object, 0) if object == self.frame_callback => self.frame_callback = 0,
+            (object, 0) if object == self.wm_base => {
+                let serial = args.u32();
+                self.send(Message::new(self.wm_base, WM_BASE_PONG).u32(serial))?;
+            }
+            (object, 0) if object == self.xdg_surface => {
+                let serial = args.u32();
+                self.send(Message::new(self.xdg_surface, XDG_SURFACE_ACK_CONFIGURE).u32(serial))?;
+                self.configured = true;
+            }
+            (object, 0) if object == self.toplevel => self.suggested = (args.i32(), args.i32()),
+            (object, 1) if object == self.toplevel => self.closed = true,
+            (object, 0) if object == self.seat => {
+                let capabilities = args.u32();
+                if capabilities & SEAT_POINTER != 0 && self.pointer == 0 {
+                    self.pointer = self.new_id();
+                    self.send(Message::new(self.seat, SEAT_GET_POINTER).u32(self.pointer))?;
+                }
+                if capabilities & SEAT_KEYBOARD != 0 && self.keyboard == 0 {
+                    self.keyboard = self.new_id();
+                    self.send(Message::new(self.seat, SEAT_GET_KEYBOARD).u32(self.keyboard))?;
+                }
+            }
+            (object, opcode) if object == self.pointer => self.handle_pointer(opcode, args)?,
+            (object, opcode) if object == self.keyboard => self.handle_keyboard(opcode, args),
+            // wl_buffer.release
+            (object, 0) => {
+                if let Some(buffer) = self.buffers.iter_mut().find(|buffer| buffer.id == object) {
+                    buffer.busy = false;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_pointer(&mut self, opcode: u16, mut args: Args) -> io::Result<()> {
+        match opcode {
+            // enter, which is when the cursor has to be set
+            0 => {
+                let (serial, _surface) = (args.u32(), args.u32());
+                self.pointer_position = (args.fixed(), args.fixed());
+                self.send(Message::new(self.pointer, POINTER_SET_CURSOR).u32(serial).u32(self.cursor_surface).i32(0).i32(0))?;
+                let (x, y) = self.pointer_position;
+                self.events.push(Event::MouseMove { x, y });
+            }
+            // motion
+            2 => {
+                let _time = args.u32();
+                self.pointer_position = (args.fixed(), args.fixed());
+                let (x, y) = self.pointer_position;
+                self.events.push(Event::MouseMove { x, y });
+            }
+            // button
+            3 => {
+                let (_serial, _time, button, state) = (args.u32(), args.u32(), args.u32(), args.u32());
+                let button = match button as u16 {
+                    BTN_LEFT => MouseButton::Left,
+                    BTN_RIGHT => MouseButton::Right,
+                    BTN_MIDDLE => MouseButton::Middle,
+                    _ => MouseButton::Unknown,
+                };
+                let (x, y) = self.pointer_position;
+                self.events.push(match state {
+                    1 => Event::MouseDown { button, x, y },
+                    _ => Event::MouseUp { button, x, y },
+                });
+            }
+            // axis, where 0 is the vertical one and positive is down
+            4 => {
+                let (_time, axis, value) = (args.u32(), args.u32(), args.fixed());
+                if axis == 0 && value != 0.0 {
+                    self.events.push(Event::MouseScroll { up: value < 0.0 });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_keyboard(&mut self, opcode: u16, mut args: Args) {
+        match opcode {
+            // keymap, which we don't need since the keys are linux key codes
+            0 => {
+                if let Some(fd) = self.fds.pop_front() {
+                    unsafe { libc::close(fd) };
+                }
+            }
+            // leave, the keys held now go to some other window
+            2 => self.held = None,
+            // key
+            3 => {
+                let (_serial, _time, key, state) = (args.u32(), args.u32(), args.u32(), args.u32());
+                let code = key_code(key as u16);
+                let modifier = self.modifiers;
+                if state == 1 {
+                    self.events.push(Event::KeyDown { modifier, code, repeated: false });
+                    let modifier_key = matches!(
+                        code,
+                        KeyCode::LeftShift | KeyCode::RightShift | KeyCode::LeftControl | KeyCode::RightControl
+                            | KeyCode::LeftAlt | KeyCode::RightAlt | KeyCode::LeftSuper | KeyCode::RightSuper
+                    );
+                    if self.repeat_rate > 0 && !modifier_key {
+                        let delay = Duration::from_millis(self.repeat_delay.max(0) as u64);
+                        self.held = Some((code, Instant::now() + delay));
+                    }
+                } else {
+                    self.events.push(Event::KeyUp { modifier, code });
+                    if self.held.is_some_and(|(held, _)| held == code) {
+                        self.held = None;
+                    }
+                }
+            }
+            // modifiers, as xkb's modifier bits which are usually in this order
+            4 => {
+                let (_serial, depressed, latched) = (args.u32(), args.u32(), args.u32());
+                let mask = depressed | latched;
+                self.modifiers = KeyMods {
+                    shift: mask & 0x1 != 0,
+                    ctrl: mask & 0x4 != 0,
+                    alt: mask & 0x8 != 0,
+                    logo: mask & 0x40 != 0,
+                };
+            }
+            // repeat info
+            5 => {
+                self.repeat_rate = args.i32();
+                self.repeat_delay = args.i32();
+            }
+            _ => {}
+        }
+    }
+
+    /// whether the compositor wants the next frame, ie: the last one was
+    /// shown. while the window is hidden the compositor might never want
+    /// one.
+    pub fn wants_frame(&self) -> bool {
+        self.frame_callback == 0
+    }
+
+    /// waits up to `timeout_ms` for an event (-1 waits forever), and then
+    /// adds every event that arrived to `events`
+    pub fn poll(&mut self, timeout_ms: i32, events: &mut Vec<Event>) -> io::Result<()> {
+        let mut timeout = timeout_ms;
+        if let Some((_, next)) = self.held {
+            let until = next.saturating_duration_since(Instant::now()).as_millis() as i32;
+            timeout = if timeout < 0 { until } else { timeout.min(until) };
+        }
+        if readable(self.socket.as_raw_fd(), timeout)? {
+            self.receive()?;
+            while readable(self.socket.as_raw_fd(), 0)? {
+                self.receive()?;
+            }
+            self.dispatch()?;
+        }
+        if let Some((code, next)) = self.held {
+            if Instant::now() >= next {
+                let modifier = self.modifiers;
+                self.events.push(Event::KeyDown { modifier, code, repeated: true });
+                self.held = Some((code, next + Duration::from_secs(1) / self.repeat_rate as u32));
+            }
+        }
+        events.append(&mut self.events);
+        Ok(())
+    }
+
+    /// shows a frame of `Bgra32` pixels, of the window's size, in a
+    /// buffer that the compositor is done with
+    pub fn present(&mut self, frame: &[u8]) -> io::Result<()> {
+        while self.buffers.iter().all(|buffer| buffer.busy) {
+            self.receive()?;
+            self.dispatch()?;
+        }
+        let buffer = self.buffers.iter_mut().find(|buffer| !buffer.busy).unwrap();
+        buffer.busy = true;
+        let (id, offset) = (buffer.id, buffer.offset);
+        let size = self.width * self.height * 4;
+        let memory = self.memory.as_mut().unwrap().bytes_mut();
+        let count = size.min(frame.len());
+        memory[offset..offset + count].copy_from_slice(&frame[..count]);
+
+        let (width, height) = (self.width as i32, self.height as i32);
+        self.send(Message::new(self.surface, SURFACE_ATTACH).u32(id).i32(0).i32(0))?;
+        self.send(Message::new(self.surface, SURFACE_DAMAGE).i32(0).i32(0).i32(width).i32(height))?;
+        self.frame_callback = self.new_id();
+        self.send(Message::new(self.surface, SURFACE_FRAME).u32(self.frame_callback))?;
+        self.send(Message::new(self.surface, SURFACE_COMMIT))
+    }
+}
+
+impl Drop for WaylandWindow {
+    fn drop(&mut self) {
+        for fd in self.fds.drain(..) {
+            unsafe { libc::close(fd) };
+        }
+    }
+}
+
+#[cfg(all(test, feature = "shm"))]
+mod tests {
+    use super::*;
+
+    /// the arguments of `message`, after its header
+    fn args(message: &[u8]) -> Args<'_> {
+        Args { data: &message[8..], at: 0 }
+    }
+
+    #[test]
+    fn numbers() {
+        let message = Message::new(3, 1).u32(7).i32(-2).i32(-2688).u32(0x1_0080).finish();
+        assert_eq!(message.len(), 24);
+        let mut args = args(&message);
+        assert_eq!(args.u32(), 7);
+        assert_eq!(args.i32(), -2);
+        assert_eq!(args.fixed(), -10.5);
+        assert_eq!(args.fixed(), 256.5);
+    }
+
+    #[test]
+    fn strings_and_arrays_are_padded() {
+        // "abc" and its nul fill 4 bytes, "hello" and its nul need 2 more
+        let message = Message::new(3, 1).string("abc").string("hello").string("").u32(9).finish();
+        let mut args = args(&message);
+        assert_eq!(args.string(), "abc");
+        assert_eq!(args.string(), "hello");
+        assert_eq!(args.string(), "");
+        assert_eq!(args.u32(), 9);
+
+        let mut data = vec![];
+        for value in [5u32, 0x0403_0201, 0x05, 42] {
+            data.extend_from_slice(&value.to_ne_bytes());
+        }
+        let mut args = Args { data: &data, at: 0 };
+        assert_eq!(args.array(), &[1, 2, 3, 4, 5][..]);
+        assert_eq!(args.u32(), 42);
+    }
+
+    #[test]
+    fn short_messages_read_as_zeros() {
+        // a compositor that sends less than it should doesn't crash us
+        let data = 12u32.to_ne_bytes();
+        let mut args = Args { data: &data, at: 0 };
+        assert!(args.array().is_empty());
+        assert_eq!(args.u32(), 0);
+        assert_eq!(args.string(), "");
+        assert_eq!(args.fixed(), 0.0);
+    }
+}
@@ -0,0 +1,1122 @@
+//! Just enough of the X11 protocol, spoken straight over the server's
+//! socket, to open a window, show frames in it and read its input. frames
+//! go through shared memory with the MIT-SHM extension when the server is
+//! on the same machine, or are sent in `PutImage` requests otherwise, so
+//! neither xlib nor a gl driver is needed. see the protocol's spec at
+//! <https://www.x.org/releases/current/doc/xproto/x11protocol.html>.
+
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use super::b_shm::readable;
+use super::{BackendConf, Event, KeyCode, KeyMods, MouseButton};
+
+// requests
+const CREATE_WINDOW: u8 = 1;
+const MAP_WINDOW: u8 = 8;
+const INTERN_ATOM: u8 = 16;
+const CHANGE_PROPERTY: u8 = 18;
+const GET_INPUT_FOCUS: u8 = 43;
+const CREATE_GC: u8 = 55;
+const PUT_IMAGE: u8 = 72;
+const QUERY_EXTENSION: u8 = 98;
+const GET_KEYBOARD_MAPPING: u8 = 101;
+// MIT-SHM's requests, which go in the second byte after its opcode
+const SHM_ATTACH: u8 = 1;
+const SHM_DETACH: u8 = 2;
+const SHM_PUT_IMAGE: u8 = 3;
+
+// events
+const KEY_PRESS: u8 = 2;
+const KEY_RELEASE: u8 = 3;
+const BUTTON_PRESS: u8 = 4;
+const BUTTON_RELEASE: u8 = 5;
+const MOTION_NOTIFY: u8 = 6;
+const EXPOSE: u8 = 12;
+const CONFIGURE_NOTIFY: u8 = 22;
+const CLIENT_MESSAGE: u8 = 33;
+const MAPPING_NOTIFY: u8 = 34;
+const GENERIC_EVENT: u8 = 35;
+
+const EVENT_MASK: u32 = 0x1 // KeyPress
+    | 0x2 // KeyRelease
+    | 0x4 // ButtonPress
+    | 0x8 // ButtonRelease
+    | 0x40 // PointerMotion
+    | 0x8000 // Exposure
+    | 0x20000; // StructureNotify
+
+// predefined atoms
+const ATOM: u32 = 4;
+const STRING: u32 = 31;
+const WM_NAME: u32 = 39;
+const WM_NORMAL_HINTS: u32 = 40;
+const WM_SIZE_HINTS: u32 = 41;
+
+const Z_PIXMAP: u8 = 2;
+const TRUE_COLOR: u8 = 4;
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn pad(length: usize) -> usize {
+    (4 - length % 4) % 4
+}
+
+/// A request being written, in our byte order (which the server is told
+/// is little endian). its length, in 4 byte units, is filled in at the end.
+struct Request(Vec<u8>);
+
+impl Request {
+    fn new(opcode: u8, data: u8) -> Request {
+        Request(vec![opcode, data, 0, 0])
+    }
+
+    fn u8(mut self, value: u8) -> Request {
+        self.0.push(value);
+        self
+    }
+
+    fn u16(mut self, value: u16) -> Request {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn u32(mut self, value: u32) -> Request {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn i16(self, value: i16) -> Request {
+        self.u16(value as u16)
+    }
+
+    /// the bytes, padded to 4
+    fn bytes(mut self, bytes: &[u8]) -> Request {
+        self.0.extend_from_slice(bytes);
+        self.0.resize(self.0.len() + pad(bytes.len()), 0);
+        self
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.0.resize(self.0.len() + pad(self.0.len()), 0);
+        let length = (self.0.len() / 4) as u16;
+        self.0[2..4].copy_from_slice(&length.to_le_bytes());
+        self.0
+    }
+}
+
+fn x_error(packet: &[u8]) -> io::Error {
+    io::Error::other(format!("X11 error {} from request {}.{}", packet[1], packet[10], u16_at(packet, 8)))
+}
+
+/// `DISPLAY` is `[host]:number[.screen]`, where no host (or `unix`)
+/// means the server's socket on this machine
+pub fn parse_display(display: &str) -> Option<(String, u16, usize)> {
+    let (host, rest) = display.rsplit_once(':')?;
+    let (number, screen) = match rest.split_once('.') {
+        Some((number, screen)) => (number, screen.parse().ok()?),
+        None => (rest, 0),
+    };
+    Some((host.to_owned(), number.parse().ok()?, screen))
+}
+
+fn hostname() -> Vec<u8> {
+    let mut name = [0u8; 256];
+    if unsafe { libc::gethostname(name.as_mut_ptr() as *mut libc::c_char, name.len()) } != 0 {
+        return vec![];
+    }
+    name.iter().take_while(|c| **c != 0).copied().collect()
+}
+
+/// the MIT-MAGIC-COOKIE-1 for display `number` in `XAUTHORITY` (or
+/// `~/.Xauthority`), if there is one. servers started without `-auth`,
+/// like a plain Xvfb, don't need one.
+fn find_cookie(number: u16, local: bool) -> Option<Vec<u8>> {
+    const FAMILY_LOCAL: u16 = 256;
+    const FAMILY_WILD: u16 = 65535;
+    let path = env::var_os("XAUTHORITY").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".Xauthority")))?;
+    let data = fs::read(path).ok()?;
+    let hostname = hostname();
+    let number = number.to_string();
+    // each entry is a family and then four strings that start with their lengths
+    let mut at = 0;
+    loop {
+        let family = u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]);
+        at += 2;
+        let mut strings = [&[][..]; 4];
+        for string in strings.iter_mut() {
+            let length = u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]) as usize;
+            *string = data.get(at + 2..at + 2 + length)?;
+            at += 2 + length;
+        }
+        let [address, display, name, cookie] = strings;
+        let host_matches = match family {
+            FAMILY_LOCAL => local && address == &hostname[..],
+            FAMILY_WILD => true,
+            _ => !local,
+        };
+        if host_matches && (display.is_empty() || display == number.as_bytes()) && name == b"MIT-MAGIC-COOKIE-1" {
+            return Some(cookie.to_vec());
+        }
+    }
+}
+
+enum Stream {
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Stream {
+    fn fd(&self) -> RawFd {
+        match self {
+            Stream::Unix(stream) => stream.as_raw_fd(),
+            Stream::Tcp(stream) => stream.as_raw_fd(),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Unix(stream) => stream.read(buf),
+            Stream::Tcp(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Unix(stream) => stream.write(buf),
+            Stream::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What the server says about itself when we connect, or the parts of it
+/// that we need.
+struct Setup {
+    id_base: u32,
+    id_mask: u32,
+    /// in 4 byte units
+    max_request_length: u16,
+    min_keycode: u8,
+    max_keycode: u8,
+    root: u32,
+    screen_width: u16,
+    screen_height: u16,
+}
+
+/// reads the setup, checking that `screen` draws 32 bit pixels the way
+/// that `Bgra32` has them
+fn parse_setup(data: &[u8], screen: usize) -> io::Result<Setup> {
+    let unsupported = |msg: &str| io::Error::new(io::ErrorKind::Unsupported, msg.to_owned());
+    let short = || io::Error::new(io::ErrorKind::InvalidData, "short X11 setup");
+    if data.len() < 32 {
+        return Err(short());
+    }
+    let vendor_length = u16_at(data, 16) as usize;
+    let (screen_count, format_count) = (data[20] as usize, data[21] as usize);
+    if data[22] != 0 {
+        return Err(unsupported("the X11 server's images are big endian"));
+    }
+    let formats = 32 + vendor_length + pad(vendor_length);
+    let has_format = (0..format_count).any(|i| {
+        let format = &data[(formats + i * 8).min(data.len())..];
+        format.len() >= 2 && format[0] == 24 && format[1] == 32
+    });
+    if !has_format {
+        return Err(unsupported("the X11 server has no 32 bit format for depth 24"));
+    }
+
+    let mut at = formats + format_count * 8;
+    for i in 0..screen_count {
+        let header = data.get(at..at + 40).ok_or_else(short)?;
+        let depth_count = header[39] as usize;
+        let (root, root_visual, root_depth) = (u32_at(header, 0), u32_at(header, 32), header[38]);
+        let (screen_width, screen_height) = (u16_at(header, 20), u16_at(header, 22));
+        at += 40;
+        let mut root_visual_ok = false;
+        for _ in 0..depth_count {
+            let depth = data.get(at..at + 8).ok_or_else(short)?;
+            let visual_count = u16_at(depth, 2) as usize;
+            let visuals = data.get(at + 8..at + 8 + visual_count * 24).ok_or_else(short)?;
+            for visual in visuals.chunks(24) {
+                if u32_at(visual, 0) == root_visual {
+                    root_visual_ok = depth[0] == 24 && visual[4] == TRUE_COLOR
+                        && (u32_at(visual, 8), u32_at(visual, 12), u32_at(visual, 16)) == (0xff0000, 0xff00, 0xff);
+                }
+            }
+            at += 8 + visual_count * 24;
+        }
+        if i == screen {
+            if root_depth != 24 || !root_visual_ok {
+                return Err(unsupported("the X11 screen isn't 24 bit truecolor"));
+            }
+            return Ok(Setup {
+                id_base: u32_at(data, 4),
+                id_mask: u32_at(data, 8),
+                max_request_length: u16_at(data, 18),
+                min_keycode: data[26],
+                max_keycode: data[27],
+                root,
+                screen_width,
+                screen_height,
+            });
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, format!("the X11 server has no screen {}", screen)))
+}
+
+/// the key for a keysym, see `X11/keysymdef.h`
+pub fn keysym_code(keysym: u32) -> KeyCode {
+    match keysym {
+        0x20 => KeyCode::Space,
+        0x27 => KeyCode::Apostrophe,
+        0x2c => KeyCode::Comma,
+        0x2d => KeyCode::Minus,
+        0x2e => KeyCode::Period,
+        0x2f => KeyCode::Slash,
+        0x30..=0x39 => KeyCode::from(KeyCode::Key0 as u32 + (keysym - 0x30)),
+        0x3b => KeyCode::Semicolon,
+        0x3d => KeyCode::Equal,
+        0x41..=0x5a => KeyCode::from(KeyCode::A as u32 + (keysym - 0x41)),
+        0x5b => KeyCode::LeftBracket,
+        0x5c => KeyCode::Backslash,
+        0x5d => KeyCode::RightBracket,
+        0x60 => KeyCode::GraveAccent,
+        0x61..=0x7a => KeyCode::from(KeyCode::A as u32 + (keysym - 0x61)),
+        0xff08 => KeyCode::Backspace,
+        0xff09 => KeyCode::Tab,
+        0xff0d => KeyCode::Enter,
+        0xff13 => KeyCode::Pause,
+        0xff14 => KeyCode::ScrollLock,
+        0xff1b => KeyCode::Escape,
+        0xff50 => KeyCode::Home,
+        0xff51 => KeyCode::Left,
+        0xff52 => KeyCode::Up,
+        0xff53 => KeyCode::Right,
+        0xff54 => KeyCode::Down,
+        0xff55 => KeyCode::PageUp,
+        0xff56 => KeyCode::PageDown,
+        0xff57 => KeyCode::End,
+        0xff61 => KeyCode::PrintScreen,
+        0xff63 => KeyCode::Insert,
+        0xff67 => KeyCode::Menu,
+        0xff7f => KeyCode::NumLock,
+        0xff8d => KeyCode::KpEnter,
+        0xffaa => KeyCode::KpMultiply,
+        0xffab => KeyCode::KpAdd,
+        0xffad => KeyCode::KpSubtract,
+        0xffae => KeyCode::KpDecimal,
+        0xffaf => KeyCode::KpDivide,
+        0xffb0..=0xffb9 => KeyCode::from(KeyCode::Kp0 as u32 + (keysym - 0xffb0)),
+        0xffbd => KeyCode::KpEqual,
+        0xffbe..=0xffd6 => KeyCode::from(KeyCode::F1 as u32 + (keysym - 0xffbe)),
+        0xffe1 => KeyCode::LeftShift,
+        0xffe2 => KeyCode::RightShift,
+        0xffe3 => KeyCode::LeftControl,
+        0xffe4 => KeyCode::RightControl,
+        0xffe5 => KeyCode::CapsLock,
+        0xffe9 => KeyCode::LeftAlt,
+        0xffea => KeyCode::RightAlt,
+        0xffeb => KeyCode::LeftSuper,
+        0xffec => KeyCode::RightSuper,
+        0xffff => KeyCode::Delete,
+        _ => KeyCode::Unknown,
+    }
+}
+
+/// the modifiers in the state of a key or button event, from before it
+fn modifiers(state: u16) -> KeyMods {
+    KeyMods {
+        shift: state & 0x1 != 0,
+        ctrl: state & 0x4 != 0,
+        alt: state & 0x8 != 0,
+        logo: state & 0x40 != 0,
+    }
+}
+
+/// A SysV shared memory segment that the server has attached to. the
+/// frames are written to it and then shown with `ShmPutImage`.
+struct Shm {
+    opcode: u8,
+    /// the type of the event that says the server is done reading
+    completion: u8,
+    segment: u32,
+    memory: *mut u8,
+    size: usize,
+    /// whether an image was put and the server might still be reading it
+    busy: bool,
+}
+
+impl Drop for Shm {
+    fn drop(&mut self) {
+        // the segment was marked to be removed once the server detaches too
+        unsafe { libc::shmdt(self.memory as *const libc::c_void) };
+    }
+}
+
+/// A window on an X11 server, with 24 bit truecolor pixels in 32 bits,
+/// ie: `Bgra32` with the alpha ignored.
+pub struct X11Window {
+    /// the window's size, which the window manager can change. the
+    /// frames that are shown keep their own size.
+    pub width: usize,
+    pub height: usize,
+    /// the window manager was asked to close the window
+    pub closed: bool,
+    /// part of the window was uncovered and has to be shown again
+    pub exposed: bool,
+    stream: Stream,
+    /// of the last request sent
+    sequence: u16,
+    /// of the last error for a request that nothing waited on
+    last_error: Option<u16>,
+    next_id: u32,
+    id_base: u32,
+    id_mask: u32,
+    max_request_bytes: usize,
+    window: u32,
+    gc: u32,
+    wm_protocols: u32,
+    wm_delete_window: u32,
+    min_keycode: u8,
+    max_keycode: u8,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<u32>,
+    /// the key that is being auto repeated
+    repeating: Option<u8>,
+    shm: Option<Shm>,
+    /// events that arrived while waiting for something else
+    pending: VecDeque<Vec<u8>>,
+}
+
+impl X11Window {
+    /// connects to the server in `DISPLAY` and opens a window there
+    pub fn open_default(bconf: &BackendConf) -> io::Result<X11Window> {
+        let display = env::var("DISPLAY").map_err(|_| io::Error::new(io::ErrorKind::NotFound, "DISPLAY isn't set"))?;
+        X11Window::open(&display, bconf)
+    }
+
+    /// connects to `display` (like `:0`) and opens a window of the size
+    /// and title in `bconf`, or one that covers the screen if it is
+    /// `fullscreen`
+    pub fn open(display: &str, bconf: &BackendConf) -> io::Result<X11Window> {
+        let (host, number, screen) = parse_display(display)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("bad DISPLAY {:?}", display)))?;
+        let local = host.is_empty() || host == "unix";
+        let mut stream = if local {
+            Stream::Unix(UnixStream::connect(format!("/tmp/.X11-unix/X{}", number))?)
+        } else {
+            Stream::Tcp(TcpStream::connect((host.as_str(), 6000 + number))?)
+        };
+
+        let cookie = find_cookie(number, local);
+        let (auth_name, auth_data): (&[u8], &[u8]) = match &cookie {
+            Some(cookie) => (b"MIT-MAGIC-COOKIE-1", cookie),
+            None => (b"", b""),
+        };
+        let mut hello = vec![b'l', 0];
+        hello.extend_from_slice(&11u16.to_le_bytes());
+        hello.extend_from_slice(&0u16.to_le_bytes());
+        hello.extend_from_slice(&(auth_name.len() as u16).to_le_bytes());
+        hello.extend_from_slice(&(auth_data.len() as u16).to_le_bytes());
+        hello.extend_from_slice(&[0, 0]);
+        for bytes in [auth_name, auth_data] {
+            hello.extend_from_slice(bytes);
+            hello.resize(hello.len() + pad(bytes.len()), 0);
+        }
+        stream.write_all(&hello)?;
+
+        let mut header = [0u8; 8];
+        stream.read_exact(&mut header)?;
+        let mut data = vec![0u8; u16_at(&header, 6) as usize * 4];
+        stream.read_exact(&mut data)?;
+        match header[0] {
+            1 => {}
+            0 => {
+                let reason = String::from_utf8_lossy(&data[..(header[1] as usize).min(data.len())]).into_owned();
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("the X11 server refused: {}", reason.trim())));
+            }
+            _ => return Err(io::Error::new(io::ErrorKind::PermissionDenied, "the X11 server wants more authentication")),
+        }
+        let setup = parse_setup(&data, screen)?;
+
+        let (width, height) = if bconf.fullscreen {
+            (setup.screen_width as usize, setup.screen_height as usize)
+        } else {
+            (bconf.window_width.max(1) as usize, bconf.window_height.max(1) as usize)
+        };
+        let mut x11 = X11Window {
+            width,
+            height,
+            closed: false,
+            exposed: false,
+            stream,
+            sequence: 0,
+            last_error: None,
+            next_id: 0,
+            id_base: setup.id_base,
+            id_mask: setup.id_mask,
+            max_request_bytes: setup.max_request_length as usize * 4,
+            window: 0,
+            gc: 0,
+            wm_protocols: 0,
+            wm_delete_window: 0,
+            min_keycode: setup.min_keycode,
+            max_keycode: setup.max_keycode,
+            keysyms_per_keycode: 0,
+            keysyms: vec![],
+            repeating: None,
+            shm: None,
+            pending: VecDeque::new(),
+        };
+        x11.load_keyboard_mapping()?;
+        x11.create_window(&setup, bconf)?;
+        if local {
+            x11.shm = x11.attach_shm(width * height * 4)?;
+        }
+        Ok(x11)
+    }
+
+    /// whether frames are shown through shared memory, rather than sent
+    /// over the socket
+    pub fn uses_shm(&self) -> bool {
+        self.shm.is_some()
+    }
+
+    fn new_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.id_base | ((self.next_id << self.id_mask.trailing_zeros()) & self.id_mask)
+    }
+
+    /// sends a request, and gives its sequence number
+    fn send(&mut self, request: Vec<u8>) -> io::Result<u16> {
+        self.stream.write_all(&request)?;
+        self.sequence = self.sequence.wrapping_add(1);
+        Ok(self.sequence)
+    }
+
+    /// reads the next reply, error or event
+    fn read_packet(&mut self) -> io::Result<Vec<u8>> {
+        let mut packet = vec![0u8; 32];
+        self.stream.read_exact(&mut packet)?;
+        // replies and generic events can be longer than 32 bytes
+        if packet[0] == 1 || packet[0] & 0x7f == GENERIC_EVENT {
+            let length = 32 + u32_at(&packet, 4) as usize * 4;
+            packet.resize(length, 0);
+            self.stream.read_exact(&mut packet[32..])?;
+        }
+        Ok(packet)
+    }
+
+    /// keeps an event or error that nothing is waiting for. true if it
+    /// was an event.
+    fn keep(&mut self, packet: Vec<u8>) -> bool {
+        match packet[0] {
+            0 => {
+                if let Some(shm) = &mut self.shm {
+                    // a ShmPutImage that failed won't complete
+                    if packet[10] == shm.opcode {
+                        shm.busy = false;
+                    }
+                }
+                self.last_error = Some(u16_at(&packet, 2));
+                false
+            }
+            1 => false,
+            kind => {
+                let completed = self.shm.as_ref().is_some_and(|shm| kind & 0x7f == shm.completion);
+                if completed {
+                    self.shm.as_mut().unwrap().busy = false;
+                } else {
+                    self.pending.push_back(packet);
+                }
+                true
+            }
+        }
+    }
+
+    /// waits for the reply to request `sequence`
+    fn reply(&mut self, sequence: u16) -> io::Result<Vec<u8>> {
+        loop {
+            let packet = self.read_packet()?;
+            if packet[0] <= 1 && u16_at(&packet, 2) == sequence {
+                return match packet[0] {
+                    0 => Err(x_error(&packet)),
+                    _ => Ok(packet),
+                };
+            }
+            self.keep(packet);
+        }
+    }
+
+    /// waits until the server has handled every request, by asking it
+    /// something
+    fn sync(&mut self) -> io::Result<()> {
+        let sequence = self.send(Request::new(GET_INPUT_FOCUS, 0).finish())?;
+        self.reply(sequence).map(|_| ())
+    }
+
+    fn intern_atom(&mut self, name: &str) -> io::Result<u32> {
+        let request = Request::new(INTERN_ATOM, 0).u16(name.len() as u16).u16(0).bytes(name.as_bytes());
+        let sequence = self.send(request.finish())?;
+        Ok(u32_at(&self.reply(sequence)?, 8))
+    }
+
+    fn change_property(&mut self, property: u32, kind: u32, format: u8, data: &[u8]) -> io::Result<()> {
+        let count = data.len() / (format as usize / 8);
+        let request = Request::new(CHANGE_PROPERTY, 0)
+            .u32(self.window).u32(property).u32(kind)
+            .u8(format).u8(0).u16(0)
+            .u32(count as u32)
+            .bytes(data);
+        self.send(request.finish()).map(|_| ())
+    }
+
+    fn load_keyboard_mapping(&mut self) -> io::Result<()> {
+        let count = self.max_keycode.saturating_sub(self.min_keycode) + 1;
+        let request = Request::new(GET_KEYBOARD_MAPPING, 0).u8(self.min_keycode).u8(count).u16(0);
+        let sequence = self.send(request.finish())?;
+        let reply = self.reply(sequence)?;
+        self.keysyms_per_keycode = reply[1] as usize;
+        self.keysyms = reply[32..].chunks(4).map(|keysym| u32_at(keysym, 0)).collect();
+        Ok(())
+    }
+
+    /// the key for an X keycode, by its first keysym (which for letters
+    /// is the lowercase one), or its second for the keypad's numbers
+    fn key_code(&self, keycode: u8) -> KeyCode {
+        let start = (keycode.wrapping_sub(self.min_keycode) as usize) * self.keysyms_per_keycode;
+        let keysyms = self.keysyms.get(start..start + self.keysyms_per_keycode).unwrap_or(&[]);
+        keysyms.iter().take(2)
+            .map(|keysym| keysym_code(*keysym))
+            .find(|code| *code != KeyCode::Unknown)
+            .unwrap_or(KeyCode::Unknown)
+    }
+
+    fn create_window(&mut self, setup: &Setup, bconf: &BackendConf) -> io::Result<()> {
+        self.window = self.new_id();
+        // the depth and visual are copied from the root window, which
+        // `parse_setup` checked
+        let request = Request::new(CREATE_WINDOW, 0)
+            .u32(self.window).u32(setup.root)
+            .i16(0).i16(0).u16(self.width as u16).u16(self.height as u16)
+            .u16(0) // border width
+            .u16(1) // InputOutput
+            .u32(0)
+            .u32(0x2 | 0x800) // background pixel and event mask
+            .u32(0) // black
+            .u32(EVENT_MASK);
+        self.send(request.finish())?;
+
+        let title = bconf.window_title.as_bytes();
+        self.change_property(WM_NAME, STRING, 8, title)?;
+        let net_wm_name = self.intern_atom("_NET_WM_NAME")?;
+        let utf8_string = self.intern_atom("UTF8_STRING")?;
+        self.change_property(net_wm_name, utf8_string, 8, title)?;
+
+        // so closing the window sends us a message rather than killing the connection
+        self.wm_protocols = self.intern_atom("WM_PROTOCOLS")?;
+        self.wm_delete_window = self.intern_atom("WM_DELETE_WINDOW")?;
+        self.change_property(self.wm_protocols, ATOM, 32, &self.wm_delete_window.to_le_bytes())?;
+
+        if bconf.fullscreen {
+            let state = self.intern_atom("_NET_WM_STATE")?;
+            let fullscreen = self.intern_atom("_NET_WM_STATE_FULLSCREEN")?;
+            self.change_property(state, ATOM, 32, &fullscreen.to_le_bytes())?;
+        } else if !bconf.window_resizable {
+            // WM_SIZE_HINTS with the same minimum and maximum size
+            let mut hints = [0u32; 18];
+            hints[0] = 16 | 32;
+            hints[5..9].copy_from_slice(&[self.width as u32, self.height as u32, self.width as u32, self.height as u32]);
+            let hints: Vec<u8> = hints.iter().flat_map(|value| value.to_le_bytes()).collect();
+            self.change_property(WM_NORMAL_HINTS, WM_SIZE_HINTS, 32, &hints)?;
+        }
+
+        self.gc = self.new_id();
+        self.send(Request::new(CREATE_GC, 0).u32(self.gc).u32(self.window).u32(0).finish())?;
+        self.send(Request::new(MAP_WINDOW, 0).u32(self.window).finish())?;
+        Ok(())
+    }
+
+    /// makes a segment of `size` bytes and has the server attach to it,
+    /// or nothing if the server doesn't have MIT-SHM or can't attach (eg:
+    /// it is in another container)
+    fn attach_shm(&mut self, size: usize) -> io::Result<Option<Shm>> {
+        let name = b"MIT-SHM";
+        let request = Request::new(QUERY_EXTENSION, 0).u16(name.len() as u16).u16(0).bytes(name);
+        let sequence = self.send(request.finish())?;
+        let reply = self.reply(sequence)?;
+        if reply[8] == 0 {
+            return Ok(None);
+        }
+        let (opcode, completion) = (reply[9], reply[10]);
+
+        let id = unsafe { libc::shmget(libc::IPC_PRIVATE, size.max(1), libc::IPC_CREAT | 0o600) };
+        if id == -1 {
+            return Ok(None);
+        }
+        let memory = unsafe { libc::shmat(id, std::ptr::null(), 0) };
+        if memory as isize == -1 {
+            unsafe { libc::shmctl(id, libc::IPC_RMID, std::ptr::null_mut()) };
+            return Ok(None);
+        }
+        let shm = Shm { opcode, completion, segment: self.new_id(), memory: memory as *mut u8, size, busy: false };
+        let request = Request::new(opcode, SHM_ATTACH).u32(shm.segment).u32(id as u32).u8(1).u8(0).u16(0);
+        let attach = self.send(request.finish())?;
+        self.last_error = None;
+        let synced = self.sync();
+        // it goes away once both of us have detached
+        unsafe { libc::shmctl(id, libc::IPC_RMID, std::ptr::null_mut()) };
+        synced?;
+        if self.last_error == Some(attach) {
+            return Ok(None);
+        }
+        Ok(Some(shm))
+    }
+
+    /// shows a `width` x `height` frame of `Bgra32` pixels in the top
+    /// left corner of the window
+    pub fn present(&mut self, frame: &[u8], width: usize, height: usize) -> io::Result<()> {
+        let size = width * height * 4;
+        if self.shm.as_ref().is_some_and(|shm| shm.size < size) {
+            let shm = self.shm.take().unwrap();
+            let request = Request::new(shm.opcode, SHM_DETACH).u32(shm.segment);
+            self.send(request.finish())?;
+            self.sync()?;
+            drop(shm);
+            self.shm = self.attach_shm(size)?;
+        }
+        self.wait_for_shm()?;
+        let (window, gc) = (self.window, self.gc);
+        match &mut self.shm {
+            Some(shm) => {
+                unsafe { std::ptr::copy_nonoverlapping(frame.as_ptr(), shm.memory, size.min(frame.len())) };
+                let request = Request::new(shm.opcode, SHM_PUT_IMAGE)
+                    .u32(window).u32(gc)
+                    .u16(width as u16).u16(height as u16) // of the image in the segment
+                    .u16(0).u16(0).u16(width as u16).u16(height as u16) // the part of it to put
+                    .i16(0).i16(0)
+                    .u8(24).u8(Z_PIXMAP)
+                    .u8(1) // send an event when done
+                    .u8(0)
+                    .u32(shm.segment)
+                    .u32(0);
+                shm.busy = true;
+                self.send(request.finish())?;
+            }
+            None => {
+                // in bands of rows that fit in a request
+                let row = width * 4;
+                let rows = ((self.max_request_bytes - 24) / row.max(1)).max(1);
+                for top in (0..height).step_by(rows) {
+                    let bottom = (top + rows).min(height);
+                    let request = Request::new(PUT_IMAGE, Z_PIXMAP)
+                        .u32(window).u32(gc)
+                        .u16(width as u16).u16((bottom - top) as u16)
+                        .i16(0).i16(top as i16)
+                        .u8(0).u8(24).u16(0)
+                        .bytes(&frame[top * row..bottom * row]);
+                    self.send(request.finish())?;
+                }
+            }
+        }
+        self.exposed = false;
+        Ok(())
+    }
+
+    /// waits for the server to be done reading the last frame from
+    /// shared memory, so the next one can be written
+    pub fn wait_for_shm(&mut self) -> io::Result<()> {
+        while self.shm.as_ref().is_some_and(|shm| shm.busy) {
+            let packet = self.read_packet()?;
+            self.keep(packet);
+        }
+        Ok(())
+    }
+
+    /// waits up to `timeout_ms` for an event (-1 waits forever), and then
+    /// adds every event that arrived to `events`
+    pub fn poll(&mut self, timeout_ms: i32, events: &mut Vec<Event>) -> io::Result<()> {
+        let mut timeout = timeout_ms;
+        while self.pending.is_empty() && readable(self.stream.fd(), timeout)? {
+            let packet = self.read_packet()?;
+            if self.keep(packet) {
+                timeout = 0;
+            }
+        }
+        while readable(self.stream.fd(), 0)? {
+            let packet = self.read_packet()?;
+            self.keep(packet);
+        }
+
+        let packets: Vec<Vec<u8>> = self.pending.drain(..).collect();
+        let mut mapping_changed = false;
+        for (i, packet) in packets.iter().enumerate() {
+            let (x, y) = (u16_at(packet, 24) as i16 as f32, u16_at(packet, 26) as i16 as f32);
+            match packet[0] & 0x7f {
+                KEY_PRESS => {
+                    let keycode = packet[1];
+                    let repeated = self.repeating.take() == Some(keycode);
+                    let (modifier, code) = (modifiers(u16_at(packet, 28)), self.key_code(keycode));
+                    events.push(Event::KeyDown { modifier, code, repeated });
+                }
+                KEY_RELEASE => {
+                    let keycode = packet[1];
+                    // auto repeat sends a release and a press at the same time
+                    let repeat = packets.get(i + 1).is_some_and(|next| {
+                        next[0] & 0x7f == KEY_PRESS && next[1] == keycode && u32_at(next, 4) == u32_at(packet, 4)
+                    });
+                    if repeat {
+                        self.repeating = Some(keycode);
+                        continue;
+                    }
+                    let (modifier, code) = (modifiers(u16_at(packet, 28)), self.key_code(keycode));
+                    events.push(Event::KeyUp { modifier, code });
+                }
+                BUTTON_PRESS | BUTTON_RELEASE => {
+                    let down = packet[0] & 0x7f == BUTTON_PRESS;
+                    let button = match packet[1] {
+                        1 => MouseButton::Left,
+                        2 => MouseButton::Middle,
+                        3 => MouseButton::Right,
+                        // the wheel, which is pressed and released at once
+                        4 | 5 => {
+                            if down {
+                                events.push(Event::MouseScroll { up: packet[1] == 4 });
+                            }
+                            continue;
+                        }
+                        // sideways scrolling
+                        6 | 7 => continue,
+                        _ => MouseButton::Unknown,
+                    };
+                    events.push(match down {
+                        true => Event::MouseDown { button, x, y },
+                        false => Event::MouseUp { button, x, y },
+                    });
+                }
+                MOTION_NOTIFY => events.push(Event::MouseMove { x, y }),
+                // the last of a group of exposes
+                EXPOSE if u16_at(packet, 16) == 0 => self.exposed = true,
+                CONFIGURE_NOTIFY => {
+                    self.width = u16_at(packet, 20) as usize;
+                    self.height = u16_at(packet, 22) as usize;
+                }
+                CLIENT_MESSAGE if u32_at(packet, 8) == self.wm_protocols && u32_at(packet, 12) == self.wm_delete_window => {
+                    self.closed = true;
+                }
+                // the keyboard mapping changed
+                MAPPING_NOTIFY if packet[4] == 1 => mapping_changed = true,
+                _ => {}
+            }
+        }
+        if mapping_changed {
+            self.load_keyboard_mapping()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "shm"))]
+mod tests {
+    use super::*;
+    use crate::backend::b_shm::{ShmBackend, ShmWindow};
+    use crate::backend::{GameLoop, TextureUpdate};
+    use crate::draw::Draw;
+    use crate::{Canvas, Rgb};
+
+    #[test]
+    fn displays() {
+        assert_eq!(parse_display(":0"), Some(("".into(), 0, 0)));
+        assert_eq!(parse_display("unix:1"), Some(("unix".into(), 1, 0)));
+        assert_eq!(parse_display("localhost:10.2"), Some(("localhost".into(), 10, 2)));
+        assert_eq!(parse_display("::1:3"), Some(("::1".into(), 3, 0)));
+        assert_eq!(parse_display("0"), None);
+        assert_eq!(parse_display(":"), None);
+        assert_eq!(parse_display(":x"), None);
+        assert_eq!(parse_display(":1.y"), None);
+    }
+
+    #[test]
+    fn keysyms() {
+        assert_eq!(keysym_code(0x61), KeyCode::A);
+        assert_eq!(keysym_code(0x41), KeyCode::A);
+        assert_eq!(keysym_code(0x7a), KeyCode::Z);
+        assert_eq!(keysym_code(0x30), KeyCode::Key0);
+        assert_eq!(keysym_code(0x39), KeyCode::Key9);
+        assert_eq!(keysym_code(0xffbe), KeyCode::F1);
+        assert_eq!(keysym_code(0xffc9), KeyCode::F12);
+        assert_eq!(keysym_code(0xffb7), KeyCode::Kp7);
+        assert_eq!(keysym_code(0xff52), KeyCode::Up);
+        assert_eq!(keysym_code(0xffe1), KeyCode::LeftShift);
+        assert_eq!(keysym_code(0xff1b), KeyCode::Escape);
+        // KP_Home, which the keypad's 7 is without num lock
+        assert_eq!(keysym_code(0xff95), KeyCode::Unknown);
+        assert_eq!(keysym_code(0), KeyCode::Unknown);
+    }
+
+    /// a setup of one 1024x768 screen whose root visual has these masks
+    fn setup(image_byte_order: u8, bits_per_pixel: u8, masks: (u32, u32, u32)) -> Vec<u8> {
+        let vendor = b"test";
+        let mut data = vec![0u8; 32];
+        data[4..8].copy_from_slice(&0x40_0000u32.to_le_bytes());
+        data[8..12].copy_from_slice(&0x1f_ffffu32.to_le_bytes());
+        data[16..18].copy_from_slice(&(vendor.len() as u16).to_le_bytes());
+        data[18..20].copy_from_slice(&0xffffu16.to_le_bytes());
+        data[20] = 1;
+        data[21] = 2;
+        data[22] = image_byte_order;
+        data[26] = 8;
+        data[27] = 255;
+        data.extend_from_slice(vendor);
+        data.extend_from_slice(&[1, 1, 32, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&[24, bits_per_pixel, 32, 0, 0, 0, 0, 0]);
+        let mut screen = vec![0u8; 40];
+        screen[0..4].copy_from_slice(&0x100u32.to_le_bytes());
+        screen[20..22].copy_from_slice(&1024u16.to_le_bytes());
+        screen[22..24].copy_from_slice(&768u16.to_le_bytes());
+        screen[32..36].copy_from_slice(&0x21u32.to_le_bytes());
+        screen[38] = 24;
+        screen[39] = 1;
+        data.extend_from_slice(&screen);
+        data.extend_from_slice(&[24, 0, 1, 0, 0, 0, 0, 0]);
+        let mut visual = vec![0u8; 24];
+        visual[0..4].copy_from_slice(&0x21u32.to_le_bytes());
+        visual[4] = TRUE_COLOR;
+        visual[8..12].copy_from_slice(&masks.0.to_le_bytes());
+        visual[12..16].copy_from_slice(&masks.1.to_le_bytes());
+        visual[16..20].copy_from_slice(&masks.2.to_le_bytes());
+        data.extend_from_slice(&visual);
+        data
+    }
+
+    const RGB_MASKS: (u32, u32, u32) = (0xff0000, 0xff00, 0xff);
+
+    #[test]
+    fn setups() {
+        let parsed = parse_setup(&setup(0, 32, RGB_MASKS), 0).unwrap();
+        assert_eq!((parsed.id_base, parsed.id_mask, parsed.max_request_length), (0x40_0000, 0x1f_ffff, 0xffff));
+        assert_eq!((parsed.min_keycode, parsed.max_keycode, parsed.root), (8, 255, 0x100));
+        assert_eq!((parsed.screen_width, parsed.screen_height), (1024, 768));
+
+        let kind = |data: &[u8], screen| parse_setup(data, screen).err().map(|e| e.kind());
+        assert_eq!(kind(&setup(1, 32, RGB_MASKS), 0), Some(io::ErrorKind::Unsupported));
+        assert_eq!(kind(&setup(0, 24, RGB_MASKS), 0), Some(io::ErrorKind::Unsupported));
+        assert_eq!(kind(&setup(0, 32, (0xff, 0xff00, 0xff0000)), 0), Some(io::ErrorKind::Unsupported));
+        assert_eq!(kind(&setup(0, 32, RGB_MASKS), 1), Some(io::ErrorKind::NotFound));
+        let data = setup(0, 32, RGB_MASKS);
+        for len in [0, 31, data.len() - 40, data.len() - 1] {
+            assert_eq!(kind(&data[..len], 0), Some(io::ErrorKind::InvalidData), "{} bytes", len);
+        }
+    }
+
+    /// a window on one end of a socket, with the other end for the test
+    /// to send events from. keycode 38 is a, and 50 is shift.
+    fn window() -> (X11Window, UnixStream) {
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let mut keysyms = vec![0; (255 - 8 + 1) * 2];
+        keysyms[(38 - 8) * 2..(38 - 8) * 2 + 2].copy_from_slice(&[0x61, 0x41]);
+        keysyms[(50 - 8) * 2] = 0xffe1;
+        let window = X11Window {
+            width: 800,
+            height: 600,
+            closed: false,
+            exposed: false,
+            stream: Stream::Unix(ours),
+            sequence: 0,
+            last_error: None,
+            next_id: 0,
+            id_base: 0,
+            id_mask: 0,
+            max_request_bytes: 0,
+            window: 2,
+            gc: 0,
+            wm_protocols: 100,
+            wm_delete_window: 101,
+            min_keycode: 8,
+            max_keycode: 255,
+            keysyms_per_keycode: 2,
+            keysyms,
+            repeating: None,
+            shm: None,
+            pending: VecDeque::new(),
+        };
+        (window, theirs)
+    }
+
+    /// a key, button or motion event
+    fn input(kind: u8, detail: u8, time: u32, (x, y): (i16, i16), state: u16) -> Vec<u8> {
+        let mut packet = vec![0u8; 32];
+        packet[0] = kind;
+        packet[1] = detail;
+        packet[4..8].copy_from_slice(&time.to_le_bytes());
+        packet[24..26].copy_from_slice(&x.to_le_bytes());
+        packet[26..28].copy_from_slice(&y.to_le_bytes());
+        packet[28..30].copy_from_slice(&state.to_le_bytes());
+        packet
+    }
+
+    fn poll(window: &mut X11Window, server: &mut UnixStream, packets: &[Vec<u8>]) -> Vec<Event> {
+        server.write_all(&packets.concat()).unwrap();
+        let mut events = vec![];
+        window.poll(0, &mut events).unwrap();
+        events
+    }
+
+    #[test]
+    fn auto_repeat_is_merged() {
+        let (mut window, mut server) = window();
+        let events = poll(&mut window, &mut server, &[
+            input(KEY_PRESS, 50, 1, (0, 0), 0),
+            input(KEY_PRESS, 38, 2, (0, 0), 1),
+            // auto repeat, twice
+            input(KEY_RELEASE, 38, 3, (0, 0), 1),
+            input(KEY_PRESS, 38, 3, (0, 0), 1),
+            input(KEY_RELEASE, 38, 4, (0, 0), 1),
+            input(KEY_PRESS, 38, 4, (0, 0), 1),
+            // really let go, and then pressed again later
+            input(KEY_RELEASE, 38, 5, (0, 0), 1),
+            input(KEY_PRESS, 38, 6, (0, 0), 1),
+            // a release and a press of different keys at once
+            input(KEY_RELEASE, 50, 7, (0, 0), 1),
+            input(KEY_PRESS, 38, 7, (0, 0), 0),
+        ]);
+        let shift = KeyMods { shift: true, ..Default::default() };
+        let none = KeyMods::default();
+        assert_eq!(events, vec![
+            Event::KeyDown { modifier: none, code: KeyCode::LeftShift, repeated: false },
+            Event::KeyDown { modifier: shift, code: KeyCode::A, repeated: false },
+            Event::KeyDown { modifier: shift, code: KeyCode::A, repeated: true },
+            Event::KeyDown { modifier: shift, code: KeyCode::A, repeated: true },
+            Event::KeyUp { modifier: shift, code: KeyCode::A },
+            Event::KeyDown { modifier: shift, code: KeyCode::A, repeated: false },
+            Event::KeyUp { modifier: shift, code: KeyCode::LeftShift },
+            Event::KeyDown { modifier: none, code: KeyCode::A, repeated: false },
+        ]);
+    }
+
+    #[test]
+    fn mouse_and_window_events() {
+        let (mut window, mut server) = window();
+        let mut expose = vec![0u8; 32];
+        expose[0] = EXPOSE;
+        let mut configure = vec![0u8; 32];
+        configure[0] = CONFIGURE_NOTIFY;
+        configure[20..24].copy_from_slice(&[0x84, 0x03, 0xbc, 0x02]);
+        let events = poll(&mut window, &mut server, &[
+            input(MOTION_NOTIFY, 0, 1, (130, -5), 0),
+            input(BUTTON_PRESS, 3, 2, (130, 60), 0),
+            input(BUTTON_RELEASE, 3, 3, (131, 60), 0x400),
+            input(BUTTON_PRESS, 4, 4, (130, 60), 0),
+            input(BUTTON_RELEASE, 4, 4, (130, 60), 0),
+            input(BUTTON_PRESS, 5, 5, (130, 60), 0),
+            input(BUTTON_PRESS, 6, 6, (130, 60), 0),
+            expose,
+            configure,
+        ]);
+        assert_eq!(events, vec![
+            Event::MouseMove { x: 130.0, y: -5.0 },
+            Event::MouseDown { button: MouseButton::Right, x: 130.0, y: 60.0 },
+            Event::MouseUp { button: MouseButton::Right, x: 131.0, y: 60.0 },
+            Event::MouseScroll { up: true },
+            Event::MouseScroll { up: false },
+        ]);
+        assert!(window.exposed);
+        assert_eq!((window.width, window.height), (900, 700));
+        assert!(!window.closed);
+
+        let mut close = vec![0u8; 32];
+        close[0] = CLIENT_MESSAGE;
+        close[8..12].copy_from_slice(&100u32.to_le_bytes());
+        close[12..16].copy_from_slice(&101u32.to_le_bytes());
+        assert!(poll(&mut window, &mut server, &[close]).is_empty());
+        assert!(window.closed);
+    }
+
+    impl X11Window {
+        /// what the window shows, read back from the server with `GetImage`
+        fn get_image(&mut self, width: usize, height: usize) -> io::Result<Vec<u8>> {
+            let request = Request::new(73, Z_PIXMAP)
+                .u32(self.window)
+                .i16(0).i16(0).u16(width as u16).u16(height as u16)
+                .u32(!0);
+            let sequence = self.send(request.finish())?;
+            Ok(self.reply(sequence)?[32..].to_vec())
+        }
+    }
+
+    /// red on the left, blue on the right
+    struct Halves(Canvas);
+
+    impl GameLoop for Halves {
+        fn update(&mut self, _events: Vec<Event>) {}
+
+        fn draw(&mut self) -> TextureUpdate<'_> {
+            let (width, height) = (self.0.width, self.0.height);
+            for y in 0..height {
+                self.0.draw_horizontal_line(y, 0, width / 2, Rgb::RED);
+                self.0.draw_horizontal_line(y, width / 2, width, Rgb::BLUE);
+            }
+            TextureUpdate::UpdateWhole(self.0.access_data())
+        }
+
+        fn init_canvas(&mut self, width: usize, height: usize, bpp: usize) -> Vec<u8> {
+            self.0 = Canvas::new(width, height, bpp);
+            self.0.access_data().to_vec()
+        }
+    }
+
+    /// needs an X11 server without a window manager in the way, eg:
+    /// `Xvfb :99 & DISPLAY=:99 cargo test --features shm -- --ignored`
+    #[test]
+    #[ignore]
+    fn frames_reach_the_screen() {
+        let bconf = BackendConf {
+            window_title: "frames_reach_the_screen".into(),
+            window_width: 64,
+            window_height: 48,
+            fullscreen: false,
+            window_resizable: false,
+        };
+        let window = X11Window::open_default(&bconf).expect("this test needs an X11 server in DISPLAY");
+        let mut backend = ShmBackend::new(&bconf, ShmWindow::X11(window), Halves(Canvas::new(0, 0, 4)));
+        backend.run(Some(3)).unwrap();
+        let window = match &mut backend.window {
+            ShmWindow::X11(window) => window,
+            _ => unreachable!(),
+        };
+        window.wait_for_shm().unwrap();
+        let image = window.get_image(64, 48).unwrap();
+        // xrgb, so blue, green, red and then a byte to skip
+        let pixel = |x: usize, y: usize| {
+            let at = (y * 64 + x) * 4;
+            Rgb { red: image[at + 2], green: image[at + 1], blue: image[at] }
+        };
+        for (x, y) in [(0, 0), (31, 47), (10, 20)] {
+            assert_eq!(pixel(x, y), Rgb::RED, "at {}, {}", x, y);
+        }
+        for (x, y) in [(32, 0), (63, 47), (50, 20)] {
+            assert_eq!(pixel(x, y), Rgb::BLUE, "at {}, {}", x, y);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{Canvas, Rgb};
+use crate::pixel_format::PixelFormat;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// rows per band when a whole canvas is split up, eg: by `Canvas::fill`
+pub const DEFAULT_BAND_HEIGHT: usize = 64;
+
+/// fills `data` with `color`. the first pixel is written by hand, and
+/// then copied onto the rest, doubling the filled part each time, so
+/// almost all of the work is done by large `memcpy`s. bytes that the
+/// format doesn't write (eg: `Rgbx`'s extra bytes) are copied from the
+/// first pixel. `data` has to be a whole number of pixels long.
+pub(crate) fn fill_pixels<P: PixelFormat>(data: &mut [u8], format: P, color: Rgb) {
+    let bpp = format.bytes_per_pixel();
+    if data.len() < bpp {
+        return;
+    }
+    format.write(&mut data[..bpp], color);
+    let mut filled = bpp;
+    while filled < data.len() {
+        let count = filled.min(data.len() - filled);
+        data.copy_within(..count, filled);
+        filled += count;
+    }
+}
+
+/// A horizontal strip of a canvas that can be drawn to on its own, so
+/// that several of them can be drawn at the same time. coordinates are
+/// the same as on the whole canvas, and anything outside of the band is
+/// clipped.
+pub struct Band<'a, P: PixelFormat> {
+    /// the row of the canvas that the band starts at
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+    pub format: P,
+    data: &'a mut [u8],
+}
+
+impl<'a, P: PixelFormat> Band<'a, P> {
+    pub fn contains_row(&self, y: usize) -> bool {
+        y >= self.top && y < self.top + self.height
+    }
+
+    /// the bytes of a row, `y` is a row of the canvas
+    pub fn row_mut(&mut self, y: usize) -> Option<&mut [u8]> {
+        if !self.contains_row(y) {
+            return None;
+        }
+        let span = self.width * self.format.bytes_per_pixel();
+        let start = (y - self.top) * span;
+        Some(&mut self.data[start..start + span])
+    }
+
+    /// the bytes of every row of the band, starting at `top`
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+
+    pub fn fill(&mut self, color: Rgb) {
+        fill_pixels(self.data, self.format, color);
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb) {
+        if x >= self.width {
+            return;
+        }
+        let (bpp, format) = (self.format.bytes_per_pixel(), self.format);
+        if let Some(row) = self.row_mut(y) {
+            format.write(&mut row[x * bpp..(x + 1) * bpp], color);
+        }
+    }
+
+    /// from x1 up to (not including) x2
+    pub fn draw_horizontal_line(&mut self, y: usize, x1: usize, x2: usize, color: Rgb) {
+        let x2 = x2.min(self.width);
+        if x1 >= x2 {
+            return;
+        }
+        let (bpp, format) = (self.format.bytes_per_pixel(), self.format);
+        if let Some(row) = self.row_mut(y) {
+            fill_pixels(&mut row[x1 * bpp..x2 * bpp], format, color);
+        }
+    }
+}
+
+impl<P: PixelFormat> Canvas<P> {
+    /// splits the canvas into bands of `band_height` rows and calls
+    /// `draw` with each of them. with the `parallel` feature the bands
+    /// are drawn at the same time on all cores, otherwise one after the
+    /// other from the top.
+    pub fn for_each_band<F>(&mut self, band_height: usize, draw: F)
+    where
+        F: Fn(&mut Band<P>) + Sync,
+    {
+        let (width, height, format) = (self.width, self.height, self.format);
+        let span = self.span();
+        if span == 0 || height == 0 {
+            return;
+        }
+        let band_height = band_height.max(1);
+        let to_band = |(i, data): (usize, &mut [u8])| {
+            let mut band = Band {
+                top: i * band_height,
+                width,
+                height: data.len() / span,
+                format,
+                data,
+            };
+            draw(&mut band);
+        };
+        #[cfg(feature = "parallel")]
+        self.data.par_chunks_mut(span * band_height).enumerate().for_each(to_band);
+        #[cfg(not(feature = "parallel"))]
+        self.data.chunks_mut(span * band_height).enumerate().for_each(to_band);
+    }
+}
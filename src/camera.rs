@@ -0,0 +1,408 @@
+use crate::world_screen::{WorldScreen, WorldRect};
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Easing {
+    Linear,
+    EaseInQuad,
+    EaseOutQuad,
+    #[default]
+    EaseInOutQuad,
+    EaseOutCubic,
+}
+
+impl Easing {
+    /// takes a progress value between 0 and 1 and returns
+    /// the eased progress value, also between 0 and 1.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => t * (2.0 - t),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    -1.0 + (4.0 - 2.0 * t) * t
+                }
+            }
+            Easing::EaseOutCubic => {
+                let f = t - 1.0;
+                f * f * f + 1.0
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ZoomAnimation {
+    screen_anchor: (f32, f32),
+    world_anchor: (f32, f32),
+    start_scale: f32,
+    end_scale: f32,
+    elapsed: f32,
+    duration: f32,
+    easing: Easing,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct FlyAnimation {
+    start_center: (f32, f32),
+    end_center: (f32, f32),
+    start_scale: f32,
+    end_scale: f32,
+    elapsed: f32,
+    duration: f32,
+    easing: Easing,
+}
+
+/// a target that the camera keeps in view. the deadzone
+/// is a half width/height in screen pixels around the center of
+/// the screen. the target can move freely inside of the deadzone
+/// without the camera moving.
+#[derive(Debug, Copy, Clone)]
+pub struct FollowTarget {
+    pub world_x: f32,
+    pub world_y: f32,
+    pub deadzone_x: f32,
+    pub deadzone_y: f32,
+    /// how quickly the camera catches up to the target.
+    /// 1.0 means snap immediately, lower values smooth the motion.
+    pub lerp: f32,
+}
+
+/// Drives the pan offset and scale of a `WorldScreen`.
+/// The camera handles everything that depends on time: kinetic
+/// panning after a drag is released, animated zooms, flying to a target,
+/// and following a target. Call `update` once per frame with
+/// the elapsed time in seconds.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    /// a rectangle in world coordinates that the camera
+    /// is not allowed to pan outside of.
+    pub bounds: Option<WorldRect>,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// fraction of the pan velocity that remains after one second
+    /// of coasting. 0.0 stops immediately, 1.0 never slows down.
+    pub friction: f32,
+    /// coasting stops once the velocity (in screen pixels per second)
+    /// falls below this value.
+    pub min_velocity: f32,
+    pub zoom_duration: f32,
+    pub zoom_easing: Easing,
+    pub follow: Option<FollowTarget>,
+
+    pub is_dragging: bool,
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    zoom_animation: Option<ZoomAnimation>,
+    fly_animation: Option<FlyAnimation>,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            bounds: None,
+            min_zoom: 0.1,
+            max_zoom: 10.0,
+            friction: 0.05,
+            min_velocity: 5.0,
+            zoom_duration: 0.15,
+            zoom_easing: Easing::EaseOutQuad,
+            follow: None,
+            is_dragging: false,
+            velocity_x: 0.0,
+            velocity_y: 0.0,
+            zoom_animation: None,
+            fly_animation: None,
+        }
+    }
+}
+
+impl Camera {
+    pub fn new() -> Camera {
+        Camera::default()
+    }
+
+    pub fn with_bounds(mut self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Camera {
+        self.bounds = Some(WorldRect::new(min_x, min_y, max_x, max_y));
+        self
+    }
+
+    pub fn with_zoom_limits(mut self, min_zoom: f32, max_zoom: f32) -> Camera {
+        self.min_zoom = min_zoom;
+        self.max_zoom = max_zoom;
+        self
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.zoom_animation.is_some() || self.fly_animation.is_some()
+    }
+
+    pub fn is_coasting(&self) -> bool {
+        !self.is_dragging && (self.velocity_x != 0.0 || self.velocity_y != 0.0)
+    }
+
+    pub fn start_drag(&mut self, screen: &mut WorldScreen, x: f32, y: f32) {
+        self.is_dragging = true;
+        self.velocity_x = 0.0;
+        self.velocity_y = 0.0;
+        self.fly_animation = None;
+        screen.reset_pan(x, y);
+    }
+
+    /// pans the screen so that the world point under the start of the drag
+    /// stays under the mouse. dt is used to track the velocity of the drag
+    /// so that the map keeps moving when the drag is released.
+    pub fn drag_to(&mut self, screen: &mut WorldScreen, x: f32, y: f32, dt: f32) {
+        if !self.is_dragging {
+            return;
+        }
+        let (before_x, before_y) = (screen.pan_offset_x, screen.pan_offset_y);
+        screen.pan_to(x, y);
+        self.clamp(screen);
+        if dt > 0.0 {
+            // velocity is tracked in screen pixels per second, and is
+            // smoothed a bit so a single jittery frame doesnt dominate:
+            let new_vx = (before_x - screen.pan_offset_x) * screen.scale_x / dt;
+            let new_vy = (before_y - screen.pan_offset_y) * screen.scale_y / dt;
+            self.velocity_x = self.velocity_x * 0.2 + new_vx * 0.8;
+            self.velocity_y = self.velocity_y * 0.2 + new_vy * 0.8;
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.is_dragging = false;
+    }
+
+    pub fn stop(&mut self) {
+        self.velocity_x = 0.0;
+        self.velocity_y = 0.0;
+        self.zoom_animation = None;
+        self.fly_animation = None;
+    }
+
+    /// starts an animated zoom that keeps the world point under
+    /// `screen_anchor` fixed on screen while the scale changes.
+    pub fn zoom_to_point(&mut self, screen: &WorldScreen, screen_anchor: (f32, f32), target_scale: f32, duration: f32, easing: Easing) {
+        let end_scale = self.clamp_zoom(target_scale);
+        let world_anchor = screen.screen_to_world(screen_anchor.0, screen_anchor.1);
+        self.fly_animation = None;
+        self.zoom_animation = Some(ZoomAnimation {
+            screen_anchor,
+            world_anchor,
+            start_scale: screen.scale_x,
+            end_scale,
+            elapsed: 0.0,
+            duration,
+            easing,
+        });
+    }
+
+    /// animated equivalent of `WorldScreen::handle_scroll`. scrolling
+    /// while a zoom is in progress continues from the zoom's target scale
+    /// so that quick scrolling still feels responsive.
+    pub fn handle_scroll(&mut self, screen: &WorldScreen, scroll_origin: (f32, f32), scrolling_up: bool, scrolling_down: bool) {
+        if !scrolling_up && !scrolling_down {
+            return;
+        }
+        let mut target = match &self.zoom_animation {
+            Some(anim) => anim.end_scale,
+            None => screen.scale_x,
+        };
+        if scrolling_up {
+            target *= screen.scale_factor_up;
+        }
+        if scrolling_down {
+            target *= screen.scale_factor_down;
+        }
+        self.zoom_to_point(screen, scroll_origin, target, self.zoom_duration, self.zoom_easing);
+    }
+
+    /// smoothly moves the camera so that (world_x, world_y) ends up
+    /// in the center of the screen at the given scale.
+    pub fn fly_to(&mut self, screen: &WorldScreen, world_x: f32, world_y: f32, target_scale: f32, duration: f32, easing: Easing) {
+        self.velocity_x = 0.0;
+        self.velocity_y = 0.0;
+        self.zoom_animation = None;
+        self.fly_animation = Some(FlyAnimation {
+            start_center: Camera::view_center(screen),
+            end_center: (world_x, world_y),
+            start_scale: screen.scale_x,
+            end_scale: self.clamp_zoom(target_scale),
+            elapsed: 0.0,
+            duration,
+            easing,
+        });
+    }
+
+    pub fn set_follow_target(&mut self, world_x: f32, world_y: f32) {
+        if let Some(follow) = &mut self.follow {
+            follow.world_x = world_x;
+            follow.world_y = world_y;
+        }
+    }
+
+    pub fn update(&mut self, screen: &mut WorldScreen, dt: f32) {
+        self.update_inertia(screen, dt);
+        self.update_zoom_animation(screen, dt);
+        self.update_fly_animation(screen, dt);
+        if !self.is_dragging && self.fly_animation.is_none() {
+            self.update_follow(screen);
+        }
+        self.clamp(screen);
+    }
+
+    /// keeps the scale within the zoom limits and keeps the view
+    /// inside of the world bounds. If the world is smaller than the view
+    /// along an axis, the world is centered along that axis instead.
+    pub fn clamp(&mut self, screen: &mut WorldScreen) {
+        let scale = self.clamp_zoom(screen.scale_x);
+        if scale != screen.scale_x {
+            // keep the center of the screen fixed when correcting the scale:
+            let (center_x, center_y) = Camera::view_center(screen);
+            screen.scale_x = scale;
+            screen.scale_y = scale;
+            Camera::center_on(screen, center_x, center_y);
+        }
+
+        let bounds = match &self.bounds {
+            Some(b) => *b,
+            None => return,
+        };
+        let view_width = screen.screen_width as f32 / screen.scale_x;
+        let view_height = screen.screen_height as f32 / screen.scale_y;
+        let (pan_x, hit_x) = Camera::clamp_axis(screen.pan_offset_x, view_width, bounds.min_x, bounds.max_x);
+        let (pan_y, hit_y) = Camera::clamp_axis(screen.pan_offset_y, view_height, bounds.min_y, bounds.max_y);
+        screen.pan_offset_x = pan_x;
+        screen.pan_offset_y = pan_y;
+        if hit_x {
+            self.velocity_x = 0.0;
+        }
+        if hit_y {
+            self.velocity_y = 0.0;
+        }
+    }
+
+    /// returns the world coordinate at the center of the screen
+    pub fn view_center(screen: &WorldScreen) -> (f32, f32) {
+        screen.screen_to_world(screen.screen_width as f32 / 2.0, screen.screen_height as f32 / 2.0)
+    }
+
+    /// pans the screen (without changing scale) so that the world coordinate
+    /// is in the center of the screen.
+    pub fn center_on(screen: &mut WorldScreen, world_x: f32, world_y: f32) {
+        screen.pan_offset_x = world_x - (screen.screen_width as f32 / 2.0) / screen.scale_x;
+        screen.pan_offset_y = world_y - (screen.screen_height as f32 / 2.0) / screen.scale_y;
+    }
+
+    fn clamp_zoom(&self, scale: f32) -> f32 {
+        scale.clamp(self.min_zoom, self.max_zoom)
+    }
+
+    // returns the clamped pan offset, and whether or not it had to be clamped
+    fn clamp_axis(pan: f32, view_size: f32, min: f32, max: f32) -> (f32, bool) {
+        let world_size = max - min;
+        if view_size >= world_size {
+            let centered = min - (view_size - world_size) / 2.0;
+            return (centered, pan != centered);
+        }
+        if pan < min {
+            (min, true)
+        } else if pan + view_size > max {
+            (max - view_size, true)
+        } else {
+            (pan, false)
+        }
+    }
+
+    fn update_inertia(&mut self, screen: &mut WorldScreen, dt: f32) {
+        if self.is_dragging || dt <= 0.0 {
+            return;
+        }
+        if self.velocity_x == 0.0 && self.velocity_y == 0.0 {
+            return;
+        }
+        screen.pan_offset_x -= self.velocity_x * dt / screen.scale_x;
+        screen.pan_offset_y -= self.velocity_y * dt / screen.scale_y;
+
+        let decay = self.friction.powf(dt);
+        self.velocity_x *= decay;
+        self.velocity_y *= decay;
+        let speed = (self.velocity_x * self.velocity_x + self.velocity_y * self.velocity_y).sqrt();
+        if speed < self.min_velocity {
+            self.velocity_x = 0.0;
+            self.velocity_y = 0.0;
+        }
+    }
+
+    fn update_zoom_animation(&mut self, screen: &mut WorldScreen, dt: f32) {
+        let anim = match &mut self.zoom_animation {
+            Some(a) => a,
+            None => return,
+        };
+        anim.elapsed += dt;
+        let t = if anim.duration > 0.0 { anim.elapsed / anim.duration } else { 1.0 };
+        let progress = anim.easing.apply(t);
+        let scale = anim.start_scale + (anim.end_scale - anim.start_scale) * progress;
+        screen.scale_x = scale;
+        screen.scale_y = scale;
+        // move the world anchor back under the screen anchor:
+        let (now_x, now_y) = screen.screen_to_world(anim.screen_anchor.0, anim.screen_anchor.1);
+        screen.pan_offset_x += anim.world_anchor.0 - now_x;
+        screen.pan_offset_y += anim.world_anchor.1 - now_y;
+        if t >= 1.0 {
+            self.zoom_animation = None;
+        }
+    }
+
+    fn update_fly_animation(&mut self, screen: &mut WorldScreen, dt: f32) {
+        let anim = match &mut self.fly_animation {
+            Some(a) => a,
+            None => return,
+        };
+        anim.elapsed += dt;
+        let t = if anim.duration > 0.0 { anim.elapsed / anim.duration } else { 1.0 };
+        let progress = anim.easing.apply(t);
+        let center_x = anim.start_center.0 + (anim.end_center.0 - anim.start_center.0) * progress;
+        let center_y = anim.start_center.1 + (anim.end_center.1 - anim.start_center.1) * progress;
+        // interpolate the scale logarithmically so that zooming in
+        // and zooming out by the same factor take the same amount of time
+        let scale = anim.start_scale * (anim.end_scale / anim.start_scale).powf(progress);
+        screen.scale_x = scale;
+        screen.scale_y = scale;
+        Camera::center_on(screen, center_x, center_y);
+        if t >= 1.0 {
+            self.fly_animation = None;
+        }
+    }
+
+    fn update_follow(&mut self, screen: &mut WorldScreen) {
+        let follow = match &self.follow {
+            Some(f) => *f,
+            None => return,
+        };
+        let (target_x, target_y) = screen.world_to_screen_f32(follow.world_x, follow.world_y);
+        let center_x = screen.screen_width as f32 / 2.0;
+        let center_y = screen.screen_height as f32 / 2.0;
+
+        let mut shift_x = 0.0;
+        if target_x < center_x - follow.deadzone_x {
+            shift_x = target_x - (center_x - follow.deadzone_x);
+        } else if target_x > center_x + follow.deadzone_x {
+            shift_x = target_x - (center_x + follow.deadzone_x);
+        }
+        let mut shift_y = 0.0;
+        if target_y < center_y - follow.deadzone_y {
+            shift_y = target_y - (center_y - follow.deadzone_y);
+        } else if target_y > center_y + follow.deadzone_y {
+            shift_y = target_y - (center_y + follow.deadzone_y);
+        }
+
+        let lerp = follow.lerp.clamp(0.0, 1.0);
+        let (world_shift_x, world_shift_y) = screen.screen_delta_to_world(shift_x * lerp, shift_y * lerp);
+        screen.pan_offset_x += world_shift_x;
+        screen.pan_offset_y += world_shift_y;
+    }
+}
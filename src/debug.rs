@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::time::Instant;
+
+use crate::backend::{Event, KeyCode};
+use crate::draw::Draw;
+use crate::font;
+use crate::world_screen::WorldScreen;
+use crate::Rgb;
+
+/// Keeps the times of the last few frames, for the fps and the frame time graph.
+#[derive(Debug, Clone)]
+pub struct FrameTimer {
+    last: Option<Instant>,
+    /// milliseconds, oldest first
+    times: VecDeque<f32>,
+    pub capacity: usize,
+}
+
+impl Default for FrameTimer {
+    fn default() -> Self {
+        FrameTimer { last: None, times: VecDeque::new(), capacity: 120 }
+    }
+}
+
+impl FrameTimer {
+    pub fn new() -> FrameTimer {
+        FrameTimer::default()
+    }
+
+    /// call once per frame, eg: at the start of `draw`
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last {
+            self.record(now.duration_since(last).as_secs_f32() * 1000.0);
+        }
+        self.last = Some(now);
+    }
+
+    /// adds a frame time in milliseconds, without measuring it
+    pub fn record(&mut self, milliseconds: f32) {
+        self.times.push_back(milliseconds);
+        while self.times.len() > self.capacity {
+            self.times.pop_front();
+        }
+    }
+
+    pub fn times(&self) -> impl Iterator<Item = &f32> {
+        self.times.iter()
+    }
+
+    /// the average over the recorded frames
+    pub fn frame_time(&self) -> f32 {
+        if self.times.is_empty() {
+            return 0.0;
+        }
+        self.times.iter().sum::<f32>() / self.times.len() as f32
+    }
+
+    pub fn fps(&self) -> f32 {
+        let frame_time = self.frame_time();
+        if frame_time > 0.0 { 1000.0 / frame_time } else { 0.0 }
+    }
+}
+
+/// a command gets what it works on, and the arguments typed after its
+/// name. what it returns is printed in the console.
+pub type Command<T> = Box<dyn FnMut(&mut T, &[&str]) -> Result<String, String>>;
+
+/// A console that runs commands registered by the game on `T`, which is
+/// whatever the commands need to change (the game state, the WorldScreen, ..).
+pub struct Console<T> {
+    commands: BTreeMap<String, (String, Command<T>)>,
+    /// what is being typed
+    pub input: String,
+    /// lines printed so far, oldest first
+    pub output: VecDeque<String>,
+    pub max_output: usize,
+    history: Vec<String>,
+    /// where Up/Down is in the history, while going through it
+    history_index: Option<usize>,
+}
+
+impl<T> Default for Console<T> {
+    fn default() -> Self {
+        Console {
+            commands: BTreeMap::new(),
+            input: String::new(),
+            output: VecDeque::new(),
+            max_output: 100,
+            history: vec![],
+            history_index: None,
+        }
+    }
+}
+
+impl<T> Console<T> {
+    pub fn new() -> Console<T> {
+        Console::default()
+    }
+
+    /// `help` and `clear` are built in, and can't be replaced
+    pub fn register<F>(&mut self, name: &str, help: &str, command: F)
+    where
+        F: FnMut(&mut T, &[&str]) -> Result<String, String> + 'static,
+    {
+        self.commands.insert(name.to_owned(), (help.to_owned(), Box::new(command)));
+    }
+
+    pub fn print(&mut self, line: &str) {
+        for line in line.lines() {
+            self.output.push_back(line.to_owned());
+        }
+        while self.output.len() > self.max_output {
+            self.output.pop_front();
+        }
+    }
+
+    /// runs a line like "goto 10 12"
+    pub fn run(&mut self, target: &mut T, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        self.print(&format!("> {}", line));
+        if self.history.last().map(|last| last != line).unwrap_or(true) {
+            self.history.push(line.to_owned());
+        }
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap();
+        let args: Vec<&str> = words.collect();
+        match name {
+            "help" => {
+                let help: Vec<String> = self.commands.iter()
+                    .map(|(name, (help, _))| format!("{} - {}", name, help))
+                    .collect();
+                self.print("help - lists the commands");
+                self.print("clear - clears the console");
+                for line in help {
+                    self.print(&line);
+                }
+            }
+            "clear" => self.output.clear(),
+            _ => {
+                let result = match self.commands.get_mut(name) {
+                    Some((_, command)) => command(target, &args),
+                    None => Err(format!("unknown command '{}', try help", name)),
+                };
+                match result {
+                    Ok(text) => self.print(&text),
+                    Err(error) => self.print(&format!("error: {}", error)),
+                }
+            }
+        }
+    }
+
+    /// edits the input line. Enter runs it, and Up/Down go through
+    /// the commands that were run before.
+    pub fn handle_key(&mut self, target: &mut T, code: KeyCode, shift: bool) {
+        match code {
+            KeyCode::Enter | KeyCode::KpEnter => {
+                let line = std::mem::take(&mut self.input);
+                self.history_index = None;
+                self.run(target, &line);
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Up => {
+                if self.history.is_empty() {
+                    return;
+                }
+                let index = match self.history_index {
+                    Some(i) => i.saturating_sub(1),
+                    None => self.history.len() - 1,
+                };
+                self.history_index = Some(index);
+                self.input = self.history[index].clone();
+            }
+            KeyCode::Down => {
+                match self.history_index {
+                    Some(i) if i + 1 < self.history.len() => {
+                        self.history_index = Some(i + 1);
+                        self.input = self.history[i + 1].clone();
+                    }
+                    _ => {
+                        self.history_index = None;
+                        self.input.clear();
+                    }
+                }
+            }
+            _ => {
+                if let Some(c) = key_to_char(code, shift) {
+                    self.input.push(c);
+                }
+            }
+        }
+    }
+}
+
+/// the character a key types, on a us keyboard
+pub fn key_to_char(code: KeyCode, shift: bool) -> Option<char> {
+    let letter = |c: char| Some(if shift { c.to_ascii_uppercase() } else { c });
+    let pick = |normal: char, shifted: char| Some(if shift { shifted } else { normal });
+    match code {
+        KeyCode::A => letter('a'),
+        KeyCode::B => letter('b'),
+        KeyCode::C => letter('c'),
+        KeyCode::D => letter('d'),
+        KeyCode::E => letter('e'),
+        KeyCode::F => letter('f'),
+        KeyCode::G => letter('g'),
+        KeyCode::H => letter('h'),
+        KeyCode::I => letter('i'),
+        KeyCode::J => letter('j'),
+        KeyCode::K => letter('k'),
+        KeyCode::L => letter('l'),
+        KeyCode::M => letter('m'),
+        KeyCode::N => letter('n'),
+        KeyCode::O => letter('o'),
+        KeyCode::P => letter('p'),
+        KeyCode::Q => letter('q'),
+        KeyCode::R => letter('r'),
+        KeyCode::S => letter('s'),
+        KeyCode::T => letter('t'),
+        KeyCode::U => letter('u'),
+        KeyCode::V => letter('v'),
+        KeyCode::W => letter('w'),
+        KeyCode::X => letter('x'),
+        KeyCode::Y => letter('y'),
+        KeyCode::Z => letter('z'),
+        KeyCode::Key0 => pick('0', ')'),
+        KeyCode::Key1 => pick('1', '!'),
+        KeyCode::Key2 => pick('2', '@'),
+        KeyCode::Key3 => pick('3', '#'),
+        KeyCode::Key4 => pick('4', '$'),
+        KeyCode::Key5 => pick('5', '%'),
+        KeyCode::Key6 => pick('6', '^'),
+        KeyCode::Key7 => pick('7', '&'),
+        KeyCode::Key8 => pick('8', '*'),
+        KeyCode::Key9 => pick('9', '('),
+        KeyCode::Kp0 => Some('0'),
+        KeyCode::Kp1 => Some('1'),
+        KeyCode::Kp2 => Some('2'),
+        KeyCode::Kp3 => Some('3'),
+        KeyCode::Kp4 => Some('4'),
+        KeyCode::Kp5 => Some('5'),
+        KeyCode::Kp6 => Some('6'),
+        KeyCode::Kp7 => Some('7'),
+        KeyCode::Kp8 => Some('8'),
+        KeyCode::Kp9 => Some('9'),
+        KeyCode::Space => Some(' '),
+        KeyCode::Minus => pick('-', '_'),
+        KeyCode::KpSubtract => Some('-'),
+        KeyCode::Equal => pick('=', '+'),
+        KeyCode::KpAdd => Some('+'),
+        KeyCode::Period => pick('.', '>'),
+        KeyCode::KpDecimal => Some('.'),
+        KeyCode::Comma => pick(',', '<'),
+        KeyCode::Slash => pick('/', '?'),
+        KeyCode::KpDivide => Some('/'),
+        KeyCode::KpMultiply => Some('*'),
+        KeyCode::Semicolon => pick(';', ':'),
+        KeyCode::Apostrophe => pick('\'', '"'),
+        KeyCode::LeftBracket => pick('[', '{'),
+        KeyCode::RightBracket => pick(']', '}'),
+        KeyCode::Backslash => pick('\\', '|'),
+        _ => None,
+    }
+}
+
+const BACKGROUND: Rgb = Rgb { red: 20, green: 20, blue: 25 };
+const TEXT: Rgb = Rgb { red: 220, green: 220, blue: 220 };
+const GRAPH: Rgb = Rgb { red: 80, green: 200, blue: 120 };
+const SLOW_FRAME: Rgb = Rgb { red: 230, green: 80, blue: 60 };
+const LINE_HEIGHT: usize = font::GLYPH_HEIGHT + 4;
+/// frames slower than this are drawn red in the graph (60 fps)
+const TARGET_FRAME_TIME: f32 = 1000.0 / 60.0;
+
+/// An overlay with the frame rate, a frame time graph, values the game
+/// wants to watch, and a `Console`. toggled with the backtick key.
+pub struct DebugOverlay<T> {
+    pub visible: bool,
+    pub timer: FrameTimer,
+    pub console: Console<T>,
+    /// lines added with `watch` since the last `draw`
+    watches: Vec<String>,
+    shift: bool,
+}
+
+impl<T> Default for DebugOverlay<T> {
+    fn default() -> Self {
+        DebugOverlay {
+            visible: false,
+            timer: FrameTimer::default(),
+            console: Console::default(),
+            watches: vec![],
+            shift: false,
+        }
+    }
+}
+
+impl<T> DebugOverlay<T> {
+    pub fn new() -> DebugOverlay<T> {
+        DebugOverlay::default()
+    }
+
+    /// returns true if the overlay used the event. while it is open, it
+    /// takes all of the key presses, so typing doesn't also move the game.
+    pub fn handle_event(&mut self, event: &Event, target: &mut T) -> bool {
+        match *event {
+            Event::KeyDown { code: KeyCode::GraveAccent, .. } => {
+                self.visible = !self.visible;
+                true
+            }
+            Event::KeyDown { code, modifier, .. } if self.visible => {
+                match code {
+                    KeyCode::LeftShift | KeyCode::RightShift => self.shift = true,
+                    KeyCode::Escape => self.visible = false,
+                    _ => self.console.handle_key(target, code, modifier.shift || self.shift),
+                }
+                true
+            }
+            Event::KeyUp { code, .. } if self.visible => {
+                if let KeyCode::LeftShift | KeyCode::RightShift = code {
+                    self.shift = false;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// adds a line to the overlay for this frame, eg: `watch("selected", cell)`
+    pub fn watch<V: Display>(&mut self, label: &str, value: V) {
+        self.watches.push(format!("{}: {}", label, value));
+    }
+
+    /// watches the mouse position on screen and in the world, and the pan and zoom
+    pub fn watch_screen(&mut self, screen: &WorldScreen, mouse_x: f32, mouse_y: f32) {
+        let (world_x, world_y) = screen.screen_to_world(mouse_x, mouse_y);
+        self.watch("mouse", format!("{:.0}, {:.0}", mouse_x, mouse_y));
+        self.watch("world", format!("{:.1}, {:.1}", world_x, world_y));
+        self.watch("pan", format!("{:.1}, {:.1}", screen.pan_offset_x, screen.pan_offset_y));
+        self.watch("zoom", format!("{:.3}", screen.zoom()));
+    }
+
+    /// draws the overlay in screen coordinates, if it is visible, and
+    /// clears the watched values for the next frame.
+    pub fn draw<D: Draw>(&mut self, canvas: &mut D, screen_width: usize, screen_height: usize) {
+        let watches = std::mem::take(&mut self.watches);
+        if !self.visible {
+            return;
+        }
+        let fill = |canvas: &mut D, x: usize, y: usize, width: usize, height: usize, color: Rgb| {
+            let (x, y, right, bottom) = (x as f32, y as f32, (x + width) as f32, (y + height) as f32);
+            canvas.fill_convex_polygon_f32(&[(x, y), (right, y), (right, bottom), (x, bottom)], color);
+        };
+
+        // stats in the top right corner
+        let graph_height = 40;
+        let width = 240.min(screen_width);
+        let x = screen_width - width;
+        let stats = 1 + watches.len();
+        let height = 8 + stats * LINE_HEIGHT + graph_height;
+        fill(canvas, x, 0, width, height, BACKGROUND);
+        let fps = format!("fps: {:.0} ({:.1} ms)", self.timer.fps(), self.timer.frame_time());
+        for (i, line) in std::iter::once(&fps).chain(watches.iter()).enumerate() {
+            font::draw_text(canvas, x as i32 + 4, (4 + i * LINE_HEIGHT) as i32, line, TEXT, 1);
+        }
+        // one bar per frame, full height is twice the target frame time
+        let graph_bottom = height - 4;
+        let times: Vec<f32> = self.timer.times().cloned().collect();
+        let bars = times.len().min(width.saturating_sub(8));
+        for (i, time) in times[times.len() - bars..].iter().enumerate() {
+            let bar = ((time / (2.0 * TARGET_FRAME_TIME)) * graph_height as f32).min(graph_height as f32) as usize;
+            let color = if *time > TARGET_FRAME_TIME { SLOW_FRAME } else { GRAPH };
+            fill(canvas, x + 4 + i, graph_bottom - bar.max(1), 1, bar.max(1), color);
+        }
+        let target_y = graph_bottom - graph_height / 2;
+        fill(canvas, x + 4, target_y, width.saturating_sub(8), 1, TEXT);
+
+        // the console along the bottom of the screen
+        let lines = 10.min(screen_height / LINE_HEIGHT).max(1);
+        let height = lines * LINE_HEIGHT + 4;
+        let y = screen_height.saturating_sub(height);
+        fill(canvas, 0, y, screen_width, height, BACKGROUND);
+        let output = self.console.output.len().min(lines - 1);
+        let start = self.console.output.len() - output;
+        for (i, line) in self.console.output.iter().skip(start).enumerate() {
+            font::draw_text(canvas, 4, (y + 2 + i * LINE_HEIGHT) as i32, line, TEXT, 1);
+        }
+        let input = format!("> {}_", self.console.input);
+        font::draw_text(canvas, 4, (y + 2 + (lines - 1) * LINE_HEIGHT) as i32, &input, GRAPH, 1);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::Rgb;
+use crate::{Canvas, Rgb};
+use crate::raster::{self, Interpolate};
+use crate::pixel_format::PixelFormat;
 
 pub trait Draw {
     fn fill(&mut self, color: Rgb);
@@ -23,4 +25,195 @@ pub trait Draw {
     fn draw_vertical_line_f32(&mut self, x: f32, y1: f32, y2: f32, color: Rgb);
 
     fn draw_diagonal_line_f32(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: Rgb);
+
+    /// fills the inside of a convex polygon. a pixel is filled if its
+    /// center is inside of the polygon. this sets every pixel on its own,
+    /// and relies on `set_pixel` to skip the ones outside of the canvas,
+    /// up to `raster::MAX_COORDINATE`.
+    fn fill_convex_polygon_f32(&mut self, points: &[(f32, f32)], color: Rgb) {
+        // the same limit as `fill_triangle_with`, so that a polygon that is
+        // far off of the canvas isn't walked row by row forever
+        let limit = raster::MAX_COORDINATE as usize;
+        convex_polygon_rows(points, limit, limit, |row, start_x, end_x| {
+            for x in start_x..end_x {
+                self.set_pixel(x, row, color);
+            }
+        });
+    }
+
+    /// draws a line that is `width` pixels wide. the ends are extended
+    /// by half of the width so that lines meeting at a corner join up.
+    fn draw_thick_line_f32(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32, color: Rgb) {
+        let dx = x2 - x1;
+        let dy = y2 - y1;
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        let half = width / 2.0;
+        // direction along the line, and the normal perpendicular to it.
+        // both are half of the width long:
+        let (ux, uy) = (dx / length * half, dy / length * half);
+        let (nx, ny) = (-uy, ux);
+        let corners = [
+            (x1 - ux + nx, y1 - uy + ny),
+            (x2 + ux + nx, y2 + uy + ny),
+            (x2 + ux - nx, y2 + uy - ny),
+            (x1 - ux - nx, y1 - uy - ny),
+        ];
+        self.fill_convex_polygon_f32(&corners, color);
+    }
+
+    /// fills a triangle, blending `attributes` between its vertices and
+    /// calling `shade` with the result to get the color of each pixel.
+    /// see `raster::rasterize_triangle` for which pixels are filled. like
+    /// `fill_convex_polygon_f32`, this relies on `set_pixel` to skip the
+    /// pixels outside of the canvas.
+    fn fill_triangle_with<T, F>(&mut self, points: &[(f32, f32); 3], attributes: [T; 3], mut shade: F)
+    where
+        T: Interpolate,
+        F: FnMut(T) -> Rgb,
+    {
+        let [a, b, c] = attributes;
+        // no vertex is further out than this, so it is as good as no limit
+        let limit = raster::MAX_COORDINATE as usize;
+        raster::rasterize_triangle(points, limit, limit, |x, y, weights| {
+            self.set_pixel(x, y, shade(T::interpolate(a, b, c, weights)));
+        });
+    }
+
+    fn fill_triangle_f32(&mut self, points: &[(f32, f32); 3], color: Rgb) {
+        self.fill_triangle_with(points, [(); 3], |_| color)
+    }
+
+    /// a smooth gradient between the colors of the 3 vertices
+    fn fill_triangle_gradient(&mut self, points: &[(f32, f32); 3], colors: [Rgb; 3]) {
+        self.fill_triangle_with(points, colors, |color| color)
+    }
+
+    /// maps a part of `texture` onto the triangle. `uvs` are the texture
+    /// coordinates of each vertex, see `Canvas::sample`. the mapping is
+    /// affine, which is exact for flat tiles that are only transformed
+    /// by a `Transform`.
+    fn draw_textured_triangle<P: PixelFormat>(&mut self, points: &[(f32, f32); 3], uvs: [(f32, f32); 3], texture: &Canvas<P>) {
+        self.fill_triangle_with(points, uvs, |(u, v)| texture.sample(u, v))
+    }
+
+    /// draws all of `texture` onto a quad (eg: a tile) as two triangles.
+    /// the corners go clockwise starting with the top left of the texture.
+    fn draw_textured_quad<P: PixelFormat>(&mut self, corners: &[(f32, f32); 4], texture: &Canvas<P>) {
+        let uvs = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        self.draw_textured_triangle(&[corners[0], corners[1], corners[2]], [uvs[0], uvs[1], uvs[2]], texture);
+        self.draw_textured_triangle(&[corners[0], corners[2], corners[3]], [uvs[0], uvs[2], uvs[3]], texture);
+    }
+}
+
+/// calls `span(row, start_x, end_x)` for every row of pixels whose centers
+/// are inside of a convex polygon, clipped to `width` x `height`
+pub(crate) fn convex_polygon_rows<F: FnMut(usize, usize, usize)>(points: &[(f32, f32)], width: usize, height: usize, mut span: F) {
+    if points.len() < 3 {
+        return;
+    }
+    let mut min_y = f32::MAX;
+    let mut max_y = f32::MIN;
+    for (_, y) in points {
+        min_y = min_y.min(*y);
+        max_y = max_y.max(*y);
+    }
+    let first_row = (min_y - 0.5).ceil().max(0.0) as usize;
+    let last_row = ((max_y - 0.5).ceil().max(0.0) as usize).min(height);
+
+    for row in first_row..last_row {
+        // sample every row at the center of its pixels, and find
+        // where the polygon's edges cross that row:
+        let sample_y = row as f32 + 0.5;
+        let mut left = f32::MAX;
+        let mut right = f32::MIN;
+        for i in 0..points.len() {
+            let (ax, ay) = points[i];
+            let (bx, by) = points[(i + 1) % points.len()];
+            if (ay <= sample_y && by > sample_y) || (by <= sample_y && ay > sample_y) {
+                let t = (sample_y - ay) / (by - ay);
+                let x = ax + t * (bx - ax);
+                left = left.min(x);
+                right = right.max(x);
+            }
+        }
+        if left > right {
+            continue;
+        }
+        let start_x = (left - 0.5).ceil().max(0.0) as usize;
+        let end_x = ((right - 0.5).ceil().max(0.0) as usize).min(width);
+        if start_x < end_x {
+            span(row, start_x, end_x);
+        }
+    }
+}
+
+/// the smallest convex polygon that contains all of the points, in
+/// counter clockwise order (andrew's monotone chain)
+pub fn convex_hull(mut points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+    let mut hull: Vec<(f32, f32)> = Vec::with_capacity(points.len() * 2);
+    for pass in 0..2 {
+        let start = hull.len();
+        let ordered: Box<dyn Iterator<Item = &(f32, f32)>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for p in ordered {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], *p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+        // the last point is the first point of the other half
+        hull.pop();
+    }
+    hull
+}
+
+/// true if (x, y) is inside of (or on the edge of) a convex polygon.
+/// see `polygon_contains_even_odd` for any other polygon.
+pub fn polygon_contains_convex(points: &[(f32, f32)], x: f32, y: f32) -> bool {
+    let n = points.len();
+    // inside if on the same side of every edge
+    let mut sign = 0.0;
+    for i in 0..n {
+        let (ax, ay) = points[i];
+        let (bx, by) = points[(i + 1) % n];
+        let cross = (bx - ax) * (y - ay) - (by - ay) * (x - ax);
+        if cross == 0.0 {
+            continue;
+        }
+        if sign == 0.0 {
+            sign = cross.signum();
+        } else if cross.signum() != sign {
+            return false;
+        }
+    }
+    n > 2
+}
+
+/// true if (x, y) is inside of a polygon by the even-odd rule, so the
+/// polygon doesn't have to be convex (lassos usually aren't). points
+/// exactly on an edge may be on either side.
+pub fn polygon_contains_even_odd(points: &[(f32, f32)], x: f32, y: f32) -> bool {
+    let mut inside = false;
+    let mut j = points.len().wrapping_sub(1);
+    for i in 0..points.len() {
+        let (xi, yi) = points[i];
+        let (xj, yj) = points[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
 }
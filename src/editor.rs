@@ -0,0 +1,578 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use crate::map_format::{self, MapFormatError};
+use crate::tile_map::{Tile, TileLayer, TileMap};
+
+/// what happens when the pointer is pressed/dragged over the map
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Tool {
+    #[default]
+    Brush,
+    Eraser,
+    /// fills every connected cell that has the same tile as the clicked cell
+    Fill,
+    /// fills the rectangle between where the pointer was pressed and released
+    Rectangle,
+    /// copies the tile under the pointer into the brush, then goes back to the brush
+    Eyedropper,
+}
+
+/// a single cell that was changed by an edit
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TileChange {
+    pub layer: usize,
+    pub x: usize,
+    pub y: usize,
+    pub before: Tile,
+    pub after: Tile,
+}
+
+/// The editing part of a map editor, without any drawing or input handling.
+/// Everything is done in cell coordinates, so a frontend only has to
+/// convert the mouse position to a cell (eg: with `TileMap::cell_at`) and
+/// call `press`/`drag`/`release`, or call the individual edit operations.
+///
+/// Every change is recorded so it can be undone. Changes made between
+/// `begin_stroke` and `end_stroke` (eg: one drag of the brush) are
+/// undone together.
+#[derive(Debug)]
+pub struct MapEditor {
+    pub map: TileMap,
+    pub tool: Tool,
+    /// the tile placed by the brush, fill, and rectangle tools
+    pub brush_tile: Tile,
+    /// width of the (square) brush and eraser in cells
+    pub brush_size: usize,
+    pub current_layer: usize,
+    /// the oldest edits are forgotten once there are more than this many
+    pub max_history: usize,
+    undo_stack: VecDeque<Vec<TileChange>>,
+    redo_stack: Vec<Vec<TileChange>>,
+    stroke: Option<Vec<TileChange>>,
+    last_cell: Option<(usize, usize)>,
+    rect_start: Option<(usize, usize)>,
+    /// how many steps were dropped off the front of the history
+    forgotten: usize,
+    /// the history position when last saved or loaded, None once that
+    /// state was undone and then replaced by a new edit
+    saved: Option<usize>,
+}
+
+impl Default for MapEditor {
+    fn default() -> MapEditor {
+        MapEditor {
+            map: TileMap::default(),
+            tool: Tool::default(),
+            brush_tile: Tile::new(1),
+            brush_size: 1,
+            current_layer: 0,
+            max_history: 200,
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+            stroke: None,
+            last_cell: None,
+            rect_start: None,
+            forgotten: 0,
+            saved: Some(0),
+        }
+    }
+}
+
+impl MapEditor {
+    pub fn new(map: TileMap) -> MapEditor {
+        MapEditor { map, ..Default::default() }
+    }
+
+    /// true if the map changed since it was loaded or last saved,
+    /// undoing back to the saved map makes it unmodified again
+    pub fn is_modified(&self) -> bool {
+        self.saved != Some(self.position())
+    }
+
+    /// the number of steps done since loading, including forgotten ones
+    fn position(&self) -> usize {
+        self.forgotten + self.undo_stack.len()
+    }
+
+    pub fn layer(&self) -> Option<&TileLayer> {
+        self.map.layers.get(self.current_layer)
+    }
+
+    pub fn select_layer(&mut self, index: usize) {
+        if index < self.map.layers.len() {
+            self.finish_pointer();
+            self.current_layer = index;
+        }
+    }
+
+    pub fn next_layer(&mut self) {
+        if !self.map.layers.is_empty() {
+            self.select_layer((self.current_layer + 1) % self.map.layers.len());
+        }
+    }
+
+    pub fn previous_layer(&mut self) {
+        if !self.map.layers.is_empty() {
+            let count = self.map.layers.len();
+            self.select_layer((self.current_layer + count - 1) % count);
+        }
+    }
+
+    pub fn toggle_layer_visibility(&mut self) {
+        if let Some(layer) = self.map.layers.get_mut(self.current_layer) {
+            layer.visible = !layer.visible;
+        }
+    }
+
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.finish_pointer();
+        self.tool = tool;
+    }
+
+    /// starts grouping changes into one undo step
+    pub fn begin_stroke(&mut self) {
+        if self.stroke.is_none() {
+            self.stroke = Some(vec![]);
+        }
+    }
+
+    pub fn end_stroke(&mut self) {
+        if let Some(changes) = self.stroke.take() {
+            self.push_history(changes);
+        }
+    }
+
+    /// sets a single cell of the current layer. returns true if it changed.
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: Tile) -> bool {
+        let layer_index = self.current_layer;
+        let layer = match self.map.layers.get_mut(layer_index) {
+            Some(l) => l,
+            None => return false,
+        };
+        let before = match layer.get(x, y) {
+            Some(t) => t,
+            None => return false,
+        };
+        if before == tile {
+            return false;
+        }
+        layer.set(x, y, tile);
+        let change = TileChange { layer: layer_index, x, y, before, after: tile };
+        match self.stroke.as_mut() {
+            Some(stroke) => stroke.push(change),
+            None => self.push_history(vec![change]),
+        }
+        true
+    }
+
+    /// places the brush tile in a square of `brush_size` cells centered on (x, y)
+    pub fn paint(&mut self, x: usize, y: usize) {
+        self.stamp(x, y, self.brush_tile);
+    }
+
+    pub fn erase(&mut self, x: usize, y: usize) {
+        self.stamp(x, y, Tile::EMPTY);
+    }
+
+    /// the cells covered by the brush when it is centered on (x, y)
+    pub fn brush_cells(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let size = self.brush_size.max(1);
+        let half = (size - 1) / 2;
+        let (width, height) = self.map.map_size();
+        let end_x = (x + size - half).min(width);
+        let end_y = (y + size - half).min(height);
+        let mut cells = vec![];
+        for cell_y in y.saturating_sub(half)..end_y {
+            for cell_x in x.saturating_sub(half)..end_x {
+                cells.push((cell_x, cell_y));
+            }
+        }
+        cells
+    }
+
+    fn stamp(&mut self, x: usize, y: usize, tile: Tile) {
+        let started = self.stroke.is_none();
+        self.begin_stroke();
+        for (cell_x, cell_y) in self.brush_cells(x, y) {
+            self.set_tile(cell_x, cell_y, tile);
+        }
+        if started {
+            self.end_stroke();
+        }
+    }
+
+    /// replaces the tile at (x, y), and every cell connected to it (not
+    /// diagonally) that has the same tile. returns how many cells changed.
+    pub fn flood_fill(&mut self, x: usize, y: usize, tile: Tile) -> usize {
+        let (width, height) = self.map.map_size();
+        let target = match self.layer().and_then(|l| l.get(x, y)) {
+            Some(t) => t,
+            None => return 0,
+        };
+        if target == tile {
+            return 0;
+        }
+        let started = self.stroke.is_none();
+        self.begin_stroke();
+        let mut count = 0;
+        // an explicit stack instead of recursion, so large
+        // areas can't overflow the call stack
+        let mut stack = vec![(x, y)];
+        while let Some((cell_x, cell_y)) = stack.pop() {
+            if self.layer().and_then(|l| l.get(cell_x, cell_y)) != Some(target) {
+                continue;
+            }
+            self.set_tile(cell_x, cell_y, tile);
+            count += 1;
+            if cell_x > 0 { stack.push((cell_x - 1, cell_y)); }
+            if cell_y > 0 { stack.push((cell_x, cell_y - 1)); }
+            if cell_x + 1 < width { stack.push((cell_x + 1, cell_y)); }
+            if cell_y + 1 < height { stack.push((cell_x, cell_y + 1)); }
+        }
+        if started {
+            self.end_stroke();
+        }
+        count
+    }
+
+    /// fills every cell between the two corners (inclusive), in any order
+    pub fn fill_rect(&mut self, corner1: (usize, usize), corner2: (usize, usize), tile: Tile) {
+        let (width, height) = self.map.map_size();
+        if width == 0 || height == 0 {
+            return;
+        }
+        let min_x = corner1.0.min(corner2.0);
+        let max_x = corner1.0.max(corner2.0).min(width - 1);
+        let min_y = corner1.1.min(corner2.1);
+        let max_y = corner1.1.max(corner2.1).min(height - 1);
+        let started = self.stroke.is_none();
+        self.begin_stroke();
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                self.set_tile(x, y, tile);
+            }
+        }
+        if started {
+            self.end_stroke();
+        }
+    }
+
+    /// the eyedropper. sets the brush to the tile at (x, y) in the current
+    /// layer. picking an empty cell makes the brush erase.
+    pub fn pick(&mut self, x: usize, y: usize) -> Option<Tile> {
+        let tile = self.layer()?.get(x, y)?;
+        self.brush_tile = tile;
+        Some(tile)
+    }
+
+    /// the pointer was pressed over a cell. applies the current tool.
+    pub fn press(&mut self, x: usize, y: usize) {
+        self.last_cell = Some((x, y));
+        match self.tool {
+            Tool::Brush | Tool::Eraser => {
+                self.begin_stroke();
+                self.apply_brush(x, y);
+            }
+            Tool::Fill => {
+                self.flood_fill(x, y, self.brush_tile);
+            }
+            Tool::Rectangle => self.rect_start = Some((x, y)),
+            Tool::Eyedropper => {
+                self.pick(x, y);
+                self.tool = Tool::Brush;
+            }
+        }
+    }
+
+    /// the pointer moved to a cell while pressed. the brush paints every
+    /// cell along the way, so moving the mouse quickly doesn't leave gaps.
+    pub fn drag(&mut self, x: usize, y: usize) {
+        let last = match self.last_cell {
+            Some(c) => c,
+            None => return,
+        };
+        if last == (x, y) {
+            return;
+        }
+        self.last_cell = Some((x, y));
+        if self.stroke.is_none() || !matches!(self.tool, Tool::Brush | Tool::Eraser) {
+            return;
+        }
+        let dx = x as isize - last.0 as isize;
+        let dy = y as isize - last.1 as isize;
+        let steps = dx.abs().max(dy.abs());
+        for step in 1..=steps {
+            let cell_x = last.0 as isize + dx * step / steps;
+            let cell_y = last.1 as isize + dy * step / steps;
+            self.apply_brush(cell_x as usize, cell_y as usize);
+        }
+    }
+
+    /// the pointer was released. the rectangle tool fills up to the last
+    /// cell the pointer was over, even if it was released outside of the map.
+    pub fn release(&mut self) {
+        if let (Some(start), Some(end)) = (self.rect_start.take(), self.last_cell) {
+            self.fill_rect(start, end, self.brush_tile);
+        }
+        self.finish_pointer();
+    }
+
+    /// the rectangle that the rectangle tool will fill if the pointer
+    /// is released now, as (start corner, current corner)
+    pub fn rect_preview(&self) -> Option<((usize, usize), (usize, usize))> {
+        Some((self.rect_start?, self.last_cell?))
+    }
+
+    fn apply_brush(&mut self, x: usize, y: usize) {
+        if self.tool == Tool::Eraser {
+            self.erase(x, y);
+        } else {
+            self.paint(x, y);
+        }
+    }
+
+    fn finish_pointer(&mut self) {
+        self.end_stroke();
+        self.last_cell = None;
+        self.rect_start = None;
+    }
+
+    fn push_history(&mut self, changes: Vec<TileChange>) {
+        if changes.is_empty() {
+            return;
+        }
+        // the saved map could only be reached by redoing
+        if self.saved > Some(self.position()) {
+            self.saved = None;
+        }
+        self.undo_stack.push_back(changes);
+        if self.undo_stack.len() > self.max_history {
+            self.undo_stack.pop_front();
+            self.forgotten += 1;
+        }
+        self.redo_stack.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// returns false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        self.finish_pointer();
+        let changes = match self.undo_stack.pop_back() {
+            Some(c) => c,
+            None => return false,
+        };
+        // undo in reverse, in case the same cell was changed more than once
+        for change in changes.iter().rev() {
+            if let Some(layer) = self.map.layers.get_mut(change.layer) {
+                layer.set(change.x, change.y, change.before);
+            }
+        }
+        self.redo_stack.push(changes);
+        true
+    }
+
+    /// returns false if there was nothing to redo
+    pub fn redo(&mut self) -> bool {
+        self.finish_pointer();
+        let changes = match self.redo_stack.pop() {
+            Some(c) => c,
+            None => return false,
+        };
+        for change in changes.iter() {
+            if let Some(layer) = self.map.layers.get_mut(change.layer) {
+                layer.set(change.x, change.y, change.after);
+            }
+        }
+        self.undo_stack.push_back(changes);
+        true
+    }
+
+    /// saves with `map_format::save`. the history is kept,
+    /// so edits made before saving can still be undone.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<(), MapFormatError> {
+        self.finish_pointer();
+        map_format::save(&self.map, path)?;
+        self.saved = Some(self.position());
+        Ok(())
+    }
+
+    /// replaces the map with the one in the file, and forgets the history
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), MapFormatError> {
+        let map = map_format::load(path)?;
+        *self = MapEditor {
+            map,
+            tool: self.tool,
+            brush_tile: self.brush_tile,
+            brush_size: self.brush_size,
+            max_history: self.max_history,
+            ..Default::default()
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_map::Orientation;
+
+    fn editor(width: usize, height: usize) -> MapEditor {
+        let mut map = TileMap::new(Orientation::Orthogonal, width, height, 32, 32);
+        map.layers.push(TileLayer::new("ground", width, height));
+        MapEditor::new(map)
+    }
+
+    /// the gids of the current layer, a row per string
+    fn rows(editor: &MapEditor) -> Vec<String> {
+        let layer = editor.layer().unwrap();
+        layer.tiles.chunks(layer.width).map(|row| row.iter().map(|tile| tile.gid.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn default_is_usable() {
+        let editor = MapEditor::default();
+        assert_eq!((editor.brush_size, editor.max_history, editor.brush_tile), (1, 200, Tile::new(1)));
+    }
+
+    #[test]
+    fn brush() {
+        let mut editor = editor(5, 4);
+        editor.paint(0, 0);
+        editor.brush_size = 3;
+        editor.brush_tile = Tile::new(2);
+        editor.paint(3, 2);
+        editor.erase(4, 3);
+        assert_eq!(rows(&editor), ["10000", "00222", "00200", "00200"]);
+        // each of them is one undo step
+        assert!(editor.undo() && editor.undo());
+        assert_eq!(rows(&editor), ["10000", "00000", "00000", "00000"]);
+    }
+
+    #[test]
+    fn flood_fill() {
+        let mut editor = editor(5, 4);
+        editor.fill_rect((2, 0), (2, 3), Tile::new(3));
+        assert_eq!(editor.flood_fill(0, 0, Tile::new(1)), 8);
+        assert_eq!(rows(&editor), ["11300", "11300", "11300", "11300"]);
+        editor.set_tile(2, 2, Tile::EMPTY);
+        assert_eq!(editor.flood_fill(4, 0, Tile::new(4)), 9);
+        assert_eq!(rows(&editor), ["11344", "11344", "11444", "11344"]);
+        // filling with the tile that is already there does nothing
+        let history = editor.undo_stack.len();
+        assert_eq!(editor.flood_fill(0, 0, Tile::new(1)), 0);
+        assert_eq!(editor.undo_stack.len(), history);
+        assert_eq!(editor.flood_fill(9, 9, Tile::new(1)), 0);
+    }
+
+    #[test]
+    fn flood_fill_ignores_diagonals() {
+        let mut editor = editor(3, 3);
+        editor.set_tile(1, 0, Tile::new(1));
+        editor.set_tile(0, 1, Tile::new(1));
+        assert_eq!(editor.flood_fill(0, 0, Tile::new(2)), 1);
+        assert_eq!(rows(&editor), ["210", "100", "000"]);
+    }
+
+    #[test]
+    fn rectangle_and_eyedropper() {
+        let mut editor = editor(5, 4);
+        editor.set_tool(Tool::Rectangle);
+        editor.brush_tile = Tile::new(5);
+        editor.press(3, 2);
+        editor.drag(1, 1);
+        assert_eq!(editor.rect_preview(), Some(((3, 2), (1, 1))));
+        // nothing is filled until it is released
+        assert_eq!(rows(&editor), ["00000"; 4]);
+        editor.release();
+        assert_eq!(rows(&editor), ["00000", "05550", "05550", "00000"]);
+        assert_eq!(editor.rect_preview(), None);
+        // corners past the edge are clipped
+        editor.fill_rect((4, 3), (10, 10), Tile::new(6));
+        assert_eq!(rows(&editor), ["00000", "05550", "05550", "00006"]);
+
+        editor.set_tool(Tool::Eyedropper);
+        editor.press(4, 3);
+        editor.release();
+        assert_eq!((editor.brush_tile, editor.tool), (Tile::new(6), Tool::Brush));
+        assert_eq!(editor.pick(0, 0), Some(Tile::EMPTY));
+        assert_eq!(editor.pick(5, 0), None);
+        assert_eq!(editor.brush_tile, Tile::EMPTY);
+    }
+
+    #[test]
+    fn a_stroke_is_undone_at_once() {
+        let mut editor = editor(5, 4);
+        editor.press(0, 0);
+        // a jump that leaves no gaps
+        editor.drag(4, 2);
+        editor.drag(4, 3);
+        editor.release();
+        let painted = ["11000", "00110", "00001", "00001"];
+        assert_eq!(rows(&editor), painted);
+        assert!(editor.is_modified());
+        assert!(editor.undo());
+        assert_eq!(rows(&editor), ["00000"; 4]);
+        assert!(!editor.can_undo());
+        assert!(editor.redo());
+        assert_eq!(rows(&editor), painted);
+        assert!(!editor.redo());
+
+        // a new edit forgets what could be redone
+        editor.undo();
+        editor.paint(2, 2);
+        assert!(!editor.can_redo());
+        assert_eq!(rows(&editor), ["00000", "00000", "00100", "00000"]);
+    }
+
+    #[test]
+    fn undoing_back_to_the_saved_map() {
+        let path = std::env::temp_dir().join(format!("simple_game_editor_{}", std::process::id()));
+        let mut editor = editor(5, 4);
+        assert!(!editor.is_modified());
+        editor.paint(0, 0);
+        assert!(editor.is_modified());
+        editor.save(&path).unwrap();
+        assert!(!editor.is_modified());
+        editor.undo();
+        assert!(editor.is_modified());
+        editor.redo();
+        assert!(!editor.is_modified());
+        editor.paint(2, 2);
+        assert!(editor.is_modified());
+        editor.undo();
+        assert!(!editor.is_modified());
+
+        // once the saved map can't be redone, it can't be reached
+        editor.undo();
+        editor.paint(4, 0);
+        editor.undo();
+        assert!(editor.is_modified());
+        editor.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!editor.is_modified());
+        assert_eq!(rows(&editor), ["10000", "00000", "00000", "00000"]);
+    }
+
+    #[test]
+    fn history_is_limited() {
+        let mut editor = editor(5, 4);
+        editor.max_history = 3;
+        for x in 0..5 {
+            editor.paint(x, 0);
+        }
+        assert_eq!(editor.undo_stack.len(), 3);
+        while editor.undo() {}
+        // the oldest two are forgotten
+        assert_eq!(rows(&editor), ["11000", "00000", "00000", "00000"]);
+        while editor.redo() {}
+        assert_eq!(rows(&editor), ["11111", "00000", "00000", "00000"]);
+    }
+}
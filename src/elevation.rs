@@ -0,0 +1,193 @@
+use std::ops::Range;
+
+use crate::draw::{self, Draw};
+use crate::tile_map::{Cell, PropertyValue, TileMap};
+use crate::transform::Transform;
+use crate::world_screen::WorldRect;
+use crate::Rgb;
+
+/// How far each cell of a map is raised, in world pixels.
+///
+/// raising a cell moves it straight up on screen, which the map's
+/// projection knows nothing about, so everything here works in world
+/// coordinates with the projection passed in (the same as `EntityLayer`),
+/// instead of pushing the projection onto the WorldScreen.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HeightMap {
+    pub width: usize,
+    pub height: usize,
+    heights: Vec<f32>,
+    max_height: f32,
+}
+
+impl HeightMap {
+    pub fn new(width: usize, height: usize) -> HeightMap {
+        HeightMap {
+            width,
+            height,
+            heights: vec![0.0; width * height],
+            max_height: 0.0,
+        }
+    }
+
+    /// reads the heights from a number property of each cell, eg: "height".
+    /// cells without the property are flat.
+    pub fn from_properties(map: &TileMap, key: &str) -> HeightMap {
+        let mut heights = HeightMap::new(map.width, map.height);
+        for ((x, y), properties) in map.properties.iter() {
+            let height = match properties.get(key) {
+                Some(PropertyValue::Int(i)) => *i as f32,
+                Some(PropertyValue::Float(f)) => *f as f32,
+                _ => continue,
+            };
+            heights.set(*x, *y, height);
+        }
+        heights
+    }
+
+    /// cells outside of the map have a height of 0
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        if x >= self.width || y >= self.height {
+            return 0.0;
+        }
+        self.heights[y * self.width + x]
+    }
+
+    /// heights below 0 are treated as 0
+    pub fn set(&mut self, x: usize, y: usize, height: f32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let height = height.max(0.0);
+        let cell = &mut self.heights[y * self.width + x];
+        let lowered_the_highest = *cell == self.max_height && height < *cell;
+        *cell = height;
+        if lowered_the_highest {
+            // something else might be as high, only a scan can tell
+            self.max_height = self.heights.iter().cloned().fold(0.0, f32::max);
+        } else {
+            self.max_height = self.max_height.max(height);
+        }
+    }
+
+    pub fn max_height(&self) -> f32 {
+        self.max_height
+    }
+
+    /// where a point on the map ends up in the world, `height` pixels up
+    pub fn raise(projection: &Transform, x: f32, y: f32, height: f32) -> (f32, f32) {
+        let (world_x, world_y) = projection.apply(x, y);
+        (world_x, world_y - height)
+    }
+
+    /// the corners of the top of a cell in world coordinates
+    pub fn top_face(&self, projection: &Transform, x: usize, y: usize) -> [(f32, f32); 4] {
+        let h = self.get(x, y);
+        let (x, y) = (x as f32, y as f32);
+        [
+            HeightMap::raise(projection, x, y, h),
+            HeightMap::raise(projection, x + 1.0, y, h),
+            HeightMap::raise(projection, x + 1.0, y + 1.0, h),
+            HeightMap::raise(projection, x, y + 1.0, h),
+        ]
+    }
+
+    /// everything a cell covers on screen: its top and its sides all
+    /// the way down to the ground
+    pub fn silhouette(&self, projection: &Transform, x: usize, y: usize) -> Vec<(f32, f32)> {
+        let mut points = self.top_face(projection, x, y).to_vec();
+        let (x, y) = (x as f32, y as f32);
+        points.extend_from_slice(&[
+            projection.apply(x, y),
+            projection.apply(x + 1.0, y),
+            projection.apply(x + 1.0, y + 1.0),
+            projection.apply(x, y + 1.0),
+        ]);
+        draw::convex_hull(points)
+    }
+
+    /// the cells that can be seen in `visible` (in world coordinates).
+    /// this reaches further down than the flat map would, since cells
+    /// below the bottom of the screen can be raised into view.
+    pub fn visible_cells(&self, projection: &Transform, visible: &WorldRect) -> (Range<usize>, Range<usize>) {
+        let inverse = match projection.inverse() {
+            Some(inverse) => inverse,
+            None => return (0..0, 0..0),
+        };
+        let bottom = visible.max_y + self.max_height;
+        let corners = [
+            inverse.apply(visible.min_x, visible.min_y),
+            inverse.apply(visible.max_x, visible.min_y),
+            inverse.apply(visible.max_x, bottom),
+            inverse.apply(visible.min_x, bottom),
+        ];
+        WorldRect::from_points(&corners).cell_range(1.0, 1.0, (self.width, self.height))
+    }
+
+    /// draws the cells back to front, each with its top in `color(cell)` and
+    /// cliffs down to its lower neighbours in front of it, shaded darker.
+    ///
+    /// with the isometric projection, cells further along x or y are
+    /// closer to the viewer, so going row by row and cell by cell always
+    /// draws a cell after everything that is behind it, whatever the heights.
+    pub fn draw<D, F>(&self, projection: &Transform, cells: (Range<usize>, Range<usize>), canvas: &mut D, color: F)
+    where
+        D: Draw,
+        F: Fn(Cell) -> Rgb,
+    {
+        let (x_range, y_range) = cells;
+        for y in y_range {
+            for x in x_range.clone() {
+                let h = self.get(x, y);
+                let top = color((x, y));
+                let (fx, fy) = (x as f32, y as f32);
+                let right = self.get(x + 1, y);
+                if h > right {
+                    let face = [
+                        HeightMap::raise(projection, fx + 1.0, fy, h),
+                        HeightMap::raise(projection, fx + 1.0, fy + 1.0, h),
+                        HeightMap::raise(projection, fx + 1.0, fy + 1.0, right),
+                        HeightMap::raise(projection, fx + 1.0, fy, right),
+                    ];
+                    canvas.fill_convex_polygon_f32(&face, top.shade(0.6));
+                }
+                let left = self.get(x, y + 1);
+                if h > left {
+                    let face = [
+                        HeightMap::raise(projection, fx, fy + 1.0, h),
+                        HeightMap::raise(projection, fx + 1.0, fy + 1.0, h),
+                        HeightMap::raise(projection, fx + 1.0, fy + 1.0, left),
+                        HeightMap::raise(projection, fx, fy + 1.0, left),
+                    ];
+                    canvas.fill_convex_polygon_f32(&face, top.shade(0.8));
+                }
+                canvas.fill_convex_polygon_f32(&self.top_face(projection, x, y), top);
+            }
+        }
+    }
+
+    /// the cell drawn on top at a world position, so clicking on a raised
+    /// cell (or its cliff) picks it rather than the flat cell behind it.
+    pub fn pick(&self, projection: &Transform, world_x: f32, world_y: f32) -> Option<Cell> {
+        let inverse = projection.inverse()?;
+        // the cell could have been raised from anywhere between the
+        // flat position under the point and max_height below it
+        let top = inverse.apply(world_x, world_y);
+        let bottom = inverse.apply(world_x, world_y + self.max_height);
+        let min_x = (top.0.min(bottom.0).floor() - 1.0).max(0.0) as usize;
+        let min_y = (top.1.min(bottom.1).floor() - 1.0).max(0.0) as usize;
+        let max_x = ((top.0.max(bottom.0).floor() + 2.0).max(0.0) as usize).min(self.width);
+        let max_y = ((top.1.max(bottom.1).floor() + 2.0).max(0.0) as usize).min(self.height);
+        // front to back, the reverse of the drawing order. a silhouette
+        // reaches down to the ground, but the part of it that isn't drawn
+        // is covered by the cells in front, which are checked first.
+        for y in (min_y..max_y).rev() {
+            for x in (min_x..max_x).rev() {
+                if draw::polygon_contains_convex(&self.silhouette(projection, x, y), world_x, world_y) {
+                    return Some((x, y));
+                }
+            }
+        }
+        None
+    }
+}
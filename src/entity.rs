@@ -0,0 +1,254 @@
+use crate::draw::{self, Draw};
+use crate::transform::Transform;
+use crate::world_screen::WorldRect;
+use crate::Rgb;
+
+pub type EntityId = u32;
+
+/// how an entity looks. sizes are in world pixels, so they
+/// don't depend on the map's tile size.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Sprite {
+    /// a flat upright image, like a tree or a unit. `anchor` is the
+    /// point of the sprite (from its top left) that is placed on the
+    /// center of the entity's footprint, usually the bottom middle.
+    Billboard { width: f32, height: f32, anchor: (f32, f32), color: Rgb },
+    /// a box that covers the whole footprint and is `height` tall, like a building
+    Block { height: f32, color: Rgb },
+}
+
+/// An object standing on the map. `position` is the map coordinate of the
+/// corner of its footprint with the lowest x and y, and it covers
+/// `footprint` cells from there. positions don't have to be whole numbers,
+/// so entities can move smoothly between cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub id: EntityId,
+    pub name: String,
+    pub position: (f32, f32),
+    pub footprint: (f32, f32),
+    pub sprite: Sprite,
+    pub visible: bool,
+}
+
+impl Entity {
+    pub fn new(name: &str, position: (f32, f32), footprint: (f32, f32), sprite: Sprite) -> Entity {
+        Entity {
+            id: 0,
+            name: name.to_owned(),
+            position,
+            footprint,
+            sprite,
+            visible: true,
+        }
+    }
+
+    /// the footprint as (min_x, min_y, max_x, max_y) in map coordinates
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        let (x, y) = self.position;
+        (x, y, x + self.footprint.0, y + self.footprint.1)
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.position.0 + self.footprint.0 / 2.0, self.position.1 + self.footprint.1 / 2.0)
+    }
+
+    /// true if this entity has to be drawn before `other`. on an isometric
+    /// map, cells further along x or y are closer to the viewer, so an entity
+    /// is behind another if it ends before the other starts on either axis.
+    /// footprints that overlap are ordered by their centers.
+    pub fn is_behind(&self, other: &Entity) -> bool {
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        let (other_min_x, other_min_y, other_max_x, other_max_y) = other.bounds();
+        if max_x <= other_min_x || max_y <= other_min_y {
+            return true;
+        }
+        if other_max_x <= min_x || other_max_y <= min_y {
+            return false;
+        }
+        let (x, y) = self.center();
+        let (other_x, other_y) = other.center();
+        x + y < other_x + other_y
+    }
+
+    /// the outline of the entity in world coordinates, as a convex polygon
+    pub fn outline(&self, projection: &Transform) -> Vec<(f32, f32)> {
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        match self.sprite {
+            Sprite::Billboard { width, height, anchor, .. } => {
+                let (center_x, center_y) = self.center();
+                let (x, y) = projection.apply(center_x, center_y);
+                let (left, top) = (x - anchor.0, y - anchor.1);
+                vec![(left, top), (left + width, top), (left + width, top + height), (left, top + height)]
+            }
+            Sprite::Block { height, .. } => {
+                let ground = [
+                    projection.apply(min_x, min_y),
+                    projection.apply(max_x, min_y),
+                    projection.apply(max_x, max_y),
+                    projection.apply(min_x, max_y),
+                ];
+                let mut points: Vec<(f32, f32)> = ground.to_vec();
+                points.extend(ground.iter().map(|(x, y)| (*x, *y - height)));
+                draw::convex_hull(points)
+            }
+        }
+    }
+
+    pub fn world_rect(&self, projection: &Transform) -> WorldRect {
+        WorldRect::from_points(&self.outline(projection))
+    }
+
+    pub fn contains(&self, projection: &Transform, world_x: f32, world_y: f32) -> bool {
+        draw::polygon_contains_convex(&self.outline(projection), world_x, world_y)
+    }
+
+    /// draws the entity in world coordinates. don't push the projection
+    /// onto the WorldScreen for this, since heights are in world pixels.
+    pub fn draw<D: Draw>(&self, projection: &Transform, canvas: &mut D) {
+        match self.sprite {
+            Sprite::Billboard { color, .. } => {
+                let outline = self.outline(projection);
+                canvas.fill_convex_polygon_f32(&outline, color);
+                draw_outline(&outline, canvas, color.shade(0.5));
+            }
+            Sprite::Block { height, color } => {
+                let (min_x, min_y, max_x, max_y) = self.bounds();
+                let ground = |x: f32, y: f32| projection.apply(x, y);
+                let raised = |x: f32, y: f32| {
+                    let (x, y) = projection.apply(x, y);
+                    (x, y - height)
+                };
+                // the two sides that face the viewer, and then the top
+                let right = [ground(max_x, min_y), ground(max_x, max_y), raised(max_x, max_y), raised(max_x, min_y)];
+                let left = [ground(min_x, max_y), ground(max_x, max_y), raised(max_x, max_y), raised(min_x, max_y)];
+                let top = [raised(min_x, min_y), raised(max_x, min_y), raised(max_x, max_y), raised(min_x, max_y)];
+                canvas.fill_convex_polygon_f32(&right, color.shade(0.6));
+                canvas.fill_convex_polygon_f32(&left, color.shade(0.8));
+                canvas.fill_convex_polygon_f32(&top, color);
+                draw_outline(&top, canvas, color.shade(0.4));
+            }
+        }
+    }
+}
+
+fn draw_outline<D: Draw>(points: &[(f32, f32)], canvas: &mut D, color: Rgb) {
+    for i in 0..points.len() {
+        let (x1, y1) = points[i];
+        let (x2, y2) = points[(i + 1) % points.len()];
+        canvas.draw_diagonal_line_f32(x1, y1, x2, y2, color);
+    }
+}
+
+/// The objects on a map, drawn in the right order on top of the tiles.
+#[derive(Debug, Clone, Default)]
+pub struct EntityLayer {
+    pub entities: Vec<Entity>,
+    next_id: EntityId,
+}
+
+impl EntityLayer {
+    pub fn new() -> EntityLayer {
+        EntityLayer::default()
+    }
+
+    /// returns the id given to the entity
+    pub fn add(&mut self, mut entity: Entity) -> EntityId {
+        self.next_id += 1;
+        entity.id = self.next_id;
+        self.entities.push(entity);
+        self.next_id
+    }
+
+    pub fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        let index = self.entities.iter().position(|e| e.id == id)?;
+        Some(self.entities.remove(index))
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.iter().find(|e| e.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.iter_mut().find(|e| e.id == id)
+    }
+
+    /// indices of the visible entities that overlap `visible` (in world
+    /// coordinates), sorted back to front.
+    ///
+    /// sorting by a single depth value breaks for entities bigger than
+    /// one cell (a long wall can be both in front of and behind a unit,
+    /// depending on where along the wall it stands), so instead every
+    /// pair of entities that overlap on screen is compared with
+    /// `Entity::is_behind`, and the result is sorted topologically.
+    pub fn draw_order(&self, projection: &Transform, visible: Option<&WorldRect>) -> Vec<usize> {
+        let mut candidates: Vec<(usize, WorldRect)> = self.entities.iter()
+            .enumerate()
+            .filter(|(_, e)| e.visible)
+            .map(|(i, e)| (i, e.world_rect(projection)))
+            .filter(|(_, rect)| visible.map(|v| v.intersects(rect)).unwrap_or(true))
+            .collect();
+        // start from a rough order, so that ties come out consistently
+        candidates.sort_by(|(a, _), (b, _)| {
+            let (ax, ay) = self.entities[*a].center();
+            let (bx, by) = self.entities[*b].center();
+            (ax + ay).partial_cmp(&(bx + by)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let n = candidates.len();
+        let mut in_front_of: Vec<Vec<usize>> = vec![vec![]; n];
+        let mut behind_count = vec![0usize; n];
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let (a, rect_a) = &candidates[i];
+                let (b, rect_b) = &candidates[j];
+                if !rect_a.intersects(rect_b) {
+                    continue;
+                }
+                if self.entities[*a].is_behind(&self.entities[*b]) {
+                    in_front_of[i].push(j);
+                    behind_count[j] += 1;
+                } else {
+                    in_front_of[j].push(i);
+                    behind_count[i] += 1;
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(n);
+        let mut done = vec![false; n];
+        while order.len() < n {
+            // the first entity in the rough order that has nothing left behind it.
+            // if there is a cycle (overlapping footprints can make one), just
+            // take the next entity in the rough order to break it.
+            let next = (0..n).find(|i| !done[*i] && behind_count[*i] == 0)
+                .or_else(|| (0..n).find(|i| !done[*i]))
+                .unwrap();
+            done[next] = true;
+            for after in in_front_of[next].iter() {
+                behind_count[*after] = behind_count[*after].saturating_sub(1);
+            }
+            order.push(candidates[next].0);
+        }
+        order
+    }
+
+    /// draws the visible entities back to front. `visible` skips entities
+    /// that are off screen, see `WorldScreen::visible_world_rect`.
+    pub fn draw<D: Draw>(&self, projection: &Transform, visible: Option<&WorldRect>, canvas: &mut D) {
+        for index in self.draw_order(projection, visible) {
+            self.entities[index].draw(projection, canvas);
+        }
+    }
+
+    /// the entity drawn on top at a world position
+    pub fn pick(&self, projection: &Transform, world_x: f32, world_y: f32) -> Option<EntityId> {
+        let point = WorldRect::new(world_x, world_y, world_x, world_y);
+        self.draw_order(projection, Some(&point))
+            .into_iter()
+            .rev()
+            .map(|i| &self.entities[i])
+            .find(|e| e.contains(projection, world_x, world_y))
+            .map(|e| e.id)
+    }
+}
@@ -0,0 +1,389 @@
+use std::ops::Range;
+
+use crate::draw::Draw;
+use crate::tile_map::Cell;
+use crate::Rgb;
+
+/// every cell on the line between two cells, including both ends.
+/// works with negative coordinates, so lines can start or end off the map.
+pub fn bresenham_line(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let step_x = if x < to.0 { 1 } else { -1 };
+    let step_y = if y < to.1 { 1 } else { -1 };
+    let mut error = dx + dy;
+    let mut out = Vec::with_capacity((dx - dy) as usize + 1);
+    loop {
+        out.push((x, y));
+        if (x, y) == to {
+            return out;
+        }
+        let error2 = 2 * error;
+        if error2 >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if error2 <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// the cells that `line_of_sight` checks, from `from` to `to`. a line
+/// doesn't always go through the same cells in both directions, so this
+/// is always drawn from the end that is highest up (or furthest left),
+/// and reversed if needed.
+pub fn sight_line(from: Cell, to: Cell) -> Vec<(i32, i32)> {
+    let (from, to) = ((from.0 as i32, from.1 as i32), (to.0 as i32, to.1 as i32));
+    if (from.1, from.0) <= (to.1, to.0) {
+        bresenham_line(from, to)
+    } else {
+        let mut line = bresenham_line(to, from);
+        line.reverse();
+        line
+    }
+}
+
+/// true if none of the cells strictly between `from` and `to` block the view.
+/// the end cells themselves may be opaque, eg: you can see a wall. it is
+/// symmetric, and true for every cell that `compute_fov` finds visible.
+pub fn line_of_sight<F: Fn(Cell) -> bool>(from: Cell, to: Cell, is_opaque: F) -> bool {
+    let line = sight_line(from, to);
+    line.iter()
+        .skip(1)
+        .take(line.len().saturating_sub(2))
+        .all(|(x, y)| !is_opaque((*x as usize, *y as usize)))
+}
+
+// how to map the octant being scanned to the map, for each of the 8 octants
+const OCTANTS: [[i32; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [0, -1, 1, 0],
+    [-1, 0, 0, 1],
+    [-1, 0, 0, -1],
+    [0, -1, -1, 0],
+    [0, 1, -1, 0],
+    [1, 0, 0, -1],
+];
+
+/// Recursive shadowcasting. Calls `mark_visible` for every cell within
+/// `radius` of `origin` that can be seen from it (including the origin,
+/// and the opaque cells that block the view). Cells outside of a map of
+/// `map_size` are treated as opaque.
+///
+/// Each of the 8 octants around the origin is scanned row by row, moving
+/// outwards. An opaque cell casts a shadow, narrowing the range of slopes
+/// that the next rows can see through, so every cell is visited at most once.
+/// Opaque cells are visible if any part of them is lit, but other cells
+/// only if their center is, which makes it symmetric: if a can see b,
+/// then b can see a.
+pub fn compute_fov<F, M>(origin: Cell, radius: usize, map_size: (usize, usize), is_opaque: F, mut mark_visible: M)
+where
+    F: Fn(Cell) -> bool,
+    M: FnMut(Cell),
+{
+    if origin.0 >= map_size.0 || origin.1 >= map_size.1 {
+        return;
+    }
+    mark_visible(origin);
+    let scan = Scan { origin, radius: radius as i32, map_size, is_opaque: &is_opaque };
+    for octant in OCTANTS.iter() {
+        scan.cast_light(1, 1.0, 0.0, *octant, &mut mark_visible);
+    }
+}
+
+struct Scan<'a, F> {
+    origin: Cell,
+    radius: i32,
+    map_size: (usize, usize),
+    is_opaque: &'a F,
+}
+
+impl<'a, F: Fn(Cell) -> bool> Scan<'a, F> {
+    fn cell(&self, dx: i32, dy: i32, [xx, xy, yx, yy]: [i32; 4]) -> Option<Cell> {
+        let x = self.origin.0 as i32 + dx * xx + dy * xy;
+        let y = self.origin.1 as i32 + dx * yx + dy * yy;
+        if x < 0 || y < 0 || x as usize >= self.map_size.0 || y as usize >= self.map_size.1 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// scans rows `row..=radius` of one octant, between the start
+    /// and end slopes (1.0 is diagonal, 0.0 is straight out)
+    fn cast_light<M: FnMut(Cell)>(&self, row: i32, mut start: f32, end: f32, octant: [i32; 4], mark_visible: &mut M) {
+        if start < end {
+            return;
+        }
+        // slightly more than the radius, so the edge of the circle isn't jagged
+        let radius_squared = (self.radius as f32 + 0.5) * (self.radius as f32 + 0.5);
+        let mut next_start = start;
+        for distance in row..=self.radius {
+            let dy = -distance;
+            let mut blocked = false;
+            for dx in -distance..=0 {
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start < right_slope {
+                    continue;
+                }
+                if end > left_slope {
+                    break;
+                }
+                let cell = self.cell(dx, dy, octant);
+                let opaque = cell.map(|c| (self.is_opaque)(c)).unwrap_or(true);
+                let center_slope = dx as f32 / dy as f32;
+                let lit = opaque || (center_slope <= start && center_slope >= end);
+                if let Some(cell) = cell {
+                    if lit && ((dx * dx + dy * dy) as f32) < radius_squared {
+                        mark_visible(cell);
+                    }
+                }
+                if blocked {
+                    if opaque {
+                        next_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if opaque && distance < self.radius {
+                    // the start of a shadow. everything past it, up to
+                    // this cell, is scanned separately
+                    blocked = true;
+                    self.cast_light(distance + 1, start, left_slope, octant, mark_visible);
+                    next_start = right_slope;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CellVisibility {
+    /// never seen
+    Unseen,
+    /// seen before, but not visible right now
+    Explored,
+    Visible,
+}
+
+/// Tracks which cells of a map are visible right now, and which have
+/// been seen before.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FogOfWar {
+    pub width: usize,
+    pub height: usize,
+    visible: Vec<bool>,
+    explored: Vec<bool>,
+}
+
+impl FogOfWar {
+    pub fn new(width: usize, height: usize) -> FogOfWar {
+        FogOfWar {
+            width,
+            height,
+            visible: vec![false; width * height],
+            explored: vec![false; width * height],
+        }
+    }
+
+    /// replaces the visible cells with what can be seen from `origin`.
+    /// previously visible cells stay explored.
+    pub fn update<F: Fn(Cell) -> bool>(&mut self, origin: Cell, radius: usize, is_opaque: F) {
+        self.clear_visible();
+        self.reveal(origin, radius, is_opaque);
+    }
+
+    /// adds what can be seen from `origin` to the visible cells, without
+    /// clearing them first. use this for more than one viewer: call
+    /// `clear_visible` once, then `reveal` for each viewer.
+    pub fn reveal<F: Fn(Cell) -> bool>(&mut self, origin: Cell, radius: usize, is_opaque: F) {
+        let width = self.width;
+        let visible = &mut self.visible;
+        let explored = &mut self.explored;
+        compute_fov(origin, radius, (self.width, self.height), is_opaque, |(x, y)| {
+            visible[y * width + x] = true;
+            explored[y * width + x] = true;
+        });
+    }
+
+    pub fn clear_visible(&mut self) {
+        for v in self.visible.iter_mut() {
+            *v = false;
+        }
+    }
+
+    /// forget everything that was explored
+    pub fn reset(&mut self) {
+        self.clear_visible();
+        for e in self.explored.iter_mut() {
+            *e = false;
+        }
+    }
+
+    pub fn get(&self, cell: Cell) -> CellVisibility {
+        if cell.0 >= self.width || cell.1 >= self.height {
+            return CellVisibility::Unseen;
+        }
+        let i = cell.1 * self.width + cell.0;
+        if self.visible[i] {
+            CellVisibility::Visible
+        } else if self.explored[i] {
+            CellVisibility::Explored
+        } else {
+            CellVisibility::Unseen
+        }
+    }
+
+    pub fn is_visible(&self, cell: Cell) -> bool {
+        self.get(cell) == CellVisibility::Visible
+    }
+
+    pub fn is_explored(&self, cell: Cell) -> bool {
+        self.get(cell) != CellVisibility::Unseen
+    }
+
+    /// covers unseen cells with `unseen`, and explored cells with
+    /// `explored` if it is set. coordinates are in cells, so push the map's
+    /// projection onto the WorldScreen first. to darken explored cells
+    /// instead of covering them, draw this into a `Layer` with an opacity.
+    pub fn draw<D: Draw>(&self, cells: (Range<usize>, Range<usize>), canvas: &mut D, unseen: Rgb, explored: Option<Rgb>) {
+        let (x_range, y_range) = cells;
+        for y in y_range {
+            for x in x_range.clone() {
+                let color = match self.get((x, y)) {
+                    CellVisibility::Unseen => unseen,
+                    CellVisibility::Explored => match explored {
+                        Some(c) => c,
+                        None => continue,
+                    },
+                    CellVisibility::Visible => continue,
+                };
+                let (x, y) = (x as f32, y as f32);
+                canvas.fill_convex_polygon_f32(&[(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)], color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn fov<F: Fn(Cell) -> bool>(origin: Cell, radius: usize, map_size: (usize, usize), is_opaque: F) -> HashSet<Cell> {
+        let mut visible = HashSet::new();
+        compute_fov(origin, radius, map_size, is_opaque, |cell| {
+            visible.insert(cell);
+        });
+        visible
+    }
+
+    /// a map with about one cell in 4 opaque, the same every time
+    fn scattered_walls(width: usize, height: usize) -> Vec<bool> {
+        let mut seed = 12345u32;
+        (0..width * height)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) & 3 == 0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn open_room_is_visible() {
+        for origin in [(4, 4), (0, 0), (8, 3)].iter() {
+            let visible = fov(*origin, 20, (9, 9), |_| false);
+            assert_eq!(visible.len(), 81, "from {:?}", origin);
+        }
+        // but only within the radius
+        let visible = fov((4, 4), 2, (9, 9), |_| false);
+        assert!(visible.contains(&(6, 4)) && visible.contains(&(5, 6)));
+        assert!(!visible.contains(&(7, 4)) && !visible.contains(&(6, 6)));
+        // and nothing from off the map
+        assert!(fov((9, 0), 20, (9, 9), |_| false).is_empty());
+    }
+
+    #[test]
+    fn a_wall_blocks_every_octant() {
+        let origin = (10, 10);
+        // a direction inside of each of the 8 octants, and straight and diagonal
+        let directions = [
+            (1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2),
+            (1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, 1), (-1, -1), (1, -1),
+        ];
+        let at = |(dx, dy): (i32, i32), times: i32| ((10 + dx * times) as usize, (10 + dy * times) as usize);
+        for direction in directions.iter().copied() {
+            let wall = at(direction, 2);
+            let visible = fov(origin, 9, (21, 21), |cell| cell == wall);
+            assert!(visible.contains(&at(direction, 1)), "{:?}", direction);
+            assert!(visible.contains(&wall), "{:?}", direction);
+            for times in 3..5 {
+                assert!(!visible.contains(&at(direction, times)), "{:?} x {}", direction, times);
+            }
+            // the other way is open
+            assert!(visible.contains(&at((-direction.0, -direction.1), 4)), "{:?}", direction);
+        }
+    }
+
+    #[test]
+    fn symmetric_with_line_of_sight() {
+        let (width, height) = (21, 17);
+        let walls = scattered_walls(width, height);
+        let is_opaque = |cell: Cell| walls[cell.1 * width + cell.0];
+        let floor: Vec<Cell> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).filter(|c| !is_opaque(*c)).collect();
+        let fields: Vec<HashSet<Cell>> = floor.iter().map(|cell| fov(*cell, 30, (width, height), is_opaque)).collect();
+        for (a, field) in floor.iter().zip(fields.iter()) {
+            for (b, other) in floor.iter().zip(fields.iter()) {
+                assert_eq!(field.contains(b), other.contains(a), "{:?} and {:?}", a, b);
+                assert_eq!(line_of_sight(*a, *b, is_opaque), line_of_sight(*b, *a, is_opaque), "{:?} and {:?}", a, b);
+                if field.contains(b) {
+                    assert!(line_of_sight(*a, *b, is_opaque), "{:?} sees {:?} past a wall", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sight_lines() {
+        let line = sight_line((3, 1), (0, 0));
+        assert_eq!((line[0], line[line.len() - 1]), ((3, 1), (0, 0)));
+        let mut reversed = sight_line((0, 0), (3, 1));
+        reversed.reverse();
+        assert_eq!(line, reversed);
+        assert!(line_of_sight((0, 0), (4, 0), |cell| cell == (0, 0) || cell == (4, 0)));
+        assert!(!line_of_sight((0, 0), (4, 0), |cell| cell == (2, 0)));
+    }
+
+    #[test]
+    fn fog_of_war_remembers() {
+        // a wall down the middle, with the two halves joined at the bottom
+        let is_opaque = |(x, y): Cell| x == 5 && y < 8;
+        let mut fog = FogOfWar::new(11, 10);
+        assert_eq!(fog.get((1, 1)), CellVisibility::Unseen);
+        fog.update((1, 1), 20, is_opaque);
+        assert!(fog.is_visible((4, 4)) && fog.is_visible((5, 4)));
+        assert_eq!(fog.get((8, 1)), CellVisibility::Unseen);
+
+        fog.update((8, 1), 20, is_opaque);
+        assert!(fog.is_visible((8, 5)));
+        assert_eq!(fog.get((4, 4)), CellVisibility::Explored);
+        assert!(fog.is_explored((1, 1)) && !fog.is_visible((1, 1)));
+
+        // two viewers at once
+        fog.clear_visible();
+        fog.reveal((1, 1), 20, is_opaque);
+        fog.reveal((8, 1), 20, is_opaque);
+        assert!(fog.is_visible((1, 1)) && fog.is_visible((8, 1)));
+
+        assert_eq!(fog.get((11, 0)), CellVisibility::Unseen);
+        fog.reset();
+        assert!((0..10).all(|y| (0..11).all(|x| fog.get((x, y)) == CellVisibility::Unseen)));
+    }
+}
@@ -0,0 +1,475 @@
+use std::ops::{Add, Range, Sub};
+
+use crate::draw::Draw;
+use crate::transform::Transform;
+use crate::world_screen::WorldRect;
+use crate::Rgb;
+
+/// Pointy hexes have a corner at the top, and are laid out in rows.
+/// Flat hexes have an edge at the top, and are laid out in columns.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum HexOrientation {
+    #[default]
+    Pointy,
+    Flat,
+}
+
+/// which rows (pointy) or columns (flat) are pushed out by half
+/// a hex in offset coordinates
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OffsetParity {
+    #[default]
+    Odd,
+    Even,
+}
+
+/// A hex in axial coordinates. The third cube coordinate `s` is implied,
+/// since q + r + s is always 0. Axial coordinates make the math simple
+/// (neighbours, distances, lines), while offset coordinates (see `HexLayout`)
+/// are the natural way to store a rectangular map in rows and columns.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+/// the 6 neighbouring directions, counter clockwise starting from the right
+/// (for pointy hexes) or the bottom right (for flat hexes)
+pub const HEX_DIRECTIONS: [Hex; 6] = [
+    Hex { q: 1, r: 0 },
+    Hex { q: 1, r: -1 },
+    Hex { q: 0, r: -1 },
+    Hex { q: -1, r: 0 },
+    Hex { q: -1, r: 1 },
+    Hex { q: 0, r: 1 },
+];
+
+impl Hex {
+    pub fn new(q: i32, r: i32) -> Hex {
+        Hex { q, r }
+    }
+
+    pub fn s(&self) -> i32 {
+        -self.q - self.r
+    }
+
+    /// returns None if the coordinates don't add up to 0
+    pub fn from_cube(q: i32, r: i32, s: i32) -> Option<Hex> {
+        if q + r + s != 0 {
+            return None;
+        }
+        Some(Hex { q, r })
+    }
+
+    pub fn to_cube(&self) -> (i32, i32, i32) {
+        (self.q, self.r, self.s())
+    }
+
+    /// the hex that contains the fractional axial coordinate (q, r).
+    /// rounds all three cube coordinates, and then fixes up the one that
+    /// was rounded the most, so the result always satisfies q + r + s = 0.
+    pub fn round(q: f32, r: f32) -> Hex {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        Hex { q: rq as i32, r: rr as i32 }
+    }
+
+    pub fn scale(&self, factor: i32) -> Hex {
+        Hex { q: self.q * factor, r: self.r * factor }
+    }
+
+    /// `direction` is an index into `HEX_DIRECTIONS`, and wraps around
+    pub fn neighbor(&self, direction: usize) -> Hex {
+        *self + HEX_DIRECTIONS[direction % 6]
+    }
+
+    pub fn neighbors(&self) -> [Hex; 6] {
+        let mut out = [*self; 6];
+        for (i, hex) in out.iter_mut().enumerate() {
+            *hex = self.neighbor(i);
+        }
+        out
+    }
+
+    /// the number of steps it takes to walk from one hex to the other
+    pub fn distance(&self, other: Hex) -> i32 {
+        let diff = *self - other;
+        (diff.q.abs() + diff.r.abs() + diff.s().abs()) / 2
+    }
+
+    /// every hex that is exactly `radius` steps away, in order around the ring
+    pub fn ring(&self, radius: u32) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![*self];
+        }
+        let radius = radius as i32;
+        let mut out = Vec::with_capacity(6 * radius as usize);
+        let mut hex = *self + HEX_DIRECTIONS[4].scale(radius);
+        for direction in 0..6 {
+            for _ in 0..radius {
+                out.push(hex);
+                hex = hex.neighbor(direction);
+            }
+        }
+        out
+    }
+
+    /// every hex within `radius` steps, starting at the center
+    /// and going outwards one ring at a time
+    pub fn spiral(&self, radius: u32) -> Vec<Hex> {
+        let mut out = vec![*self];
+        for ring in 1..=radius {
+            out.extend(self.ring(ring));
+        }
+        out
+    }
+
+    /// the hexes along a straight line between the two hexes, including both ends.
+    /// consecutive hexes in the result are always neighbours.
+    pub fn line_to(&self, other: Hex) -> Vec<Hex> {
+        let steps = self.distance(other);
+        if steps == 0 {
+            return vec![*self];
+        }
+        // nudge the line slightly, so that points that land exactly on
+        // an edge between two hexes always round the same way
+        let (start_q, start_r) = (self.q as f32 + 1e-6, self.r as f32 + 1e-6);
+        let (end_q, end_r) = (other.q as f32 + 1e-6, other.r as f32 + 1e-6);
+        (0..=steps)
+            .map(|i| {
+                let t = i as f32 / steps as f32;
+                Hex::round(start_q + (end_q - start_q) * t, start_r + (end_r - start_r) * t)
+            })
+            .collect()
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex { q: self.q + other.q, r: self.r + other.r }
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex {
+        Hex { q: self.q - other.q, r: self.r - other.r }
+    }
+}
+
+/// How hexes are placed in the world. `size` is the distance from the
+/// center of a hex to a corner. it can be different horizontally and
+/// vertically to squash the hexes, eg: for a pseudo 3d look.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HexLayout {
+    pub orientation: HexOrientation,
+    pub parity: OffsetParity,
+    pub size: (f32, f32),
+    /// world position of the center of hex (0, 0)
+    pub origin: (f32, f32),
+}
+
+impl Default for HexLayout {
+    fn default() -> Self {
+        HexLayout {
+            orientation: HexOrientation::Pointy,
+            parity: OffsetParity::Odd,
+            size: (1.0, 1.0),
+            origin: (0.0, 0.0),
+        }
+    }
+}
+
+const SQRT_3: f32 = 1.732_050_8;
+
+impl HexLayout {
+    pub fn new(orientation: HexOrientation, size: f32, origin: (f32, f32)) -> HexLayout {
+        HexLayout {
+            orientation,
+            size: (size, size),
+            origin,
+            ..Default::default()
+        }
+    }
+
+    /// transform from axial coordinates to world coordinates. since it
+    /// is linear, it can also be pushed onto a `WorldScreen` to draw in
+    /// axial coordinates, just like `TileMap::projection`.
+    pub fn transform(&self) -> Transform {
+        let (size_x, size_y) = self.size;
+        let (origin_x, origin_y) = self.origin;
+        match self.orientation {
+            HexOrientation::Pointy => Transform::new(
+                SQRT_3 * size_x, 0.0,
+                SQRT_3 / 2.0 * size_x, 1.5 * size_y,
+                origin_x, origin_y,
+            ),
+            HexOrientation::Flat => Transform::new(
+                1.5 * size_x, SQRT_3 / 2.0 * size_y,
+                0.0, SQRT_3 * size_y,
+                origin_x, origin_y,
+            ),
+        }
+    }
+
+    /// the center of the hex in world coordinates
+    pub fn hex_to_world(&self, hex: Hex) -> (f32, f32) {
+        self.transform().apply(hex.q as f32, hex.r as f32)
+    }
+
+    /// the hex under a world position. this is exact: the point is
+    /// converted to fractional axial coordinates, which are then rounded
+    /// to the nearest hex center, so it works right up to the edges.
+    pub fn world_to_hex(&self, world_x: f32, world_y: f32) -> Hex {
+        let (q, r) = self.world_to_axial(world_x, world_y);
+        Hex::round(q, r)
+    }
+
+    /// fractional axial coordinates of a world position
+    pub fn world_to_axial(&self, world_x: f32, world_y: f32) -> (f32, f32) {
+        match self.transform().inverse() {
+            Some(inverse) => inverse.apply(world_x, world_y),
+            None => (0.0, 0.0),
+        }
+    }
+
+    /// the 6 corners of the hex in world coordinates, in clockwise order on screen
+    pub fn corners(&self, hex: Hex) -> [(f32, f32); 6] {
+        let (center_x, center_y) = self.hex_to_world(hex);
+        let (size_x, size_y) = self.size;
+        let start_angle: f32 = match self.orientation {
+            HexOrientation::Pointy => -30.0,
+            HexOrientation::Flat => 0.0,
+        };
+        let mut corners = [(0.0, 0.0); 6];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let angle = (start_angle + 60.0 * i as f32).to_radians();
+            *corner = (center_x + size_x * angle.cos(), center_y + size_y * angle.sin());
+        }
+        corners
+    }
+
+    /// converts to (column, row) offset coordinates. pointy layouts push
+    /// every other row to the right, and flat layouts push every other
+    /// column down.
+    pub fn to_offset(&self, hex: Hex) -> (i32, i32) {
+        let shift = |v: i32| match self.parity {
+            OffsetParity::Odd => (v - (v & 1)) / 2,
+            OffsetParity::Even => (v + (v & 1)) / 2,
+        };
+        match self.orientation {
+            HexOrientation::Pointy => (hex.q + shift(hex.r), hex.r),
+            HexOrientation::Flat => (hex.q, hex.r + shift(hex.q)),
+        }
+    }
+
+    pub fn from_offset(&self, col: i32, row: i32) -> Hex {
+        let shift = |v: i32| match self.parity {
+            OffsetParity::Odd => (v - (v & 1)) / 2,
+            OffsetParity::Even => (v + (v & 1)) / 2,
+        };
+        match self.orientation {
+            HexOrientation::Pointy => Hex { q: col - shift(row), r: row },
+            HexOrientation::Flat => Hex { q: col, r: row - shift(col) },
+        }
+    }
+
+    /// the range of columns and rows (in offset coordinates) that can
+    /// overlap a world rectangle, clamped to a map of `map_size` columns
+    /// and rows. use this with `WorldScreen::visible_world_rect` to only
+    /// draw the hexes that are on screen.
+    pub fn offset_range(&self, rect: &WorldRect, map_size: (usize, usize)) -> (Range<usize>, Range<usize>) {
+        let corners = [
+            (rect.min_x, rect.min_y),
+            (rect.max_x, rect.min_y),
+            (rect.max_x, rect.max_y),
+            (rect.min_x, rect.max_y),
+        ];
+        let (mut min_col, mut min_row) = (i32::MAX, i32::MAX);
+        let (mut max_col, mut max_row) = (i32::MIN, i32::MIN);
+        for (x, y) in corners.iter() {
+            let (col, row) = self.to_offset(self.world_to_hex(*x, *y));
+            min_col = min_col.min(col);
+            min_row = min_row.min(row);
+            max_col = max_col.max(col);
+            max_row = max_row.max(row);
+        }
+        // offset rows/columns zig zag, so grow by one hex on every side
+        let clamp = |v: i32, max: usize| v.clamp(0, max as i32) as usize;
+        (
+            clamp(min_col - 1, map_size.0)..clamp(max_col + 2, map_size.0),
+            clamp(min_row - 1, map_size.1)..clamp(max_row + 2, map_size.1),
+        )
+    }
+
+    pub fn draw_hex<D: Draw>(&self, hex: Hex, canvas: &mut D, color: Rgb) {
+        let corners = self.corners(hex);
+        for i in 0..6 {
+            let (x1, y1) = corners[i];
+            let (x2, y2) = corners[(i + 1) % 6];
+            canvas.draw_diagonal_line_f32(x1, y1, x2, y2, color);
+        }
+    }
+
+    pub fn fill_hex<D: Draw>(&self, hex: Hex, canvas: &mut D, color: Rgb) {
+        canvas.fill_convex_polygon_f32(&self.corners(hex), color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUTS: [(HexOrientation, OffsetParity); 4] = [
+        (HexOrientation::Pointy, OffsetParity::Odd),
+        (HexOrientation::Pointy, OffsetParity::Even),
+        (HexOrientation::Flat, OffsetParity::Odd),
+        (HexOrientation::Flat, OffsetParity::Even),
+    ];
+
+    fn layout(orientation: HexOrientation, parity: OffsetParity) -> HexLayout {
+        HexLayout { orientation, parity, size: (10.0, 6.0), origin: (100.0, -40.0) }
+    }
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3
+    }
+
+    #[test]
+    fn cube_coordinates() {
+        assert_eq!(Hex::new(1, -3).to_cube(), (1, -3, 2));
+        assert_eq!(Hex::from_cube(1, -3, 2), Some(Hex::new(1, -3)));
+        assert_eq!(Hex::from_cube(1, 1, 1), None);
+    }
+
+    #[test]
+    fn offset_known_values() {
+        let offset = |orientation, parity, col, row| layout(orientation, parity).from_offset(col, row);
+        use HexOrientation::*;
+        use OffsetParity::*;
+        assert_eq!(offset(Pointy, Odd, 0, 1), Hex::new(0, 1));
+        assert_eq!(offset(Pointy, Odd, 3, 3), Hex::new(2, 3));
+        assert_eq!(offset(Pointy, Odd, 0, -1), Hex::new(1, -1));
+        assert_eq!(offset(Pointy, Even, 0, 1), Hex::new(-1, 1));
+        assert_eq!(offset(Pointy, Even, 3, 3), Hex::new(1, 3));
+        assert_eq!(offset(Flat, Odd, 1, 0), Hex::new(1, 0));
+        assert_eq!(offset(Flat, Odd, 3, 2), Hex::new(3, 1));
+        assert_eq!(offset(Flat, Even, 1, 0), Hex::new(1, -1));
+        assert_eq!(offset(Flat, Even, -1, 0), Hex::new(-1, 0));
+    }
+
+    #[test]
+    fn offset_round_trip() {
+        for (orientation, parity) in LAYOUTS.iter().copied() {
+            let layout = layout(orientation, parity);
+            for row in -5..=5 {
+                for col in -5..=5 {
+                    let hex = layout.from_offset(col, row);
+                    assert_eq!(layout.to_offset(hex), (col, row), "{:?}", (orientation, parity));
+                    let hex = Hex::new(col, row);
+                    let (col, row) = layout.to_offset(hex);
+                    assert_eq!(layout.from_offset(col, row), hex, "{:?}", (orientation, parity));
+                }
+            }
+        }
+    }
+
+    /// the rows (pointy) or columns (flat) of the parity are the ones
+    /// that are pushed out by half a hex, including the negative ones
+    #[test]
+    fn offset_parity_pushes_out() {
+        for (orientation, parity) in LAYOUTS.iter().copied() {
+            let layout = layout(orientation, parity);
+            for line in -4..4 {
+                let pushed = (line & 1 == 1) == (parity == OffsetParity::Odd);
+                let (here, next) = match orientation {
+                    HexOrientation::Pointy => (layout.hex_to_world(layout.from_offset(0, line)).0, layout.hex_to_world(layout.from_offset(0, line + 1)).0),
+                    HexOrientation::Flat => (layout.hex_to_world(layout.from_offset(line, 0)).1, layout.hex_to_world(layout.from_offset(line + 1, 0)).1),
+                };
+                let half = match orientation {
+                    HexOrientation::Pointy => SQRT_3 / 2.0 * layout.size.0,
+                    HexOrientation::Flat => SQRT_3 / 2.0 * layout.size.1,
+                };
+                let expected = if pushed { half } else { -half };
+                assert!((here - next - expected).abs() < 1e-3, "{:?} line {}: {} {}", (orientation, parity), line, here, next);
+            }
+        }
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(Hex::round(2.1, -0.9), Hex::new(2, -1));
+        // q, r and s all round down, so q, which is off the most, is fixed up
+        assert_eq!(Hex::round(0.45, 0.3), Hex::new(1, 0));
+        // the same, for r and for s
+        assert_eq!(Hex::round(0.3, 0.45), Hex::new(0, 1));
+        assert_eq!(Hex::round(-0.3, -0.3), Hex::new(0, 0));
+        assert_eq!(Hex::round(-0.4, -0.45), Hex::new(0, -1));
+    }
+
+    #[test]
+    fn distance_and_neighbors() {
+        assert_eq!(Hex::new(0, 0).distance(Hex::new(3, -1)), 3);
+        assert_eq!(Hex::new(-2, 3).distance(Hex::new(1, -1)), 4);
+        assert_eq!(Hex::new(1, -1).distance(Hex::new(-2, 3)), 4);
+
+        let center = Hex::new(2, -5);
+        let neighbors = center.neighbors();
+        for (i, neighbor) in neighbors.iter().enumerate() {
+            assert_eq!(center.distance(*neighbor), 1);
+            assert_eq!(neighbors.iter().filter(|n| *n == neighbor).count(), 1);
+            // each one is next to the one after it, going around
+            assert_eq!(neighbor.distance(neighbors[(i + 1) % 6]), 1);
+        }
+        assert_eq!(center.neighbor(6), center.neighbor(0));
+        assert_eq!(neighbors[0], Hex::new(3, -5));
+
+        for radius in 1..4 {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring.iter().all(|hex| center.distance(*hex) == radius as i32));
+        }
+        assert_eq!(center.spiral(2).len(), 19);
+
+        let line = Hex::new(-3, 1).line_to(Hex::new(2, 2));
+        assert_eq!((line[0], line[line.len() - 1], line.len()), (Hex::new(-3, 1), Hex::new(2, 2), 7));
+        assert!(line.windows(2).all(|pair| pair[0].distance(pair[1]) == 1));
+    }
+
+    #[test]
+    fn world_round_trip() {
+        let pointy = HexLayout::new(HexOrientation::Pointy, 1.0, (0.0, 0.0));
+        assert!(close(pointy.hex_to_world(Hex::new(1, 0)), (SQRT_3, 0.0)));
+        assert!(close(pointy.hex_to_world(Hex::new(0, 1)), (SQRT_3 / 2.0, 1.5)));
+        let flat = HexLayout::new(HexOrientation::Flat, 1.0, (0.0, 0.0));
+        assert!(close(flat.hex_to_world(Hex::new(1, 0)), (1.5, SQRT_3 / 2.0)));
+        assert!(close(flat.hex_to_world(Hex::new(0, 1)), (0.0, SQRT_3)));
+        assert!(close(pointy.corners(Hex::new(0, 0))[5], (0.0, -1.0)));
+        assert!(close(flat.corners(Hex::new(0, 0))[0], (1.0, 0.0)));
+
+        for (orientation, parity) in LAYOUTS.iter().copied() {
+            let layout = layout(orientation, parity);
+            for hex in Hex::new(1, -2).spiral(4) {
+                let center = layout.hex_to_world(hex);
+                assert_eq!(layout.world_to_hex(center.0, center.1), hex);
+                let axial = layout.world_to_axial(center.0, center.1);
+                assert!(close(axial, (hex.q as f32, hex.r as f32)));
+                // most of the way out to each corner is still inside
+                for corner in layout.corners(hex).iter() {
+                    let x = center.0 + (corner.0 - center.0) * 0.95;
+                    let y = center.1 + (corner.1 - center.1) * 0.95;
+                    assert_eq!(layout.world_to_hex(x, y), hex, "{:?}", (orientation, parity));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::draw::Draw;
+use crate::transform::Transform;
+use crate::world_screen::WorldScreen;
+use crate::{Canvas, Rgb};
+
+/// whether a layer is drawn in world coordinates (and therefore
+/// has to be redrawn whenever the camera moves) or in screen coordinates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LayerSpace {
+    World,
+    Screen,
+}
+
+/// An off-screen canvas that is composited with other layers into
+/// the final output. A layer remembers whether its contents are out of date,
+/// so static layers (like a background map) only have to be drawn when
+/// something actually changed.
+pub struct Layer {
+    pub name: String,
+    pub canvas: Canvas,
+    pub space: LayerSpace,
+    pub visible: bool,
+    /// 0.0 is fully transparent, 1.0 is fully opaque
+    pub opacity: f32,
+    /// layers with a higher z are drawn on top of layers with a lower z.
+    /// layers with the same z are drawn in the order they were added.
+    pub z: i32,
+    /// pixels of this color are treated as transparent. layers are
+    /// cleared to this color before being redrawn.
+    pub color_key: Option<Rgb>,
+    dirty: bool,
+}
+
+impl Layer {
+    pub fn new(name: &str, space: LayerSpace, z: i32) -> Layer {
+        Layer {
+            name: name.to_owned(),
+            canvas: Canvas::default(),
+            space,
+            visible: true,
+            opacity: 1.0,
+            z,
+            color_key: Some(Rgb::TRANSPARENT_KEY),
+            dirty: true,
+        }
+    }
+
+    /// an opaque layer that fully covers everything below it.
+    /// useful for the bottom most layer.
+    pub fn opaque(mut self) -> Layer {
+        self.color_key = None;
+        self
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// fills the layer with its transparent color so it can be
+    /// redrawn from scratch.
+    pub fn clear(&mut self) {
+        let color = self.color_key.unwrap_or(Rgb::BLACK);
+        self.canvas.fill(color);
+    }
+}
+
+/// A stack of named layers that are composited into one output canvas.
+#[derive(Default)]
+pub struct Layers {
+    pub layers: Vec<Layer>,
+    /// the color the output is cleared to before any layers are drawn
+    pub background: Rgb,
+    output: Canvas,
+    output_dirty: bool,
+    last_view: Option<Transform>,
+}
+
+impl Layers {
+    pub fn new(width: usize, height: usize, bpp: usize, background: Rgb) -> Layers {
+        Layers {
+            layers: vec![],
+            background,
+            output: Canvas::new_with_color(width, height, background, bpp),
+            output_dirty: true,
+            last_view: None,
+        }
+    }
+
+    pub fn add(&mut self, mut layer: Layer) {
+        layer.canvas = Canvas::new(self.output.width, self.output.height, self.output.bpp());
+        layer.clear();
+        layer.dirty = true;
+        self.layers.push(layer);
+        self.output_dirty = true;
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+
+    /// any change made through this reference (visibility, opacity, z) is
+    /// assumed to change the output, so the output will be composited again.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        let layer = self.layers.iter_mut().find(|l| l.name == name);
+        if layer.is_some() {
+            self.output_dirty = true;
+        }
+        layer
+    }
+
+    pub fn needs_redraw(&self, name: &str) -> bool {
+        self.get(name).map(|l| l.dirty).unwrap_or(false)
+    }
+
+    pub fn invalidate(&mut self, name: &str) {
+        if let Some(layer) = self.layers.iter_mut().find(|l| l.name == name) {
+            layer.dirty = true;
+        }
+    }
+
+    /// marks every world space layer as needing a redraw if the pan,
+    /// zoom, or rotation of `screen` changed since the last time this was called.
+    pub fn update_view(&mut self, screen: &WorldScreen) {
+        let view = screen.view_transform();
+        if self.last_view == Some(view) {
+            return;
+        }
+        self.last_view = Some(view);
+        for layer in self.layers.iter_mut().filter(|l| l.space == LayerSpace::World) {
+            layer.dirty = true;
+        }
+    }
+
+    /// redraws the layer through `screen`'s camera, if the layer needs it.
+    /// the layer's canvas is temporarily swapped into the world screen,
+    /// so `draw` can use the world screen exactly like it normally would.
+    pub fn draw_world<F: FnOnce(&mut WorldScreen)>(&mut self, name: &str, screen: &mut WorldScreen, draw: F) {
+        let layer = match self.layers.iter_mut().find(|l| l.name == name) {
+            Some(l) => l,
+            None => return,
+        };
+        if !layer.dirty {
+            return;
+        }
+        layer.clear();
+        std::mem::swap(&mut layer.canvas, &mut screen.canvas);
+        draw(screen);
+        std::mem::swap(&mut layer.canvas, &mut screen.canvas);
+        layer.dirty = false;
+        self.output_dirty = true;
+    }
+
+    /// redraws the layer directly in screen space, if the layer needs it.
+    pub fn draw_screen<F: FnOnce(&mut Canvas)>(&mut self, name: &str, draw: F) {
+        let layer = match self.layers.iter_mut().find(|l| l.name == name) {
+            Some(l) => l,
+            None => return,
+        };
+        if !layer.dirty {
+            return;
+        }
+        layer.clear();
+        draw(&mut layer.canvas);
+        layer.dirty = false;
+        self.output_dirty = true;
+    }
+
+    /// blends all visible layers together, from the lowest z to the highest.
+    /// returns true if the output changed since the last call.
+    pub fn composite(&mut self) -> bool {
+        if !self.output_dirty {
+            return false;
+        }
+        self.output.fill(self.background);
+        let mut order: Vec<&Layer> = self.layers.iter().filter(|l| l.visible).collect();
+        order.sort_by_key(|l| l.z);
+        for layer in order {
+            self.output.blend_from(&layer.canvas, layer.opacity, layer.color_key);
+        }
+        self.output_dirty = false;
+        true
+    }
+
+    pub fn output(&self) -> &Canvas {
+        &self.output
+    }
+}
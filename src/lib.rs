@@ -1,18 +1,43 @@
 use draw::Draw;
+use pixel_format::{PixelFormat, Rgbx};
 
 pub mod backend;
+pub mod band;
+pub mod camera;
+pub mod debug;
 pub mod draw;
+pub mod editor;
+pub mod elevation;
+pub mod entity;
+pub mod font;
+pub mod fov;
+pub mod hex;
+pub mod layer;
+pub mod map_format;
+pub mod palette;
+pub mod pathfinding;
+pub mod pixel_format;
+pub mod raster;
+pub mod selection;
+pub mod tile_map;
+pub mod tiled;
+pub mod transform;
+pub mod ui;
+pub mod viewport;
 pub mod world_screen;
 
+/// pixels in rows from the top left, each `format.bytes_per_pixel()` long.
+/// a plain `Canvas` is red, green, blue and then any number of bytes that
+/// are left alone, see `pixel_format` for the others.
 #[derive(Default)]
-pub struct Canvas {
+pub struct Canvas<P: PixelFormat = Rgbx> {
     pub width: usize,
     pub height: usize,
     data: Vec<u8>,
-    pub bpp: usize,
+    pub format: P,
 }
 
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub struct Rgb {
     pub red: u8,
     pub green: u8,
@@ -65,67 +90,160 @@ impl From<(Point, Point)> for LineSegment {
 }
 
 impl Rgb {
+    /// color used by layers to mark pixels that should not be drawn
+    pub const TRANSPARENT_KEY: Rgb = Rgb { red: 255, green: 0, blue: 255 };
     pub const RED: Rgb = Rgb { red: 255, green: 0, blue: 0 };
     pub const BLUE: Rgb = Rgb { red: 0, green: 0, blue: 255 };
     pub const GREEN: Rgb = Rgb { red: 0, green: 255, blue: 0 };
     pub const BLACK: Rgb = Rgb { red: 0, green: 0, blue: 0 };
     pub const WHITE: Rgb = Rgb { red: 255, green: 255, blue: 255 };
+
+    /// multiplies each channel by `factor`, eg: 0.5 is half as bright
+    pub fn shade(self, factor: f32) -> Rgb {
+        Rgb {
+            red: (self.red as f32 * factor).min(255.0) as u8,
+            green: (self.green as f32 * factor).min(255.0) as u8,
+            blue: (self.blue as f32 * factor).min(255.0) as u8,
+        }
+    }
 }
 
 
 macro_rules! get_red_index {
     ($me:tt, $i:tt, $j:tt) => {
-        ($i * $me.bpp) + ($j * $me.span())
+        ($i * $me.bpp()) + ($j * $me.span())
     };
 }
 
 impl Canvas {
     pub fn new(width: usize, height: usize, bpp: usize,) -> Canvas {
-        let data = vec![0; width * height * bpp];
+        Canvas::with_format(width, height, Rgbx { bpp })
+    }
+
+    pub fn new_with_color(width: usize, height: usize, color: Rgb, bpp: usize) -> Canvas {
+        let mut canvas = Canvas::new(width, height, bpp);
+        canvas.fill(color);
+        canvas
+    }
+}
+
+impl<P: PixelFormat> Canvas<P> {
+    /// a canvas with every byte 0, eg: `Canvas::with_format(w, h, Bgra32)`
+    pub fn with_format(width: usize, height: usize, format: P) -> Canvas<P> {
+        let data = vec![0; width * height * format.bytes_per_pixel()];
         Canvas {
             data,
             width,
             height,
-            bpp,
+            format,
         }
     }
 
+    #[inline(always)]
+    pub fn bpp(&self) -> usize {
+        self.format.bytes_per_pixel()
+    }
+
     #[inline(always)]
     pub fn span(&self) -> usize {
-        self.width * self.bpp
+        self.width * self.bpp()
     }
 
-    pub fn new_with_color(width: usize, height: usize, color: Rgb, bpp: usize) -> Canvas {
-        let mut canvas = Canvas::new(width, height, bpp);
-        canvas.fill(color);
-        canvas
+    /// a copy of this canvas in another format
+    pub fn convert<Q: PixelFormat>(&self, format: Q) -> Canvas<Q> {
+        let mut converted = Canvas::with_format(self.width, self.height, format);
+        self.convert_into(&mut converted);
+        converted
+    }
+
+    /// converts every pixel into `dest`, which has to be the same size.
+    /// this doesn't allocate, so it can be done every frame, eg: to draw
+    /// in one format and present in the backend's.
+    pub fn convert_into<Q: PixelFormat>(&self, dest: &mut Canvas<Q>) {
+        assert_eq!((self.width, self.height), (dest.width, dest.height), "cannot convert to a canvas of a different size");
+        let (src_bpp, dest_bpp) = (self.bpp(), dest.bpp());
+        let (src_format, dest_format) = (self.format, dest.format);
+        for (src, dest) in self.data.chunks_exact(src_bpp).zip(dest.data.chunks_exact_mut(dest_bpp)) {
+            dest_format.write(dest, src_format.read(src));
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Rgb {
+        let red_index = get_red_index!(self, x, y);
+        self.format.read(&self.data[red_index..red_index + self.bpp()])
     }
 
     pub fn into_raw(self) -> (usize, usize, Vec<u8>) {
         (self.width, self.height, self.data)
     }
+
+    /// copies all of `src` into this canvas with its top left corner
+    /// at (dest_x, dest_y). parts that land outside of this canvas are clipped.
+    /// both canvases must have the same bytes per pixel.
+    pub fn blit(&mut self, src: &Canvas<P>, dest_x: usize, dest_y: usize) {
+        assert_eq!(self.bpp(), src.bpp(), "cannot blit between canvases with different bpp");
+        if dest_x >= self.width || dest_y >= self.height {
+            return;
+        }
+        let copy_width = src.width.min(self.width - dest_x);
+        let copy_height = src.height.min(self.height - dest_y);
+        let row_bytes = copy_width * self.bpp();
+        let src_span = src.span();
+        for row in 0..copy_height {
+            let src_start = row * src_span;
+            let dest_start = get_red_index!(self, dest_x, (dest_y + row));
+            self.data[dest_start..dest_start + row_bytes]
+                .copy_from_slice(&src.data[src_start..src_start + row_bytes]);
+        }
+    }
+
+    /// draws `src` on top of this canvas. both canvases must be the same size
+    /// and bpp. pixels of `src` that match `color_key` are skipped, and every
+    /// other pixel is mixed with what is already here according to `opacity`.
+    pub fn blend_from(&mut self, src: &Canvas<P>, opacity: f32, color_key: Option<Rgb>) {
+        assert_eq!(self.bpp(), src.bpp(), "cannot blend canvases with different bpp");
+        assert_eq!((self.width, self.height), (src.width, src.height), "cannot blend canvases of different sizes");
+        if opacity <= 0.0 {
+            return;
+        }
+        let alpha = (opacity.min(1.0) * 256.0) as u32;
+        let inverse = 256 - alpha;
+        let (bpp, span, format) = (self.bpp(), self.span(), self.format);
+        let mix = |src: u8, dest: u8| ((src as u32 * alpha + dest as u32 * inverse) >> 8) as u8;
+        self.for_each_band(band::DEFAULT_BAND_HEIGHT, |band| {
+            let start = band.top * span;
+            let src_rows = &src.data[start..start + band.height * span];
+            for (dest, src) in band.data_mut().chunks_exact_mut(bpp).zip(src_rows.chunks_exact(bpp)) {
+                let src_color = format.read(src);
+                if Some(src_color) == color_key {
+                    continue;
+                }
+                if alpha >= 256 {
+                    format.write(dest, src_color);
+                    continue;
+                }
+                let dest_color = format.read(dest);
+                format.write(dest, Rgb {
+                    red: mix(src_color.red, dest_color.red),
+                    green: mix(src_color.green, dest_color.green),
+                    blue: mix(src_color.blue, dest_color.blue),
+                });
+            }
+        });
+    }
 }
 
-impl draw::Draw for Canvas {
+impl<P: PixelFormat> draw::Draw for Canvas<P> {
     fn fill(&mut self, color: Rgb) {
-        let span = self.span();
-        for i in 0..self.width {
-            for j in 0..self.height {
-                let x_offset = i * self.bpp;
-                let y_offset = j * span;
-                let red_index = x_offset + y_offset;
-                self.set_pixel_from_index(red_index, color);
-            }
-        }
+        self.for_each_band(band::DEFAULT_BAND_HEIGHT, |band| band.fill(color));
     }
 
     /// red_index should be the index of the data vector for
     /// a red value of the pixel we wish to set.
     #[inline(always)]
     fn set_pixel_from_index(&mut self, red_index: usize, color: Rgb) {
-        self.data[red_index] = color.red;
-        self.data[red_index + 1] = color.green;
-        self.data[red_index + 2] = color.blue;
+        let bpp = self.bpp();
+        self.format.write(&mut self.data[red_index..red_index + bpp], color);
     }
 
     fn set_pixel(&mut self, x: usize, y: usize, color: Rgb) {
@@ -139,18 +257,17 @@ impl draw::Draw for Canvas {
     }
 
     fn draw_horizontal_line(&mut self, y: usize, x1: usize, x2: usize, color: Rgb) {
-        let span = self.span();
-        let y_offset = y * span;
-        for i in x1..x2 {
-            let x_offset = i * self.bpp;
-            let red_index = x_offset + y_offset;
-            self.set_pixel_from_index(red_index, color);
+        if x1 >= x2 {
+            return;
         }
+        let start = get_red_index!(self, x1, y);
+        let end = get_red_index!(self, x2, y);
+        band::fill_pixels(&mut self.data[start..end], self.format, color);
     }
 
     fn draw_vertical_line(&mut self, x: usize, y1: usize, y2: usize, color: Rgb) {
         let span = self.span();
-        let x_offset = x * self.bpp;
+        let x_offset = x * self.bpp();
         for j in y1..y2 {
             let y_offset = j * span;
             let red_index = x_offset + y_offset;
@@ -194,4 +311,24 @@ impl draw::Draw for Canvas {
     fn draw_diagonal_line_f32(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: Rgb) {
         self.draw_diagonal_line(x1 as usize, y1 as usize, x2 as usize, y2 as usize, color)
     }
+
+    fn fill_convex_polygon_f32(&mut self, points: &[(f32, f32)], color: Rgb) {
+        let (width, height) = (self.width, self.height);
+        draw::convex_polygon_rows(points, width, height, |row, start_x, end_x| {
+            self.draw_horizontal_line(row, start_x, end_x, color);
+        });
+    }
+
+    fn fill_triangle_with<T, F>(&mut self, points: &[(f32, f32); 3], attributes: [T; 3], mut shade: F)
+    where
+        T: raster::Interpolate,
+        F: FnMut(T) -> Rgb,
+    {
+        let [a, b, c] = attributes;
+        let (width, height, bpp, span) = (self.width, self.height, self.bpp(), self.span());
+        raster::rasterize_triangle(points, width, height, |x, y, weights| {
+            let color = shade(T::interpolate(a, b, c, weights));
+            self.set_pixel_from_index(x * bpp + y * span, color);
+        });
+    }
 }
@@ -0,0 +1,617 @@
+//! Saving and loading a `TileMap` in this project's own formats:
+//!
+//! - json: human readable, good for diffs and hand editing.
+//! - binary: compact, made of tagged chunks.
+//!
+//! Both formats carry a `major.minor` version. A reader can load any file
+//! with the same major version, even one written by a newer minor version:
+//! unknown json fields and unknown binary chunks are skipped. A new major
+//! version means old readers can't understand the file, and they refuse it.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::tile_map::{MapObject, ObjectLayer, Orientation, Properties, PropertyValue, Tile, TileLayer, TileMap, Tileset};
+
+pub const FORMAT_NAME: &str = "simple-game-map";
+pub const BINARY_MAGIC: &[u8; 4] = b"SGMB";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+pub const FORMAT_VERSION: FormatVersion = FormatVersion { major: 1, minor: 0 };
+
+impl FormatVersion {
+    pub fn can_read(&self, file_version: FormatVersion) -> bool {
+        self.major == file_version.major
+    }
+}
+
+impl fmt::Display for FormatVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[derive(Debug)]
+pub enum MapFormatError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// the file is not a map saved by this project
+    NotAMap,
+    UnsupportedVersion(FormatVersion),
+    /// the binary data ended early or contains a bad value
+    Corrupt(String),
+    /// a size or length is too big to be saved in the binary format
+    TooBig(usize),
+}
+
+impl fmt::Display for MapFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapFormatError::Io(e) => write!(f, "failed to read map: {}", e),
+            MapFormatError::Json(e) => write!(f, "failed to parse map json: {}", e),
+            MapFormatError::NotAMap => write!(f, "file is not a {} file", FORMAT_NAME),
+            MapFormatError::UnsupportedVersion(v) => write!(f, "map format version {} is not supported (expected {}.x)", v, FORMAT_VERSION.major),
+            MapFormatError::Corrupt(msg) => write!(f, "corrupt map file: {}", msg),
+            MapFormatError::TooBig(v) => write!(f, "{} is too big for the binary map format", v),
+        }
+    }
+}
+
+impl std::error::Error for MapFormatError {}
+
+impl From<std::io::Error> for MapFormatError {
+    fn from(e: std::io::Error) -> Self {
+        MapFormatError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for MapFormatError {
+    fn from(e: serde_json::Error) -> Self {
+        MapFormatError::Json(e)
+    }
+}
+
+#[derive(Serialize)]
+struct MapFileRef<'a> {
+    format: &'a str,
+    version: FormatVersion,
+    map: &'a TileMap,
+}
+
+/// saves as json if the path ends in .json, otherwise as binary
+pub fn save<P: AsRef<Path>>(map: &TileMap, path: P) -> Result<(), MapFormatError> {
+    let path = path.as_ref();
+    if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        fs::write(path, to_json(map)?)?;
+    } else {
+        fs::write(path, to_binary(map)?)?;
+    }
+    Ok(())
+}
+
+/// loads either format. the format is detected from the contents,
+/// not the file extension.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<TileMap, MapFormatError> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(BINARY_MAGIC) {
+        return from_binary(&bytes);
+    }
+    let text = String::from_utf8(bytes).map_err(|_| MapFormatError::NotAMap)?;
+    from_json(&text)
+}
+
+pub fn to_json(map: &TileMap) -> Result<String, MapFormatError> {
+    let file = MapFileRef { format: FORMAT_NAME, version: FORMAT_VERSION, map };
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+pub fn from_json(text: &str) -> Result<TileMap, MapFormatError> {
+    let mut value: Value = serde_json::from_str(text)?;
+    // check the header before trying to understand the map itself,
+    // so that a newer file gives a version error instead of a parse error
+    if value.get("format").and_then(Value::as_str) != Some(FORMAT_NAME) {
+        return Err(MapFormatError::NotAMap);
+    }
+    let version: FormatVersion = match value.get("version") {
+        Some(v) => serde_json::from_value(v.clone())?,
+        None => return Err(MapFormatError::NotAMap),
+    };
+    if !FORMAT_VERSION.can_read(version) {
+        return Err(MapFormatError::UnsupportedVersion(version));
+    }
+    let map = match value.get_mut("map") {
+        Some(m) => m.take(),
+        None => return Err(MapFormatError::NotAMap),
+    };
+    Ok(serde_json::from_value(map)?)
+}
+
+const CHUNK_HEADER: &[u8; 4] = b"HEAD";
+const CHUNK_TILESET: &[u8; 4] = b"TSET";
+const CHUNK_LAYER: &[u8; 4] = b"LAYR";
+const CHUNK_OBJECTS: &[u8; 4] = b"OBJL";
+const CHUNK_PROPERTIES: &[u8; 4] = b"PROP";
+
+/// the binary format is the magic bytes, the version, and then a list of
+/// chunks. every chunk is a 4 byte tag, a u32 length, and then its data.
+/// all numbers are little endian.
+pub fn to_binary(map: &TileMap) -> Result<Vec<u8>, MapFormatError> {
+    let mut out = vec![];
+    out.extend_from_slice(BINARY_MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.major.to_le_bytes());
+    out.extend_from_slice(&FORMAT_VERSION.minor.to_le_bytes());
+
+    let mut chunk = Writer::default();
+    chunk.u8(match map.orientation {
+        Orientation::Orthogonal => 0,
+        Orientation::Isometric => 1,
+    });
+    chunk.usize(map.width)?;
+    chunk.usize(map.height)?;
+    chunk.usize(map.tile_width)?;
+    chunk.usize(map.tile_height)?;
+    chunk.f32(map.origin.0);
+    chunk.f32(map.origin.1);
+    chunk.finish(CHUNK_HEADER, &mut out)?;
+
+    for tileset in map.tilesets.iter() {
+        let mut chunk = Writer::default();
+        chunk.u32(tileset.first_gid);
+        chunk.string(&tileset.name)?;
+        chunk.optional_string(&tileset.source)?;
+        chunk.usize(tileset.tile_width)?;
+        chunk.usize(tileset.tile_height)?;
+        chunk.u32(tileset.tile_count);
+        chunk.u32(tileset.columns);
+        chunk.optional_string(&tileset.image)?;
+        chunk.finish(CHUNK_TILESET, &mut out)?;
+    }
+
+    for layer in map.layers.iter() {
+        let mut chunk = Writer::default();
+        chunk.string(&layer.name)?;
+        chunk.bool(layer.visible);
+        chunk.f32(layer.opacity);
+        chunk.usize(layer.width)?;
+        chunk.usize(layer.height)?;
+        for tile in layer.tiles.iter() {
+            chunk.u32(tile.to_raw());
+        }
+        chunk.finish(CHUNK_LAYER, &mut out)?;
+    }
+
+    for layer in map.object_layers.iter() {
+        let mut chunk = Writer::default();
+        chunk.string(&layer.name)?;
+        chunk.bool(layer.visible);
+        chunk.f32(layer.opacity);
+        chunk.usize(layer.objects.len())?;
+        for object in layer.objects.iter() {
+            chunk.u32(object.id);
+            chunk.string(&object.name)?;
+            chunk.string(&object.kind)?;
+            chunk.f32(object.x);
+            chunk.f32(object.y);
+            chunk.f32(object.width);
+            chunk.f32(object.height);
+            chunk.f32(object.rotation);
+            chunk.bool(object.visible);
+            match object.tile {
+                Some(tile) => {
+                    chunk.bool(true);
+                    chunk.u32(tile.to_raw());
+                }
+                None => chunk.bool(false),
+            }
+        }
+        chunk.finish(CHUNK_OBJECTS, &mut out)?;
+    }
+
+    if !map.properties.is_empty() {
+        let mut chunk = Writer::default();
+        chunk.usize(map.properties.len())?;
+        for ((x, y), properties) in map.properties.iter() {
+            chunk.usize(*x)?;
+            chunk.usize(*y)?;
+            chunk.usize(properties.len())?;
+            for (key, value) in properties.iter() {
+                chunk.string(key)?;
+                match value {
+                    PropertyValue::Bool(v) => {
+                        chunk.u8(0);
+                        chunk.bool(*v);
+                    }
+                    PropertyValue::Int(v) => {
+                        chunk.u8(1);
+                        chunk.bytes(&v.to_le_bytes());
+                    }
+                    PropertyValue::Float(v) => {
+                        chunk.u8(2);
+                        chunk.bytes(&v.to_le_bytes());
+                    }
+                    PropertyValue::String(v) => {
+                        chunk.u8(3);
+                        chunk.string(v)?;
+                    }
+                }
+            }
+        }
+        chunk.finish(CHUNK_PROPERTIES, &mut out)?;
+    }
+    Ok(out)
+}
+
+pub fn from_binary(bytes: &[u8]) -> Result<TileMap, MapFormatError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(4)? != BINARY_MAGIC {
+        return Err(MapFormatError::NotAMap);
+    }
+    let version = FormatVersion {
+        major: reader.u16()?,
+        minor: reader.u16()?,
+    };
+    if !FORMAT_VERSION.can_read(version) {
+        return Err(MapFormatError::UnsupportedVersion(version));
+    }
+
+    let mut map = TileMap::default();
+    let mut saw_header = false;
+    while !reader.is_empty() {
+        let tag = reader.take(4)?;
+        let len = reader.u32()? as usize;
+        let mut chunk = Reader { bytes: reader.take(len)?, pos: 0 };
+        match tag {
+            t if t == CHUNK_HEADER => {
+                map.orientation = match chunk.u8()? {
+                    0 => Orientation::Orthogonal,
+                    1 => Orientation::Isometric,
+                    other => return Err(MapFormatError::Corrupt(format!("unknown orientation {}", other))),
+                };
+                map.width = chunk.usize()?;
+                map.height = chunk.usize()?;
+                map.tile_width = chunk.usize()?;
+                map.tile_height = chunk.usize()?;
+                map.origin = (chunk.f32()?, chunk.f32()?);
+                saw_header = true;
+            }
+            t if t == CHUNK_TILESET => {
+                map.tilesets.push(Tileset {
+                    first_gid: chunk.u32()?,
+                    name: chunk.string()?,
+                    source: chunk.optional_string()?,
+                    tile_width: chunk.usize()?,
+                    tile_height: chunk.usize()?,
+                    tile_count: chunk.u32()?,
+                    columns: chunk.u32()?,
+                    image: chunk.optional_string()?,
+                });
+            }
+            t if t == CHUNK_LAYER => {
+                let name = chunk.string()?;
+                let visible = chunk.bool()?;
+                let opacity = chunk.f32()?;
+                let width = chunk.usize()?;
+                let height = chunk.usize()?;
+                // every tile is 4 bytes, so a size that doesn't fit in the
+                // rest of the chunk is corrupt. check before allocating.
+                let count = match width.checked_mul(height) {
+                    Some(count) if count <= chunk.remaining() / 4 => count,
+                    _ => return Err(MapFormatError::Corrupt(format!("layer of {}x{} tiles doesn't fit in its chunk", width, height))),
+                };
+                let mut tiles = Vec::with_capacity(count);
+                for _ in 0..count {
+                    tiles.push(Tile::from_raw(chunk.u32()?));
+                }
+                map.layers.push(TileLayer { name, visible, opacity, width, height, tiles });
+            }
+            t if t == CHUNK_OBJECTS => {
+                let name = chunk.string()?;
+                let visible = chunk.bool()?;
+                let opacity = chunk.f32()?;
+                let count = chunk.usize()?;
+                let mut objects = vec![];
+                for _ in 0..count {
+                    objects.push(MapObject {
+                        id: chunk.u32()?,
+                        name: chunk.string()?,
+                        kind: chunk.string()?,
+                        x: chunk.f32()?,
+                        y: chunk.f32()?,
+                        width: chunk.f32()?,
+                        height: chunk.f32()?,
+                        rotation: chunk.f32()?,
+                        visible: chunk.bool()?,
+                        tile: if chunk.bool()? { Some(Tile::from_raw(chunk.u32()?)) } else { None },
+                    });
+                }
+                map.object_layers.push(ObjectLayer { name, visible, opacity, objects });
+            }
+            t if t == CHUNK_PROPERTIES => {
+                let count = chunk.usize()?;
+                for _ in 0..count {
+                    let cell = (chunk.usize()?, chunk.usize()?);
+                    let num_properties = chunk.usize()?;
+                    let mut properties: Properties = BTreeMap::new();
+                    for _ in 0..num_properties {
+                        let key = chunk.string()?;
+                        let value = match chunk.u8()? {
+                            0 => PropertyValue::Bool(chunk.bool()?),
+                            1 => PropertyValue::Int(i64::from_le_bytes(chunk.array()?)),
+                            2 => PropertyValue::Float(f64::from_le_bytes(chunk.array()?)),
+                            3 => PropertyValue::String(chunk.string()?),
+                            other => return Err(MapFormatError::Corrupt(format!("unknown property type {}", other))),
+                        };
+                        properties.insert(key, value);
+                    }
+                    map.properties.insert(cell, properties);
+                }
+            }
+            // a chunk added by a newer minor version. skip it.
+            _ => {}
+        }
+    }
+    if !saw_header {
+        return Err(MapFormatError::Corrupt("missing header chunk".into()));
+    }
+    Ok(map)
+}
+
+#[derive(Default)]
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.data.push(v);
+    }
+
+    fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    /// sizes are saved as u32, so a bigger one can't be saved
+    fn usize(&mut self, v: usize) -> Result<(), MapFormatError> {
+        let v = u32::try_from(v).map_err(|_| MapFormatError::TooBig(v))?;
+        self.u32(v);
+        Ok(())
+    }
+
+    fn f32(&mut self, v: f32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn string(&mut self, v: &str) -> Result<(), MapFormatError> {
+        self.usize(v.len())?;
+        self.bytes(v.as_bytes());
+        Ok(())
+    }
+
+    fn optional_string(&mut self, v: &Option<String>) -> Result<(), MapFormatError> {
+        match v {
+            Some(s) => {
+                self.bool(true);
+                self.string(s)
+            }
+            None => {
+                self.bool(false);
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self, tag: &[u8; 4], out: &mut Vec<u8>) -> Result<(), MapFormatError> {
+        let len = u32::try_from(self.data.len()).map_err(|_| MapFormatError::TooBig(self.data.len()))?;
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&self.data);
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MapFormatError> {
+        if self.remaining() < len {
+            return Err(MapFormatError::Corrupt("unexpected end of data".into()));
+        }
+        let out = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(out)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], MapFormatError> {
+        let mut out = [0; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, MapFormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, MapFormatError> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u16(&mut self) -> Result<u16, MapFormatError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, MapFormatError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn usize(&mut self) -> Result<usize, MapFormatError> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn f32(&mut self) -> Result<f32, MapFormatError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String, MapFormatError> {
+        let len = self.usize()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| MapFormatError::Corrupt("string is not utf8".into()))
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>, MapFormatError> {
+        if self.bool()? {
+            Ok(Some(self.string()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn test_map() -> TileMap {
+        let mut map = TileMap::new(Orientation::Isometric, 4, 3, 64, 32);
+        map.origin = (16.0, 32.0);
+        map.tilesets.push(Tileset {
+            first_gid: 1,
+            name: "ground".into(),
+            source: None,
+            tile_width: 64,
+            tile_height: 32,
+            tile_count: 8,
+            columns: 4,
+            image: Some("ground.png".into()),
+        });
+        let mut layer = TileLayer::new("ground", 4, 3);
+        layer.set(1, 2, Tile::new(3));
+        layer.set(3, 0, Tile { flip_horizontal: true, ..Tile::new(5) });
+        map.layers.push(layer);
+        map.object_layers.push(ObjectLayer {
+            name: "things".into(),
+            visible: true,
+            opacity: 0.5,
+            objects: vec![MapObject { id: 7, name: "door".into(), tile: Some(Tile::new(2)), ..Default::default() }],
+        });
+        map.set_cell_property(0, 0, "spawn", PropertyValue::Bool(true));
+        map.set_cell_property(3, 2, "move_cost", PropertyValue::Int(3));
+        map.set_cell_property(3, 2, "height", PropertyValue::Float(1.5));
+        map.set_cell_property(1, 1, "trigger", PropertyValue::String("open_door".into()));
+        map
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let map = test_map();
+        assert_eq!(from_json(&to_json(&map).unwrap()).unwrap(), map);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let map = test_map();
+        assert_eq!(from_binary(&to_binary(&map).unwrap()).unwrap(), map);
+    }
+
+    #[test]
+    fn newer_minor_version_loads() {
+        let map = test_map();
+        let mut value: Value = serde_json::from_str(&to_json(&map).unwrap()).unwrap();
+        value["version"]["minor"] = 7.into();
+        value["map"]["weather"] = "rain".into();
+        assert_eq!(from_json(&value.to_string()).unwrap(), map);
+
+        let mut binary = to_binary(&map).unwrap();
+        binary[6..8].copy_from_slice(&7u16.to_le_bytes());
+        binary.extend_from_slice(b"WTHR");
+        binary.extend_from_slice(&4u32.to_le_bytes());
+        binary.extend_from_slice(b"rain");
+        assert_eq!(from_binary(&binary).unwrap(), map);
+    }
+
+    #[test]
+    fn newer_major_version_is_refused() {
+        let map = test_map();
+        let mut value: Value = serde_json::from_str(&to_json(&map).unwrap()).unwrap();
+        value["version"]["major"] = 2.into();
+        assert!(matches!(from_json(&value.to_string()), Err(MapFormatError::UnsupportedVersion(_))));
+
+        let mut binary = to_binary(&map).unwrap();
+        binary[4..6].copy_from_slice(&2u16.to_le_bytes());
+        assert!(matches!(from_binary(&binary), Err(MapFormatError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn truncated_binary_is_an_error() {
+        let binary = to_binary(&test_map()).unwrap();
+        // cutting the file between two chunks leaves a valid file with
+        // fewer chunks, anywhere else has to fail
+        let mut chunk_ends = vec![];
+        let mut at = 8;
+        while at < binary.len() {
+            at += 8 + u32::from_le_bytes(binary[at + 4..at + 8].try_into().unwrap()) as usize;
+            chunk_ends.push(at);
+        }
+        for len in (0..binary.len()).filter(|len| !chunk_ends.contains(len)) {
+            assert!(from_binary(&binary[..len]).is_err(), "loaded the first {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn corrupt_layer_size_is_an_error() {
+        let mut map = test_map();
+        map.tilesets.clear();
+        map.object_layers.clear();
+        map.properties.clear();
+        let binary = to_binary(&map).unwrap();
+        // the layer's width and height follow its name, visible flag and opacity
+        let layer = binary.windows(4).position(|w| w == CHUNK_LAYER).unwrap() + 8;
+        let width = layer + 4 + "ground".len() + 1 + 4;
+        for (w, h) in [(u32::MAX, u32::MAX), (1 << 16, 1 << 16), (4, 4)] {
+            let mut corrupt = binary.clone();
+            corrupt[width..width + 4].copy_from_slice(&w.to_le_bytes());
+            corrupt[width + 4..width + 8].copy_from_slice(&h.to_le_bytes());
+            assert!(matches!(from_binary(&corrupt), Err(MapFormatError::Corrupt(_))));
+        }
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        assert!(matches!(from_binary(b"SGMB\x01\x00\x00\x00LAYR\xff\xff\xff\xff"), Err(MapFormatError::Corrupt(_))));
+        assert!(matches!(from_binary(b"not a map"), Err(MapFormatError::NotAMap)));
+        assert!(matches!(from_json("{\"format\": \"something else\"}"), Err(MapFormatError::NotAMap)));
+        let mut binary = to_binary(&test_map()).unwrap();
+        binary[16] = 9; // the orientation, the first thing in the header chunk
+        assert!(matches!(from_binary(&binary), Err(MapFormatError::Corrupt(_))));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::pixel_format::{Indexed8, PixelFormat};
+use crate::{Canvas, Rgb};
+
+/// The 256 colors that the indices of a `Canvas<Indexed8>` stand for when
+/// it is shown. drawing only ever stores indices (draw with
+/// `Indexed8::color(index)`), and the colors are looked up in `present`,
+/// so changing the palette recolors everything that was drawn with it
+/// without drawing it again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub colors: [Rgb; 256],
+}
+
+/// the fixed `Indexed8` palette, so an indexed canvas looks the same
+/// until colors are changed
+impl Default for Palette {
+    fn default() -> Self {
+        let mut colors = [Rgb::default(); 256];
+        for (i, color) in colors.iter_mut().enumerate() {
+            *color = Indexed8::color(i as u8);
+        }
+        Palette { colors }
+    }
+}
+
+impl Palette {
+    pub fn new() -> Palette {
+        Palette::default()
+    }
+
+    pub fn get(&self, index: u8) -> Rgb {
+        self.colors[index as usize]
+    }
+
+    pub fn set(&mut self, index: u8, color: Rgb) {
+        self.colors[index as usize] = color;
+    }
+
+    /// sets the colors starting at `first`, eg: a ramp for water
+    pub fn set_range(&mut self, first: u8, colors: &[Rgb]) {
+        for (i, color) in colors.iter().enumerate() {
+            if let Some(entry) = self.colors.get_mut(first as usize + i) {
+                *entry = *color;
+            }
+        }
+    }
+
+    /// a copy of this palette with every cycle rotated to where it is
+    /// at `time` seconds. it always starts from this palette, so the
+    /// cycles never drift, however the time steps.
+    pub fn cycled(&self, cycles: &[ColorCycle], time: f32) -> Palette {
+        let mut palette = self.clone();
+        for cycle in cycles {
+            cycle.apply(self, &mut palette, time);
+        }
+        palette
+    }
+
+    /// colors every pixel of `src` into `dest` (which has to be the same
+    /// size), in whatever format `dest` is. this is the only place that
+    /// an indexed canvas turns into colors, eg: the backend's canvas.
+    pub fn present<P: PixelFormat>(&self, src: &Canvas<Indexed8>, dest: &mut Canvas<P>) {
+        assert_eq!((src.width, src.height), (dest.width, dest.height), "cannot present to a canvas of a different size");
+        let (bpp, format) = (dest.bpp(), dest.format);
+        dest.for_each_band(crate::band::DEFAULT_BAND_HEIGHT, |band| {
+            let start = band.top * band.width;
+            let indices = &src.access_indices()[start..start + band.width * band.height];
+            for (pixel, index) in band.data_mut().chunks_exact_mut(bpp).zip(indices.iter()) {
+                format.write(pixel, self.colors[*index as usize]);
+            }
+        });
+    }
+}
+
+/// Rotates a run of palette entries over time, so that eg: water
+/// drawn with the colors `first..first + count` seems to flow.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorCycle {
+    pub first: u8,
+    pub count: u8,
+    /// how many entries it moves along each second. negative cycles
+    /// go the other way.
+    pub speed: f32,
+}
+
+impl ColorCycle {
+    pub fn new(first: u8, count: u8, speed: f32) -> ColorCycle {
+        ColorCycle { first, count, speed }
+    }
+
+    /// writes the entries of `base` into `palette`, shifted along by
+    /// how far the cycle has gone at `time`
+    pub fn apply(&self, base: &Palette, palette: &mut Palette, time: f32) {
+        let (first, count) = (self.first as usize, self.count as usize);
+        if count == 0 || first + count > 256 {
+            return;
+        }
+        let shift = ((time * self.speed).floor() as i64).rem_euclid(count as i64) as usize;
+        for i in 0..count {
+            palette.colors[first + (i + shift) % count] = base.colors[first + i];
+        }
+    }
+}
+
+/// Replaces some palette indices with others while drawing, so one
+/// sprite can be drawn in different colors, eg: a unit for each team.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteSwap {
+    map: [u8; 256],
+}
+
+impl Default for PaletteSwap {
+    fn default() -> Self {
+        let mut map = [0; 256];
+        for (i, entry) in map.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+        PaletteSwap { map }
+    }
+}
+
+impl PaletteSwap {
+    /// a swap that doesn't change anything
+    pub fn new() -> PaletteSwap {
+        PaletteSwap::default()
+    }
+
+    /// draws `from` as `to`
+    pub fn swap(mut self, from: u8, to: u8) -> PaletteSwap {
+        self.map[from as usize] = to;
+        self
+    }
+
+    /// draws the run of entries starting at `from` as the ones starting at `to`
+    pub fn swap_range(mut self, from: u8, to: u8, count: u8) -> PaletteSwap {
+        for i in 0..count {
+            self.map[from.wrapping_add(i) as usize] = to.wrapping_add(i);
+        }
+        self
+    }
+
+    pub fn index(&self, index: u8) -> u8 {
+        self.map[index as usize]
+    }
+
+    /// the color to draw palette entry `index` with, for shapes drawn
+    /// through `Draw` rather than from a sprite
+    pub fn color(&self, index: u8) -> Rgb {
+        Indexed8::color(self.index(index))
+    }
+}
+
+impl Canvas<Indexed8> {
+    /// the palette index of every pixel, row by row
+    pub fn access_indices(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn get_index(&self, x: usize, y: usize) -> u8 {
+        self.data[x + y * self.width]
+    }
+
+    pub fn set_index(&mut self, x: usize, y: usize, index: u8) {
+        if x >= self.width || y >= self.height { return; }
+        self.data[x + y * self.width] = index;
+    }
+
+    /// draws `sprite` with its top left corner at (x, y), which can be
+    /// off the canvas. every index goes through `swap`, and pixels that
+    /// are `transparent` in the sprite are skipped.
+    pub fn draw_sprite(&mut self, sprite: &Canvas<Indexed8>, x: i32, y: i32, swap: &PaletteSwap, transparent: Option<u8>) {
+        for row in 0..sprite.height {
+            let dest_y = y + row as i32;
+            if dest_y < 0 || dest_y >= self.height as i32 {
+                continue;
+            }
+            for column in 0..sprite.width {
+                let dest_x = x + column as i32;
+                if dest_x < 0 || dest_x >= self.width as i32 {
+                    continue;
+                }
+                let index = sprite.get_index(column, row);
+                if Some(index) == transparent {
+                    continue;
+                }
+                self.set_index(dest_x as usize, dest_y as usize, swap.index(index));
+            }
+        }
+    }
+}
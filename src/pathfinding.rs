@@ -0,0 +1,536 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::ops::Range;
+
+use crate::draw::Draw;
+use crate::tile_map::Cell;
+use crate::Rgb;
+
+/// which cells count as neighbours on a square grid
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Neighbors {
+    /// up, down, left, right
+    #[default]
+    Four,
+    /// also the diagonals
+    Eight,
+}
+
+/// estimate of the remaining cost to the goal used by A*. for the path to
+/// be the shortest one, the estimate must never be more than the real cost,
+/// so with a cost below 1.0 per step, lower `Pathfinder::heuristic_weight`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Heuristic {
+    /// best for 4 neighbours
+    #[default]
+    Manhattan,
+    /// best for 8 neighbours where diagonals cost sqrt(2)
+    Octile,
+    /// best for 8 neighbours where diagonals cost the same as straight moves
+    Chebyshev,
+    Euclidean,
+    /// always 0, which turns A* into Dijkstra's algorithm
+    Zero,
+}
+
+impl Heuristic {
+    pub fn estimate(&self, from: Cell, to: Cell) -> f32 {
+        let dx = (from.0 as f32 - to.0 as f32).abs();
+        let dy = (from.1 as f32 - to.1 as f32).abs();
+        match self {
+            Heuristic::Manhattan => dx + dy,
+            Heuristic::Octile => dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy),
+            Heuristic::Chebyshev => dx.max(dy),
+            Heuristic::Euclidean => (dx * dx + dy * dy).sqrt(),
+            Heuristic::Zero => 0.0,
+        }
+    }
+}
+
+/// the cost of stepping from one cell to a neighbouring cell,
+/// or None if that step isn't possible (eg: the cell is a wall).
+/// any `Fn(Cell, Cell) -> Option<f32>` closure is a movement cost.
+pub trait MovementCost {
+    fn cost(&self, from: Cell, to: Cell) -> Option<f32>;
+}
+
+impl<F: Fn(Cell, Cell) -> Option<f32>> MovementCost for F {
+    fn cost(&self, from: Cell, to: Cell) -> Option<f32> {
+        self(from, to)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Path<N> {
+    /// includes the start and the goal
+    pub nodes: Vec<N>,
+    pub cost: f32,
+}
+
+// the binary heap is a max heap, so the ordering is
+// reversed to pop the lowest estimate first
+struct Open<N> {
+    estimate: f32,
+    node: N,
+}
+
+impl<N> PartialEq for Open<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl<N> Eq for Open<N> {}
+
+impl<N> PartialOrd for Open<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Ord for Open<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// A* over any kind of graph, eg: hex grids, where `Hex::neighbors` and
+/// `Hex::distance` make the neighbours and heuristic. `neighbors` returns
+/// each node that can be reached from a node, along with the cost of getting
+/// there. Square grids can use the simpler `Pathfinder` instead.
+pub fn astar<N, FN, FH>(start: N, goal: N, mut neighbors: FN, heuristic: FH) -> Option<Path<N>>
+where
+    N: Copy + Eq + Hash,
+    FN: FnMut(N) -> Vec<(N, f32)>,
+    FH: Fn(N) -> f32,
+{
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<N, N> = HashMap::new();
+    let mut best_cost: HashMap<N, f32> = HashMap::new();
+    best_cost.insert(start, 0.0);
+    open.push(Open { estimate: heuristic(start), node: start });
+
+    while let Some(Open { node, estimate }) = open.pop() {
+        let cost = best_cost[&node];
+        if node == goal {
+            let mut nodes = vec![goal];
+            let mut current = goal;
+            while let Some(previous) = came_from.get(&current) {
+                nodes.push(*previous);
+                current = *previous;
+            }
+            nodes.reverse();
+            return Some(Path { nodes, cost });
+        }
+        // a node can be in the open list more than once, if a cheaper
+        // way to it was found after it was added. skip the stale entries.
+        if estimate > cost + heuristic(node) {
+            continue;
+        }
+        for (next, step_cost) in neighbors(node) {
+            let next_cost = cost + step_cost;
+            if best_cost.get(&next).map(|c| next_cost < *c).unwrap_or(true) {
+                best_cost.insert(next, next_cost);
+                came_from.insert(next, node);
+                open.push(Open { estimate: next_cost + heuristic(next), node: next });
+            }
+        }
+    }
+    None
+}
+
+/// Finds paths on a square grid of `width` by `height` cells.
+/// Which cells can be entered, and how much it costs, is decided by
+/// the `MovementCost` passed to each search, so the same pathfinder can be
+/// used for units that move differently.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Pathfinder {
+    pub width: usize,
+    pub height: usize,
+    pub neighbors: Neighbors,
+    pub heuristic: Heuristic,
+    /// values above 1.0 make A* faster, but the path may not be the shortest
+    pub heuristic_weight: f32,
+    /// with 8 neighbours, allows moving diagonally past the corner of a
+    /// cell that can't be entered
+    pub cut_corners: bool,
+}
+
+impl Default for Pathfinder {
+    fn default() -> Self {
+        Pathfinder::new(0, 0, Neighbors::Four)
+    }
+}
+
+const STRAIGHT: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const DIAGONAL: [(isize, isize); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+impl Pathfinder {
+    pub fn new(width: usize, height: usize, neighbors: Neighbors) -> Pathfinder {
+        Pathfinder {
+            width,
+            height,
+            neighbors,
+            heuristic: match neighbors {
+                Neighbors::Four => Heuristic::Manhattan,
+                Neighbors::Eight => Heuristic::Octile,
+            },
+            heuristic_weight: 1.0,
+            cut_corners: false,
+        }
+    }
+
+    fn offset(&self, cell: Cell, (dx, dy): (isize, isize)) -> Option<Cell> {
+        let x = cell.0 as isize + dx;
+        let y = cell.1 as isize + dy;
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// the cells next to `cell` on the grid, passable or not
+    pub fn adjacent(&self, cell: Cell) -> Vec<Cell> {
+        let diagonals: &[(isize, isize)] = match self.neighbors {
+            Neighbors::Four => &[],
+            Neighbors::Eight => &DIAGONAL,
+        };
+        STRAIGHT.iter().chain(diagonals.iter())
+            .filter_map(|d| self.offset(cell, *d))
+            .collect()
+    }
+
+    /// the neighbours that can be moved to from `cell`, with the cost of each
+    /// step. diagonal steps cost sqrt(2) times the movement cost.
+    pub fn neighbors<C: MovementCost>(&self, cell: Cell, cost: &C) -> Vec<(Cell, f32)> {
+        let mut out = Vec::with_capacity(8);
+        for d in STRAIGHT.iter() {
+            if let Some(next) = self.offset(cell, *d) {
+                if let Some(c) = cost.cost(cell, next) {
+                    out.push((next, c));
+                }
+            }
+        }
+        if self.neighbors == Neighbors::Four {
+            return out;
+        }
+        for d in DIAGONAL.iter() {
+            let next = match self.offset(cell, *d) {
+                Some(n) => n,
+                None => continue,
+            };
+            if !self.cut_corners {
+                let side_x = (next.0, cell.1);
+                let side_y = (cell.0, next.1);
+                if cost.cost(cell, side_x).is_none() || cost.cost(cell, side_y).is_none() {
+                    continue;
+                }
+            }
+            if let Some(c) = cost.cost(cell, next) {
+                out.push((next, c * std::f32::consts::SQRT_2));
+            }
+        }
+        out
+    }
+
+    /// the cheapest path from start to goal, or None if the goal can't be reached
+    pub fn find_path<C: MovementCost>(&self, start: Cell, goal: Cell, cost: &C) -> Option<Path<Cell>> {
+        if start.0 >= self.width || start.1 >= self.height || goal.0 >= self.width || goal.1 >= self.height {
+            return None;
+        }
+        let weight = self.heuristic_weight;
+        astar(
+            start,
+            goal,
+            |cell| self.neighbors(cell, cost),
+            |cell| self.heuristic.estimate(cell, goal) * weight,
+        )
+    }
+
+    /// the cost of the cheapest path from every cell to the nearest goal.
+    /// one search answers "how far to the closest goal" for every cell at
+    /// once, which is cheaper than a path per cell when there are many
+    /// agents (eg: monsters chasing the player).
+    pub fn dijkstra_map<C: MovementCost>(&self, goals: &[Cell], cost: &C) -> DistanceMap {
+        let mut distances = DistanceMap {
+            width: self.width,
+            height: self.height,
+            distances: vec![f32::INFINITY; self.width * self.height],
+        };
+        let mut open = BinaryHeap::new();
+        for goal in goals.iter().filter(|g| g.0 < self.width && g.1 < self.height) {
+            distances.set(*goal, 0.0);
+            open.push(Open { estimate: 0.0, node: *goal });
+        }
+        while let Some(Open { node, estimate }) = open.pop() {
+            if estimate > distances.get(node) {
+                continue;
+            }
+            // searching backwards from the goals, so the cost that matters
+            // is of moving from the neighbour into this cell
+            for next in self.adjacent(node) {
+                let step = self.neighbors(next, cost).into_iter().find(|(n, _)| *n == node);
+                if let Some((_, step_cost)) = step {
+                    let next_distance = estimate + step_cost;
+                    if next_distance < distances.get(next) {
+                        distances.set(next, next_distance);
+                        open.push(Open { estimate: next_distance, node: next });
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    /// a direction for every cell that leads towards the nearest goal.
+    /// any number of agents can follow it, each only looking at the cell
+    /// it is standing on.
+    pub fn flow_field<C: MovementCost>(&self, goals: &[Cell], cost: &C) -> FlowField {
+        let distances = self.dijkstra_map(goals, cost);
+        let mut next = vec![None; self.width * self.height];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let here = distances.get((x, y));
+                if here == 0.0 || here.is_infinite() {
+                    continue;
+                }
+                // the neighbour where the remaining distance is lowest, after
+                // paying to step there. only ones that are closer to a goal,
+                // so that following the field can't go around in circles.
+                next[y * self.width + x] = self.neighbors((x, y), cost).into_iter()
+                    .filter(|(cell, _)| distances.get(*cell) < here)
+                    .map(|(cell, step)| (cell, step + distances.get(cell)))
+                    .filter(|(_, total)| total.is_finite())
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+                    .map(|(cell, _)| cell);
+            }
+        }
+        FlowField { distances, next }
+    }
+}
+
+/// the distance to the nearest goal for every cell of a grid.
+/// unreachable cells are infinite.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DistanceMap {
+    pub width: usize,
+    pub height: usize,
+    pub distances: Vec<f32>,
+}
+
+impl DistanceMap {
+    pub fn get(&self, cell: Cell) -> f32 {
+        if cell.0 >= self.width || cell.1 >= self.height {
+            return f32::INFINITY;
+        }
+        self.distances[cell.1 * self.width + cell.0]
+    }
+
+    fn set(&mut self, cell: Cell, distance: f32) {
+        self.distances[cell.1 * self.width + cell.0] = distance;
+    }
+
+    /// the largest distance that isn't infinite, useful to scale colors
+    pub fn max_finite(&self) -> f32 {
+        self.distances.iter().copied().filter(|d| d.is_finite()).fold(0.0, f32::max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FlowField {
+    pub distances: DistanceMap,
+    next: Vec<Option<Cell>>,
+}
+
+impl FlowField {
+    /// the cell to move to next from `cell`. None at a goal, if no goal can
+    /// be reached, or if no neighbour is closer to one (eg: on free moves).
+    pub fn next_cell(&self, cell: Cell) -> Option<Cell> {
+        if cell.0 >= self.distances.width || cell.1 >= self.distances.height {
+            return None;
+        }
+        self.next[cell.1 * self.distances.width + cell.0]
+    }
+
+    /// the direction to move in from `cell`, as a step of -1, 0, or 1 on each axis
+    pub fn direction(&self, cell: Cell) -> Option<(isize, isize)> {
+        let next = self.next_cell(cell)?;
+        Some((next.0 as isize - cell.0 as isize, next.1 as isize - cell.1 as isize))
+    }
+
+    /// follows the field from `cell` to a goal. None if it doesn't get to
+    /// one, or takes more steps than there are cells.
+    pub fn path_from(&self, cell: Cell) -> Option<Path<Cell>> {
+        let cost = self.distances.get(cell);
+        if cost.is_infinite() {
+            return None;
+        }
+        let mut nodes = vec![cell];
+        let mut current = cell;
+        for _ in 0..self.distances.width * self.distances.height {
+            match self.next_cell(current) {
+                Some(next) => {
+                    nodes.push(next);
+                    current = next;
+                }
+                None if self.distances.get(current) == 0.0 => return Some(Path { nodes, cost }),
+                None => return None,
+            }
+        }
+        None
+    }
+}
+
+/// draws the path as lines between the centers of its cells. coordinates
+/// are in cells, so push the map's projection onto the WorldScreen first.
+pub fn draw_path<D: Draw>(path: &Path<Cell>, canvas: &mut D, color: Rgb) {
+    for pair in path.nodes.windows(2) {
+        let (x1, y1) = (pair[0].0 as f32 + 0.5, pair[0].1 as f32 + 0.5);
+        let (x2, y2) = (pair[1].0 as f32 + 0.5, pair[1].1 as f32 + 0.5);
+        canvas.draw_diagonal_line_f32(x1, y1, x2, y2, color);
+    }
+}
+
+/// shades each cell from `near` (at a goal) to `far` (the furthest reachable
+/// cell). unreachable cells are left alone.
+pub fn draw_distance_map<D: Draw>(distances: &DistanceMap, cells: (Range<usize>, Range<usize>), canvas: &mut D, near: Rgb, far: Rgb) {
+    let max = distances.max_finite().max(f32::EPSILON);
+    let mix = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    let (x_range, y_range) = cells;
+    for y in y_range {
+        for x in x_range.clone() {
+            let distance = distances.get((x, y));
+            if distance.is_infinite() {
+                continue;
+            }
+            let t = distance / max;
+            let color = Rgb {
+                red: mix(near.red, far.red, t),
+                green: mix(near.green, far.green, t),
+                blue: mix(near.blue, far.blue, t),
+            };
+            let (x, y) = (x as f32, y as f32);
+            canvas.fill_convex_polygon_f32(&[(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)], color);
+        }
+    }
+}
+
+/// draws a short line in each cell pointing to where the flow field goes next
+pub fn draw_flow_field<D: Draw>(field: &FlowField, cells: (Range<usize>, Range<usize>), canvas: &mut D, color: Rgb) {
+    let (x_range, y_range) = cells;
+    for y in y_range {
+        for x in x_range.clone() {
+            if let Some((dx, dy)) = field.direction((x, y)) {
+                let (center_x, center_y) = (x as f32 + 0.5, y as f32 + 0.5);
+                let (tip_x, tip_y) = (center_x + dx as f32 * 0.35, center_y + dy as f32 * 0.35);
+                canvas.draw_diagonal_line_f32(center_x, center_y, tip_x, tip_y, color);
+                // a dot at the tip, so the direction can be told apart
+                let dot = 0.08;
+                canvas.fill_convex_polygon_f32(
+                    &[(tip_x - dot, tip_y - dot), (tip_x + dot, tip_y - dot), (tip_x + dot, tip_y + dot), (tip_x - dot, tip_y + dot)],
+                    color,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a grid from rows of text, where `#` is a wall that can't be
+    /// stepped into or out of
+    fn walls<'a>(rows: &'a [&'a str]) -> (Pathfinder, impl Fn(Cell, Cell) -> Option<f32> + 'a) {
+        let finder = Pathfinder::new(rows[0].len(), rows.len(), Neighbors::Four);
+        let wall = move |cell: Cell| rows[cell.1].as_bytes()[cell.0] == b'#';
+        let cost = move |from: Cell, to: Cell| if wall(from) || wall(to) { None } else { Some(1.0) };
+        (finder, cost)
+    }
+
+    #[test]
+    fn no_path_through_a_wall() {
+        let (finder, cost) = walls(&[
+            "..#..",
+            "..#..",
+            "..#..",
+        ]);
+        assert_eq!(finder.find_path((0, 1), (4, 1), &cost), None);
+        assert_eq!(finder.find_path((0, 1), (1, 2), &cost).map(|path| path.cost), Some(2.0));
+        // the goal is off the map
+        assert_eq!(finder.find_path((0, 1), (5, 1), &cost), None);
+        assert!(finder.dijkstra_map(&[(4, 1)], &cost).get((0, 1)).is_infinite());
+    }
+
+    #[test]
+    fn cutting_corners() {
+        let (mut finder, cost) = walls(&[
+            ".#",
+            "..",
+        ]);
+        finder.neighbors = Neighbors::Eight;
+        let diagonal = |finder: &Pathfinder| finder.neighbors((0, 0), &cost).iter().any(|(cell, _)| *cell == (1, 1));
+        // past the corner of the wall
+        assert!(!diagonal(&finder));
+        assert_eq!(finder.find_path((0, 0), (1, 1), &cost).unwrap().nodes, [(0, 0), (0, 1), (1, 1)]);
+        finder.cut_corners = true;
+        assert!(diagonal(&finder));
+        let path = finder.find_path((0, 0), (1, 1), &cost).unwrap();
+        assert_eq!(path.nodes, [(0, 0), (1, 1)]);
+        assert_eq!(path.cost, std::f32::consts::SQRT_2);
+    }
+
+    #[test]
+    fn distance_to_the_nearest_goal() {
+        let (finder, cost) = walls(&[
+            ".....",
+            ".###.",
+            ".....",
+        ]);
+        let distances = finder.dijkstra_map(&[(0, 0), (4, 2)], &cost);
+        let rows: Vec<Vec<f32>> = distances.distances.chunks(5).map(|row| row.to_vec()).collect();
+        let inf = f32::INFINITY;
+        assert_eq!(rows, [
+            [0.0, 1.0, 2.0, 3.0, 2.0],
+            [1.0, inf, inf, inf, 1.0],
+            [2.0, 3.0, 2.0, 1.0, 0.0],
+        ]);
+        assert_eq!(distances.max_finite(), 3.0);
+    }
+
+    #[test]
+    fn flow_field_only_goes_closer() {
+        let (mut finder, cost) = walls(&[
+            "......",
+            ".##.#.",
+            "...#..",
+            ".#....",
+        ]);
+        finder.neighbors = Neighbors::Eight;
+        let field = finder.flow_field(&[(5, 3)], &cost);
+        for y in 0..4 {
+            for x in 0..6 {
+                let here = field.distances.get((x, y));
+                match field.next_cell((x, y)) {
+                    Some(next) => assert!(field.distances.get(next) < here, "{:?} -> {:?}", (x, y), next),
+                    None => assert!(here == 0.0 || here.is_infinite(), "{:?} is stuck", (x, y)),
+                }
+            }
+        }
+        let path = field.path_from((0, 0)).unwrap();
+        assert_eq!((path.nodes[0], *path.nodes.last().unwrap()), ((0, 0), (5, 3)));
+        assert_eq!(path.cost, field.distances.get((0, 0)));
+        assert_eq!(field.path_from((5, 3)).unwrap().nodes, [(5, 3)]);
+        assert_eq!(field.path_from((1, 1)), None);
+    }
+
+    #[test]
+    fn path_from_stops_going_around_in_circles() {
+        // a field that was edited by hand to loop between two cells
+        let distances = DistanceMap { width: 3, height: 1, distances: vec![2.0, 1.0, 0.0] };
+        let field = FlowField { distances, next: vec![Some((1, 0)), Some((0, 0)), None] };
+        assert_eq!(field.path_from((0, 0)), None);
+        assert_eq!(field.path_from((2, 0)).unwrap().nodes, [(2, 0)]);
+    }
+}
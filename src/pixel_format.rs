@@ -0,0 +1,190 @@
+use crate::Rgb;
+
+/// How the color of a pixel is laid out in the bytes of a `Canvas`.
+///
+/// every format except `Rgbx` has a fixed number of bytes per pixel,
+/// so a `Canvas<Bgra32>` can only be blitted onto another `Canvas<Bgra32>`,
+/// and anything else has to go through `Canvas::convert`.
+pub trait PixelFormat: Copy + Default + Send + Sync {
+    fn bytes_per_pixel(&self) -> usize;
+
+    /// `pixel` is exactly `bytes_per_pixel` long
+    fn write(&self, pixel: &mut [u8], color: Rgb);
+
+    fn read(&self, pixel: &[u8]) -> Rgb;
+}
+
+/// red, green and blue, followed by `bpp - 3` bytes that a color doesn't
+/// set (eg: the alpha of the miniquad texture). `write` leaves them alone,
+/// but whole pixels are copied by fills (from the first filled pixel) and
+/// blits. this is what a plain `Canvas` is, for when the bytes per pixel
+/// are only known at runtime.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rgbx {
+    pub bpp: usize,
+}
+
+impl Default for Rgbx {
+    fn default() -> Self {
+        Rgbx { bpp: 4 }
+    }
+}
+
+impl PixelFormat for Rgbx {
+    #[inline(always)]
+    fn bytes_per_pixel(&self) -> usize {
+        self.bpp
+    }
+
+    #[inline(always)]
+    fn write(&self, pixel: &mut [u8], color: Rgb) {
+        pixel[0] = color.red;
+        pixel[1] = color.green;
+        pixel[2] = color.blue;
+    }
+
+    #[inline(always)]
+    fn read(&self, pixel: &[u8]) -> Rgb {
+        Rgb { red: pixel[0], green: pixel[1], blue: pixel[2] }
+    }
+}
+
+/// 3 bytes: red, green, blue. what `ImageBackend` saves as a png.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Rgb24;
+
+impl PixelFormat for Rgb24 {
+    #[inline(always)]
+    fn bytes_per_pixel(&self) -> usize {
+        3
+    }
+
+    #[inline(always)]
+    fn write(&self, pixel: &mut [u8], color: Rgb) {
+        pixel[0] = color.red;
+        pixel[1] = color.green;
+        pixel[2] = color.blue;
+    }
+
+    #[inline(always)]
+    fn read(&self, pixel: &[u8]) -> Rgb {
+        Rgb { red: pixel[0], green: pixel[1], blue: pixel[2] }
+    }
+}
+
+/// 4 bytes: red, green, blue, and alpha which is always opaque.
+/// what `MQBackend` uploads as its texture.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Rgba32;
+
+impl PixelFormat for Rgba32 {
+    #[inline(always)]
+    fn bytes_per_pixel(&self) -> usize {
+        4
+    }
+
+    #[inline(always)]
+    fn write(&self, pixel: &mut [u8], color: Rgb) {
+        pixel[0] = color.red;
+        pixel[1] = color.green;
+        pixel[2] = color.blue;
+        pixel[3] = 255;
+    }
+
+    #[inline(always)]
+    fn read(&self, pixel: &[u8]) -> Rgb {
+        Rgb { red: pixel[0], green: pixel[1], blue: pixel[2] }
+    }
+}
+
+/// 4 bytes: blue, green, red, and alpha which is always opaque.
+/// the usual layout of linux framebuffers and X11/wayland shared memory.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Bgra32;
+
+impl PixelFormat for Bgra32 {
+    #[inline(always)]
+    fn bytes_per_pixel(&self) -> usize {
+        4
+    }
+
+    #[inline(always)]
+    fn write(&self, pixel: &mut [u8], color: Rgb) {
+        pixel[0] = color.blue;
+        pixel[1] = color.green;
+        pixel[2] = color.red;
+        pixel[3] = 255;
+    }
+
+    #[inline(always)]
+    fn read(&self, pixel: &[u8]) -> Rgb {
+        Rgb { red: pixel[2], green: pixel[1], blue: pixel[0] }
+    }
+}
+
+/// 1 byte of brightness, eg: for masks. colors are turned into their
+/// luma (how bright they look, so green counts for more than blue).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Gray8;
+
+impl PixelFormat for Gray8 {
+    #[inline(always)]
+    fn bytes_per_pixel(&self) -> usize {
+        1
+    }
+
+    #[inline(always)]
+    fn write(&self, pixel: &mut [u8], color: Rgb) {
+        let luma = color.red as u32 * 77 + color.green as u32 * 150 + color.blue as u32 * 29;
+        pixel[0] = (luma >> 8) as u8;
+    }
+
+    #[inline(always)]
+    fn read(&self, pixel: &[u8]) -> Rgb {
+        Rgb { red: pixel[0], green: pixel[0], blue: pixel[0] }
+    }
+}
+
+/// 1 byte that is an index into a palette of 256 colors, with 3 bits
+/// of red, 3 of green and 2 of blue. colors are written as the closest
+/// palette entry, and `Indexed8::color(index)` is exactly the color of
+/// an index, so drawing with it stores that index.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Indexed8;
+
+impl Indexed8 {
+    /// the color that the palette has at `index`
+    pub fn color(index: u8) -> Rgb {
+        let index = index as u32;
+        Rgb {
+            red: ((index >> 5) * 255 / 7) as u8,
+            green: (((index >> 2) & 7) * 255 / 7) as u8,
+            blue: ((index & 3) * 255 / 3) as u8,
+        }
+    }
+
+    /// the index of the palette color closest to `color`
+    pub fn index(color: Rgb) -> u8 {
+        let red = (color.red as u32 * 7 + 127) / 255;
+        let green = (color.green as u32 * 7 + 127) / 255;
+        let blue = (color.blue as u32 * 3 + 127) / 255;
+        ((red << 5) | (green << 2) | blue) as u8
+    }
+}
+
+impl PixelFormat for Indexed8 {
+    #[inline(always)]
+    fn bytes_per_pixel(&self) -> usize {
+        1
+    }
+
+    #[inline(always)]
+    fn write(&self, pixel: &mut [u8], color: Rgb) {
+        pixel[0] = Indexed8::index(color);
+    }
+
+    #[inline(always)]
+    fn read(&self, pixel: &[u8]) -> Rgb {
+        Indexed8::color(pixel[0])
+    }
+}
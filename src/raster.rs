@@ -0,0 +1,232 @@
+use crate::{Canvas, Rgb};
+use crate::pixel_format::PixelFormat;
+
+/// how many bits of the fixed point screen coordinates are below the
+/// pixel, ie: vertices are snapped to 1/256th of a pixel.
+pub const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_SCALE: f32 = (1 << SUBPIXEL_BITS) as f32;
+const HALF_PIXEL: i64 = 1 << (SUBPIXEL_BITS - 1);
+/// vertices further from the origin than this (in pixels) are rejected,
+/// so that the fixed point edge functions can't overflow.
+pub const MAX_COORDINATE: f32 = 4_000_000.0;
+
+/// something that can be blended between the 3 vertices of a
+/// triangle, eg: a color or a texture coordinate.
+pub trait Interpolate: Copy {
+    /// `weights` are the barycentric coordinates of the pixel, they add up to 1.
+    fn interpolate(a: Self, b: Self, c: Self, weights: [f32; 3]) -> Self;
+}
+
+/// for triangles that are a single color
+impl Interpolate for () {
+    fn interpolate(_: (), _: (), _: (), _: [f32; 3]) {}
+}
+
+impl Interpolate for f32 {
+    fn interpolate(a: f32, b: f32, c: f32, weights: [f32; 3]) -> f32 {
+        a * weights[0] + b * weights[1] + c * weights[2]
+    }
+}
+
+impl Interpolate for (f32, f32) {
+    fn interpolate(a: (f32, f32), b: (f32, f32), c: (f32, f32), weights: [f32; 3]) -> (f32, f32) {
+        (
+            f32::interpolate(a.0, b.0, c.0, weights),
+            f32::interpolate(a.1, b.1, c.1, weights),
+        )
+    }
+}
+
+impl Interpolate for Rgb {
+    fn interpolate(a: Rgb, b: Rgb, c: Rgb, weights: [f32; 3]) -> Rgb {
+        let channel = |a: u8, b: u8, c: u8| {
+            f32::interpolate(a as f32, b as f32, c as f32, weights).round().clamp(0.0, 255.0) as u8
+        };
+        Rgb {
+            red: channel(a.red, b.red, c.red),
+            green: channel(a.green, b.green, c.green),
+            blue: channel(a.blue, b.blue, c.blue),
+        }
+    }
+}
+
+/// twice the signed area of the triangle (a, b, p). positive when p is
+/// to the right of a -> b on a screen where y goes down.
+#[inline(always)]
+fn edge(a: (i64, i64), b: (i64, i64), p: (i64, i64)) -> i64 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+/// with the vertices in clockwise order (on screen), a top edge is
+/// horizontal with the triangle below it, and a left edge goes up.
+fn is_top_left(a: (i64, i64), b: (i64, i64)) -> bool {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    (dy == 0 && dx > 0) || dy < 0
+}
+
+fn to_fixed(point: (f32, f32)) -> Option<(i64, i64)> {
+    let (x, y) = point;
+    if !(x.abs() <= MAX_COORDINATE && y.abs() <= MAX_COORDINATE) {
+        return None;
+    }
+    Some(((x * SUBPIXEL_SCALE).round() as i64, (y * SUBPIXEL_SCALE).round() as i64))
+}
+
+/// calls `plot(x, y, weights)` for every pixel of a `width` x `height`
+/// screen whose center is inside of the triangle. `weights` are how much
+/// of each vertex is at the pixel center.
+///
+/// pixel centers exactly on an edge follow the top-left rule (the same
+/// one as Direct3D and OpenGL): they belong to the triangle only if the
+/// edge is a top or a left edge. so triangles that share an edge, like
+/// the two halves of a tile, never draw a pixel twice or leave a gap.
+/// the winding of the points doesn't matter.
+pub fn rasterize_triangle<F>(points: &[(f32, f32); 3], width: usize, height: usize, mut plot: F)
+where
+    F: FnMut(usize, usize, [f32; 3]),
+{
+    let (a, b, c) = match (to_fixed(points[0]), to_fixed(points[1]), to_fixed(points[2])) {
+        (Some(a), Some(b), Some(c)) => (a, b, c),
+        _ => return,
+    };
+    let area = edge(a, b, c);
+    if area == 0 || width == 0 || height == 0 {
+        return;
+    }
+    // make the winding clockwise, and remember which vertex went where
+    let (b, c, swapped) = if area < 0 { (c, b, true) } else { (b, c, false) };
+    let area = area.abs();
+
+    // only the pixels whose centers can be inside
+    let min_x = a.0.min(b.0).min(c.0);
+    let max_x = a.0.max(b.0).max(c.0);
+    let min_y = a.1.min(b.1).min(c.1);
+    let max_y = a.1.max(b.1).max(c.1);
+    let first_column = ((min_x - HALF_PIXEL) >> SUBPIXEL_BITS).max(0);
+    let last_column = ((max_x - HALF_PIXEL) >> SUBPIXEL_BITS).min(width as i64 - 1);
+    let first_row = ((min_y - HALF_PIXEL) >> SUBPIXEL_BITS).max(0);
+    let last_row = ((max_y - HALF_PIXEL) >> SUBPIXEL_BITS).min(height as i64 - 1);
+    if first_column > last_column || first_row > last_row {
+        return;
+    }
+
+    // w0 is the weight of a (opposite edge b -> c), and so on. edges that
+    // aren't top-left are biased by 1, so that 0 on them is outside.
+    let bias = |from, to| if is_top_left(from, to) { 0 } else { -1 };
+    let (bias0, bias1, bias2) = (bias(b, c), bias(c, a), bias(a, b));
+    // how much each edge function changes when moving one pixel right
+    let step = |from: (i64, i64), to: (i64, i64)| -((to.1 - from.1) << SUBPIXEL_BITS);
+    let (step0, step1, step2) = (step(b, c), step(c, a), step(a, b));
+
+    let inverse_area = 1.0 / area as f32;
+    for row in first_row..=last_row {
+        let start = ((first_column << SUBPIXEL_BITS) + HALF_PIXEL, (row << SUBPIXEL_BITS) + HALF_PIXEL);
+        let mut w0 = edge(b, c, start);
+        let mut w1 = edge(c, a, start);
+        let mut w2 = edge(a, b, start);
+        for column in first_column..=last_column {
+            if w0 + bias0 >= 0 && w1 + bias1 >= 0 && w2 + bias2 >= 0 {
+                let (l0, l1, l2) = (w0 as f32 * inverse_area, w1 as f32 * inverse_area, w2 as f32 * inverse_area);
+                let weights = if swapped { [l0, l2, l1] } else { [l0, l1, l2] };
+                plot(column as usize, row as usize, weights);
+            }
+            w0 += step0;
+            w1 += step1;
+            w2 += step2;
+        }
+    }
+}
+
+impl<P: PixelFormat> Canvas<P> {
+    /// the color at a texture coordinate, where (0, 0) is the top left
+    /// corner of this canvas and (1, 1) is the bottom right. coordinates
+    /// outside of that repeat the texture. no filtering, it is the
+    /// nearest pixel.
+    pub fn sample(&self, u: f32, v: f32) -> Rgb {
+        if self.width == 0 || self.height == 0 {
+            return Rgb::default();
+        }
+        let x = (u * self.width as f32).floor() as i64;
+        let y = (v * self.height as f32).floor() as i64;
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.get_pixel(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the pixels a triangle covers, with their weights, in the order
+    /// they are plotted
+    fn pixels(points: &[(f32, f32); 3], width: usize, height: usize) -> Vec<(usize, usize, [f32; 3])> {
+        let mut pixels = vec![];
+        rasterize_triangle(points, width, height, |x, y, weights| pixels.push((x, y, weights)));
+        pixels
+    }
+
+    #[test]
+    fn tile_halves_cover_every_pixel_once() {
+        // a diamond tile, cut along both diagonals, and a square one
+        // whose corners are between pixel centers
+        let tiles = [
+            [(8.0, 0.0), (16.0, 4.0), (8.0, 8.0), (0.0, 4.0)],
+            [(0.25, 0.75), (12.5, 0.75), (12.5, 9.5), (0.25, 9.5)],
+        ];
+        for [top, right, bottom, left] in tiles.iter().copied() {
+            for (first, second) in [([top, right, bottom], [bottom, left, top]), ([left, top, right], [right, bottom, left])] {
+                let mut counts = vec![0; 17 * 11];
+                for (x, y, _) in pixels(&first, 17, 11).into_iter().chain(pixels(&second, 17, 11)) {
+                    counts[y * 17 + x] += 1;
+                }
+                for y in 0..11 {
+                    for x in 0..17 {
+                        let center = (x as f32 + 0.5, y as f32 + 0.5);
+                        let inside = inside_tile(&[top, right, bottom, left], center);
+                        assert!(counts[y * 17 + x] == inside as i32, "pixel {:?} drawn {} times", (x, y), counts[y * 17 + x]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// whether a point is strictly inside of a convex polygon, or on
+    /// one of its top or left edges
+    fn inside_tile(polygon: &[(f32, f32); 4], point: (f32, f32)) -> bool {
+        (0..4).all(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % 4]);
+            let side = (b.0 - a.0) * (point.1 - a.1) - (b.1 - a.1) * (point.0 - a.0);
+            side > 0.0 || (side == 0.0 && ((b.1 == a.1 && b.0 > a.0) || b.1 < a.1))
+        })
+    }
+
+    #[test]
+    fn winding_does_not_matter() {
+        let clockwise = [(1.5, 0.25), (9.75, 6.0), (0.5, 8.5)];
+        let counter_clockwise = [clockwise[0], clockwise[2], clockwise[1]];
+        let drawn = pixels(&clockwise, 12, 12);
+        let reversed: Vec<_> = pixels(&counter_clockwise, 12, 12)
+            .into_iter()
+            .map(|(x, y, [a, c, b])| (x, y, [a, b, c]))
+            .collect();
+        assert!(!drawn.is_empty());
+        assert_eq!(drawn, reversed);
+    }
+
+    #[test]
+    fn weights_at_the_vertices() {
+        // the top left corner is on a pixel center, and is drawn because
+        // both of its edges are top or left edges. putting each of the
+        // vertices there in turn gives its weight.
+        let (corner, right, below) = ((0.5, 0.5), (8.5, 0.5), (0.5, 8.5));
+        let weights = |points| pixels(&points, 10, 10).into_iter().find(|p| (p.0, p.1) == (0, 0)).map(|p| p.2);
+        assert_eq!(weights([corner, right, below]), Some([1.0, 0.0, 0.0]));
+        assert_eq!(weights([below, corner, right]), Some([0.0, 1.0, 0.0]));
+        assert_eq!(weights([right, below, corner]), Some([0.0, 0.0, 1.0]));
+        // and halfway along an edge it is half of each end
+        let drawn = pixels(&[corner, right, below], 10, 10);
+        assert!(drawn.contains(&(4, 0, [0.5, 0.5, 0.0])));
+        assert!(drawn.contains(&(0, 4, [0.5, 0.0, 0.5])));
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::backend::KeyMods;
+use crate::draw::{self, Draw};
+use crate::tile_map::Cell;
+use crate::world_screen::{WorldRect, WorldScreen};
+use crate::Rgb;
+
+/// what selecting some cells does to the cells that are already selected
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SelectionMode {
+    #[default]
+    Replace,
+    Add,
+    Remove,
+    Toggle,
+}
+
+impl SelectionMode {
+    /// shift adds, ctrl removes, and both toggle
+    pub fn from_mods(mods: &KeyMods) -> SelectionMode {
+        match (mods.shift, mods.ctrl) {
+            (true, true) => SelectionMode::Toggle,
+            (true, false) => SelectionMode::Add,
+            (false, true) => SelectionMode::Remove,
+            (false, false) => SelectionMode::Replace,
+        }
+    }
+}
+
+/// A set of selected cells. cells are stored in map coordinates, so the
+/// selection stays put when the view is panned or zoomed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Selection {
+    cells: BTreeSet<Cell>,
+}
+
+impl Selection {
+    pub fn new() -> Selection {
+        Selection::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, cell: Cell) -> bool {
+        self.cells.contains(&cell)
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cell> {
+        self.cells.iter()
+    }
+
+    pub fn apply<I: IntoIterator<Item = Cell>>(&mut self, mode: SelectionMode, cells: I) {
+        if mode == SelectionMode::Replace {
+            self.cells.clear();
+        }
+        for cell in cells {
+            match mode {
+                SelectionMode::Replace | SelectionMode::Add => {
+                    self.cells.insert(cell);
+                }
+                SelectionMode::Remove => {
+                    self.cells.remove(&cell);
+                }
+                SelectionMode::Toggle => {
+                    if !self.cells.remove(&cell) {
+                        self.cells.insert(cell);
+                    }
+                }
+            }
+        }
+    }
+
+    /// fills the selected cells that are in `cells`, and outlines the edges
+    /// of the selection. coordinates are in cells, so push the map's
+    /// projection onto the WorldScreen first.
+    pub fn draw<D: Draw>(&self, cells: (Range<usize>, Range<usize>), canvas: &mut D, fill: Option<Rgb>, outline: Rgb) {
+        let (x_range, y_range) = cells;
+        for &(x, y) in self.cells.iter() {
+            if !x_range.contains(&x) || !y_range.contains(&y) {
+                continue;
+            }
+            let (fx, fy) = (x as f32, y as f32);
+            if let Some(color) = fill {
+                canvas.fill_convex_polygon_f32(&[(fx, fy), (fx + 1.0, fy), (fx + 1.0, fy + 1.0), (fx, fy + 1.0)], color);
+            }
+            // only the edges between a selected and an unselected cell
+            if y == 0 || !self.contains((x, y - 1)) {
+                canvas.draw_diagonal_line_f32(fx, fy, fx + 1.0, fy, outline);
+            }
+            if !self.contains((x, y + 1)) {
+                canvas.draw_diagonal_line_f32(fx, fy + 1.0, fx + 1.0, fy + 1.0, outline);
+            }
+            if x == 0 || !self.contains((x - 1, y)) {
+                canvas.draw_diagonal_line_f32(fx, fy, fx, fy + 1.0, outline);
+            }
+            if !self.contains((x + 1, y)) {
+                canvas.draw_diagonal_line_f32(fx + 1.0, fy, fx + 1.0, fy + 1.0, outline);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DragShape {
+    Rectangle,
+    Lasso,
+}
+
+/// how far (in screen pixels) the mouse has to move before a press
+/// counts as a drag rather than a click
+const CLICK_DISTANCE: f32 = 4.0;
+
+/// A rectangle or lasso being dragged out with the mouse, in screen
+/// coordinates. it is turned into cells with `cells`, using whatever the
+/// view is when the drag ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DragSelect {
+    pub shape: DragShape,
+    pub mode: SelectionMode,
+    points: Vec<(f32, f32)>,
+}
+
+impl DragSelect {
+    pub fn new(shape: DragShape, mode: SelectionMode, x: f32, y: f32) -> DragSelect {
+        DragSelect { shape, mode, points: vec![(x, y)] }
+    }
+
+    pub fn drag_to(&mut self, x: f32, y: f32) {
+        match self.shape {
+            DragShape::Rectangle => {
+                self.points.truncate(1);
+                self.points.push((x, y));
+            }
+            DragShape::Lasso => {
+                // skip points that are too close together to matter
+                let (last_x, last_y) = self.points[self.points.len() - 1];
+                if (x - last_x).abs() + (y - last_y).abs() >= 2.0 {
+                    self.points.push((x, y));
+                }
+            }
+        }
+    }
+
+    /// true if the mouse never moved far enough to be a drag
+    pub fn is_click(&self) -> bool {
+        let (start_x, start_y) = self.points[0];
+        self.points.iter().all(|(x, y)| (x - start_x).abs() < CLICK_DISTANCE && (y - start_y).abs() < CLICK_DISTANCE)
+    }
+
+    /// the outline of the selection on screen
+    pub fn polygon(&self) -> Vec<(f32, f32)> {
+        match self.shape {
+            DragShape::Rectangle => {
+                let (x1, y1) = self.points[0];
+                let (x2, y2) = self.points[self.points.len() - 1];
+                vec![(x1, y1), (x2, y1), (x2, y2), (x1, y2)]
+            }
+            DragShape::Lasso => self.points.clone(),
+        }
+    }
+
+    /// the cells whose centers are inside of the rectangle or lasso. a click
+    /// gives the cell under the mouse. the map's projection has to be pushed
+    /// onto `screen` (the same as for drawing the map), so this works the
+    /// same for square and isometric grids, and with a rotated camera.
+    pub fn cells(&self, screen: &WorldScreen, map_size: (usize, usize)) -> Vec<Cell> {
+        if self.is_click() {
+            let (x, y) = screen.screen_to_world(self.points[0].0, self.points[0].1);
+            if x < 0.0 || y < 0.0 || x as usize >= map_size.0 || y as usize >= map_size.1 {
+                return vec![];
+            }
+            return vec![(x as usize, y as usize)];
+        }
+        let polygon = self.polygon();
+        // only the cells under the bounding box of the polygon have to be checked
+        let bounds = WorldRect::from_points(&polygon);
+        let corners = [
+            screen.screen_to_world(bounds.min_x, bounds.min_y),
+            screen.screen_to_world(bounds.max_x, bounds.min_y),
+            screen.screen_to_world(bounds.max_x, bounds.max_y),
+            screen.screen_to_world(bounds.min_x, bounds.max_y),
+        ];
+        let (x_range, y_range) = WorldRect::from_points(&corners).cell_range(1.0, 1.0, map_size);
+        let mut out = vec![];
+        for y in y_range {
+            for x in x_range.clone() {
+                let (center_x, center_y) = screen.world_to_screen_f32(x as f32 + 0.5, y as f32 + 0.5);
+                if draw::polygon_contains_even_odd(&polygon, center_x, center_y) {
+                    out.push((x, y));
+                }
+            }
+        }
+        out
+    }
+
+    /// outlines the rectangle or lasso. this is in screen coordinates,
+    /// so draw it onto the WorldScreen's canvas, not the WorldScreen.
+    pub fn draw<D: Draw>(&self, canvas: &mut D, color: Rgb) {
+        let polygon = self.polygon();
+        for i in 0..polygon.len() {
+            let (x1, y1) = polygon[i];
+            let (x2, y2) = polygon[(i + 1) % polygon.len()];
+            canvas.draw_diagonal_line_f32(x1, y1, x2, y2, color);
+        }
+    }
+}
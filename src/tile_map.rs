@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::transform::Transform;
+
+pub const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY_FLAG: u32 = 0x4000_0000;
+pub const FLIPPED_DIAGONALLY_FLAG: u32 = 0x2000_0000;
+pub const ROTATED_HEXAGONAL_120_FLAG: u32 = 0x1000_0000;
+const ALL_FLAGS: u32 = FLIPPED_HORIZONTALLY_FLAG | FLIPPED_VERTICALLY_FLAG
+    | FLIPPED_DIAGONALLY_FLAG | ROTATED_HEXAGONAL_120_FLAG;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    #[default]
+    Orthogonal,
+    Isometric,
+}
+
+/// A single cell of a tile layer. `gid` is a global tile id: 0 means
+/// the cell is empty, otherwise it points into one of the map's tilesets.
+/// tiles are saved as their raw id (gid + flip flags) to keep map files small.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
+pub struct Tile {
+    pub gid: u32,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    pub const EMPTY: Tile = Tile { gid: 0, flip_horizontal: false, flip_vertical: false, flip_diagonal: false };
+
+    pub fn new(gid: u32) -> Tile {
+        Tile { gid, ..Tile::EMPTY }
+    }
+
+    /// splits a raw id (with the flip flags in its top bits,
+    /// as stored by the Tiled editor) into the gid and the flags.
+    pub fn from_raw(raw: u32) -> Tile {
+        Tile {
+            gid: raw & !ALL_FLAGS,
+            flip_horizontal: raw & FLIPPED_HORIZONTALLY_FLAG != 0,
+            flip_vertical: raw & FLIPPED_VERTICALLY_FLAG != 0,
+            flip_diagonal: raw & FLIPPED_DIAGONALLY_FLAG != 0,
+        }
+    }
+
+    pub fn to_raw(&self) -> u32 {
+        let mut raw = self.gid;
+        if self.flip_horizontal { raw |= FLIPPED_HORIZONTALLY_FLAG; }
+        if self.flip_vertical { raw |= FLIPPED_VERTICALLY_FLAG; }
+        if self.flip_diagonal { raw |= FLIPPED_DIAGONALLY_FLAG; }
+        raw
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gid == 0
+    }
+}
+
+impl From<u32> for Tile {
+    fn from(raw: u32) -> Self {
+        Tile::from_raw(raw)
+    }
+}
+
+impl From<Tile> for u32 {
+    fn from(tile: Tile) -> Self {
+        tile.to_raw()
+    }
+}
+
+/// a value attached to a cell of the map
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+pub type Properties = BTreeMap<String, PropertyValue>;
+
+/// the (x, y) of a cell of a map or a grid
+pub type Cell = (usize, usize);
+
+/// a tileset used by the map. tiles with a gid between `first_gid`
+/// and `first_gid + tile_count` belong to this tileset.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tileset {
+    pub first_gid: u32,
+    pub name: String,
+    /// path to an external tileset file, if the tileset was not embedded in the map
+    pub source: Option<String>,
+    pub tile_width: usize,
+    pub tile_height: usize,
+    pub tile_count: u32,
+    pub columns: u32,
+    pub image: Option<String>,
+}
+
+impl Tileset {
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && (self.tile_count == 0 || gid - self.first_gid < self.tile_count)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub width: usize,
+    pub height: usize,
+    /// row major, `width * height` tiles
+    pub tiles: Vec<Tile>,
+}
+
+impl TileLayer {
+    pub fn new(name: &str, width: usize, height: usize) -> TileLayer {
+        TileLayer {
+            name: name.to_owned(),
+            visible: true,
+            opacity: 1.0,
+            width,
+            height,
+            tiles: vec![Tile::EMPTY; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.tiles[y * self.width + x])
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, tile: Tile) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        self.tiles[y * self.width + x] = tile;
+    }
+}
+
+/// a free floating object (spawn point, trigger area, etc) placed in
+/// the map. positions are in pixels, like the Tiled editor stores them.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub kind: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub rotation: f32,
+    pub visible: bool,
+    /// set if this object displays a tile
+    pub tile: Option<Tile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub objects: Vec<MapObject>,
+}
+
+/// A grid of tiles with any number of layers.
+/// Cell (x, y) of the map is placed on screen according to
+/// the map's `projection`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileMap {
+    pub orientation: Orientation,
+    pub width: usize,
+    pub height: usize,
+    pub tile_width: usize,
+    pub tile_height: usize,
+    /// world position that the map's projection starts at
+    pub origin: (f32, f32),
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<TileLayer>,
+    pub object_layers: Vec<ObjectLayer>,
+    /// properties of individual cells, eg: movement cost, spawn points.
+    #[serde(with = "cell_properties")]
+    pub properties: BTreeMap<(usize, usize), Properties>,
+}
+
+impl TileMap {
+    pub fn new(orientation: Orientation, width: usize, height: usize, tile_width: usize, tile_height: usize) -> TileMap {
+        TileMap {
+            orientation,
+            width,
+            height,
+            tile_width,
+            tile_height,
+            ..Default::default()
+        }
+    }
+
+    pub fn map_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find(|l| l.name == name)
+    }
+
+    /// the tileset a gid belongs to, and the id of the tile within that tileset
+    pub fn tileset_for(&self, gid: u32) -> Option<(&Tileset, u32)> {
+        if gid == 0 {
+            return None;
+        }
+        // tilesets are sorted by first_gid, so the last one
+        // that starts before the gid is the one it belongs to
+        self.tilesets.iter()
+            .filter(|t| t.first_gid <= gid)
+            .max_by_key(|t| t.first_gid)
+            .filter(|t| t.contains(gid))
+            .map(|t| (t, gid - t.first_gid))
+    }
+
+    /// transform from map coordinates (where each cell is 1x1) to world
+    /// pixels. push this onto a `WorldScreen` to draw in map coordinates.
+    /// for isometric maps, the top corner of cell (0, 0) is placed so that
+    /// the whole map has positive x coordinates, the same way Tiled does it.
+    pub fn projection(&self) -> Transform {
+        let tile_width = self.tile_width as f32;
+        let tile_height = self.tile_height as f32;
+        let (origin_x, origin_y) = self.origin;
+        match self.orientation {
+            Orientation::Orthogonal => Transform::scale(tile_width, tile_height)
+                .then(Transform::translate(origin_x, origin_y)),
+            Orientation::Isometric => {
+                let shift_x = self.height as f32 * tile_width / 2.0;
+                Transform::isometric(tile_width, tile_height, origin_x + shift_x, origin_y)
+            }
+        }
+    }
+
+    pub fn cell_properties(&self, x: usize, y: usize) -> Option<&Properties> {
+        self.properties.get(&(x, y))
+    }
+
+    pub fn set_cell_property(&mut self, x: usize, y: usize, key: &str, value: PropertyValue) {
+        self.properties.entry((x, y)).or_default().insert(key.to_owned(), value);
+    }
+
+    /// converts an object's pixel position to map coordinates. Tiled stores
+    /// object positions on isometric maps as if the map was orthogonal with
+    /// square tiles of `tile_height`, so those are divided by the tile height.
+    pub fn object_position(&self, object: &MapObject) -> (f32, f32) {
+        match self.orientation {
+            Orientation::Orthogonal => (
+                object.x / self.tile_width as f32,
+                object.y / self.tile_height as f32,
+            ),
+            Orientation::Isometric => (
+                object.x / self.tile_height as f32,
+                object.y / self.tile_height as f32,
+            ),
+        }
+    }
+
+    /// the cell under a world position, or None if that's outside of the map
+    pub fn cell_at(&self, world_x: f32, world_y: f32) -> Option<(usize, usize)> {
+        let (map_x, map_y) = self.projection().inverse()?.apply(world_x, world_y);
+        if map_x < 0.0 || map_y < 0.0 {
+            return None;
+        }
+        let (x, y) = (map_x as usize, map_y as usize);
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some((x, y))
+    }
+}
+
+/// json objects can only have string keys, so the cell properties
+/// are saved as a list of `{ x, y, properties }` instead of a map.
+mod cell_properties {
+    use super::Properties;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize)]
+    struct CellEntry {
+        x: usize,
+        y: usize,
+        properties: Properties,
+    }
+
+    pub fn serialize<S: Serializer>(map: &BTreeMap<(usize, usize), Properties>, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries: Vec<CellEntry> = map.iter()
+            .map(|((x, y), properties)| CellEntry { x: *x, y: *y, properties: properties.clone() })
+            .collect();
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<(usize, usize), Properties>, D::Error> {
+        let entries = Vec::<CellEntry>::deserialize(deserializer)?;
+        Ok(entries.into_iter().map(|e| ((e.x, e.y), e.properties)).collect())
+    }
+}
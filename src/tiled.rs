@@ -0,0 +1,599 @@
+//! Loads maps made with the [Tiled](https://www.mapeditor.org/) editor,
+//! either in the TMX (xml) or the JSON (.tmj) format, into a `TileMap`.
+//!
+//! Supported: orthogonal and isometric maps, tile layers (csv, base64,
+//! base64 + zlib/gzip, and plain xml encoding), object layers, group layers
+//! (which are flattened), embedded tilesets, and external tilesets.
+//! Infinite maps and zstd compression are not supported.
+
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::tile_map::{MapObject, ObjectLayer, Orientation, Tile, TileLayer, TileMap, Tileset};
+
+#[derive(Debug)]
+pub enum TiledError {
+    Io(std::io::Error),
+    Xml(roxmltree::Error),
+    Json(serde_json::Error),
+    /// the file was parsed, but is missing something or contains a bad value
+    Invalid(String),
+    /// the file uses a Tiled feature that this loader doesn't handle
+    Unsupported(String),
+}
+
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TiledError::Io(e) => write!(f, "failed to read tiled file: {}", e),
+            TiledError::Xml(e) => write!(f, "failed to parse tmx: {}", e),
+            TiledError::Json(e) => write!(f, "failed to parse tiled json: {}", e),
+            TiledError::Invalid(msg) => write!(f, "invalid tiled map: {}", msg),
+            TiledError::Unsupported(msg) => write!(f, "unsupported tiled feature: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TiledError {}
+
+impl From<std::io::Error> for TiledError {
+    fn from(e: std::io::Error) -> Self {
+        TiledError::Io(e)
+    }
+}
+
+impl From<roxmltree::Error> for TiledError {
+    fn from(e: roxmltree::Error) -> Self {
+        TiledError::Xml(e)
+    }
+}
+
+impl From<serde_json::Error> for TiledError {
+    fn from(e: serde_json::Error) -> Self {
+        TiledError::Json(e)
+    }
+}
+
+fn invalid<T>(msg: &str) -> Result<T, TiledError> {
+    Err(TiledError::Invalid(msg.to_owned()))
+}
+
+/// loads a .tmx or .tmj/.json map depending on the file extension.
+/// external tilesets are loaded relative to the map's directory.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<TileMap, TiledError> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)?;
+    let mut map = match path.extension().and_then(|e| e.to_str()) {
+        Some("tmx") => parse_tmx(&contents)?,
+        Some("tmj") | Some("json") => parse_tmj(&contents)?,
+        _ => return Err(TiledError::Unsupported(format!("unknown map extension: {}", path.display()))),
+    };
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    load_external_tilesets(&mut map, &base_dir)?;
+    Ok(map)
+}
+
+/// fills in the details of every tileset that only has a `source`.
+pub fn load_external_tilesets(map: &mut TileMap, base_dir: &Path) -> Result<(), TiledError> {
+    for tileset in map.tilesets.iter_mut() {
+        let source = match &tileset.source {
+            Some(s) => s.clone(),
+            None => continue,
+        };
+        let tileset_path: PathBuf = base_dir.join(&source);
+        let contents = fs::read_to_string(&tileset_path)?;
+        let loaded = if source.ends_with(".tsx") {
+            let doc = roxmltree::Document::parse(&contents)?;
+            parse_tmx_tileset(doc.root_element(), tileset.first_gid)?
+        } else {
+            let value: Value = serde_json::from_str(&contents)?;
+            parse_tmj_tileset(&value, tileset.first_gid)?
+        };
+        *tileset = Tileset { source: Some(source), ..loaded };
+    }
+    Ok(())
+}
+
+fn parse_orientation(orientation: &str) -> Result<Orientation, TiledError> {
+    match orientation {
+        "orthogonal" => Ok(Orientation::Orthogonal),
+        "isometric" => Ok(Orientation::Isometric),
+        other => Err(TiledError::Unsupported(format!("{} orientation", other))),
+    }
+}
+
+/// turns the raw contents of a layer's data (after the encoding
+/// was removed) into tiles, checking that there's one per cell.
+fn tiles_from_raw(raw: Vec<u32>, width: usize, height: usize) -> Result<Vec<Tile>, TiledError> {
+    // the size comes from the file, so it can be anything
+    if width.checked_mul(height) != Some(raw.len()) {
+        return Err(TiledError::Invalid(format!(
+            "layer has {} tiles but should have {}x{}", raw.len(), width, height
+        )));
+    }
+    Ok(raw.into_iter().map(Tile::from_raw).collect())
+}
+
+fn decode_csv(data: &str) -> Result<Vec<u32>, TiledError> {
+    data.split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<u32>().map_err(|_| TiledError::Invalid(format!("bad tile id: {}", s))))
+        .collect()
+}
+
+fn decode_base64(data: &str, compression: Option<&str>) -> Result<Vec<u32>, TiledError> {
+    let bytes = base64::decode(data.trim())
+        .map_err(|e| TiledError::Invalid(format!("bad base64 layer data: {}", e)))?;
+    let bytes = match compression {
+        None | Some("") => bytes,
+        Some("zlib") => {
+            let mut out = vec![];
+            flate2::read::ZlibDecoder::new(&bytes[..]).read_to_end(&mut out)?;
+            out
+        }
+        Some("gzip") => {
+            let mut out = vec![];
+            flate2::read::GzDecoder::new(&bytes[..]).read_to_end(&mut out)?;
+            out
+        }
+        Some(other) => return Err(TiledError::Unsupported(format!("{} compression", other))),
+    };
+    if bytes.len() % 4 != 0 {
+        return invalid("base64 layer data is not a multiple of 4 bytes");
+    }
+    Ok(bytes.chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn attr<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attribute(name)
+}
+
+fn attr_parse<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Result<Option<T>, TiledError> {
+    match node.attribute(name) {
+        None => Ok(None),
+        Some(v) => v.parse::<T>()
+            .map(Some)
+            .map_err(|_| TiledError::Invalid(format!("bad value for {}: {}", name, v))),
+    }
+}
+
+fn attr_required<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Result<T, TiledError> {
+    match attr_parse(node, name)? {
+        Some(v) => Ok(v),
+        None => Err(TiledError::Invalid(format!("<{}> is missing {}", node.tag_name().name(), name))),
+    }
+}
+
+pub fn parse_tmx(contents: &str) -> Result<TileMap, TiledError> {
+    let doc = roxmltree::Document::parse(contents)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("map") {
+        return invalid("root element is not <map>");
+    }
+    if attr(root, "infinite") == Some("1") {
+        return Err(TiledError::Unsupported("infinite maps".into()));
+    }
+    let mut map = TileMap::new(
+        parse_orientation(attr(root, "orientation").unwrap_or("orthogonal"))?,
+        attr_required(root, "width")?,
+        attr_required(root, "height")?,
+        attr_required(root, "tilewidth")?,
+        attr_required(root, "tileheight")?,
+    );
+
+    for child in root.children().filter(|n| n.is_element()) {
+        if child.has_tag_name("tileset") {
+            let first_gid = attr_required(child, "firstgid")?;
+            let tileset = match attr(child, "source") {
+                Some(source) => Tileset {
+                    first_gid,
+                    source: Some(source.to_owned()),
+                    ..Default::default()
+                },
+                None => parse_tmx_tileset(child, first_gid)?,
+            };
+            map.tilesets.push(tileset);
+        }
+    }
+    map.tilesets.sort_by_key(|t| t.first_gid);
+    parse_tmx_layers(root, &mut map, true, 1.0)?;
+    Ok(map)
+}
+
+fn parse_tmx_tileset(node: roxmltree::Node, first_gid: u32) -> Result<Tileset, TiledError> {
+    let image = node.children()
+        .find(|n| n.has_tag_name("image"))
+        .and_then(|n| attr(n, "source"))
+        .map(str::to_owned);
+    Ok(Tileset {
+        first_gid,
+        name: attr(node, "name").unwrap_or("").to_owned(),
+        source: None,
+        tile_width: attr_parse(node, "tilewidth")?.unwrap_or(0),
+        tile_height: attr_parse(node, "tileheight")?.unwrap_or(0),
+        tile_count: attr_parse(node, "tilecount")?.unwrap_or(0),
+        columns: attr_parse(node, "columns")?.unwrap_or(0),
+        image,
+    })
+}
+
+/// group layers are flattened: their children are added to the map
+/// directly, inheriting the group's visibility and opacity.
+fn parse_tmx_layers(parent: roxmltree::Node, map: &mut TileMap, visible: bool, opacity: f32) -> Result<(), TiledError> {
+    for node in parent.children().filter(|n| n.is_element()) {
+        let layer_visible = visible && attr(node, "visible") != Some("0");
+        let layer_opacity = opacity * attr_parse::<f32>(node, "opacity")?.unwrap_or(1.0);
+        let name = attr(node, "name").unwrap_or("").to_owned();
+        match node.tag_name().name() {
+            "layer" => {
+                let width = attr_parse(node, "width")?.unwrap_or(map.width);
+                let height = attr_parse(node, "height")?.unwrap_or(map.height);
+                let data = match node.children().find(|n| n.has_tag_name("data")) {
+                    Some(d) => d,
+                    None => return invalid("<layer> has no <data>"),
+                };
+                if data.children().any(|n| n.has_tag_name("chunk")) {
+                    return Err(TiledError::Unsupported("infinite maps".into()));
+                }
+                let raw = match attr(data, "encoding") {
+                    Some("csv") => decode_csv(data.text().unwrap_or(""))?,
+                    Some("base64") => decode_base64(data.text().unwrap_or(""), attr(data, "compression"))?,
+                    Some(other) => return Err(TiledError::Unsupported(format!("{} encoding", other))),
+                    None => data.children()
+                        .filter(|n| n.has_tag_name("tile"))
+                        .map(|n| attr_parse(n, "gid").map(|gid| gid.unwrap_or(0)))
+                        .collect::<Result<Vec<u32>, TiledError>>()?,
+                };
+                map.layers.push(TileLayer {
+                    name,
+                    visible: layer_visible,
+                    opacity: layer_opacity,
+                    width,
+                    height,
+                    tiles: tiles_from_raw(raw, width, height)?,
+                });
+            }
+            "objectgroup" => {
+                let mut objects = vec![];
+                for object in node.children().filter(|n| n.has_tag_name("object")) {
+                    objects.push(MapObject {
+                        id: attr_parse(object, "id")?.unwrap_or(0),
+                        name: attr(object, "name").unwrap_or("").to_owned(),
+                        // "class" replaced "type" in newer versions of Tiled
+                        kind: attr(object, "type").or_else(|| attr(object, "class")).unwrap_or("").to_owned(),
+                        x: attr_parse(object, "x")?.unwrap_or(0.0),
+                        y: attr_parse(object, "y")?.unwrap_or(0.0),
+                        width: attr_parse(object, "width")?.unwrap_or(0.0),
+                        height: attr_parse(object, "height")?.unwrap_or(0.0),
+                        rotation: attr_parse(object, "rotation")?.unwrap_or(0.0),
+                        visible: attr(object, "visible") != Some("0"),
+                        tile: attr_parse::<u32>(object, "gid")?.map(Tile::from_raw),
+                    });
+                }
+                map.object_layers.push(ObjectLayer {
+                    name,
+                    visible: layer_visible,
+                    opacity: layer_opacity,
+                    objects,
+                });
+            }
+            "group" => parse_tmx_layers(node, map, layer_visible, layer_opacity)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+
+fn json_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn json_usize(value: &Value, key: &str) -> Option<usize> {
+    value.get(key).and_then(Value::as_u64).map(|v| v as usize)
+}
+
+fn json_f32(value: &Value, key: &str) -> Option<f32> {
+    value.get(key).and_then(Value::as_f64).map(|v| v as f32)
+}
+
+fn json_required(value: &Value, key: &str) -> Result<usize, TiledError> {
+    json_usize(value, key).ok_or_else(|| TiledError::Invalid(format!("missing {}", key)))
+}
+
+pub fn parse_tmj(contents: &str) -> Result<TileMap, TiledError> {
+    let root: Value = serde_json::from_str(contents)?;
+    if root.get("infinite").and_then(Value::as_bool) == Some(true) {
+        return Err(TiledError::Unsupported("infinite maps".into()));
+    }
+    let mut map = TileMap::new(
+        parse_orientation(json_str(&root, "orientation").unwrap_or("orthogonal"))?,
+        json_required(&root, "width")?,
+        json_required(&root, "height")?,
+        json_required(&root, "tilewidth")?,
+        json_required(&root, "tileheight")?,
+    );
+
+    if let Some(tilesets) = root.get("tilesets").and_then(Value::as_array) {
+        for tileset in tilesets {
+            let first_gid = json_required(tileset, "firstgid")? as u32;
+            let tileset = match json_str(tileset, "source") {
+                Some(source) => Tileset {
+                    first_gid,
+                    source: Some(source.to_owned()),
+                    ..Default::default()
+                },
+                None => parse_tmj_tileset(tileset, first_gid)?,
+            };
+            map.tilesets.push(tileset);
+        }
+    }
+    map.tilesets.sort_by_key(|t| t.first_gid);
+    if let Some(layers) = root.get("layers").and_then(Value::as_array) {
+        parse_tmj_layers(layers, &mut map, true, 1.0)?;
+    }
+    Ok(map)
+}
+
+fn parse_tmj_tileset(value: &Value, first_gid: u32) -> Result<Tileset, TiledError> {
+    Ok(Tileset {
+        first_gid,
+        name: json_str(value, "name").unwrap_or("").to_owned(),
+        source: None,
+        tile_width: json_usize(value, "tilewidth").unwrap_or(0),
+        tile_height: json_usize(value, "tileheight").unwrap_or(0),
+        tile_count: json_usize(value, "tilecount").unwrap_or(0) as u32,
+        columns: json_usize(value, "columns").unwrap_or(0) as u32,
+        image: json_str(value, "image").map(str::to_owned),
+    })
+}
+
+fn parse_tmj_layers(layers: &[Value], map: &mut TileMap, visible: bool, opacity: f32) -> Result<(), TiledError> {
+    for layer in layers {
+        let layer_visible = visible && layer.get("visible").and_then(Value::as_bool).unwrap_or(true);
+        let layer_opacity = opacity * json_f32(layer, "opacity").unwrap_or(1.0);
+        let name = json_str(layer, "name").unwrap_or("").to_owned();
+        match json_str(layer, "type") {
+            Some("tilelayer") => {
+                if layer.get("chunks").is_some() {
+                    return Err(TiledError::Unsupported("infinite maps".into()));
+                }
+                let width = json_usize(layer, "width").unwrap_or(map.width);
+                let height = json_usize(layer, "height").unwrap_or(map.height);
+                let raw = match layer.get("data") {
+                    Some(Value::Array(ids)) => ids.iter()
+                        .map(|v| v.as_u64().map(|id| id as u32).ok_or_else(|| TiledError::Invalid("bad tile id".into())))
+                        .collect::<Result<Vec<u32>, TiledError>>()?,
+                    Some(Value::String(data)) => decode_base64(data, json_str(layer, "compression"))?,
+                    _ => return invalid("tile layer has no data"),
+                };
+                map.layers.push(TileLayer {
+                    name,
+                    visible: layer_visible,
+                    opacity: layer_opacity,
+                    width,
+                    height,
+                    tiles: tiles_from_raw(raw, width, height)?,
+                });
+            }
+            Some("objectgroup") => {
+                let mut objects = vec![];
+                let values = layer.get("objects").and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]);
+                for object in values {
+                    objects.push(MapObject {
+                        id: json_usize(object, "id").unwrap_or(0) as u32,
+                        name: json_str(object, "name").unwrap_or("").to_owned(),
+                        kind: json_str(object, "type").or_else(|| json_str(object, "class")).unwrap_or("").to_owned(),
+                        x: json_f32(object, "x").unwrap_or(0.0),
+                        y: json_f32(object, "y").unwrap_or(0.0),
+                        width: json_f32(object, "width").unwrap_or(0.0),
+                        height: json_f32(object, "height").unwrap_or(0.0),
+                        rotation: json_f32(object, "rotation").unwrap_or(0.0),
+                        visible: object.get("visible").and_then(Value::as_bool).unwrap_or(true),
+                        tile: object.get("gid").and_then(Value::as_u64).map(|gid| Tile::from_raw(gid as u32)),
+                    });
+                }
+                map.object_layers.push(ObjectLayer {
+                    name,
+                    visible: layer_visible,
+                    opacity: layer_opacity,
+                    objects,
+                });
+            }
+            Some("group") => {
+                let children = layer.get("layers").and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]);
+                parse_tmj_layers(children, map, layer_visible, layer_opacity)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_map::{FLIPPED_DIAGONALLY_FLAG, FLIPPED_HORIZONTALLY_FLAG, FLIPPED_VERTICALLY_FLAG};
+    use std::io::Write;
+
+    fn asset(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/assets").join(name)
+    }
+
+    /// the (x, y, tile) of every tile of a layer that isn't empty
+    fn tiles(layer: &TileLayer) -> Vec<(usize, usize, Tile)> {
+        layer.tiles.iter().enumerate()
+            .filter(|(_, tile)| !tile.is_empty())
+            .map(|(i, tile)| (i % layer.width, i / layer.width, *tile))
+            .collect()
+    }
+
+    /// a 3x2 tmx map with one layer whose `<data>` is `data`
+    fn tmx(data: &str) -> String {
+        format!(
+            r#"<map orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16">
+                 <layer name="ground" width="3" height="2">{}</layer>
+               </map>"#,
+            data
+        )
+    }
+
+    /// the same as `tmx`, as json with `data` and `extra` fields
+    fn tmj(data: &str, extra: &str) -> String {
+        format!(
+            r#"{{"orientation": "orthogonal", "width": 3, "height": 2, "tilewidth": 16, "tileheight": 16,
+                 "layers": [{{"type": "tilelayer", "name": "ground", "width": 3, "height": 2, "data": {}{}}}]}}"#,
+            data, extra
+        )
+    }
+
+    const RAW: [u32; 6] = [
+        1, 0, 2 | FLIPPED_HORIZONTALLY_FLAG,
+        3 | FLIPPED_VERTICALLY_FLAG, 4 | FLIPPED_DIAGONALLY_FLAG, 5 | FLIPPED_HORIZONTALLY_FLAG | FLIPPED_VERTICALLY_FLAG,
+    ];
+
+    fn raw_bytes() -> Vec<u8> {
+        RAW.iter().flat_map(|raw| raw.to_le_bytes().to_vec()).collect()
+    }
+
+    fn zlib(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn tmx_asset() {
+        let map = load(asset("iso_map.tmx")).unwrap();
+        assert_eq!((map.orientation, map.width, map.height, map.tile_width, map.tile_height), (Orientation::Isometric, 12, 10, 64, 32));
+        // from iso_tiles.tsx
+        assert_eq!(map.tilesets, [Tileset {
+            first_gid: 1,
+            name: "iso_tiles".into(),
+            source: Some("iso_tiles.tsx".into()),
+            tile_width: 64,
+            tile_height: 32,
+            tile_count: 8,
+            columns: 4,
+            image: Some("iso_tiles.png".into()),
+        }]);
+
+        let ground = map.layer("ground").unwrap();
+        assert_eq!((ground.width, ground.height, ground.tiles.len()), (12, 10, 120));
+        assert!(ground.tiles[..12].iter().all(|tile| *tile == Tile::new(3)));
+        assert_eq!(ground.tiles[12..24].iter().map(|tile| tile.gid).collect::<Vec<_>>(), [3, 1, 1, 1, 2, 1, 1, 1, 1, 2, 1, 3]);
+
+        // zlib compressed, inside of a group
+        let decoration = map.layer("decoration").unwrap();
+        assert_eq!(decoration.opacity, 0.8);
+        assert_eq!(tiles(decoration), [
+            (8, 2, Tile { flip_horizontal: true, ..Tile::new(4) }),
+            (3, 3, Tile::new(5)),
+            (4, 3, Tile::new(5)),
+            (8, 6, Tile::new(5)),
+        ]);
+
+        let spawns = &map.object_layers[0];
+        assert_eq!(spawns.name, "spawns");
+        let objects: Vec<_> = spawns.objects.iter().map(|o| (o.id, o.name.as_str(), o.kind.as_str(), o.x, o.y, o.width, o.height)).collect();
+        assert_eq!(objects, [(1, "player", "spawn", 64.0, 64.0, 0.0, 0.0), (2, "chest", "item", 160.0, 96.0, 32.0, 32.0)]);
+    }
+
+    #[test]
+    fn tmj_asset() {
+        let map = load(asset("ortho_map.tmj")).unwrap();
+        assert_eq!((map.orientation, map.width, map.height, map.tile_width, map.tile_height), (Orientation::Orthogonal, 16, 12, 32, 32));
+        assert_eq!(map.tilesets.len(), 1);
+        assert_eq!((map.tilesets[0].name.as_str(), map.tilesets[0].tile_count, map.tilesets[0].columns), ("ortho_tiles", 4, 2));
+
+        // base64
+        let ground = map.layer("ground").unwrap();
+        assert_eq!(ground.tiles.len(), 16 * 12);
+        assert!(ground.tiles[..16].iter().all(|tile| *tile == Tile::new(1)));
+        assert_eq!(ground.tiles[16..32].iter().map(|tile| tile.gid).collect::<Vec<_>>(), [1, 3, 3, 3, 3, 3, 3, 2, 3, 3, 3, 3, 3, 3, 2, 1]);
+
+        // an array of ids
+        let walls = map.layer("walls").unwrap();
+        assert_eq!(tiles(walls), (2..9).map(|y| (5, y, Tile::new(4))).collect::<Vec<_>>());
+
+        let objects = &map.object_layers[0].objects;
+        assert_eq!(objects.len(), 1);
+        assert_eq!((objects[0].name.as_str(), objects[0].kind.as_str(), objects[0].x, objects[0].y), ("door", "trigger", 160.0, 96.0));
+    }
+
+    #[test]
+    fn every_encoding_gives_the_same_tiles() {
+        let csv = RAW.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+        let xml: String = RAW.iter().map(|raw| format!(r#"<tile gid="{}"/>"#, raw)).collect();
+        let maps = [
+            parse_tmx(&tmx(&format!(r#"<data encoding="csv">{}</data>"#, csv))).unwrap(),
+            parse_tmx(&tmx(&format!("<data>{}</data>", xml))).unwrap(),
+            parse_tmx(&tmx(&format!(r#"<data encoding="base64">{}</data>"#, base64::encode(raw_bytes())))).unwrap(),
+            parse_tmx(&tmx(&format!(r#"<data encoding="base64" compression="zlib">{}</data>"#, base64::encode(zlib(&raw_bytes()))))).unwrap(),
+            parse_tmx(&tmx(&format!(r#"<data encoding="base64" compression="gzip">{}</data>"#, base64::encode(gzip(&raw_bytes()))))).unwrap(),
+            parse_tmj(&tmj(&format!("[{}]", csv), "")).unwrap(),
+            parse_tmj(&tmj(&format!(r#""{}""#, base64::encode(raw_bytes())), r#", "encoding": "base64""#)).unwrap(),
+            parse_tmj(&tmj(&format!(r#""{}""#, base64::encode(zlib(&raw_bytes()))), r#", "compression": "zlib""#)).unwrap(),
+        ];
+        let expected: Vec<Tile> = RAW.iter().map(|raw| Tile::from_raw(*raw)).collect();
+        for map in maps.iter() {
+            assert_eq!(map.layers[0].tiles, expected);
+        }
+    }
+
+    #[test]
+    fn flip_flags() {
+        let tiles: Vec<Tile> = RAW.iter().map(|raw| Tile::from_raw(*raw)).collect();
+        assert_eq!(tiles[2], Tile { flip_horizontal: true, ..Tile::new(2) });
+        assert_eq!(tiles[3], Tile { flip_vertical: true, ..Tile::new(3) });
+        assert_eq!(tiles[4], Tile { flip_diagonal: true, ..Tile::new(4) });
+        assert_eq!(tiles[5], Tile { flip_horizontal: true, flip_vertical: true, ..Tile::new(5) });
+        for (tile, raw) in tiles.iter().zip(RAW.iter()) {
+            assert_eq!(tile.to_raw(), *raw);
+        }
+    }
+
+    #[test]
+    fn bad_data_is_an_error() {
+        let bad_tmx = [
+            // too few and too many tiles
+            r#"<data encoding="csv">1,2,3,4,5</data>"#.to_owned(),
+            r#"<data encoding="csv">1,2,3,4,5,6,7</data>"#.to_owned(),
+            r#"<data encoding="csv">1,2,x,4,5,6</data>"#.to_owned(),
+            r#"<data encoding="base64">not base64!</data>"#.to_owned(),
+            // 5 bytes isn't a whole number of tiles
+            format!(r#"<data encoding="base64">{}</data>"#, base64::encode([1, 2, 3, 4, 5])),
+            format!(r#"<data encoding="base64" compression="zlib">{}</data>"#, base64::encode(raw_bytes())),
+            format!(r#"<data encoding="base64" compression="zstd">{}</data>"#, base64::encode(raw_bytes())),
+            r#"<data encoding="hex">00</data>"#.to_owned(),
+        ];
+        for data in bad_tmx.iter() {
+            assert!(parse_tmx(&tmx(data)).is_err(), "{}", data);
+        }
+        assert!(parse_tmj(&tmj("[1, 2, -3, 4, 5, 6]", "")).is_err());
+        assert!(parse_tmj(&tmj("[1, 2, 3]", "")).is_err());
+
+        // a size so big that width * height overflows
+        let huge = format!(
+            r#"<map width="3" height="2" tilewidth="16" tileheight="16">
+                 <layer width="{}" height="{}"><data encoding="csv">1,2</data></layer>
+               </map>"#,
+            usize::MAX / 2 + 1, 2
+        );
+        assert!(matches!(parse_tmx(&huge), Err(TiledError::Invalid(_))));
+        assert!(matches!(tiles_from_raw(vec![0; 2], usize::MAX, usize::MAX), Err(TiledError::Invalid(_))));
+    }
+}
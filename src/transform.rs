@@ -0,0 +1,213 @@
+use std::ops::Mul;
+
+/// A 2D affine transformation. A point is transformed like:
+///
+/// ```text
+/// | x' |   | a  c  tx |   | x |
+/// | y' | = | b  d  ty | * | y |
+/// | 1  |   | 0  0  1  |   | 1 |
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 };
+
+    pub fn new(a: f32, b: f32, c: f32, d: f32, tx: f32, ty: f32) -> Transform {
+        Transform { a, b, c, d, tx, ty }
+    }
+
+    pub fn translate(x: f32, y: f32) -> Transform {
+        Transform { tx: x, ty: y, ..Transform::IDENTITY }
+    }
+
+    pub fn scale(x: f32, y: f32) -> Transform {
+        Transform { a: x, d: y, ..Transform::IDENTITY }
+    }
+
+    /// rotates counter clockwise by `radians`. since screen space
+    /// has y pointing down, this appears clockwise on screen.
+    pub fn rotate(radians: f32) -> Transform {
+        let (sin, cos) = radians.sin_cos();
+        Transform { a: cos, b: sin, c: -sin, d: cos, tx: 0.0, ty: 0.0 }
+    }
+
+    pub fn shear(x: f32, y: f32) -> Transform {
+        Transform { c: x, b: y, ..Transform::IDENTITY }
+    }
+
+    /// rotates by `radians` around the point (x, y) instead of the origin
+    pub fn rotate_around(radians: f32, x: f32, y: f32) -> Transform {
+        Transform::translate(-x, -y)
+            .then(Transform::rotate(radians))
+            .then(Transform::translate(x, y))
+    }
+
+    /// the projection used by the isometric examples: moving one
+    /// cell along x goes half a tile right and down, and one cell along y
+    /// goes half a tile left and down. (origin_x, origin_y) is where
+    /// the top corner of cell (0, 0) ends up.
+    pub fn isometric(tile_width: f32, tile_height: f32, origin_x: f32, origin_y: f32) -> Transform {
+        let half_w = tile_width / 2.0;
+        let half_h = tile_height / 2.0;
+        Transform { a: half_w, b: half_h, c: -half_w, d: half_h, tx: origin_x, ty: origin_y }
+    }
+
+    /// returns a transform that applies `self` first and then `next`
+    pub fn then(&self, next: Transform) -> Transform {
+        Transform {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            tx: next.a * self.tx + next.c * self.ty + next.tx,
+            ty: next.b * self.tx + next.d * self.ty + next.ty,
+        }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// returns None if the transform collapses space (ie: a scale of 0)
+    /// and therefore cannot be undone.
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.determinant();
+        // compared to the size of the matrix, so that a tiny scale (a map
+        // zoomed far out) can still be undone, but two axes that are only
+        // parallel because of rounding can't
+        let size = (self.a.abs() + self.b.abs()) * (self.c.abs() + self.d.abs());
+        if !det.is_finite() || det.abs() <= size * f32::EPSILON {
+            return None;
+        }
+        let inv = 1.0 / det;
+        let a = self.d * inv;
+        let b = -self.b * inv;
+        let c = -self.c * inv;
+        let d = self.a * inv;
+        Some(Transform {
+            a,
+            b,
+            c,
+            d,
+            tx: -(a * self.tx + c * self.ty),
+            ty: -(b * self.tx + d * self.ty),
+        })
+    }
+
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.c * y + self.tx,
+            self.b * x + self.d * y + self.ty,
+        )
+    }
+
+    /// same as apply, but ignores the translation. useful for
+    /// transforming directions and distances instead of positions.
+    pub fn apply_vector(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.c * y,
+            self.b * x + self.d * y,
+        )
+    }
+
+    /// true if horizontal lines stay horizontal, and vertical
+    /// lines stay vertical after this transform.
+    pub fn is_axis_aligned(&self) -> bool {
+        self.b == 0.0 && self.c == 0.0
+    }
+}
+
+/// `a * b` follows the usual matrix convention: `b` is applied first, then `a`.
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        rhs.then(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        let tolerance = 1e-4 * (1.0 + b.0.abs().max(b.1.abs()));
+        (a.0 - b.0).abs() < tolerance && (a.1 - b.1).abs() < tolerance
+    }
+
+    fn is_identity(t: &Transform) -> bool {
+        close((t.a, t.b), (1.0, 0.0)) && close((t.c, t.d), (0.0, 1.0)) && close((t.tx, t.ty), (0.0, 0.0))
+    }
+
+    #[test]
+    fn then_applies_self_first() {
+        let move_then_scale = Transform::translate(1.0, 2.0).then(Transform::scale(10.0, 100.0));
+        assert_eq!(move_then_scale.apply(0.0, 0.0), (10.0, 200.0));
+        let scale_then_move = Transform::scale(10.0, 100.0).then(Transform::translate(1.0, 2.0));
+        assert_eq!(scale_then_move.apply(0.0, 0.0), (1.0, 2.0));
+        assert_eq!(scale_then_move.apply(1.0, 1.0), (11.0, 102.0));
+        // and `*` the other way around
+        assert_eq!(Transform::scale(10.0, 100.0) * Transform::translate(1.0, 2.0), move_then_scale);
+        assert!(close(Transform::rotate_around(std::f32::consts::PI, 5.0, 5.0).apply(4.0, 5.0), (6.0, 5.0)));
+    }
+
+    #[test]
+    fn inverse_undoes() {
+        let transforms = [
+            Transform::IDENTITY,
+            Transform::translate(-30.0, 12.5),
+            Transform::rotate(0.7).then(Transform::scale(3.0, 0.5)),
+            Transform::isometric(64.0, 32.0, 400.0, 20.0),
+            Transform::shear(0.5, 0.0).then(Transform::translate(3.0, 4.0)),
+            // zoomed very far out, and very far in
+            Transform::isometric(64.0, 32.0, 400.0, 20.0).then(Transform::scale(1e-5, 1e-5)),
+            Transform::scale(1e-4, 2e-4).then(Transform::translate(-5.0, 7.0)),
+            Transform::scale(1e5, 1e5),
+        ];
+        for t in transforms.iter() {
+            let inverse = t.inverse().unwrap_or_else(|| panic!("{:?} has no inverse", t));
+            assert!(is_identity(&inverse.then(*t)), "{:?}", inverse.then(*t));
+            assert!(is_identity(&t.then(inverse)), "{:?}", t.then(inverse));
+            let (x, y) = t.apply(3.0, -7.0);
+            assert!(close(inverse.apply(x, y), (3.0, -7.0)));
+        }
+    }
+
+    #[test]
+    fn collapsed_space_has_no_inverse() {
+        assert_eq!(Transform::scale(0.0, 1.0).inverse(), None);
+        assert_eq!(Transform::scale(1e-4, 0.0).inverse(), None);
+        // both axes go the same way
+        assert_eq!(Transform::new(1.0, 2.0, 2.0, 4.0, 0.0, 0.0).inverse(), None);
+        assert_eq!(Transform::new(1e-3, 2e-3, 2e-3, 4e-3, 0.0, 0.0).inverse(), None);
+        assert_eq!(Transform::scale(f32::INFINITY, 1.0).inverse(), None);
+        assert_eq!(Transform::scale(f32::NAN, 1.0).inverse(), None);
+    }
+
+    #[test]
+    fn isometric_cell_corners() {
+        let t = Transform::isometric(64.0, 32.0, 100.0, 50.0);
+        // the top, right, bottom and left corners of cell (0, 0)
+        assert_eq!(t.apply(0.0, 0.0), (100.0, 50.0));
+        assert_eq!(t.apply(1.0, 0.0), (132.0, 66.0));
+        assert_eq!(t.apply(1.0, 1.0), (100.0, 82.0));
+        assert_eq!(t.apply(0.0, 1.0), (68.0, 66.0));
+        // and of cell (2, 1)
+        assert_eq!(t.apply(2.0, 1.0), (132.0, 98.0));
+        assert_eq!(t.apply_vector(1.0, 1.0), (0.0, 32.0));
+    }
+}
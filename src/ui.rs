@@ -0,0 +1,569 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::backend::{Event, MouseButton};
+use crate::draw::Draw;
+use crate::font;
+use crate::Rgb;
+
+/// a rectangle in screen pixels
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// the part of both rectangles that overlaps. width or height is 0 if they don't.
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Rect::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+    }
+
+    /// shrinks the rectangle by `amount` on every side
+    pub fn shrink(&self, amount: f32) -> Rect {
+        Rect::new(
+            self.x + amount,
+            self.y + amount,
+            (self.width - 2.0 * amount).max(0.0),
+            (self.height - 2.0 * amount).max(0.0),
+        )
+    }
+}
+
+/// colors and sizes of the widgets
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Style {
+    pub panel: Rgb,
+    pub title_bar: Rgb,
+    pub widget: Rgb,
+    pub widget_hovered: Rgb,
+    pub widget_active: Rgb,
+    pub accent: Rgb,
+    pub text: Rgb,
+    pub text_scale: usize,
+    /// height of a row of widgets
+    pub row_height: f32,
+    /// space between the edge of a panel and its widgets
+    pub padding: f32,
+    /// space between widgets
+    pub spacing: f32,
+    /// how wide widgets are when they aren't inside of a panel
+    pub default_width: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            panel: Rgb { red: 45, green: 45, blue: 50 },
+            title_bar: Rgb { red: 30, green: 30, blue: 35 },
+            widget: Rgb { red: 70, green: 70, blue: 80 },
+            widget_hovered: Rgb { red: 90, green: 90, blue: 105 },
+            widget_active: Rgb { red: 110, green: 110, blue: 130 },
+            accent: Rgb { red: 80, green: 150, blue: 230 },
+            text: Rgb { red: 230, green: 230, blue: 230 },
+            text_scale: 1,
+            row_height: 20.0,
+            padding: 6.0,
+            spacing: 4.0,
+            default_width: 200.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Rect { rect: Rect, color: Rgb },
+    Text { x: f32, y: f32, text: String, color: Rgb, clip: Rect },
+}
+
+/// where the next widget goes, inside of the screen, a panel, or a scroll area
+#[derive(Debug, Clone)]
+struct Frame {
+    rect: Rect,
+    clip: Rect,
+    cursor_x: f32,
+    cursor_y: f32,
+    /// widgets are placed left to right instead of top to bottom
+    row: bool,
+    row_height: f32,
+    /// for panels, the command to replace with the panel's
+    /// background once its height is known
+    background: Option<usize>,
+    /// for scroll areas, the id that its offset is kept under
+    scroll_id: Option<u64>,
+}
+
+/// A small immediate-mode ui. there is no tree of widgets that has to be
+/// kept in sync with the game: every frame the widgets are declared again
+/// between `begin` and `end`, and each one returns what happened to it.
+///
+/// widgets don't draw anything right away. they are recorded, and drawn
+/// onto a canvas with `render`, so they can be declared while the canvas
+/// is borrowed by something else, and scroll areas can clip their contents.
+///
+/// ```text
+/// // in update():
+/// if ui.handle_event(&event) { continue; }
+/// // in draw():
+/// ui.begin(width, height);
+/// ui.begin_panel("tools", 10.0, 10.0, 160.0);
+/// if ui.button("reset") { ... }
+/// ui.checkbox("show grid", &mut show_grid);
+/// ui.end_panel();
+/// ui.end();
+/// ui.render(&mut canvas);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Ui {
+    pub style: Style,
+    mouse_x: f32,
+    mouse_y: f32,
+    mouse_down: bool,
+    /// the mouse went down or up since the last frame
+    pressed: bool,
+    pressed_at: (f32, f32),
+    released: bool,
+    /// scroll steps since the last frame, positive is up
+    scroll: f32,
+
+    /// the widget the mouse was pressed on, until it is released
+    active: Option<u64>,
+    /// the left button was pressed over the ui, so the ui gets
+    /// everything until it is released
+    captured: bool,
+    frames: Vec<Frame>,
+    id_stack: Vec<u64>,
+    commands: Vec<Command>,
+    /// panels from the last frame, to know which mouse events are for the ui
+    panels: Vec<Rect>,
+    current_panels: Vec<Rect>,
+    /// scroll areas from the last frame, to know which scrolling is for the ui
+    scroll_areas: Vec<Rect>,
+    current_scroll_areas: Vec<Rect>,
+    scroll_offsets: HashMap<u64, f32>,
+}
+
+impl Ui {
+    pub fn new() -> Ui {
+        Ui::default()
+    }
+
+    /// feeds an event to the ui. returns true if the ui used it, in which
+    /// case the game should ignore it (eg: don't pan when dragging a slider).
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::MouseMove { x, y } => {
+                self.mouse_x = x;
+                self.mouse_y = y;
+                self.captured
+            }
+            Event::MouseDown { button: MouseButton::Left, x, y } => {
+                self.mouse_x = x;
+                self.mouse_y = y;
+                self.mouse_down = true;
+                self.pressed = true;
+                self.pressed_at = (x, y);
+                self.captured = self.is_over_ui(x, y);
+                self.captured
+            }
+            Event::MouseUp { button: MouseButton::Left, x, y } => {
+                self.mouse_x = x;
+                self.mouse_y = y;
+                self.mouse_down = false;
+                self.released = true;
+                // a drag that started outside of the ui still has to end
+                // outside of it, even if it is released over a panel
+                std::mem::take(&mut self.captured)
+            }
+            Event::MouseDown { x, y, .. } => self.is_over_ui(x, y),
+            Event::MouseUp { .. } => false,
+            Event::MouseScroll { up } => {
+                let (x, y) = (self.mouse_x, self.mouse_y);
+                if !self.is_over_ui(x, y) && !self.scroll_areas.iter().any(|r| r.contains(x, y)) {
+                    return false;
+                }
+                self.scroll += if up { 1.0 } else { -1.0 };
+                true
+            }
+            Event::KeyDown { .. } | Event::KeyUp { .. } => false,
+        }
+    }
+
+    /// true if a position is over one of last frame's panels
+    pub fn is_over_ui(&self, x: f32, y: f32) -> bool {
+        self.panels.iter().any(|r| r.contains(x, y))
+    }
+
+    /// true while a widget is being dragged or pressed
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn begin(&mut self, screen_width: usize, screen_height: usize) {
+        self.commands.clear();
+        self.current_panels.clear();
+        self.current_scroll_areas.clear();
+        self.id_stack.clear();
+        let screen = Rect::new(0.0, 0.0, screen_width as f32, screen_height as f32);
+        let padding = self.style.padding;
+        self.frames = vec![Frame {
+            rect: Rect::new(padding, padding, self.style.default_width, screen.height),
+            clip: screen,
+            cursor_x: padding,
+            cursor_y: padding,
+            row: false,
+            row_height: 0.0,
+            background: None,
+            scroll_id: None,
+        }];
+    }
+
+    pub fn end(&mut self) {
+        self.panels = std::mem::take(&mut self.current_panels);
+        self.scroll_areas = std::mem::take(&mut self.current_scroll_areas);
+        if self.released || !self.mouse_down {
+            self.active = None;
+        }
+        self.pressed = false;
+        self.released = false;
+        self.scroll = 0.0;
+    }
+
+    /// draws everything declared since `begin`, in screen coordinates
+    pub fn render<D: Draw>(&self, canvas: &mut D) {
+        for command in self.commands.iter() {
+            match command {
+                Command::Rect { rect, color } => {
+                    if rect.width <= 0.0 || rect.height <= 0.0 {
+                        continue;
+                    }
+                    let (x, y) = (rect.x, rect.y);
+                    let (right, bottom) = (x + rect.width, y + rect.height);
+                    canvas.fill_convex_polygon_f32(&[(x, y), (right, y), (right, bottom), (x, bottom)], *color);
+                }
+                Command::Text { x, y, text, color, clip } => {
+                    font::text_pixels(text, *x as i32, *y as i32, self.style.text_scale, |px, py| {
+                        if px >= 0 && py >= 0 && clip.contains(px as f32 + 0.5, py as f32 + 0.5) {
+                            canvas.set_pixel(px as usize, py as usize, *color);
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn id(&self, label: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.id_stack.hash(&mut hasher);
+        label.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// reserves space for the next widget. `width` is only used in rows,
+    /// otherwise widgets are as wide as the panel.
+    fn allocate(&mut self, width: f32, height: f32) -> Rect {
+        let spacing = self.style.spacing;
+        let frame = self.frame();
+        if frame.row {
+            let rect = Rect::new(frame.cursor_x, frame.cursor_y, width, height);
+            frame.cursor_x += width + spacing;
+            frame.row_height = frame.row_height.max(height);
+            rect
+        } else {
+            let rect = Rect::new(frame.rect.x, frame.cursor_y, frame.rect.width, height);
+            frame.cursor_y += height + spacing;
+            rect
+        }
+    }
+
+    fn clip(&self) -> Rect {
+        self.frames.last().unwrap().clip
+    }
+
+    fn is_hovered(&self, rect: &Rect) -> bool {
+        rect.contains(self.mouse_x, self.mouse_y) && self.clip().contains(self.mouse_x, self.mouse_y)
+    }
+
+    /// the common part of clickable widgets: becomes active when pressed,
+    /// and returns (hovered, clicked). a click is a press and release on the
+    /// same widget, so dragging off of a button cancels it.
+    fn interact(&mut self, id: u64, rect: &Rect) -> (bool, bool) {
+        let hovered = self.is_hovered(rect) && (self.active.is_none() || self.active == Some(id));
+        // where the mouse was pressed, since it may have moved since then
+        let (press_x, press_y) = self.pressed_at;
+        if self.pressed && self.active.is_none() && rect.contains(press_x, press_y) && self.clip().contains(press_x, press_y) {
+            self.active = Some(id);
+        }
+        let clicked = hovered && self.released && self.active == Some(id);
+        (hovered, clicked)
+    }
+
+    fn widget_color(&self, id: u64, hovered: bool) -> Rgb {
+        if self.active == Some(id) {
+            self.style.widget_active
+        } else if hovered {
+            self.style.widget_hovered
+        } else {
+            self.style.widget
+        }
+    }
+
+    fn push_rect(&mut self, rect: Rect, color: Rgb) {
+        let rect = rect.intersect(&self.clip());
+        self.commands.push(Command::Rect { rect, color });
+    }
+
+    fn push_text(&mut self, x: f32, y: f32, text: &str, color: Rgb) {
+        let clip = self.clip();
+        self.commands.push(Command::Text { x, y, text: text.to_owned(), color, clip });
+    }
+
+    /// the y to draw text at, to center it in a rect
+    fn text_y(&self, rect: &Rect) -> f32 {
+        rect.y + ((rect.height - font::text_height(self.style.text_scale) as f32) / 2.0).round()
+    }
+
+    fn text_width(&self, text: &str) -> f32 {
+        font::text_width(text, self.style.text_scale) as f32
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let rect = self.allocate(self.text_width(text), self.style.row_height);
+        let y = self.text_y(&rect);
+        self.push_text(rect.x, y, text, self.style.text);
+    }
+
+    /// returns true on the frame the button is clicked
+    pub fn button(&mut self, text: &str) -> bool {
+        let id = self.id(text);
+        let width = self.text_width(text) + 2.0 * self.style.padding;
+        let rect = self.allocate(width, self.style.row_height);
+        let (hovered, clicked) = self.interact(id, &rect);
+        let color = self.widget_color(id, hovered);
+        self.push_rect(rect, color);
+        let x = rect.x + ((rect.width - self.text_width(text)) / 2.0).round();
+        let y = self.text_y(&rect);
+        self.push_text(x, y, text, self.style.text);
+        clicked
+    }
+
+    /// returns true on the frame the value changes
+    pub fn checkbox(&mut self, text: &str, value: &mut bool) -> bool {
+        let id = self.id(text);
+        let size = (self.style.row_height - 6.0).max(6.0);
+        let width = size + self.style.spacing + self.text_width(text);
+        let rect = self.allocate(width, self.style.row_height);
+        let (hovered, clicked) = self.interact(id, &rect);
+        if clicked {
+            *value = !*value;
+        }
+        let the_box = Rect::new(rect.x, rect.y + (rect.height - size) / 2.0, size, size);
+        let color = self.widget_color(id, hovered);
+        self.push_rect(the_box, color);
+        if *value {
+            let accent = self.style.accent;
+            self.push_rect(the_box.shrink(3.0), accent);
+        }
+        let y = self.text_y(&rect);
+        self.push_text(rect.x + size + self.style.spacing, y, text, self.style.text);
+        clicked
+    }
+
+    /// drag or click anywhere along the slider to set the value.
+    /// returns true on the frames the value changes.
+    pub fn slider(&mut self, text: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let id = self.id(text);
+        let rect = self.allocate(self.style.default_width, self.style.row_height);
+        let (hovered, _) = self.interact(id, &rect);
+        let mut changed = false;
+        if self.active == Some(id) && self.mouse_down && rect.width > 0.0 {
+            let t = ((self.mouse_x - rect.x) / rect.width).clamp(0.0, 1.0);
+            let new_value = min + t * (max - min);
+            changed = new_value != *value;
+            *value = new_value;
+        }
+        let color = self.widget_color(id, hovered);
+        self.push_rect(rect, color);
+        let t = if max > min { ((*value - min) / (max - min)).clamp(0.0, 1.0) } else { 0.0 };
+        let filled = Rect::new(rect.x, rect.y, rect.width * t, rect.height);
+        let accent = self.style.accent;
+        self.push_rect(filled, accent);
+        let label = format!("{}: {:.2}", text, value);
+        let x = rect.x + self.style.padding;
+        let y = self.text_y(&rect);
+        self.push_text(x, y, &label, self.style.text);
+        changed
+    }
+
+    /// a thin line across the panel
+    pub fn separator(&mut self) {
+        let rect = self.allocate(self.style.default_width, 1.0);
+        let color = self.style.widget;
+        self.push_rect(rect, color);
+    }
+
+    /// places the widgets up to `end_row` next to each other
+    pub fn begin_row(&mut self) {
+        let frame = self.frame();
+        frame.row = true;
+        frame.cursor_x = frame.rect.x;
+        frame.row_height = 0.0;
+    }
+
+    pub fn end_row(&mut self) {
+        let spacing = self.style.spacing;
+        let frame = self.frame();
+        frame.row = false;
+        frame.cursor_y += frame.row_height + spacing;
+    }
+
+    /// starts a panel with a title bar at a position on screen. it grows
+    /// to fit what is put in it, and doesn't take up any space in the
+    /// layout it is in. mouse events over a panel are used by
+    /// the ui (see `handle_event`), so clicks don't go through it.
+    pub fn begin_panel(&mut self, title: &str, x: f32, y: f32, width: f32) {
+        let padding = self.style.padding;
+        let title_height = self.style.row_height;
+        let clip = self.clip();
+        // the background is drawn first, but its size is only known in end_panel
+        let background = self.commands.len();
+        self.commands.push(Command::Rect { rect: Rect::default(), color: self.style.panel });
+        let title_rect = Rect::new(x, y, width, title_height);
+        self.push_rect(title_rect, self.style.title_bar);
+        let text_y = self.text_y(&title_rect);
+        self.push_text(x + padding, text_y, title, self.style.text);
+        self.id_stack.push(self.id(title));
+        let content = Rect::new(x + padding, y + title_height + padding, width - 2.0 * padding, 0.0);
+        self.frames.push(Frame {
+            rect: content,
+            clip: Rect::new(x, y, width, clip.y + clip.height - y).intersect(&clip),
+            cursor_x: content.x,
+            cursor_y: content.y,
+            row: false,
+            row_height: 0.0,
+            background: Some(background),
+            scroll_id: None,
+        });
+    }
+
+    pub fn end_panel(&mut self) {
+        let frame = match self.frames.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        self.id_stack.pop();
+        let padding = self.style.padding;
+        // the last widget added spacing after itself
+        let bottom = frame.cursor_y - self.style.spacing + padding;
+        let x = frame.rect.x - padding;
+        let y = frame.rect.y - padding - self.style.row_height;
+        let rect = Rect::new(x, y, frame.rect.width + 2.0 * padding, bottom - y);
+        if let Some(index) = frame.background {
+            self.commands[index] = Command::Rect { rect: rect.intersect(&frame.clip), color: self.style.panel };
+        }
+        self.current_panels.push(rect);
+    }
+
+    /// starts an area `height` pixels tall that scrolls with the mouse wheel
+    /// when there is more in it than fits. the wheel over it is used by the
+    /// ui, even when it isn't in a panel.
+    pub fn begin_scroll_area(&mut self, label: &str, height: f32) {
+        let id = self.id(label);
+        let rect = self.allocate(self.style.default_width, height);
+        let offset = *self.scroll_offsets.get(&id).unwrap_or(&0.0);
+        let bar_width = self.style.padding;
+        let clip = rect.intersect(&self.clip());
+        self.id_stack.push(id);
+        let content = Rect::new(rect.x, rect.y, rect.width - bar_width - self.style.spacing, height);
+        self.frames.push(Frame {
+            rect: content,
+            clip,
+            cursor_x: content.x,
+            cursor_y: content.y - offset,
+            row: false,
+            row_height: 0.0,
+            background: None,
+            scroll_id: Some(id),
+        });
+    }
+
+    pub fn end_scroll_area(&mut self) {
+        let frame = match self.frames.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        self.id_stack.pop();
+        let id = match frame.scroll_id {
+            Some(id) => id,
+            None => return,
+        };
+        self.current_scroll_areas.push(frame.clip);
+        let offset = *self.scroll_offsets.get(&id).unwrap_or(&0.0);
+        let content_height = frame.cursor_y + offset - frame.rect.y - self.style.spacing;
+        let visible = frame.rect.height;
+        let max_offset = (content_height - visible).max(0.0);
+        let mut offset = offset;
+        if frame.clip.contains(self.mouse_x, self.mouse_y) && self.scroll != 0.0 {
+            offset -= self.scroll * self.style.row_height;
+        }
+        let offset = offset.max(0.0).min(max_offset);
+        self.scroll_offsets.insert(id, offset);
+        if max_offset > 0.0 {
+            // the scroll bar, with a handle as tall as the part that is visible
+            let bar = Rect::new(frame.rect.x + frame.rect.width + self.style.spacing, frame.rect.y, self.style.padding, visible);
+            let handle_height = (visible * visible / content_height).max(8.0);
+            let handle_y = bar.y + (visible - handle_height) * offset / max_offset;
+            let (widget, accent) = (self.style.widget, self.style.accent);
+            self.push_rect(bar, widget);
+            self.push_rect(Rect::new(bar.x, handle_y, bar.width, handle_height), accent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// two buttons side by side with no space between them, and which
+    /// of them were clicked
+    fn buttons(ui: &mut Ui) -> (bool, bool) {
+        ui.begin(200, 100);
+        ui.begin_row();
+        let clicked = (ui.button("left"), ui.button("right"));
+        ui.end_row();
+        ui.end();
+        clicked
+    }
+
+    #[test]
+    fn click_on_a_shared_edge_hits_one_button() {
+        let mut ui = Ui::new();
+        ui.style.spacing = 0.0;
+        buttons(&mut ui);
+        let edge = ui.style.padding + ui.text_width("left") + 2.0 * ui.style.padding;
+        let y = ui.style.padding + 1.0;
+        ui.handle_event(&Event::MouseDown { button: MouseButton::Left, x: edge, y });
+        buttons(&mut ui);
+        ui.handle_event(&Event::MouseUp { button: MouseButton::Left, x: edge, y });
+        assert_eq!(buttons(&mut ui), (false, true));
+    }
+}
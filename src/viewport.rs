@@ -0,0 +1,213 @@
+use crate::backend::Event;
+use crate::draw::Draw;
+use crate::world_screen::WorldScreen;
+use crate::{Canvas, Rgb};
+
+/// a rectangle in screen pixels
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct ScreenRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl ScreenRect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> ScreenRect {
+        ScreenRect { x, y, width, height }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x as f32 && y >= self.y as f32
+            && x < (self.x + self.width) as f32
+            && y < (self.y + self.height) as f32
+    }
+}
+
+/// A camera looking at the world, that renders into a sub rectangle of
+/// the output canvas. Each viewport has its own `WorldScreen` (and therefore
+/// its own pan/zoom) whose canvas is the size of the viewport, so anything
+/// drawn outside of the viewport is clipped automatically.
+pub struct Viewport {
+    pub name: String,
+    pub rect: ScreenRect,
+    pub screen: WorldScreen,
+    pub border: Option<Rgb>,
+    pub visible: bool,
+}
+
+impl Viewport {
+    pub fn new(name: &str, rect: ScreenRect, bpp: usize) -> Viewport {
+        let mut viewport = Viewport {
+            name: name.to_owned(),
+            rect,
+            screen: WorldScreen::default(),
+            border: None,
+            visible: true,
+        };
+        viewport.screen.scale_x = 1.0;
+        viewport.screen.scale_y = 1.0;
+        viewport.screen.scale_factor_up = 1.040;
+        viewport.screen.scale_factor_down = 0.960;
+        viewport.resize(rect, bpp);
+        viewport
+    }
+
+    /// moves/resizes the viewport. the pan and zoom are kept.
+    pub fn resize(&mut self, rect: ScreenRect, bpp: usize) {
+        self.rect = rect;
+        self.screen.canvas = Canvas::new(rect.width, rect.height, bpp);
+        self.screen.screen_width = rect.width;
+        self.screen.screen_height = rect.height;
+    }
+
+    /// converts a position on the output canvas to a
+    /// position within this viewport
+    pub fn to_local(&self, x: f32, y: f32) -> (f32, f32) {
+        (x - self.rect.x as f32, y - self.rect.y as f32)
+    }
+
+    /// converts a position on the output canvas to the world
+    /// coordinate under it, as seen by this viewport's camera
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        let (local_x, local_y) = self.to_local(x, y);
+        self.screen.screen_to_world(local_x, local_y)
+    }
+
+    fn draw_border(&self, target: &mut Canvas, color: Rgb) {
+        let ScreenRect { x, y, width, height } = self.rect;
+        if width == 0 || height == 0 || x >= target.width || y >= target.height {
+            return;
+        }
+        let right = (x + width - 1).min(target.width - 1);
+        let bottom = (y + height - 1).min(target.height - 1);
+        target.draw_horizontal_line(y, x, right + 1, color);
+        target.draw_horizontal_line(bottom, x, right + 1, color);
+        target.draw_vertical_line(x, y, bottom + 1, color);
+        target.draw_vertical_line(right, y, bottom + 1, color);
+    }
+}
+
+/// an event that was routed to a viewport. mouse positions in `event`
+/// are relative to the top left of the viewport, and `world` is the
+/// world coordinate under the mouse as seen by that viewport.
+#[derive(Debug, Copy, Clone)]
+pub struct RoutedEvent {
+    pub viewport: usize,
+    pub event: Event,
+    pub world: Option<(f32, f32)>,
+}
+
+/// A set of viewports that are composited, in order, into one canvas.
+/// Later viewports are drawn on top of earlier ones (eg: a minimap should be
+/// added after the main view).
+#[derive(Default)]
+pub struct Viewports {
+    pub viewports: Vec<Viewport>,
+    /// the viewport that receives keyboard events. set to whichever
+    /// viewport was last clicked.
+    pub focused: Option<usize>,
+    // while a mouse button is held, all mouse events go to the viewport
+    // where the button was pressed, even if the mouse leaves it.
+    captured: Option<usize>,
+    last_mouse: (f32, f32),
+}
+
+impl Viewports {
+    pub fn new() -> Viewports {
+        Viewports::default()
+    }
+
+    /// returns the index of the new viewport
+    pub fn add(&mut self, viewport: Viewport) -> usize {
+        self.viewports.push(viewport);
+        self.viewports.len() - 1
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Viewport> {
+        self.viewports.iter().find(|v| v.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Viewport> {
+        self.viewports.iter_mut().find(|v| v.name == name)
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.viewports.iter().position(|v| v.name == name)
+    }
+
+    /// the top most visible viewport under the screen position
+    pub fn viewport_at(&self, x: f32, y: f32) -> Option<usize> {
+        self.viewports.iter()
+            .enumerate()
+            .rev()
+            .find(|(_, v)| v.visible && v.rect.contains(x, y))
+            .map(|(i, _)| i)
+    }
+
+    /// figures out which viewport an event belongs to, and converts its
+    /// mouse coordinates to that viewport. returns None if there was no
+    /// viewport to send it to.
+    pub fn route_event(&mut self, event: Event) -> Option<RoutedEvent> {
+        let target = match event {
+            Event::MouseDown { x, y, .. } => {
+                self.last_mouse = (x, y);
+                let target = self.viewport_at(x, y);
+                if self.captured.is_none() {
+                    self.captured = target;
+                }
+                self.focused = target;
+                self.captured
+            }
+            Event::MouseUp { x, y, .. } => {
+                self.last_mouse = (x, y);
+                let target = self.captured.or_else(|| self.viewport_at(x, y));
+                self.captured = None;
+                target
+            }
+            Event::MouseMove { x, y } => {
+                self.last_mouse = (x, y);
+                self.captured.or_else(|| self.viewport_at(x, y))
+            }
+            Event::MouseScroll { .. } => {
+                let (x, y) = self.last_mouse;
+                self.viewport_at(x, y)
+            }
+            Event::KeyDown { .. } | Event::KeyUp { .. } => self.focused,
+        }?;
+
+        let viewport = &self.viewports[target];
+        let to_local = |x: f32, y: f32| viewport.to_local(x, y);
+        let (event, local_mouse) = match event {
+            Event::MouseDown { button, x, y } => {
+                let (x, y) = to_local(x, y);
+                (Event::MouseDown { button, x, y }, Some((x, y)))
+            }
+            Event::MouseUp { button, x, y } => {
+                let (x, y) = to_local(x, y);
+                (Event::MouseUp { button, x, y }, Some((x, y)))
+            }
+            Event::MouseMove { x, y } => {
+                let (x, y) = to_local(x, y);
+                (Event::MouseMove { x, y }, Some((x, y)))
+            }
+            Event::MouseScroll { .. } => {
+                (event, Some(to_local(self.last_mouse.0, self.last_mouse.1)))
+            }
+            other => (other, None),
+        };
+        let world = local_mouse.map(|(x, y)| viewport.screen.screen_to_world(x, y));
+        Some(RoutedEvent { viewport: target, event, world })
+    }
+
+    /// copies every visible viewport into the target canvas,
+    /// and draws the borders of viewports that have one.
+    pub fn composite(&self, target: &mut Canvas) {
+        for viewport in self.viewports.iter().filter(|v| v.visible) {
+            target.blit(&viewport.screen.canvas, viewport.rect.x, viewport.rect.y);
+            if let Some(color) = viewport.border {
+                viewport.draw_border(target, color);
+            }
+        }
+    }
+}
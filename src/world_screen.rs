@@ -1,9 +1,113 @@
 use crate::draw::Draw;
 use crate::Canvas;
+use crate::pixel_format::{PixelFormat, Rgbx};
+use crate::transform::Transform;
+use crate::raster::Interpolate;
+use std::ops::Range;
+
+/// an axis aligned rectangle in world coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WorldRect {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+/// how wide lines drawn through the WorldScreen are.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StrokeWidth {
+    /// a constant width in screen pixels, no matter the zoom
+    Screen(f32),
+    /// a width in world units. lines get thicker when zooming in
+    /// and thinner when zooming out, but never thinner than 1 pixel.
+    World(f32),
+}
+
+impl Default for StrokeWidth {
+    fn default() -> Self {
+        StrokeWidth::Screen(1.0)
+    }
+}
+
+/// coarse description of how zoomed out the camera is, so that
+/// maps can skip details that would just turn into noise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DetailLevel {
+    Minimal,
+    Simplified,
+    Full,
+}
+
+/// the scales below which the detail level drops.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LodThresholds {
+    pub simplified_below: f32,
+    pub minimal_below: f32,
+}
+
+impl Default for LodThresholds {
+    fn default() -> Self {
+        LodThresholds {
+            simplified_below: 0.5,
+            minimal_below: 0.1,
+        }
+    }
+}
+
+impl WorldRect {
+    pub fn new(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> WorldRect {
+        WorldRect { min_x, min_y, max_x, max_y }
+    }
+
+    /// the smallest rectangle that contains all of the points
+    pub fn from_points(points: &[(f32, f32)]) -> WorldRect {
+        let mut rect = WorldRect::new(f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for (x, y) in points {
+            rect.min_x = rect.min_x.min(*x);
+            rect.min_y = rect.min_y.min(*y);
+            rect.max_x = rect.max_x.max(*x);
+            rect.max_y = rect.max_y.max(*y);
+        }
+        rect
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max_y - self.min_y
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+
+    pub fn intersects(&self, other: &WorldRect) -> bool {
+        self.min_x <= other.max_x && self.max_x >= other.min_x
+            && self.min_y <= other.max_y && self.max_y >= other.min_y
+    }
+
+    /// returns the range of cells (x range, y range) that overlap this rect,
+    /// for a grid of `map_size` cells where cell (x, y) covers
+    /// (x * cell_width, y * cell_height) to ((x + 1) * cell_width, (y + 1) * cell_height).
+    pub fn cell_range(&self, cell_width: f32, cell_height: f32, map_size: (usize, usize)) -> (Range<usize>, Range<usize>) {
+        let to_range = |min: f32, max: f32, cell: f32, count: usize| {
+            let start = (min / cell).floor().max(0.0) as usize;
+            let end = ((max / cell).floor() + 1.0).max(0.0) as usize;
+            start.min(count)..end.min(count)
+        };
+        (
+            to_range(self.min_x, self.max_x, cell_width, map_size.0),
+            to_range(self.min_y, self.max_y, cell_height, map_size.1),
+        )
+    }
+}
 
 #[derive(Default)]
-pub struct WorldScreen {
-    pub canvas: Canvas,
+pub struct WorldScreen<P: PixelFormat = Rgbx> {
+    pub canvas: Canvas<P>,
 
     pub screen_width: usize,
     pub screen_height: usize,
@@ -16,23 +120,138 @@ pub struct WorldScreen {
     pub scale_y: f32,
     pub scale_factor_up: f32,
     pub scale_factor_down: f32,
+    /// rotation of the camera in radians, around the center of the screen.
+    pub rotation: f32,
+    /// width of the lines drawn through the `Draw` impl
+    pub stroke: StrokeWidth,
+    pub lod_thresholds: LodThresholds,
+
+    /// local transforms pushed by whoever is drawing. each entry is already
+    /// combined with the entries below it.
+    transform_stack: Vec<Transform>,
 }
 
-impl WorldScreen {
-    // convert from world map space to screen space
+impl<P: PixelFormat> WorldScreen<P> {
+    /// the transform from world space to screen space, as
+    /// defined by the current pan, scale, and rotation.
+    pub fn view_transform(&self) -> Transform {
+        let view = Transform::translate(-self.pan_offset_x, -self.pan_offset_y)
+            .then(Transform::scale(self.scale_x, self.scale_y));
+        if self.rotation == 0.0 {
+            return view;
+        }
+        let center_x = self.screen_width as f32 / 2.0;
+        let center_y = self.screen_height as f32 / 2.0;
+        view.then(Transform::rotate_around(self.rotation, center_x, center_y))
+    }
+
+    /// the transform on top of the transform stack (from local
+    /// space to world space). identity if nothing was pushed.
+    pub fn current_transform(&self) -> Transform {
+        match self.transform_stack.last() {
+            Some(t) => *t,
+            None => Transform::IDENTITY,
+        }
+    }
+
+    /// the transform from local space all the way to screen space.
+    pub fn full_transform(&self) -> Transform {
+        self.current_transform().then(self.view_transform())
+    }
+
+    /// everything drawn until the matching `pop_transform` will be
+    /// transformed by `transform` first, and then by whatever
+    /// was already on the stack.
+    pub fn push_transform(&mut self, transform: Transform) {
+        let combined = transform.then(self.current_transform());
+        self.transform_stack.push(combined);
+    }
+
+    pub fn pop_transform(&mut self) -> Option<Transform> {
+        self.transform_stack.pop()
+    }
+
+    pub fn clear_transforms(&mut self) {
+        self.transform_stack.clear();
+    }
+
+    // convert from world map space to screen space.
+    // if transforms were pushed, the input is in the local space of the
+    // top transform rather than world space.
     pub fn world_to_screen(&self, world_x: f32, world_y: f32) -> (usize, usize) {
-        (
-            (self.scale_x * (world_x - self.pan_offset_x)) as usize,
-            (self.scale_y * (world_y - self.pan_offset_y)) as usize
-        )
+        let (x, y) = self.world_to_screen_f32(world_x, world_y);
+        (x as usize, y as usize)
+    }
+
+    // same as world_to_screen, but without truncating to a pixel.
+    // useful when the result can be off screen (negative)
+    pub fn world_to_screen_f32(&self, world_x: f32, world_y: f32) -> (f32, f32) {
+        self.full_transform().apply(world_x, world_y)
     }
 
     // convert a screen space pixel value to where it is in the world map
     pub fn screen_to_world(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
-        (
-            screen_x / self.scale_x + self.pan_offset_x,
-            screen_y / self.scale_y + self.pan_offset_y
-        )
+        match self.full_transform().inverse() {
+            Some(inverse) => inverse.apply(screen_x, screen_y),
+            None => (self.pan_offset_x, self.pan_offset_y),
+        }
+    }
+
+    /// the part of the world that is currently on screen. if the camera is
+    /// rotated (or the current transform is not axis aligned) this is the
+    /// bounding box of the visible area, so it can contain a bit more
+    /// than what is actually visible.
+    pub fn visible_world_rect(&self) -> WorldRect {
+        let width = self.screen_width as f32;
+        let height = self.screen_height as f32;
+        let corners = [
+            self.screen_to_world(0.0, 0.0),
+            self.screen_to_world(width, 0.0),
+            self.screen_to_world(width, height),
+            self.screen_to_world(0.0, height),
+        ];
+        WorldRect::from_points(&corners)
+    }
+
+    /// the range of cells that are on screen, for a grid that starts at
+    /// the origin of the current transform. for an isometric map, push the
+    /// isometric projection first and use a cell size of 1x1.
+    pub fn visible_cell_range(&self, cell_width: f32, cell_height: f32, map_size: (usize, usize)) -> (Range<usize>, Range<usize>) {
+        self.visible_world_rect().cell_range(cell_width, cell_height, map_size)
+    }
+
+    /// how many screen pixels one world unit covers. if x and y are scaled
+    /// differently (or there is shear) this is the average of the two.
+    pub fn zoom(&self) -> f32 {
+        self.view_transform().determinant().abs().sqrt()
+    }
+
+    /// the current stroke width converted to screen pixels
+    pub fn stroke_width_px(&self) -> f32 {
+        match self.stroke {
+            StrokeWidth::Screen(px) => px,
+            StrokeWidth::World(width) => (width * self.zoom()).max(1.0),
+        }
+    }
+
+    pub fn detail_level(&self) -> DetailLevel {
+        let zoom = self.zoom();
+        if zoom < self.lod_thresholds.minimal_below {
+            DetailLevel::Minimal
+        } else if zoom < self.lod_thresholds.simplified_below {
+            DetailLevel::Simplified
+        } else {
+            DetailLevel::Full
+        }
+    }
+
+    /// converts a distance/direction in screen pixels to a distance/direction
+    /// in world units, taking into account scale and rotation.
+    pub fn screen_delta_to_world(&self, dx: f32, dy: f32) -> (f32, f32) {
+        match self.view_transform().inverse() {
+            Some(inverse) => inverse.apply_vector(dx, dy),
+            None => (0.0, 0.0),
+        }
     }
 
     pub fn reset_pan(&mut self, pan_x: f32, pan_y: f32) {
@@ -41,8 +260,9 @@ impl WorldScreen {
     }
 
     pub fn pan_to(&mut self, pan_x: f32, pan_y: f32) {
-        self.pan_offset_x -= (pan_x - self.start_pan_x) / self.scale_x;
-        self.pan_offset_y -= (pan_y - self.start_pan_y) / self.scale_y;
+        let (dx, dy) = self.screen_delta_to_world(pan_x - self.start_pan_x, pan_y - self.start_pan_y);
+        self.pan_offset_x -= dx;
+        self.pan_offset_y -= dy;
 
         self.start_pan_x = pan_x;
         self.start_pan_y = pan_y;
@@ -68,7 +288,7 @@ impl WorldScreen {
     }
 }
 
-impl Draw for WorldScreen {
+impl<P: PixelFormat> Draw for WorldScreen<P> {
     fn fill(&mut self, color: crate::Rgb) {
         self.canvas.fill(color)
     }
@@ -98,20 +318,117 @@ impl Draw for WorldScreen {
     }
 
     fn draw_horizontal_line_f32(&mut self, y: f32, x1: f32, x2: f32, color: crate::Rgb) {
-        let (start_x, _) = self.world_to_screen(x1, y);
-        let (end_x, y_value) = self.world_to_screen(x2, y);
-        self.canvas.draw_horizontal_line(y_value, start_x, end_x, color)
+        self.draw_line_f32(x1, y, x2, y, color)
     }
 
     fn draw_vertical_line_f32(&mut self, x: f32, y1: f32, y2: f32, color: crate::Rgb) {
-        let (_, start_y) = self.world_to_screen(x, y1);
-        let (x_value, end_y) = self.world_to_screen(x, y2);
-        self.canvas.draw_vertical_line(x_value, start_y, end_y, color)
+        self.draw_line_f32(x, y1, x, y2, color)
     }
 
     fn draw_diagonal_line_f32(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: crate::Rgb) {
-        let (start_x, start_y) = self.world_to_screen(x1, y1);
-        let (end_x, end_y) = self.world_to_screen(x2, y2);
-        self.canvas.draw_diagonal_line(start_x, start_y, end_x, end_y, color)
+        self.draw_line_f32(x1, y1, x2, y2, color)
+    }
+
+    fn fill_convex_polygon_f32(&mut self, points: &[(f32, f32)], color: crate::Rgb) {
+        let transform = self.full_transform();
+        let screen_points: Vec<(f32, f32)> = points.iter()
+            .map(|(x, y)| transform.apply(*x, *y))
+            .collect();
+        self.canvas.fill_convex_polygon_f32(&screen_points, color)
+    }
+
+    /// `width` is interpreted the same way as the current stroke, ie:
+    /// in world units if the stroke is `StrokeWidth::World`.
+    fn draw_thick_line_f32(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32, color: crate::Rgb) {
+        let width_px = match self.stroke {
+            StrokeWidth::Screen(_) => width,
+            StrokeWidth::World(_) => (width * self.zoom()).max(1.0),
+        };
+        let (x1, y1) = self.world_to_screen_f32(x1, y1);
+        let (x2, y2) = self.world_to_screen_f32(x2, y2);
+        self.canvas.draw_thick_line_f32(x1, y1, x2, y2, width_px, color)
+    }
+
+    /// the attributes are blended in screen space, which is the same as
+    /// blending them in the world since the transforms are affine.
+    fn fill_triangle_with<T, F>(&mut self, points: &[(f32, f32); 3], attributes: [T; 3], shade: F)
+    where
+        T: Interpolate,
+        F: FnMut(T) -> crate::Rgb,
+    {
+        let transform = self.full_transform();
+        let screen_points = [
+            transform.apply(points[0].0, points[0].1),
+            transform.apply(points[1].0, points[1].1),
+            transform.apply(points[2].0, points[2].1),
+        ];
+        self.canvas.fill_triangle_with(&screen_points, attributes, shade)
+    }
+}
+
+impl<P: PixelFormat> WorldScreen<P> {
+    /// transforms both end points to screen space, clips the line to the
+    /// canvas, and then picks the cheapest line drawing method of the canvas.
+    /// lines wider than a pixel are drawn as filled polygons.
+    /// a horizontal line in the world can end up diagonal on screen
+    /// if there is rotation/shear.
+    fn draw_line_f32(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: crate::Rgb) {
+        let (x1, y1) = self.world_to_screen_f32(x1, y1);
+        let (x2, y2) = self.world_to_screen_f32(x2, y2);
+        let width_px = self.stroke_width_px();
+        if width_px > 1.0 {
+            return self.canvas.draw_thick_line_f32(x1, y1, x2, y2, width_px, color);
+        }
+        let max_x = self.canvas.width as f32;
+        let max_y = self.canvas.height as f32;
+        let (x1, y1, x2, y2) = match clip_line(x1, y1, x2, y2, max_x, max_y) {
+            Some(clipped) => clipped,
+            None => return,
+        };
+        let (start_x, start_y) = (x1 as usize, y1 as usize);
+        let (end_x, end_y) = (x2 as usize, y2 as usize);
+        if start_y == end_y {
+            if start_y >= self.canvas.height { return; }
+            let (lo, hi) = if start_x <= end_x { (start_x, end_x) } else { (end_x, start_x) };
+            self.canvas.draw_horizontal_line(start_y, lo, hi.min(self.canvas.width), color)
+        } else if start_x == end_x {
+            if start_x >= self.canvas.width { return; }
+            let (lo, hi) = if start_y <= end_y { (start_y, end_y) } else { (end_y, start_y) };
+            self.canvas.draw_vertical_line(start_x, lo, hi.min(self.canvas.height), color)
+        } else {
+            self.canvas.draw_diagonal_line(start_x, start_y, end_x, end_y, color)
+        }
+    }
+}
+
+/// Liang-Barsky line clipping against the rectangle (0, 0) -> (max_x, max_y).
+/// returns None if no part of the line is inside of the rectangle.
+fn clip_line(x1: f32, y1: f32, x2: f32, y2: f32, max_x: f32, max_y: f32) -> Option<(f32, f32, f32, f32)> {
+    let dx = x2 - x1;
+    let dy = y2 - y1;
+    let mut t0 = 0.0f32;
+    let mut t1 = 1.0f32;
+    let checks = [
+        (-dx, x1),
+        (dx, max_x - x1),
+        (-dy, y1),
+        (dy, max_y - y1),
+    ];
+    for (p, q) in checks.iter() {
+        if *p == 0.0 {
+            if *q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let r = q / p;
+        if *p < 0.0 {
+            if r > t1 { return None; }
+            if r > t0 { t0 = r; }
+        } else {
+            if r < t0 { return None; }
+            if r < t1 { t1 = r; }
+        }
     }
+    Some((x1 + t0 * dx, y1 + t0 * dy, x1 + t1 * dx, y1 + t1 * dy))
 }